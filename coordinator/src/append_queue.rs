@@ -0,0 +1,233 @@
+//! Group commit for appends. Appends to a ledger are queued, and a task of their own serves
+//! the queue: it takes the appends whose heights follow one another as a batch, which is
+//! written to the ledger store, sent to each endorser in one call, and whose receipts are
+//! attached in one write. The appends that arrive in the meantime make up the next batch.
+//! Callers only wait for the results of their own appends, so a caller that goes away leaves
+//! the others unaffected.
+
+use crate::errors::CoordinatorError;
+use ledger::{Handle, NimbleDigest, Receipts};
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+use tokio::sync::{oneshot, Notify};
+
+pub type AppendResult = Result<(NimbleDigest, Receipts), CoordinatorError>;
+
+/// An append waiting in the queue of its ledger
+pub struct PendingAppend {
+  pub height: usize,
+  pub block: Vec<u8>,
+  pub request_id: Vec<u8>,
+  reply: oneshot::Sender<AppendResult>,
+}
+
+impl PendingAppend {
+  pub fn reply(self, res: AppendResult) {
+    // the caller may have gone away
+    let _ = self.reply.send(res);
+  }
+}
+
+#[derive(Default)]
+struct LedgerQueue {
+  appends: Vec<PendingAppend>,
+  // whether a task serves the queue
+  served: bool,
+  // notified when an append is queued, for a server waiting for the next height
+  arrived: Arc<Notify>,
+}
+
+/// The next batch of appends to a ledger
+pub enum Batch {
+  Ready(Vec<PendingAppend>),
+  /// the next height is missing, and appending later heights would fail
+  Waiting(Arc<Notify>),
+  /// the queue is empty, and no longer served
+  Done,
+}
+
+pub struct AppendQueues {
+  max_batch_size: usize,
+  queues: Mutex<HashMap<Handle, LedgerQueue>>,
+}
+
+impl AppendQueues {
+  pub fn new(max_batch_size: usize) -> Self {
+    AppendQueues {
+      max_batch_size,
+      queues: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Queues an append to the ledger, made by the request with the ID if the client gave one,
+  /// and returns the receiver of its result
+  pub fn push(
+    &self,
+    handle: &Handle,
+    height: usize,
+    block: Vec<u8>,
+    request_id: Vec<u8>,
+  ) -> oneshot::Receiver<AppendResult> {
+    let (reply, result) = oneshot::channel();
+    let mut queues = self.queues.lock().unwrap();
+    let queue = queues.entry(*handle).or_default();
+    queue.appends.push(PendingAppend {
+      height,
+      block,
+      request_id,
+      reply,
+    });
+    queue.arrived.notify_one();
+    result
+  }
+
+  /// Makes the caller the server of the ledger's queue, unless the queue is served already or
+  /// has nothing left to serve. The caller serves it until the queue is empty.
+  pub fn claim(self: &Arc<Self>, handle: &Handle) -> Option<Serving> {
+    let mut queues = self.queues.lock().unwrap();
+    match queues.get_mut(handle) {
+      Some(queue) if !queue.served && !queue.appends.is_empty() => {
+        queue.served = true;
+        Some(Serving {
+          queues: self.clone(),
+          handle: *handle,
+          done: false,
+        })
+      },
+      _ => None,
+    }
+  }
+
+  // drops the ledger's queue, whose server stopped before it was empty; the callers of the
+  // appends left in it learn that they failed
+  fn abandon(&self, handle: &Handle) {
+    let mut queues = self.queues.lock().unwrap();
+    queues.remove(handle);
+  }
+
+  /// Puts appends taken from the ledger's queue back into it
+  pub fn requeue(&self, handle: &Handle, appends: Vec<PendingAppend>) {
+    if appends.is_empty() {
+      return;
+    }
+    let mut queues = self.queues.lock().unwrap();
+    queues.entry(*handle).or_default().appends.extend(appends);
+  }
+
+  /// Takes the next batch from the ledger's queue: the appends at consecutive heights from the
+  /// lowest one queued. If `next`, the height after the previous batch, is not queued yet, the
+  /// server is asked to wait for it if `wait` is set. The service of the queue ends once the
+  /// queue is empty.
+  pub fn take_batch(&self, handle: &Handle, next: Option<usize>, wait: bool) -> Batch {
+    let mut queues = self.queues.lock().unwrap();
+    let queue = match queues.get_mut(handle) {
+      Some(queue) => queue,
+      None => return Batch::Done,
+    };
+    if queue.appends.is_empty() {
+      queues.remove(handle);
+      return Batch::Done;
+    }
+
+    queue.appends.sort_by_key(|append| append.height);
+    let first = queue.appends[0].height;
+    if wait && next.is_some_and(|next| first > next) {
+      return Batch::Waiting(queue.arrived.clone());
+    }
+    let mut len = 1;
+    while len < queue.appends.len()
+      && len < self.max_batch_size
+      && queue.appends[len].height == first + len
+    {
+      len += 1;
+    }
+    Batch::Ready(queue.appends.drain(..len).collect())
+  }
+}
+
+/// Held by the task that serves a ledger's queue. If the task stops before the queue is
+/// empty, e.g., because it panicked, the appends left in the queue fail.
+pub struct Serving {
+  queues: Arc<AppendQueues>,
+  handle: Handle,
+  done: bool,
+}
+
+impl Serving {
+  /// Marks the queue as served until it was found empty
+  pub fn finish(mut self) {
+    self.done = true;
+  }
+}
+
+impl Drop for Serving {
+  fn drop(&mut self) {
+    if !self.done {
+      self.queues.abandon(&self.handle);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heights(batch: Batch) -> Vec<usize> {
+    match batch {
+      Batch::Ready(appends) => appends.iter().map(|append| append.height).collect(),
+      Batch::Waiting(_) => vec![0],
+      Batch::Done => Vec::new(),
+    }
+  }
+
+  #[tokio::test]
+  pub async fn check_append_queues() {
+    let queues = Arc::new(AppendQueues::new(3));
+    let handle = NimbleDigest::digest(b"ledger");
+    let mut results = Vec::new();
+    for height in [5, 2, 1, 3, 4, 7] {
+      results.push(queues.push(&handle, height, vec![height as u8], Vec::new()));
+    }
+
+    // only one caller serves the queue
+    let serving = queues.claim(&handle).unwrap();
+    assert!(queues.claim(&handle).is_none());
+
+    // batches are runs of consecutive heights, up to the maximum size
+    assert_eq!(heights(queues.take_batch(&handle, None, true)), [1, 2, 3]);
+    match queues.take_batch(&handle, Some(4), true) {
+      Batch::Ready(appends) => queues.requeue(&handle, appends),
+      _ => panic!("expected a batch"),
+    }
+    assert_eq!(heights(queues.take_batch(&handle, Some(4), true)), [4, 5]);
+    // a server may wait for a missing height, or go on without it
+    assert!(matches!(
+      queues.take_batch(&handle, Some(6), true),
+      Batch::Waiting(_)
+    ));
+    assert_eq!(heights(queues.take_batch(&handle, Some(6), false)), [7]);
+
+    // a caller whose append was dropped learns that it failed
+    assert!(results[0].try_recv().is_err());
+
+    // an empty queue is no longer served, and is served again once it fills up
+    assert!(matches!(
+      queues.take_batch(&handle, Some(8), true),
+      Batch::Done
+    ));
+    serving.finish();
+    let mut result = queues.push(&handle, 8, Vec::new(), Vec::new());
+    let serving = queues.claim(&handle).unwrap();
+
+    // the appends left to a server that stops fail, and the queue starts over
+    drop(serving);
+    assert!(matches!(
+      result.try_recv(),
+      Err(oneshot::error::TryRecvError::Closed)
+    ));
+    let _result = queues.push(&handle, 8, Vec::new(), Vec::new());
+    assert!(queues.claim(&handle).is_some());
+  }
+}
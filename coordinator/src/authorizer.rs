@@ -0,0 +1,289 @@
+//! The authorization of the coordinator's clients. With mutual TLS, a client is identified by
+//! the certificate it presents, and an authorizer decides which calls it may make on which
+//! ledgers before the coordinator serves them.
+
+use crate::errors::{AuthorizationError, ConfigError};
+use ledger::NimbleDigest;
+use serde::Deserialize;
+use std::{convert::TryFrom, path::Path, sync::Arc};
+use tonic::transport::Certificate;
+
+/// The calls on a ledger that clients are authorized to make
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+  NewLedger,
+  Append,
+  Read,
+  Delete,
+}
+
+/// The certificates a client presented over TLS, the first of which is its own
+#[derive(Clone, Debug, Default)]
+pub struct ClientIdentity {
+  certs: Option<Arc<Vec<Certificate>>>,
+}
+
+impl ClientIdentity {
+  pub fn new(certs: Option<Arc<Vec<Certificate>>>) -> Self {
+    ClientIdentity { certs }
+  }
+
+  // the DER encoding of the client's certificate
+  fn cert(&self) -> Option<&[u8]> {
+    self
+      .certs
+      .as_ref()
+      .and_then(|certs| certs.first())
+      .map(|cert| cert.get_ref())
+  }
+
+  pub fn is_anonymous(&self) -> bool {
+    self.cert().is_none()
+  }
+
+  /// Returns the SHA-256 fingerprint of the client's certificate in lowercase hex
+  pub fn fingerprint(&self) -> Option<String> {
+    self
+      .cert()
+      .map(|cert| hex::encode(NimbleDigest::digest(cert).to_bytes()))
+  }
+
+  /// Returns whether the client's certificate is valid for the DNS name
+  pub fn has_dns_name(&self, dns_name: &str) -> bool {
+    let cert = match self.cert().map(webpki::EndEntityCert::try_from) {
+      Some(Ok(cert)) => cert,
+      _ => return false,
+    };
+    match webpki::DnsNameRef::try_from_ascii_str(dns_name) {
+      Ok(name) => cert.verify_is_valid_for_dns_name(name).is_ok(),
+      Err(_) => false,
+    }
+  }
+}
+
+pub trait Authorizer: Send + Sync {
+  /// Checks that the client may make the call `operation` on the ledger with the handle
+  fn authorize(
+    &self,
+    client: &ClientIdentity,
+    operation: Operation,
+    handle: &[u8],
+  ) -> Result<(), AuthorizationError>;
+}
+
+/// Lets every client make every call, which is what the coordinator does without a policy
+pub struct AllowAll;
+
+impl Authorizer for AllowAll {
+  fn authorize(
+    &self,
+    _client: &ClientIdentity,
+    _operation: Operation,
+    _handle: &[u8],
+  ) -> Result<(), AuthorizationError> {
+    Ok(())
+  }
+}
+
+/// The calls that the clients with an identity may make, on the ledgers whose handles start
+/// with one of the prefixes, or on every ledger if no prefix is given. The identity is
+/// `dns:NAME` for the clients whose certificates are valid for NAME, `sha256:FINGERPRINT` for
+/// the client with that certificate, or `*` for every client, with a certificate or not.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientPolicy {
+  pub identity: String,
+  #[serde(default)]
+  pub handle_prefixes: Vec<String>,
+  pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+  clients: Vec<ClientPolicy>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Identity {
+  Any,
+  DnsName(String),
+  Fingerprint(String),
+}
+
+impl Identity {
+  fn parse(identity: &str) -> Result<Identity, ConfigError> {
+    let invalid = || ConfigError::InvalidPolicy(format!("invalid identity {:?}", identity));
+    if identity == "*" {
+      Ok(Identity::Any)
+    } else if let Some(name) = identity.strip_prefix("dns:") {
+      webpki::DnsNameRef::try_from_ascii_str(name).map_err(|_| invalid())?;
+      Ok(Identity::DnsName(name.to_string()))
+    } else if let Some(fingerprint) = identity.strip_prefix("sha256:") {
+      // fingerprints may be given the way `openssl x509 -fingerprint` prints them
+      let fingerprint = fingerprint.replace(':', "").to_lowercase();
+      match hex::decode(&fingerprint) {
+        Ok(bytes) if bytes.len() == 32 => Ok(Identity::Fingerprint(fingerprint)),
+        _ => Err(invalid()),
+      }
+    } else {
+      Err(invalid())
+    }
+  }
+
+  fn matches(&self, client: &ClientIdentity) -> bool {
+    match self {
+      Identity::Any => true,
+      Identity::DnsName(name) => client.has_dns_name(name),
+      Identity::Fingerprint(fingerprint) => client.fingerprint().as_ref() == Some(fingerprint),
+    }
+  }
+}
+
+/// Authorizes the calls that a client policy lets the client make, and refuses the others
+pub struct PolicyAuthorizer {
+  policies: Vec<(Identity, ClientPolicy)>,
+}
+
+impl PolicyAuthorizer {
+  pub fn new(policies: Vec<ClientPolicy>) -> Result<Self, ConfigError> {
+    let policies = policies
+      .into_iter()
+      .map(|policy| Ok((Identity::parse(&policy.identity)?, policy)))
+      .collect::<Result<Vec<_>, ConfigError>>()?;
+    Ok(PolicyAuthorizer { policies })
+  }
+
+  /// Reads the client policies from a TOML or YAML file, which lists them as `clients`
+  pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+    let contents = std::fs::read_to_string(path)
+      .map_err(|error| ConfigError::InvalidPolicy(format!("{}: {}", path, error)))?;
+    let invalid = |error: String| ConfigError::InvalidPolicy(format!("{}: {}", path, error));
+    let file: PolicyFile = match Path::new(path).extension().and_then(|e| e.to_str()) {
+      Some("toml") => toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?,
+      Some("yaml") | Some("yml") => {
+        serde_yaml::from_str(&contents).map_err(|e| invalid(e.to_string()))?
+      },
+      _ => return Err(invalid("expected a .toml, .yaml or .yml file".to_string())),
+    };
+    PolicyAuthorizer::new(file.clients)
+  }
+}
+
+impl Authorizer for PolicyAuthorizer {
+  fn authorize(
+    &self,
+    client: &ClientIdentity,
+    operation: Operation,
+    handle: &[u8],
+  ) -> Result<(), AuthorizationError> {
+    let mut known = false;
+    for (identity, policy) in &self.policies {
+      if !identity.matches(client) {
+        continue;
+      }
+      known = true;
+      let on_ledger = policy.handle_prefixes.is_empty()
+        || policy
+          .handle_prefixes
+          .iter()
+          .any(|prefix| handle.starts_with(prefix.as_bytes()));
+      if on_ledger && policy.operations.contains(&operation) {
+        return Ok(());
+      }
+    }
+    if !known && client.is_anonymous() {
+      Err(AuthorizationError::Unauthenticated)
+    } else {
+      Err(AuthorizationError::PermissionDenied)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a self-signed certificate for client-a.example.com, in base64url-encoded DER
+  const CLIENT_CERT: &str = "MIIBnTCCAUSgAwIBAgIUGjPi-47Qa2jwFTp3bqb2UsZNIP0wCgYIKoZIzj0EAwIwEzERMA8GA1UEAwwIY2xpZW50LWEwIBcNMjYxMDE5MTQyNzMzWhgPMjEyNjA5MjUxNDI3MzNaMBMxETAPBgNVBAMMCGNsaWVudC1hMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEuG0V2XGCv9dGMIlbjLs6j4Mpe4vyMCZrdwO8MTibMAhRLMPBo3LY8xcfArJz_r8-Th4lublnMMLEAo6PhLBgiKN0MHIwHQYDVR0OBBYEFD130xw6rGRm8FBq-jKQ8mHgmU_2MB8GA1UdIwQYMBaAFD130xw6rGRm8FBq-jKQ8mHgmU_2MA8GA1UdEwEB_wQFMAMBAf8wHwYDVR0RBBgwFoIUY2xpZW50LWEuZXhhbXBsZS5jb20wCgYIKoZIzj0EAwIDRwAwRAIgLF7jw4QkkmTPqVTKfWxDmGFuZohTLm-IosJqQoLT6toCIDo67uZEAqnconVDPKL12SnhzEKx-rBYp1uAIIV5FTMb";
+  const CLIENT_FINGERPRINT: &str =
+    "E7:C1:32:9B:68:58:56:6F:74:58:32:41:FD:74:25:5C:78:3F:40:1D:7B:24:B5:78:0C:EA:01:C5:0D:9F:E4:6D";
+
+  // Builds a client from the DER encoding of its certificate, the way tonic hands over the
+  // certificates of its peers: in DER, wrapped by `Certificate::from_pem` despite the name
+  fn client_with_der(der: &[u8]) -> ClientIdentity {
+    ClientIdentity::new(Some(Arc::new(vec![Certificate::from_pem(der)])))
+  }
+
+  #[test]
+  pub fn check_policy_authorizer() {
+    let cert = base64_url::decode(CLIENT_CERT).unwrap();
+    let client_a = client_with_der(&cert);
+    assert!(client_a.has_dns_name("client-a.example.com"));
+    assert!(!client_a.has_dns_name("client-b.example.com"));
+    let client_b = client_with_der(b"another certificate");
+    let anonymous = ClientIdentity::default();
+
+    let authorizer = PolicyAuthorizer::new(vec![
+      ClientPolicy {
+        identity: "dns:client-a.example.com".to_string(),
+        handle_prefixes: vec!["a/".to_string()],
+        operations: vec![Operation::NewLedger, Operation::Append, Operation::Read],
+      },
+      ClientPolicy {
+        identity: format!("sha256:{}", CLIENT_FINGERPRINT),
+        handle_prefixes: vec!["shared/".to_string()],
+        operations: vec![Operation::Delete],
+      },
+      ClientPolicy {
+        identity: "*".to_string(),
+        handle_prefixes: vec!["public/".to_string()],
+        operations: vec![Operation::Read],
+      },
+    ])
+    .unwrap();
+
+    // a client makes the calls its policies let it make, on their ledgers only
+    assert!(authorizer
+      .authorize(&client_a, Operation::Append, b"a/ledger")
+      .is_ok());
+    assert!(authorizer
+      .authorize(&client_a, Operation::Delete, b"shared/ledger")
+      .is_ok());
+    assert_eq!(
+      authorizer.authorize(&client_a, Operation::Delete, b"a/ledger"),
+      Err(AuthorizationError::PermissionDenied)
+    );
+    assert_eq!(
+      authorizer.authorize(&client_b, Operation::Append, b"a/ledger"),
+      Err(AuthorizationError::PermissionDenied)
+    );
+
+    // every client may read the public ledgers, but a client without a certificate may do
+    // nothing else
+    for client in [&client_a, &client_b, &anonymous] {
+      assert!(authorizer
+        .authorize(client, Operation::Read, b"public/ledger")
+        .is_ok());
+    }
+    assert_eq!(
+      authorizer.authorize(&anonymous, Operation::Append, b"public/ledger"),
+      Err(AuthorizationError::PermissionDenied)
+    );
+    let authorizer = PolicyAuthorizer::new(Vec::new()).unwrap();
+    assert_eq!(
+      authorizer.authorize(&anonymous, Operation::Read, b"a/ledger"),
+      Err(AuthorizationError::Unauthenticated)
+    );
+
+    for identity in ["client-a", "dns:", "sha256:e7c1", "dns:client a"] {
+      let res = PolicyAuthorizer::new(vec![ClientPolicy {
+        identity: identity.to_string(),
+        handle_prefixes: Vec::new(),
+        operations: vec![Operation::Read],
+      }]);
+      assert!(matches!(res, Err(ConfigError::InvalidPolicy(_))));
+    }
+  }
+}
@@ -0,0 +1,56 @@
+//! A background task that brings endorsers that lag behind on ledgers up to date, so that
+//! appends never wait for an endorser to be caught up.
+
+use crate::{config::CatchUpConfig, coordinator_state::CoordinatorState};
+use std::{sync::Arc, time::Duration};
+use tracing::info;
+
+pub struct CatchUp {
+  coordinator: Arc<CoordinatorState>,
+  config: CatchUpConfig,
+}
+
+impl CatchUp {
+  pub fn new(coordinator: Arc<CoordinatorState>, config: CatchUpConfig) -> Self {
+    CatchUp {
+      coordinator,
+      config,
+    }
+  }
+
+  /// Catches up the endorsers that appends and reads find lagging as soon as they are found,
+  /// and looks for lagging endorsers every scan interval, for as long as the coordinator runs
+  pub async fn run(self) {
+    let mut interval = tokio::time::interval(Duration::from_secs(self.config.scan_interval_secs));
+    loop {
+      let scan = tokio::select! {
+        _ = interval.tick() => true,
+        _ = self.coordinator.lagging_endorsers_reported() => false,
+      };
+      // a standby is not connected to the endorsers
+      if !self.coordinator.is_leader() {
+        continue;
+      }
+      self.catch_up(scan).await;
+    }
+  }
+
+  /// Runs a round of catch-up, after looking for lagging endorsers if `scan` is set, and
+  /// returns the number of entries replayed to endorsers
+  pub async fn catch_up(&self, scan: bool) -> usize {
+    if scan {
+      let num_lagging = self.coordinator.find_lagging_endorsers().await;
+      if num_lagging > 0 {
+        info!("found {} ledgers that endorsers lag behind on", num_lagging);
+      }
+    }
+    let num_replayed = self
+      .coordinator
+      .catch_up_endorsers(self.config.batch_size)
+      .await;
+    if num_replayed > 0 {
+      info!("replayed {} entries to lagging endorsers", num_replayed);
+    }
+    num_replayed
+  }
+}
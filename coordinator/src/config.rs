@@ -0,0 +1,814 @@
+//! The configuration of the coordinator. Settings are taken from a TOML or YAML file, if one
+//! is given, then from environment variables, and then from the command line, each of which
+//! overrides the previous ones. Secrets may be kept in files of their own, so that they show
+//! up neither in the configuration file nor in the command line of the coordinator.
+
+use crate::errors::ConfigError;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, str::FromStr};
+use tonic::transport::Endpoint;
+
+/// The prefix of the environment variables that override settings: `store.type` is
+/// overridden by `NIMBLE_COORDINATOR_STORE_TYPE`, for instance
+pub const ENV_PREFIX: &str = "NIMBLE_COORDINATOR_";
+
+const STORE_TYPES: [&str; 5] = [
+  "memory",
+  "filestore",
+  "table",
+  "mongodb_cosmos",
+  "replicated",
+];
+const CONTENT_STORE_TYPES: [&str; 3] = ["memory", "filestore", "s3"];
+
+// the settings that can be overridden, by their path in the configuration file
+const SETTINGS: [&str; 49] = [
+  "host",
+  "port",
+  "ctrl_port",
+  "endorsers",
+  "channels",
+  "coordinator_id",
+  "lease_duration_ms",
+  "backup_dir",
+  "retain_entries",
+  "prune_interval_secs",
+  "otlp_endpoint",
+  "store.type",
+  "store.nimble_db",
+  "store.cosmos_url",
+  "store.storage_account",
+  "store.storage_master_key",
+  "store.storage_master_key_file",
+  "store.fstore_dir",
+  "store.replicas",
+  "store.cache_size",
+  "store.key_file",
+  "content_store.type",
+  "content_store.threshold",
+  "content_store.dir",
+  "content_store.s3_endpoint",
+  "content_store.s3_bucket",
+  "content_store.s3_region",
+  "content_store.s3_access_key",
+  "content_store.s3_secret_key",
+  "content_store.s3_secret_key_file",
+  "timeouts.endorser_connect_secs",
+  "timeouts.endorser_request_secs",
+  "tls.cert",
+  "tls.key",
+  "tls.client_ca",
+  "endorser_tls.ca",
+  "endorser_tls.cert",
+  "endorser_tls.key",
+  "supervisor.spare_endorsers",
+  "supervisor.probe_interval_secs",
+  "supervisor.failure_threshold",
+  "supervisor.quorum_margin",
+  "supervisor.min_interval_secs",
+  "catch_up.scan_interval_secs",
+  "catch_up.batch_size",
+  "appends.max_batch_size",
+  "control.token",
+  "control.token_file",
+  "authorization.policy_file",
+];
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoordinatorConfig {
+  pub host: String,
+  pub port: u16,
+  pub ctrl_port: u16,
+  /// the URIs of the endorsers, which may be left empty if the endorsers are taken from the
+  /// view ledger of an elected coordinator
+  pub endorsers: Vec<String>,
+  /// the number of gRPC channels to each endorser
+  pub channels: usize,
+  /// the name this coordinator holds the lease under (`host:port` by default)
+  pub coordinator_id: Option<String>,
+  /// elect a leader among the coordinators sharing the store, with a lease this long
+  pub lease_duration_ms: Option<u64>,
+  /// the directory backups are written to, which enables backups
+  pub backup_dir: Option<String>,
+  /// prune each ledger down to this many entries after its checkpoint
+  pub retain_entries: Option<usize>,
+  pub prune_interval_secs: u64,
+  /// export spans to the OTLP collector at this URI
+  pub otlp_endpoint: Option<String>,
+  pub store: StoreConfig,
+  pub content_store: ContentStoreConfig,
+  pub timeouts: TimeoutConfig,
+  pub tls: TlsConfig,
+  pub endorser_tls: EndorserTlsConfig,
+  pub supervisor: SupervisorConfig,
+  pub catch_up: CatchUpConfig,
+  pub appends: AppendsConfig,
+  pub control: ControlConfig,
+  pub authorization: AuthorizationConfig,
+}
+
+/// The ledger store, and the keys and caches in front of it
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+  #[serde(rename = "type")]
+  pub store_type: String,
+  pub nimble_db: String,
+  pub cosmos_url: Option<String>,
+  pub storage_account: Option<String>,
+  pub storage_master_key: Option<String>,
+  pub storage_master_key_file: Option<String>,
+  pub fstore_dir: Option<String>,
+  /// the replicas of a "replicated" store, as type[:KEY=VALUE,...] separated by ';'
+  pub replicas: Option<String>,
+  /// cache up to this many ledger tails and entries in front of the store
+  pub cache_size: Option<usize>,
+  /// a file with a base64url-encoded 32-byte key for encrypting blocks in the store
+  pub key_file: Option<String>,
+}
+
+/// The store that keeps large blocks out of the ledger store, if any
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentStoreConfig {
+  #[serde(rename = "type")]
+  pub store_type: Option<String>,
+  /// blocks larger than this many bytes are kept in the content store
+  pub threshold: Option<usize>,
+  pub dir: Option<String>,
+  pub s3_endpoint: Option<String>,
+  pub s3_bucket: Option<String>,
+  pub s3_region: Option<String>,
+  pub s3_access_key: Option<String>,
+  pub s3_secret_key: Option<String>,
+  pub s3_secret_key_file: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+  pub endorser_connect_secs: u64,
+  pub endorser_request_secs: u64,
+}
+
+/// The certificate and key, in PEM files, the gRPC service is served with over TLS
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+  pub cert: Option<String>,
+  pub key: Option<String>,
+  /// the CA certificate, in a PEM file, that clients must present a certificate signed by
+  pub client_ca: Option<String>,
+}
+
+/// The TLS connections to the endorsers with https URIs. Their certificates must be signed by
+/// the CA, and the coordinator presents the certificate and key, if given, to endorsers that
+/// require one. All are PEM files.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndorserTlsConfig {
+  pub ca: Option<String>,
+  pub cert: Option<String>,
+  pub key: Option<String>,
+}
+
+/// The supervisor of the endorsers, which runs if spare endorsers are given. It moves to a
+/// view of spare endorsers once no more than `quorum_margin` endorsers beyond a quorum are
+/// live, and an endorser counts as down once it failed `failure_threshold` probes in a row.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SupervisorConfig {
+  pub spare_endorsers: Vec<String>,
+  pub probe_interval_secs: u64,
+  pub failure_threshold: u32,
+  pub quorum_margin: usize,
+  /// the least time between two view changes made by the supervisor
+  pub min_interval_secs: u64,
+}
+
+/// The background task that brings lagging endorsers up to date. It catches an endorser up as
+/// soon as an append finds it lagging, and looks for lagging endorsers every scan interval.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CatchUpConfig {
+  pub scan_interval_secs: u64,
+  /// the number of entries read from the ledger store at a time
+  pub batch_size: usize,
+}
+
+/// The batching of appends, which is off unless asked for. Appends to the same ledger that
+/// arrive while the previous ones are with the endorsers are sent to them together, as long as
+/// their heights follow one another.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppendsConfig {
+  /// the most appends sent to the endorsers together, where 1, the default, sends each on its
+  /// own
+  pub max_batch_size: usize,
+}
+
+/// The control service. If a token is set, every request to it must carry the token as
+/// `Authorization: Bearer <token>`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlConfig {
+  pub token: Option<String>,
+  pub token_file: Option<String>,
+}
+
+/// The authorization of the gRPC service's clients. Without a policy, every client may make
+/// every call.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthorizationConfig {
+  /// a TOML or YAML file with the calls each client may make on which ledgers
+  pub policy_file: Option<String>,
+}
+
+impl Default for CoordinatorConfig {
+  fn default() -> Self {
+    CoordinatorConfig {
+      host: "[::1]".to_string(),
+      port: 8080,
+      ctrl_port: 8090,
+      endorsers: vec!["http://[::1]:9090".to_string()],
+      channels: 1,
+      coordinator_id: None,
+      lease_duration_ms: None,
+      backup_dir: None,
+      retain_entries: None,
+      prune_interval_secs: 3600,
+      otlp_endpoint: None,
+      store: StoreConfig::default(),
+      content_store: ContentStoreConfig::default(),
+      timeouts: TimeoutConfig::default(),
+      tls: TlsConfig::default(),
+      endorser_tls: EndorserTlsConfig::default(),
+      supervisor: SupervisorConfig::default(),
+      catch_up: CatchUpConfig::default(),
+      appends: AppendsConfig::default(),
+      control: ControlConfig::default(),
+      authorization: AuthorizationConfig::default(),
+    }
+  }
+}
+
+impl Default for StoreConfig {
+  fn default() -> Self {
+    StoreConfig {
+      store_type: "memory".to_string(),
+      nimble_db: "nimble_cosmosdb".to_string(),
+      cosmos_url: None,
+      storage_account: None,
+      storage_master_key: None,
+      storage_master_key_file: None,
+      fstore_dir: None,
+      replicas: None,
+      cache_size: None,
+      key_file: None,
+    }
+  }
+}
+
+impl Default for SupervisorConfig {
+  fn default() -> Self {
+    SupervisorConfig {
+      spare_endorsers: Vec::new(),
+      probe_interval_secs: 5,
+      failure_threshold: 3,
+      quorum_margin: 0,
+      min_interval_secs: 300,
+    }
+  }
+}
+
+impl Default for CatchUpConfig {
+  fn default() -> Self {
+    CatchUpConfig {
+      scan_interval_secs: 30,
+      batch_size: 64,
+    }
+  }
+}
+
+impl Default for AppendsConfig {
+  fn default() -> Self {
+    AppendsConfig { max_batch_size: 1 }
+  }
+}
+
+impl Default for TimeoutConfig {
+  fn default() -> Self {
+    TimeoutConfig {
+      endorser_connect_secs: 10,
+      endorser_request_secs: 10,
+    }
+  }
+}
+
+/// Returns the environment variable that overrides a setting
+pub fn env_var(key: &str) -> String {
+  format!("{}{}", ENV_PREFIX, key.replace('.', "_").to_uppercase())
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| ConfigError::InvalidSetting(key.to_string(), value.to_string()))
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+  value
+    .split(',')
+    .map(|e| e.trim())
+    .filter(|e| !e.is_empty())
+    .map(|e| e.to_string())
+    .collect()
+}
+
+fn require<T>(key: &str, value: &Option<T>) -> Result<(), ConfigError> {
+  match value {
+    Some(_) => Ok(()),
+    None => Err(ConfigError::MissingSetting(key.to_string())),
+  }
+}
+
+fn require_positive(key: &str, value: u64) -> Result<(), ConfigError> {
+  if value == 0 {
+    return Err(ConfigError::InvalidSetting(
+      key.to_string(),
+      value.to_string(),
+    ));
+  }
+  Ok(())
+}
+
+fn require_uris(key: &str, uris: &[String]) -> Result<(), ConfigError> {
+  for uri in uris {
+    let valid = (uri.starts_with("http://") || uri.starts_with("https://"))
+      && Endpoint::from_shared(uri.clone()).is_ok();
+    if !valid {
+      return Err(ConfigError::InvalidSetting(key.to_string(), uri.clone()));
+    }
+  }
+  Ok(())
+}
+
+// Replaces the secret with the contents of its file, if it is kept in one
+fn read_secret(
+  key: &str,
+  secret: &mut Option<String>,
+  file: &Option<String>,
+) -> Result<(), ConfigError> {
+  if let Some(path) = file {
+    if secret.is_some() {
+      return Err(ConfigError::InvalidSecret(format!(
+        "{} and {}_file are both set",
+        key, key
+      )));
+    }
+    let value = std::fs::read_to_string(path).map_err(|error| {
+      ConfigError::InvalidSecret(format!("cannot read {}_file {} ({})", key, path, error))
+    })?;
+    *secret = Some(value.trim().to_string());
+  }
+  Ok(())
+}
+
+impl CoordinatorConfig {
+  /// Loads the configuration from the file at `path`, if any, overrides its settings with the
+  /// environment variables returned by `env` and then with `overrides`, reads the secrets kept
+  /// in files, and validates the result
+  pub fn load(
+    path: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+    overrides: &[(&str, String)],
+  ) -> Result<CoordinatorConfig, ConfigError> {
+    let mut config = match path {
+      Some(path) => CoordinatorConfig::from_file(path)?,
+      None => CoordinatorConfig::default(),
+    };
+    for key in SETTINGS.iter() {
+      let var = env_var(key);
+      if let Some(value) = env(&var) {
+        config
+          .set(key, &value)
+          .map_err(|_| ConfigError::InvalidSetting(var, value))?;
+      }
+    }
+    for (key, value) in overrides {
+      config.set(key, value)?;
+    }
+    config.read_secrets()?;
+    config.validate()?;
+    Ok(config)
+  }
+
+  fn from_file(path: &str) -> Result<CoordinatorConfig, ConfigError> {
+    let contents = std::fs::read_to_string(path)
+      .map_err(|error| ConfigError::FailedToReadConfig(format!("{}: {}", path, error)))?;
+    let invalid = |error: String| ConfigError::InvalidConfigFile(format!("{}: {}", path, error));
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+      Some("toml") => toml::from_str(&contents).map_err(|e| invalid(e.to_string())),
+      Some("yaml") | Some("yml") => {
+        serde_yaml::from_str(&contents).map_err(|e| invalid(e.to_string()))
+      },
+      _ => Err(invalid("expected a .toml, .yaml or .yml file".to_string())),
+    }
+  }
+
+  /// Sets the setting at `key`, its path in the configuration file, to `value`
+  pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+    let string = || Some(value.to_string());
+    match key {
+      "host" => self.host = value.to_string(),
+      "port" => self.port = parse(key, value)?,
+      "ctrl_port" => self.ctrl_port = parse(key, value)?,
+      "endorsers" => self.endorsers = parse_list(value),
+      "channels" => self.channels = parse(key, value)?,
+      "coordinator_id" => self.coordinator_id = string(),
+      "lease_duration_ms" => self.lease_duration_ms = Some(parse(key, value)?),
+      "backup_dir" => self.backup_dir = string(),
+      "retain_entries" => self.retain_entries = Some(parse(key, value)?),
+      "prune_interval_secs" => self.prune_interval_secs = parse(key, value)?,
+      "otlp_endpoint" => self.otlp_endpoint = string(),
+      "store.type" => self.store.store_type = value.to_string(),
+      "store.nimble_db" => self.store.nimble_db = value.to_string(),
+      "store.cosmos_url" => self.store.cosmos_url = string(),
+      "store.storage_account" => self.store.storage_account = string(),
+      "store.storage_master_key" => self.store.storage_master_key = string(),
+      "store.storage_master_key_file" => self.store.storage_master_key_file = string(),
+      "store.fstore_dir" => self.store.fstore_dir = string(),
+      "store.replicas" => self.store.replicas = string(),
+      "store.cache_size" => self.store.cache_size = Some(parse(key, value)?),
+      "store.key_file" => self.store.key_file = string(),
+      "content_store.type" => self.content_store.store_type = string(),
+      "content_store.threshold" => self.content_store.threshold = Some(parse(key, value)?),
+      "content_store.dir" => self.content_store.dir = string(),
+      "content_store.s3_endpoint" => self.content_store.s3_endpoint = string(),
+      "content_store.s3_bucket" => self.content_store.s3_bucket = string(),
+      "content_store.s3_region" => self.content_store.s3_region = string(),
+      "content_store.s3_access_key" => self.content_store.s3_access_key = string(),
+      "content_store.s3_secret_key" => self.content_store.s3_secret_key = string(),
+      "content_store.s3_secret_key_file" => self.content_store.s3_secret_key_file = string(),
+      "timeouts.endorser_connect_secs" => self.timeouts.endorser_connect_secs = parse(key, value)?,
+      "timeouts.endorser_request_secs" => self.timeouts.endorser_request_secs = parse(key, value)?,
+      "tls.cert" => self.tls.cert = string(),
+      "tls.key" => self.tls.key = string(),
+      "tls.client_ca" => self.tls.client_ca = string(),
+      "endorser_tls.ca" => self.endorser_tls.ca = string(),
+      "endorser_tls.cert" => self.endorser_tls.cert = string(),
+      "endorser_tls.key" => self.endorser_tls.key = string(),
+      "supervisor.spare_endorsers" => self.supervisor.spare_endorsers = parse_list(value),
+      "supervisor.probe_interval_secs" => self.supervisor.probe_interval_secs = parse(key, value)?,
+      "supervisor.failure_threshold" => self.supervisor.failure_threshold = parse(key, value)?,
+      "supervisor.quorum_margin" => self.supervisor.quorum_margin = parse(key, value)?,
+      "supervisor.min_interval_secs" => self.supervisor.min_interval_secs = parse(key, value)?,
+      "catch_up.scan_interval_secs" => self.catch_up.scan_interval_secs = parse(key, value)?,
+      "catch_up.batch_size" => self.catch_up.batch_size = parse(key, value)?,
+      "appends.max_batch_size" => self.appends.max_batch_size = parse(key, value)?,
+      "control.token" => self.control.token = string(),
+      "control.token_file" => self.control.token_file = string(),
+      "authorization.policy_file" => self.authorization.policy_file = string(),
+      _ => {
+        return Err(ConfigError::InvalidSetting(
+          key.to_string(),
+          value.to_string(),
+        ))
+      },
+    }
+    Ok(())
+  }
+
+  fn read_secrets(&mut self) -> Result<(), ConfigError> {
+    read_secret(
+      "store.storage_master_key",
+      &mut self.store.storage_master_key,
+      &self.store.storage_master_key_file,
+    )?;
+    read_secret(
+      "content_store.s3_secret_key",
+      &mut self.content_store.s3_secret_key,
+      &self.content_store.s3_secret_key_file,
+    )?;
+    read_secret(
+      "control.token",
+      &mut self.control.token,
+      &self.control.token_file,
+    )
+  }
+
+  /// Checks that the settings are consistent, and that the stores have what they need
+  pub fn validate(&self) -> Result<(), ConfigError> {
+    let store = &self.store;
+    match store.store_type.as_str() {
+      "filestore" => require("store.fstore_dir", &store.fstore_dir)?,
+      "table" => {
+        require("store.storage_account", &store.storage_account)?;
+        require("store.storage_master_key", &store.storage_master_key)?;
+      },
+      "mongodb_cosmos" => require("store.cosmos_url", &store.cosmos_url)?,
+      "replicated" => require("store.replicas", &store.replicas)?,
+      t if STORE_TYPES.contains(&t) => (),
+      t => {
+        return Err(ConfigError::InvalidSetting(
+          "store.type".to_string(),
+          t.to_string(),
+        ))
+      },
+    }
+    if let Some(size) = store.cache_size {
+      require_positive("store.cache_size", size as u64)?;
+    }
+
+    let content_store = &self.content_store;
+    match content_store.store_type.as_deref() {
+      None => {
+        if content_store.threshold.is_some() {
+          return Err(ConfigError::MissingSetting(
+            "content_store.type".to_string(),
+          ));
+        }
+      },
+      Some("filestore") => require("content_store.dir", &content_store.dir)?,
+      Some("s3") => {
+        require("content_store.s3_endpoint", &content_store.s3_endpoint)?;
+        require("content_store.s3_bucket", &content_store.s3_bucket)?;
+        require("content_store.s3_access_key", &content_store.s3_access_key)?;
+        require("content_store.s3_secret_key", &content_store.s3_secret_key)?;
+      },
+      Some(t) if CONTENT_STORE_TYPES.contains(&t) => (),
+      Some(t) => {
+        return Err(ConfigError::InvalidSetting(
+          "content_store.type".to_string(),
+          t.to_string(),
+        ))
+      },
+    }
+
+    require_uris("endorsers", &self.endorsers)?;
+    require_uris(
+      "supervisor.spare_endorsers",
+      &self.supervisor.spare_endorsers,
+    )?;
+    // an elected coordinator may take the endorsers from the view ledger instead
+    if self.endorsers.is_empty() && self.lease_duration_ms.is_none() {
+      return Err(ConfigError::MissingSetting("endorsers".to_string()));
+    }
+
+    require_positive("channels", self.channels as u64)?;
+    require_positive("prune_interval_secs", self.prune_interval_secs)?;
+    require_positive(
+      "timeouts.endorser_connect_secs",
+      self.timeouts.endorser_connect_secs,
+    )?;
+    require_positive(
+      "timeouts.endorser_request_secs",
+      self.timeouts.endorser_request_secs,
+    )?;
+    require_positive(
+      "supervisor.probe_interval_secs",
+      self.supervisor.probe_interval_secs,
+    )?;
+    require_positive(
+      "supervisor.failure_threshold",
+      self.supervisor.failure_threshold as u64,
+    )?;
+    require_positive(
+      "catch_up.scan_interval_secs",
+      self.catch_up.scan_interval_secs,
+    )?;
+    require_positive("catch_up.batch_size", self.catch_up.batch_size as u64)?;
+    require_positive("appends.max_batch_size", self.appends.max_batch_size as u64)?;
+    if let Some(duration) = self.lease_duration_ms {
+      require_positive("lease_duration_ms", duration)?;
+    }
+
+    let endorser_tls = &self.endorser_tls;
+    match (&endorser_tls.cert, &endorser_tls.key) {
+      (Some(_), None) => return Err(ConfigError::MissingSetting("endorser_tls.key".to_string())),
+      (None, Some(_)) => return Err(ConfigError::MissingSetting("endorser_tls.cert".to_string())),
+      // the coordinator only presents a certificate over TLS, which needs the CA
+      (Some(_), Some(_)) => require("endorser_tls.ca", &endorser_tls.ca)?,
+      (None, None) => (),
+    }
+
+    match (&self.tls.cert, &self.tls.key) {
+      (Some(_), None) => Err(ConfigError::MissingSetting("tls.key".to_string())),
+      // clients can only present certificates to a service served over TLS
+      (None, key) if key.is_some() || self.tls.client_ca.is_some() => {
+        Err(ConfigError::MissingSetting("tls.cert".to_string()))
+      },
+      _ => Ok(()),
+    }
+  }
+
+  /// Returns the arguments that `CoordinatorState::new` passes on to the stores
+  pub fn store_args(&self) -> HashMap<String, String> {
+    let mut args = HashMap::new();
+    let store = &self.store;
+    let content_store = &self.content_store;
+    let coordinator_id = match &self.coordinator_id {
+      Some(id) => id.clone(),
+      None => format!("{}:{}", self.host, self.port),
+    };
+    for (key, value) in [
+      ("NIMBLE_DB", Some(store.nimble_db.clone())),
+      ("COSMOS_URL", store.cosmos_url.clone()),
+      ("STORAGE_ACCOUNT", store.storage_account.clone()),
+      ("STORAGE_MASTER_KEY", store.storage_master_key.clone()),
+      ("NIMBLE_FSTORE_DIR", store.fstore_dir.clone()),
+      ("REPLICAS", store.replicas.clone()),
+      ("LEDGER_CACHE_SIZE", store.cache_size.map(|s| s.to_string())),
+      ("LEDGER_KEY_FILE", store.key_file.clone()),
+      ("CONTENT_STORE", content_store.store_type.clone()),
+      (
+        "CONTENT_THRESHOLD",
+        content_store.threshold.map(|t| t.to_string()),
+      ),
+      ("NIMBLE_CONTENT_DIR", content_store.dir.clone()),
+      ("S3_ENDPOINT", content_store.s3_endpoint.clone()),
+      ("S3_BUCKET", content_store.s3_bucket.clone()),
+      ("S3_REGION", content_store.s3_region.clone()),
+      ("S3_ACCESS_KEY", content_store.s3_access_key.clone()),
+      ("S3_SECRET_KEY", content_store.s3_secret_key.clone()),
+      ("BACKUP_DIR", self.backup_dir.clone()),
+      ("RETAIN_ENTRIES", self.retain_entries.map(|r| r.to_string())),
+      (
+        "LEASE_DURATION",
+        self.lease_duration_ms.map(|d| d.to_string()),
+      ),
+      ("COORDINATOR_ID", Some(coordinator_id)),
+      (
+        "ENDORSER_CONNECT_TIMEOUT",
+        Some(self.timeouts.endorser_connect_secs.to_string()),
+      ),
+      (
+        "ENDORSER_REQUEST_TIMEOUT",
+        Some(self.timeouts.endorser_request_secs.to_string()),
+      ),
+      ("ENDORSER_TLS_CA", self.endorser_tls.ca.clone()),
+      ("ENDORSER_TLS_CERT", self.endorser_tls.cert.clone()),
+      ("ENDORSER_TLS_KEY", self.endorser_tls.key.clone()),
+      (
+        "APPEND_BATCH_SIZE",
+        Some(self.appends.max_batch_size.to_string()),
+      ),
+    ] {
+      if let Some(value) = value {
+        args.insert(key.to_string(), value);
+      }
+    }
+    args
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::Rng;
+
+  fn temp_file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!(
+      "nimble-config-{:016x}-{}",
+      rand::thread_rng().gen::<u64>(),
+      name
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+  }
+
+  #[test]
+  pub fn check_config() {
+    let no_env = |_: &str| None;
+
+    // the defaults describe a coordinator with an in-memory store and a local endorser
+    let config = CoordinatorConfig::load(None, no_env, &[]).unwrap();
+    assert_eq!(config, CoordinatorConfig::default());
+    assert_eq!(config.store_args()["COORDINATOR_ID"], "[::1]:8080");
+
+    // a secret is read from its file
+    let key_file = temp_file("key", "c2VjcmV0\n");
+    let toml_file = temp_file(
+      "coordinator.toml",
+      &format!(
+        r#"
+        port = 8081
+        endorsers = ["http://[::1]:9091", "http://[::1]:9092"]
+
+        [store]
+        type = "table"
+        storage_account = "account"
+        storage_master_key_file = "{}"
+
+        [timeouts]
+        endorser_request_secs = 30
+
+        [catch_up]
+        batch_size = 16
+
+        [appends]
+        max_batch_size = 32
+        "#,
+        key_file
+      ),
+    );
+    let config = CoordinatorConfig::load(Some(&toml_file), no_env, &[]).unwrap();
+    assert_eq!(config.port, 8081);
+    assert_eq!(config.endorsers.len(), 2);
+    assert_eq!(
+      config.store.storage_master_key,
+      Some("c2VjcmV0".to_string())
+    );
+    let args = config.store_args();
+    assert_eq!(args["STORAGE_MASTER_KEY"], "c2VjcmV0");
+    assert_eq!(args["ENDORSER_REQUEST_TIMEOUT"], "30");
+    assert_eq!(args["ENDORSER_CONNECT_TIMEOUT"], "10");
+    assert_eq!(config.catch_up.batch_size, 16);
+    assert_eq!(config.catch_up.scan_interval_secs, 30);
+    assert_eq!(args["APPEND_BATCH_SIZE"], "32");
+
+    // the environment overrides the file, and the command line overrides the environment
+    let env = |var: &str| match var {
+      "NIMBLE_COORDINATOR_PORT" => Some("8082".to_string()),
+      "NIMBLE_COORDINATOR_STORE_STORAGE_ACCOUNT" => Some("other".to_string()),
+      _ => None,
+    };
+    let config =
+      CoordinatorConfig::load(Some(&toml_file), env, &[("port", "8083".to_string())]).unwrap();
+    assert_eq!(config.port, 8083);
+    assert_eq!(config.store.storage_account, Some("other".to_string()));
+
+    // the same configuration in YAML
+    let yaml_file = temp_file(
+      "coordinator.yaml",
+      "port: 8081\nstore:\n  type: filestore\n  fstore_dir: /tmp/nimble\n",
+    );
+    let config = CoordinatorConfig::load(Some(&yaml_file), no_env, &[]).unwrap();
+    assert_eq!(config.store.fstore_dir, Some("/tmp/nimble".to_string()));
+    // appends are batched only when asked to
+    assert_eq!(config.appends.max_batch_size, 1);
+
+    // invalid settings are rejected with the setting they concern
+    let res = CoordinatorConfig::load(None, no_env, &[("store.type", "table".to_string())]);
+    assert_eq!(
+      res,
+      Err(ConfigError::MissingSetting(
+        "store.storage_account".to_string()
+      ))
+    );
+    let res = CoordinatorConfig::load(None, no_env, &[("endorsers", "[::1]:9090".to_string())]);
+    assert!(matches!(res, Err(ConfigError::InvalidSetting(k, _)) if k == "endorsers"));
+    let env = |var: &str| match var {
+      "NIMBLE_COORDINATOR_CHANNELS" => Some("many".to_string()),
+      _ => None,
+    };
+    let res = CoordinatorConfig::load(None, env, &[]);
+    assert!(
+      matches!(res, Err(ConfigError::InvalidSetting(k, _)) if k == "NIMBLE_COORDINATOR_CHANNELS")
+    );
+    // the token of the control service may be kept in a file as well
+    let token_file = temp_file("token", "s3cr3t\n");
+    let env = |var: &str| match var {
+      "NIMBLE_COORDINATOR_CONTROL_TOKEN_FILE" => Some(token_file.clone()),
+      _ => None,
+    };
+    let config = CoordinatorConfig::load(None, env, &[]).unwrap();
+    assert_eq!(config.control.token, Some("s3cr3t".to_string()));
+    let res = CoordinatorConfig::load(None, no_env, &[("appends.max_batch_size", "0".to_string())]);
+    assert!(matches!(res, Err(ConfigError::InvalidSetting(k, _)) if k == "appends.max_batch_size"));
+    let res = CoordinatorConfig::load(None, no_env, &[("tls.client_ca", "ca.pem".to_string())]);
+    assert_eq!(
+      res,
+      Err(ConfigError::MissingSetting("tls.cert".to_string()))
+    );
+    let res = CoordinatorConfig::load(
+      None,
+      no_env,
+      &[
+        ("endorser_tls.cert", "coordinator.pem".to_string()),
+        ("endorser_tls.key", "coordinator.key".to_string()),
+      ],
+    );
+    assert_eq!(
+      res,
+      Err(ConfigError::MissingSetting("endorser_tls.ca".to_string()))
+    );
+
+    let bad_file = temp_file("coordinator.toml", "prot = 8080\n");
+    let res = CoordinatorConfig::load(Some(&bad_file), no_env, &[]);
+    assert!(matches!(res, Err(ConfigError::InvalidConfigFile(_))));
+    let res = CoordinatorConfig::load(
+      Some(&toml_file),
+      no_env,
+      &[("store.storage_master_key", "inline".to_string())],
+    );
+    assert!(matches!(res, Err(ConfigError::InvalidSecret(_))));
+
+    for file in [key_file, toml_file, yaml_file, bad_file, token_file] {
+      let _ = std::fs::remove_file(file);
+    }
+  }
+}
@@ -1,4 +1,9 @@
-use crate::errors::CoordinatorError;
+use crate::{
+  append_queue::{AppendQueues, Batch, PendingAppend},
+  endorser_tls::{verify_tls_certificate, EndorserConnector, EndorserTls},
+  errors::CoordinatorError,
+  metrics,
+};
 use ledger::{
   compute_aggregated_block_hash, compute_cut_diffs, compute_max_cut,
   errors::VerificationError,
@@ -7,26 +12,176 @@ use ledger::{
   Nonces, Receipt, Receipts, VerifierState,
 };
 use rand::random;
+use serde::{Deserialize, Serialize};
 use std::{
   collections::{HashMap, HashSet},
   convert::TryInto,
-  ops::Deref,
-  sync::{Arc, RwLock},
+  future::Future,
+  ops::{Deref, Range},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, RwLock,
+  },
+  time::{SystemTime, UNIX_EPOCH},
+};
+use store::backup::{BackupManifest, BackupWriter};
+use store::content::{
+  content_hash, decode_content_ref, encode_content_ref, filestore::FileContentStore,
+  in_memory::InMemoryContentStore, s3::S3ContentStore, ContentStore,
 };
 use store::ledger::{
-  azure_table::TableLedgerStore, filestore::FileStore, in_memory::InMemoryLedgerStore,
-  mongodb_cosmos::MongoCosmosLedgerStore, LedgerEntry, LedgerStore,
+  azure_table::TableLedgerStore,
+  cached::CachedLedgerStore,
+  encrypted::{EncryptedLedgerStore, KEY_SIZE as ENCRYPTION_KEY_SIZE},
+  faulty::{FaultRule, FaultyLedgerStore},
+  filestore::FileStore,
+  in_memory::InMemoryLedgerStore,
+  metered::MeteredLedgerStore,
+  mongodb_cosmos::MongoCosmosLedgerStore,
+  replicated::ReplicatedLedgerStore,
+  Lease, LedgerEntry, LedgerStore,
 };
 use store::{errors::LedgerStoreError, errors::StorageError};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use tonic::{
   transport::{Channel, Endpoint},
   Code, Status,
 };
+use tracing::{error, info, instrument, warn};
 
 use ledger::endorser_proto;
 
 const DEFAULT_NUM_GRPC_CHANNELS: usize = 1; // the default number of GRPC channels
+const DEFAULT_CONTENT_THRESHOLD: usize = 512; // bytes: blocks larger than this are stored out of line
+const BACKUP_LIST_PAGE_SIZE: usize = 100; // the number of ledgers listed at a time by a backup
+const PRUNE_LIST_PAGE_SIZE: usize = 100; // the number of ledgers listed at a time by pruning
+const COUNT_LIST_PAGE_SIZE: usize = 1000; // the number of ledgers listed at a time by a count
+
+// the fraction of its lease that a leader gives up early, so that it stops acting as the
+// leader before any other coordinator, whose clock may be ahead, can take over
+const LEASE_MARGIN_FRACTION: u64 = 4;
+
+// The election of a leader among the coordinators that share the ledger store
+struct Election {
+  // the name this coordinator holds the lease under
+  id: String,
+  // milliseconds: the time a lease lasts unless it is renewed
+  duration: u64,
+  // the lease this coordinator last wrote, if it believes it is the leader
+  lease: RwLock<Option<Lease>>,
+}
+
+impl Election {
+  fn get_lease(&self) -> Option<Lease> {
+    match self.lease.read() {
+      Ok(lease) => lease.clone(),
+      Err(_) => None,
+    }
+  }
+
+  fn set_lease(&self, lease: Option<Lease>) {
+    if let Ok(mut l) = self.lease.write() {
+      *l = lease;
+    }
+  }
+}
+
+fn now_millis() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+/// Why the endorsers of a view were chosen. The record follows the list of endorsers in the
+/// block of the view, so the receipts of the view change cover it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ViewChangeRecord {
+  /// who asked for the view change, i.e., an operator or the supervisor of the endorsers
+  pub initiator: String,
+  pub reason: String,
+  /// the coordinator that changed the view, if coordinators are elected
+  pub coordinator_id: Option<String>,
+  /// milliseconds since the Unix epoch
+  pub timestamp: u64,
+  /// the URIs of the endorsers that joined the view
+  pub added: Vec<String>,
+  /// the URIs of the endorsers that left the view
+  pub removed: Vec<String>,
+}
+
+/// The latest view change the coordinator started, which is in flight until it finishes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reconfiguration {
+  pub initiator: String,
+  pub reason: String,
+  /// milliseconds since the Unix epoch
+  pub started_at: u64,
+  pub finished_at: Option<u64>,
+  /// why the view change failed, if it did
+  pub error: Option<String>,
+}
+
+// Marks the view change it was created for as finished once dropped, so that a view change
+// that was given up on midway is not reported as in flight forever
+struct ReconfigurationGuard<'a> {
+  latest: &'a Mutex<Option<Reconfiguration>>,
+  started_at: u64,
+  error: Option<String>,
+}
+
+impl Drop for ReconfigurationGuard<'_> {
+  fn drop(&mut self) {
+    if let Ok(mut latest) = self.latest.lock() {
+      if let Some(r) = latest.as_mut() {
+        if r.started_at == self.started_at && r.finished_at.is_none() {
+          r.finished_at = Some(now_millis());
+          r.error = self.error.take();
+        }
+      }
+    }
+  }
+}
+
+// Readers of the list of endorsers in the block of a view ignore the bytes that follow it, so
+// blocks with and without a record are read alike
+fn encode_view_block(
+  endorsers: &EndorserHostnames,
+  record: &ViewChangeRecord,
+) -> Result<Block, CoordinatorError> {
+  let mut bytes = Vec::new();
+  for res in [
+    bincode::serialize_into(&mut bytes, endorsers),
+    bincode::serialize_into(&mut bytes, record),
+  ] {
+    if let Err(error) = res {
+      error!("Failed to serialize the view {:?}", error);
+      return Err(CoordinatorError::FailedToSerde);
+    }
+  }
+  Ok(Block::new(&bytes))
+}
+
+/// Returns the endorsers listed in the block of a view, and the record of the view change
+/// if the block has one
+pub fn decode_view_block(
+  block: &[u8],
+) -> Result<(EndorserHostnames, Option<ViewChangeRecord>), CoordinatorError> {
+  let mut reader = block;
+  let endorsers: EndorserHostnames = match bincode::deserialize_from(&mut reader) {
+    Ok(endorsers) => endorsers,
+    Err(error) => {
+      error!("Failed to deserialize the endorsers of a view {:?}", error);
+      return Err(CoordinatorError::FailedToSerde);
+    },
+  };
+  let record = if reader.is_empty() {
+    None
+  } else {
+    bincode::deserialize_from(&mut reader).ok()
+  };
+  Ok((endorsers, record))
+}
 
 struct EndorserClients {
   clients: Vec<endorser_proto::endorser_call_client::EndorserCallClient<Channel>>,
@@ -35,28 +190,204 @@ struct EndorserClients {
 
 type EndorserConnMap = HashMap<Vec<u8>, EndorserClients>;
 
+// The public key, a client, the URI and the ledger tail map of an endorser
+type EndorserState = (
+  Vec<u8>,
+  endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  String,
+  Vec<endorser_proto::LedgerTailMapEntry>,
+);
+
 type LedgerStoreRef = Arc<Box<dyn LedgerStore + Send + Sync>>;
+type ContentStoreRef = Arc<Box<dyn ContentStore + Send + Sync>>;
+
+// The ledgers that endorsers were found to lag behind on, each with the height from which the
+// endorser is to be brought up to date, waiting for the catch-up task
+#[derive(Default)]
+struct LaggingLedgers {
+  ledgers: Mutex<HashMap<(Vec<u8>, Handle), usize>>,
+  reported: tokio::sync::Notify,
+}
+
+impl LaggingLedgers {
+  fn report(&self, pk: &[u8], handle: &Handle, start: usize) {
+    if let Ok(mut ledgers) = self.ledgers.lock() {
+      let height = ledgers.entry((pk.to_vec(), *handle)).or_insert(start);
+      *height = std::cmp::min(*height, start);
+      metrics::LAGGING_LEDGERS.set(ledgers.len() as i64);
+    }
+    self.reported.notify_one();
+  }
+
+  fn take(&self) -> HashMap<(Vec<u8>, Handle), usize> {
+    match self.ledgers.lock() {
+      Ok(mut ledgers) => {
+        metrics::LAGGING_LEDGERS.set(0);
+        std::mem::take(&mut *ledgers)
+      },
+      Err(_) => HashMap::new(),
+    }
+  }
+}
+
+// The entries added to the ledger store whose receipts are being gathered from the endorsers,
+// each with the number of calls gathering them. A retried request that finds its entry without
+// receipts waits for them while the entry is listed, and endorses the entry itself otherwise.
+#[derive(Default)]
+struct EndorsingEntries {
+  entries: Mutex<HashMap<(Handle, usize), usize>>,
+}
+
+impl EndorsingEntries {
+  // Lists the entries at `heights` until the returned guard is dropped
+  fn mark(&self, handle: &Handle, heights: Range<usize>) -> Endorsing<'_> {
+    if let Ok(mut entries) = self.entries.lock() {
+      for height in heights.clone() {
+        *entries.entry((*handle, height)).or_insert(0) += 1;
+      }
+    }
+    Endorsing {
+      entries: self,
+      handle: *handle,
+      heights,
+    }
+  }
+
+  // Lists the entry at `height` unless it is listed already
+  fn try_mark(&self, handle: &Handle, height: usize) -> Option<Endorsing<'_>> {
+    let mut entries = self.entries.lock().ok()?;
+    if entries.contains_key(&(*handle, height)) {
+      return None;
+    }
+    entries.insert((*handle, height), 1);
+    Some(Endorsing {
+      entries: self,
+      handle: *handle,
+      heights: height..height + 1,
+    })
+  }
+}
+
+struct Endorsing<'a> {
+  entries: &'a EndorsingEntries,
+  handle: Handle,
+  heights: Range<usize>,
+}
+
+impl Drop for Endorsing<'_> {
+  fn drop(&mut self) {
+    if let Ok(mut entries) = self.entries.entries.lock() {
+      for height in self.heights.clone() {
+        let key = (self.handle, height);
+        match entries.get_mut(&key) {
+          Some(count) if *count > 1 => *count -= 1,
+          _ => {
+            entries.remove(&key);
+          },
+        }
+      }
+    }
+  }
+}
 
 pub struct CoordinatorState {
   pub(crate) ledger_store: LedgerStoreRef,
+  content_store: Option<ContentStoreRef>,
+  content_threshold: usize,
   conn_map: Arc<RwLock<EndorserConnMap>>,
   verifier_state: Arc<RwLock<VerifierState>>,
   num_grpc_channels: usize,
+  endorser_connect_timeout: std::time::Duration,
+  endorser_request_timeout: std::time::Duration,
+  // set if endorsers with https URIs can be connected to over TLS
+  endorser_tls: Option<Arc<EndorserTls>>,
+  // held for reading by operations that write to the ledger store, and for writing by a
+  // backup while it captures a consistent cut
+  append_gate: Arc<tokio::sync::RwLock<()>>,
+  // the number of entries kept in each ledger after its checkpoint, if ledgers are pruned
+  retain_entries: Option<usize>,
+  // the directory backups are written to, if backups are enabled
+  backup_dir: Option<std::path::PathBuf>,
+  // held by backups and pruning, so that a backup never reads entries as they are pruned
+  maintenance: tokio::sync::Mutex<()>,
+  // set if coordinators that share the ledger store elect a leader, which alone serves
+  election: Option<Election>,
+  // the ledgers that endorsers lag behind on, which are replayed to them in the background
+  lagging: Arc<LaggingLedgers>,
+  // the entries whose receipts are being gathered, so that retries can tell them from entries
+  // that were left without receipts
+  endorsing: EndorsingEntries,
+  // the latest view change, for reporting its status
+  reconfiguration: Mutex<Option<Reconfiguration>>,
+  // set while the coordinator is drained, during which it does not serve
+  draining: AtomicBool,
+  // set if appends to the same ledger are batched
+  append_queues: Option<Arc<AppendQueues>>,
+  // announces the ledgers whose new entries got their receipts, to the clients watching them
+  updates: broadcast::Sender<Handle>,
 }
 
 const ENDORSER_MPSC_CHANNEL_BUFFER: usize = 8; // limited by the number of endorsers
-const ENDORSER_CONNECT_TIMEOUT: u64 = 10; // seconds: the connect timeout to endorsres
-const ENDORSER_REQUEST_TIMEOUT: u64 = 10; // seconds: the request timeout to endorsers
+const DEFAULT_ENDORSER_CONNECT_TIMEOUT: u64 = 10; // seconds: the connect timeout to endorsers
+const DEFAULT_ENDORSER_REQUEST_TIMEOUT: u64 = 10; // seconds: the request timeout to endorsers
+const DEFAULT_CATCH_UP_BATCH_SIZE: usize = 64; // entries read from the ledger store at a time
+const APPEND_GAP_WAIT: u64 = 100; // milliseconds: the wait for a missing height in a batch
+const UPDATES_CHANNEL_BUFFER: usize = 1024; // updates kept for watchers that fall behind
 
 const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
 
+// Connects to the endorser at `uri`, over TLS if its scheme is https, and returns the
+// connector that kept the certificate the endorser presented
+async fn connect_endorser(
+  uri: &str,
+  tls: Option<&EndorserTls>,
+  connect_timeout: std::time::Duration,
+  request_timeout: std::time::Duration,
+) -> Result<(Channel, Option<EndorserConnector>), CoordinatorError> {
+  // the connector makes the TLS connection itself, so the channel is given a plain URI
+  let (plain_uri, connector) = match (uri.strip_prefix("https://"), tls) {
+    (Some(rest), Some(tls)) => (
+      format!("http://{}", rest),
+      Some(tls.connector(request_timeout)),
+    ),
+    (Some(_), None) => {
+      error!(
+        "No CA is set for connecting to the endorser {} over TLS",
+        uri
+      );
+      return Err(CoordinatorError::InvalidEndorserTls);
+    },
+    (None, _) => (uri.to_string(), None),
+  };
+  let endpoint = match Endpoint::from_shared(plain_uri) {
+    Ok(endpoint) => endpoint
+      .connect_timeout(connect_timeout)
+      .timeout(request_timeout),
+    Err(error) => {
+      error!("Failed to resolve the endorser host name: {:?}", error);
+      return Err(CoordinatorError::CannotResolveHostName);
+    },
+  };
+  let res = match &connector {
+    Some(connector) => endpoint.connect_with_connector(connector.clone()).await,
+    None => endpoint.connect().await,
+  };
+  match res {
+    Ok(channel) => Ok((channel, connector)),
+    Err(error) => {
+      error!("Failed to connect to the endorser {}: {:?}", uri, error);
+      Err(CoordinatorError::FailedToConnectToEndorser)
+    },
+  }
+}
+
 async fn get_public_key_with_retry(
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   request: endorser_proto::GetPublicKeyReq,
 ) -> Result<tonic::Response<endorser_proto::GetPublicKeyResp>, Status> {
   loop {
     let res = endorser_client
-      .get_public_key(tonic::Request::new(request.clone()))
+      .get_public_key(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -82,7 +413,7 @@ async fn new_ledger_with_retry(
 ) -> Result<tonic::Response<endorser_proto::NewLedgerResp>, Status> {
   loop {
     let res = endorser_client
-      .new_ledger(tonic::Request::new(request.clone()))
+      .new_ledger(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -108,7 +439,90 @@ async fn append_with_retry(
 ) -> Result<tonic::Response<endorser_proto::AppendResp>, Status> {
   loop {
     let res = endorser_client
-      .append(tonic::Request::new(request.clone()))
+      .append(telemetry::request(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return Ok(resp);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+// Sends a batch of appends to an endorser, or the appends one at a time to an endorser that
+// does not implement batches, e.g., an older one. Returns the receipts of the appends the
+// endorser made, and the error that stopped it, if any.
+async fn append_batch_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  request: endorser_proto::AppendBatchReq,
+) -> (Vec<Vec<u8>>, Option<Status>) {
+  let status = loop {
+    let res = endorser_client
+      .append_batch(telemetry::request(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return (resp.into_inner().receipts, None);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          _ => {
+            break status;
+          },
+        };
+      },
+    };
+  };
+  if status.code() != Code::Unimplemented {
+    return (Vec::new(), Some(status));
+  }
+
+  let endorser_proto::AppendBatchReq {
+    handle,
+    expected_height,
+    entries,
+  } = request;
+  let mut receipts = Vec::new();
+  for (i, entry) in entries.into_iter().enumerate() {
+    let res = append_with_retry(
+      endorser_client,
+      endorser_proto::AppendReq {
+        handle: handle.clone(),
+        block_hash: entry.block_hash,
+        expected_height: expected_height + i as u64,
+        block: entry.block,
+        nonces: entry.nonces,
+      },
+    )
+    .await;
+    match res {
+      Ok(resp) => receipts.push(resp.into_inner().receipt),
+      Err(status) => return (receipts, Some(status)),
+    }
+  }
+  (receipts, None)
+}
+
+async fn delete_ledger_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  request: endorser_proto::DeleteLedgerReq,
+) -> Result<tonic::Response<endorser_proto::DeleteLedgerResp>, Status> {
+  loop {
+    let res = endorser_client
+      .delete_ledger(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -134,7 +548,7 @@ async fn read_latest_with_retry(
 ) -> Result<tonic::Response<endorser_proto::ReadLatestResp>, Status> {
   loop {
     let res = endorser_client
-      .read_latest(tonic::Request::new(request.clone()))
+      .read_latest(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -164,7 +578,7 @@ async fn initialize_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::InitializeStateResp>, Status> {
   loop {
     let res = endorser_client
-      .initialize_state(tonic::Request::new(endorser_proto::InitializeStateReq {
+      .initialize_state(telemetry::request(endorser_proto::InitializeStateReq {
         group_identity: group_identity.clone(),
         ledger_tail_map: ledger_tail_map.deref().clone(),
         view_tail_metablock: view_tail_metablock.clone(),
@@ -196,7 +610,33 @@ async fn finalize_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::FinalizeStateResp>, Status> {
   loop {
     let res = endorser_client
-      .finalize_state(tonic::Request::new(request.clone()))
+      .finalize_state(telemetry::request(request.clone()))
+      .await;
+    match res {
+      Ok(resp) => {
+        return Ok(resp);
+      },
+      Err(status) => {
+        match status.code() {
+          Code::ResourceExhausted => {
+            continue;
+          },
+          _ => {
+            return Err(status);
+          },
+        };
+      },
+    };
+  }
+}
+
+async fn advance_view_with_retry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  request: endorser_proto::AdvanceViewReq,
+) -> Result<tonic::Response<endorser_proto::AdvanceViewResp>, Status> {
+  loop {
+    let res = endorser_client
+      .advance_view(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -222,7 +662,7 @@ async fn read_state_with_retry(
 ) -> Result<tonic::Response<endorser_proto::ReadStateResp>, Status> {
   loop {
     let res = endorser_client
-      .read_state(tonic::Request::new(request.clone()))
+      .read_state(telemetry::request(request.clone()))
       .await;
     match res {
       Ok(resp) => {
@@ -252,7 +692,7 @@ async fn activate_with_retry(
 ) -> Result<tonic::Response<endorser_proto::ActivateResp>, Status> {
   loop {
     let res = endorser_client
-      .activate(tonic::Request::new(endorser_proto::ActivateReq {
+      .activate(telemetry::request(endorser_proto::ActivateReq {
         old_config: old_config.clone(),
         new_config: new_config.clone(),
         ledger_tail_maps: ledger_tail_maps.deref().clone(),
@@ -278,80 +718,173 @@ async fn activate_with_retry(
   }
 }
 
+// Replays the entries of a ledger from `start` to `end` to an endorser, and returns the number
+// of entries it signed. Entries are read from the ledger store `batch_size` at a time. An entry
+// that the endorser already holds is skipped, since the endorser may have been brought up to
+// date by someone else in the meantime, unless it is the endorser's tail, which it signs again.
 async fn update_endorser(
   ledger_store: LedgerStoreRef,
   endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
   handle: NimbleDigest,
   start: usize,
   end: usize,
-) -> Result<(), Status> {
-  for idx in start..=end {
-    let ledger_entry = {
-      let res = ledger_store.read_ledger_by_index(&handle, idx).await;
-      if res.is_err() {
-        eprintln!("Failed to read ledger by index {:?}", res);
-        return Err(Status::aborted("Failed to read ledger by index"));
-      }
-      res.unwrap()
-    };
-
-    let receipt = if idx == 0 {
-      let endorser_proto::NewLedgerResp { receipt } = new_ledger_with_retry(
-        endorser_client,
-        endorser_proto::NewLedgerReq {
-          handle: handle.to_bytes(),
-          block_hash: compute_aggregated_block_hash(
-            &ledger_entry.get_block().hash().to_bytes(),
-            &ledger_entry.get_nonces().hash().to_bytes(),
-          )
-          .to_bytes(),
-          block: ledger_entry.get_block().to_bytes(),
+  batch_size: usize,
+) -> Result<usize, Status> {
+  let mut num_replayed = 0;
+  let mut batch_start = start;
+  while batch_start <= end {
+    let batch_end = std::cmp::min(end, batch_start + batch_size - 1);
+    let reads = (batch_start..=batch_end)
+      .map(|idx| {
+        let ledger_store = ledger_store.clone();
+        tokio::spawn(async move { ledger_store.read_ledger_by_index(&handle, idx).await })
+      })
+      .collect::<Vec<_>>();
+
+    for (idx, read) in (batch_start..=batch_end).zip(reads) {
+      let ledger_entry = match read.await {
+        Ok(Ok(ledger_entry)) => ledger_entry,
+        res => {
+          error!("Failed to read ledger by index {:?}", res);
+          return Err(Status::aborted("Failed to read ledger by index"));
         },
-      )
-      .await?
-      .into_inner();
-      receipt
-    } else {
-      let endorser_proto::AppendResp { receipt } = append_with_retry(
-        endorser_client,
-        endorser_proto::AppendReq {
-          handle: handle.to_bytes(),
-          block_hash: compute_aggregated_block_hash(
-            &ledger_entry.get_block().hash().to_bytes(),
-            &ledger_entry.get_nonces().hash().to_bytes(),
-          )
-          .to_bytes(),
-          expected_height: idx as u64,
-          block: ledger_entry.get_block().to_bytes(),
-          nonces: ledger_entry.get_nonces().to_bytes(),
+      };
+      match replay_entry(endorser_client, &handle, idx, &ledger_entry).await {
+        Ok(receipt) => {
+          num_replayed += 1;
+          attach_receipt(&ledger_store, &handle, idx, &receipt).await;
         },
-      )
-      .await?
-      .into_inner();
+        Err(status)
+          if matches!(
+            status.code(),
+            Code::AlreadyExists | Code::InvalidArgument | Code::PermissionDenied
+          ) =>
+        {
+          continue;
+        },
+        Err(status) => return Err(status),
+      }
+    }
+    batch_start = batch_end + 1;
+  }
 
-      receipt
-    };
+  Ok(num_replayed)
+}
 
-    let res = Receipt::from_bytes(&receipt);
-    if res.is_ok() {
-      let receipt_rs = res.unwrap();
-      let mut receipts = Receipts::new();
-      receipts.add(&receipt_rs);
-      let res = ledger_store
-        .attach_ledger_receipts(&handle, idx, &receipts)
-        .await;
-      if res.is_err() {
-        eprintln!(
-          "Failed to attach ledger receipt to the ledger store ({:?})",
-          res
-        );
-      }
-    } else {
-      eprintln!("Failed to parse a receipt ({:?})", res);
+// Sends a ledger entry at height `idx` to an endorser, and returns the endorser's receipt
+async fn replay_entry(
+  endorser_client: &mut endorser_proto::endorser_call_client::EndorserCallClient<Channel>,
+  handle: &NimbleDigest,
+  idx: usize,
+  ledger_entry: &LedgerEntry,
+) -> Result<Vec<u8>, Status> {
+  let receipt = if idx == 0 {
+    let endorser_proto::NewLedgerResp { receipt } = new_ledger_with_retry(
+      endorser_client,
+      endorser_proto::NewLedgerReq {
+        handle: handle.to_bytes(),
+        block_hash: compute_aggregated_block_hash(
+          &content_hash(ledger_entry.get_block()).to_bytes(),
+          &ledger_entry.get_nonces().hash().to_bytes(),
+        )
+        .to_bytes(),
+        block: ledger_entry.get_block().to_bytes(),
+      },
+    )
+    .await?
+    .into_inner();
+    receipt
+  } else if ledger_entry.is_tombstone() {
+    let endorser_proto::DeleteLedgerResp { receipt } = delete_ledger_with_retry(
+      endorser_client,
+      endorser_proto::DeleteLedgerReq {
+        handle: handle.to_bytes(),
+        expected_height: idx as u64,
+      },
+    )
+    .await?
+    .into_inner();
+    receipt
+  } else {
+    let endorser_proto::AppendResp { receipt } = append_with_retry(
+      endorser_client,
+      endorser_proto::AppendReq {
+        handle: handle.to_bytes(),
+        block_hash: compute_aggregated_block_hash(
+          &content_hash(ledger_entry.get_block()).to_bytes(),
+          &ledger_entry.get_nonces().hash().to_bytes(),
+        )
+        .to_bytes(),
+        expected_height: idx as u64,
+        block: ledger_entry.get_block().to_bytes(),
+        nonces: ledger_entry.get_nonces().to_bytes(),
+      },
+    )
+    .await?
+    .into_inner();
+    receipt
+  };
+
+  Ok(receipt)
+}
+
+async fn attach_receipt(
+  ledger_store: &LedgerStoreRef,
+  handle: &Handle,
+  idx: usize,
+  receipt: &[u8],
+) {
+  let res = Receipt::from_bytes(receipt);
+  if let Ok(receipt_rs) = res {
+    let mut receipts = Receipts::new();
+    receipts.add(&receipt_rs);
+    let res = ledger_store
+      .attach_ledger_receipts(handle, idx, &receipts)
+      .await;
+    if res.is_err() {
+      error!(
+        "Failed to attach ledger receipt to the ledger store ({:?})",
+        res
+      );
     }
+  } else {
+    error!("Failed to parse a receipt ({:?})", res);
   }
+}
+
+fn tail_maps(states: &[EndorserState]) -> Vec<endorser_proto::LedgerTailMap> {
+  states
+    .iter()
+    .map(
+      |(_pk, _client, _uri, entries)| endorser_proto::LedgerTailMap {
+        entries: entries.clone(),
+      },
+    )
+    .collect()
+}
 
-  Ok(())
+// Returns each ledger of `max_cut` that an endorser with the ledger tail map `entries` lags
+// behind on, along with the first and the last heights it is missing
+fn lagging_ledgers(
+  entries: &[endorser_proto::LedgerTailMapEntry],
+  max_cut: &[endorser_proto::LedgerTailMapEntry],
+) -> Vec<(Handle, usize, usize)> {
+  let heights = entries
+    .iter()
+    .map(|entry| (entry.handle.clone(), entry.height as usize))
+    .collect::<HashMap<Vec<u8>, usize>>();
+  max_cut
+    .iter()
+    .filter_map(|entry| {
+      let start = match heights.get(&entry.handle) {
+        Some(height) if *height >= entry.height as usize => return None,
+        Some(height) => height + 1,
+        None => 0,
+      };
+      let handle = NimbleDigest::from_bytes(&entry.handle).ok()?;
+      Some((handle, start, entry.height as usize))
+    })
+    .collect()
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -368,16 +901,19 @@ fn process_error(
   handle: Option<&NimbleDigest>,
   status: &Status,
 ) -> CoordinatorAction {
+  metrics::ENDORSER_ERRORS
+    .with_label_values(&[endorser, &metrics::code_label(status.code())])
+    .inc();
   match status.code() {
     Code::Aborted => {
-      eprintln!("operation aborted to due to ledger store");
+      warn!("operation aborted to due to ledger store");
       CoordinatorAction::DoNothing
     },
     Code::AlreadyExists => {
       if let Some(h) = handle {
-        eprintln!("ledger {:?} already exists in endorser {}", h, endorser);
+        warn!("ledger {:?} already exists in endorser {}", h, endorser);
       } else {
-        eprintln!(
+        warn!(
           "the requested operation was already done in endorser {}",
           endorser
         );
@@ -385,25 +921,25 @@ fn process_error(
       CoordinatorAction::IncrementReceipt
     },
     Code::Cancelled => {
-      eprintln!("endorser {} is locked", endorser);
+      warn!("endorser {} is locked", endorser);
       CoordinatorAction::DoNothing
     },
     Code::FailedPrecondition | Code::NotFound => {
       if let Some(h) = handle {
-        eprintln!("ledger {:?} lags behind in endorser {}", h, endorser);
+        warn!("ledger {:?} lags behind in endorser {}", h, endorser);
       } else {
-        eprintln!("a ledger lags behind in endorser {}", endorser);
+        warn!("a ledger lags behind in endorser {}", endorser);
       }
       CoordinatorAction::UpdateEndorser
     },
     Code::InvalidArgument => {
       if let Some(h) = handle {
-        eprintln!(
+        warn!(
           "the requested height for ledger {:?} in endorser {} is too small",
           h, endorser
         );
       } else {
-        eprintln!(
+        warn!(
           "the requested height for a ledger in endorser {} is too small",
           endorser
         );
@@ -412,12 +948,12 @@ fn process_error(
     },
     Code::OutOfRange => {
       if let Some(h) = handle {
-        eprintln!(
+        warn!(
           "the requested height for ledger {:?} in endorser {} is out of range",
           h, endorser
         );
       } else {
-        eprintln!(
+        warn!(
           "the requested height for a ledger in endorser {} is out of range",
           endorser
         );
@@ -426,62 +962,425 @@ fn process_error(
     },
 
     Code::Unavailable => {
-      eprintln!("the endorser is already finalized");
+      warn!("the endorser is already finalized");
       CoordinatorAction::DoNothing
     },
     Code::Unimplemented => {
-      eprintln!("the endorser is not initialized");
+      warn!("the endorser is not initialized");
+      CoordinatorAction::DoNothing
+    },
+    Code::PermissionDenied => {
+      if let Some(h) = handle {
+        warn!("ledger {:?} is deleted in endorser {}", h, endorser);
+      } else {
+        warn!("a ledger is deleted in endorser {}", endorser);
+      }
       CoordinatorAction::DoNothing
     },
     Code::ResourceExhausted => CoordinatorAction::Retry,
     Code::Internal | Code::Unknown => CoordinatorAction::RemoveEndorser,
     _ => {
-      eprintln!("Unhandled status={:?}", status);
+      error!("Unhandled status={:?}", status);
       CoordinatorAction::DoNothing
     },
   }
 }
 
+// Handles an append that an endorser failed, and returns the error to count against it, if
+// any. An endorser that lags behind on the ledger is brought up to date in the background,
+// so that the append does not wait for it.
+fn append_error(
+  endorser: &str,
+  pk: &[u8],
+  handle: &Handle,
+  status: &Status,
+  lagging: &LaggingLedgers,
+) -> Option<CoordinatorError> {
+  match process_error(endorser, Some(handle), status) {
+    CoordinatorAction::UpdateEndorser => {
+      let height_to_start = {
+        if status.code() == Code::NotFound {
+          0
+        } else {
+          let bytes = status.details();
+          let ledger_height = u64::from_le_bytes(bytes[0..].try_into().unwrap()) as usize;
+          ledger_height.checked_add(1).unwrap()
+        }
+      };
+      lagging.report(pk, handle, height_to_start);
+      None
+    },
+    CoordinatorAction::RemoveEndorser => Some(CoordinatorError::UnexpectedError),
+    CoordinatorAction::IncrementReceipt => Some(CoordinatorError::LedgerAlreadyExists),
+    _ => {
+      if status.code() == Code::PermissionDenied {
+        Some(CoordinatorError::LedgerDeleted)
+      } else {
+        Some(CoordinatorError::FailedToAppendLedger)
+      }
+    },
+  }
+}
+
+// Puts the ledger store behind encryption of blocks and a cache of ledger tails and recent
+// entries, if enabled. The cache sits on top, so it holds plaintext, and the latency of the
+// ledger store is measured below both. Faults, which only tests inject, are injected right
+// above the ledger store, so they look like its own errors to every other layer.
+fn wrap_ledger_store<S: LedgerStore + Send + Sync + 'static>(
+  ledger_store: S,
+  encryption_key: Option<&[u8]>,
+  cache_capacity: Option<usize>,
+  faults: &[FaultRule],
+) -> LedgerStoreRef {
+  if faults.is_empty() {
+    return wrap_metered_store(
+      MeteredLedgerStore::new(ledger_store),
+      encryption_key,
+      cache_capacity,
+    );
+  }
+  let ledger_store = FaultyLedgerStore::new(ledger_store);
+  for rule in faults {
+    ledger_store.inject(rule.clone());
+  }
+  wrap_metered_store(
+    MeteredLedgerStore::new(ledger_store),
+    encryption_key,
+    cache_capacity,
+  )
+}
+
+fn wrap_metered_store<S: LedgerStore + Send + Sync + 'static>(
+  ledger_store: MeteredLedgerStore<S>,
+  encryption_key: Option<&[u8]>,
+  cache_capacity: Option<usize>,
+) -> LedgerStoreRef {
+  match (encryption_key, cache_capacity) {
+    (Some(key), Some(capacity)) => Arc::new(Box::new(CachedLedgerStore::new(
+      EncryptedLedgerStore::new(ledger_store, key).unwrap(),
+      capacity,
+    ))),
+    (Some(key), None) => Arc::new(Box::new(
+      EncryptedLedgerStore::new(ledger_store, key).unwrap(),
+    )),
+    (None, Some(capacity)) => Arc::new(Box::new(CachedLedgerStore::new(ledger_store, capacity))),
+    (None, None) => Arc::new(Box::new(ledger_store)),
+  }
+}
+
+// the type of a replica of a replicated ledger store and the arguments to open it with
+type ReplicaArgs = (String, HashMap<String, String>);
+
+// Parses the replicas of a replicated ledger store, given as `type[:KEY=VALUE,...]` separated
+// by `;`, where the keys override the ledger store arguments of the coordinator
+fn parse_replicas(
+  spec: &str,
+  args: &HashMap<String, String>,
+) -> Result<Vec<ReplicaArgs>, CoordinatorError> {
+  let mut replicas = Vec::new();
+  for replica in spec.split(';').filter(|r| !r.trim().is_empty()) {
+    let (replica_type, overrides) = match replica.trim().split_once(':') {
+      Some((t, o)) => (t, o),
+      None => (replica.trim(), ""),
+    };
+    if !["memory", "filestore", "table", "mongodb_cosmos"].contains(&replica_type) {
+      error!("Unknown replica type {}", replica_type);
+      return Err(CoordinatorError::InvalidReplicas);
+    }
+
+    let mut replica_args = args.clone();
+    for kv in overrides.split(',').filter(|kv| !kv.is_empty()) {
+      match kv.split_once('=') {
+        Some((k, v)) => {
+          replica_args.insert(k.to_string(), v.to_string());
+        },
+        None => {
+          error!("Invalid replica argument {}", kv);
+          return Err(CoordinatorError::InvalidReplicas);
+        },
+      }
+    }
+    replicas.push((replica_type.to_string(), replica_args));
+  }
+
+  if replicas.is_empty() {
+    error!("A replicated ledger store needs at least one replica");
+    return Err(CoordinatorError::InvalidReplicas);
+  }
+  Ok(replicas)
+}
+
+async fn open_replica(
+  replica_type: &str,
+  args: &HashMap<String, String>,
+) -> Box<dyn LedgerStore + Send + Sync> {
+  match replica_type {
+    "mongodb_cosmos" => Box::new(MongoCosmosLedgerStore::new(args).await.unwrap()),
+    "table" => Box::new(TableLedgerStore::new(args).await.unwrap()),
+    "filestore" => Box::new(FileStore::new(args).await.unwrap()),
+    _ => Box::new(InMemoryLedgerStore::new()),
+  }
+}
+
 impl CoordinatorState {
   pub async fn new(
     ledger_store_type: &str,
     args: &HashMap<String, String>,
     num_grpc_channels_opt: Option<usize>,
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    Self::open(ledger_store_type, args, num_grpc_channels_opt, &[]).await
+  }
+
+  /// Creates a coordinator whose ledger store fails as `faults` say, to test how failures of
+  /// the ledger store are handled
+  #[cfg(test)]
+  pub async fn new_with_faults(
+    ledger_store_type: &str,
+    args: &HashMap<String, String>,
+    faults: &[FaultRule],
+  ) -> Result<CoordinatorState, CoordinatorError> {
+    Self::open(ledger_store_type, args, None, faults).await
+  }
+
+  async fn open(
+    ledger_store_type: &str,
+    args: &HashMap<String, String>,
+    num_grpc_channels_opt: Option<usize>,
+    faults: &[FaultRule],
   ) -> Result<CoordinatorState, CoordinatorError> {
     let num_grpc_channels = match num_grpc_channels_opt {
       Some(n) => n,
       None => DEFAULT_NUM_GRPC_CHANNELS,
     };
-    let coordinator = match ledger_store_type {
-      "mongodb_cosmos" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(MongoCosmosLedgerStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
-      },
-      "table" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(TableLedgerStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
+    let cache_capacity = match args.get("LEDGER_CACHE_SIZE") {
+      None => None,
+      Some(c) => match c.parse::<usize>() {
+        Ok(c) if c > 0 => Some(c),
+        _ => {
+          error!("Invalid ledger cache size {}", c);
+          return Err(CoordinatorError::InvalidLedgerCacheSize);
+        },
       },
-      "filestore" => CoordinatorState {
-        ledger_store: Arc::new(Box::new(FileStore::new(args).await.unwrap())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
+    };
+    // The key is kept base64url-encoded in a file so that it does not show up in the
+    // command line of the coordinator
+    let encryption_key = match args.get("LEDGER_KEY_FILE") {
+      None => None,
+      Some(path) => {
+        let key = std::fs::read_to_string(path)
+          .ok()
+          .and_then(|k| base64_url::decode(k.trim()).ok());
+        match key {
+          Some(k) if k.len() == ENCRYPTION_KEY_SIZE => Some(k),
+          _ => {
+            error!(
+              "The ledger key file {} must hold a base64url-encoded {}-byte key",
+              path, ENCRYPTION_KEY_SIZE
+            );
+            return Err(CoordinatorError::InvalidLedgerKey);
+          },
+        }
       },
-      _ => CoordinatorState {
-        ledger_store: Arc::new(Box::new(InMemoryLedgerStore::new())),
-        conn_map: Arc::new(RwLock::new(HashMap::new())),
-        verifier_state: Arc::new(RwLock::new(VerifierState::new())),
-        num_grpc_channels,
+    };
+    // Blocks moved to a content store would be kept there in the clear
+    if encryption_key.is_some() && args.contains_key("CONTENT_STORE") {
+      error!("A content store cannot be used with a ledger key file");
+      return Err(CoordinatorError::InvalidContentStore);
+    }
+    let key = encryption_key.as_deref();
+    let ledger_store: LedgerStoreRef = match ledger_store_type {
+      "mongodb_cosmos" => wrap_ledger_store(
+        MongoCosmosLedgerStore::new(args).await.unwrap(),
+        key,
+        cache_capacity,
+        faults,
+      ),
+      "table" => wrap_ledger_store(
+        TableLedgerStore::new(args).await.unwrap(),
+        key,
+        cache_capacity,
+        faults,
+      ),
+      "filestore" => wrap_ledger_store(
+        FileStore::new(args).await.unwrap(),
+        key,
+        cache_capacity,
+        faults,
+      ),
+      "replicated" => {
+        let spec = match args.get("REPLICAS") {
+          Some(spec) => spec,
+          None => {
+            error!("A replicated ledger store needs its replicas");
+            return Err(CoordinatorError::InvalidReplicas);
+          },
+        };
+        let mut replicas = Vec::new();
+        for (replica_type, replica_args) in parse_replicas(spec, args)? {
+          replicas.push(open_replica(&replica_type, &replica_args).await);
+        }
+        // the list of replicas is never empty
+        wrap_ledger_store(
+          ReplicatedLedgerStore::new(replicas).unwrap(),
+          key,
+          cache_capacity,
+          faults,
+        )
       },
+      _ => wrap_ledger_store(InMemoryLedgerStore::new(), key, cache_capacity, faults),
     };
 
-    let res = coordinator.ledger_store.read_view_ledger_tail().await;
-    if res.is_err() {
-      eprintln!("Failed to read the view ledger tail {:?}", res);
+    // Blocks are kept out of line only if a content store is configured
+    let content_store: Option<ContentStoreRef> = match args.get("CONTENT_STORE") {
+      None => None,
+      Some(content_store_type) => match content_store_type.as_str() {
+        "memory" => Some(Arc::new(Box::new(InMemoryContentStore::new()))),
+        "filestore" => Some(Arc::new(Box::new(
+          FileContentStore::new(args).await.unwrap(),
+        ))),
+        "s3" => Some(Arc::new(Box::new(S3ContentStore::new(args).await.unwrap()))),
+        _ => {
+          error!("Unknown content store type {}", content_store_type);
+          return Err(CoordinatorError::InvalidContentStore);
+        },
+      },
+    };
+    let retain_entries = match args.get("RETAIN_ENTRIES") {
+      None => None,
+      Some(r) => match r.parse::<usize>() {
+        Ok(r) => Some(r),
+        Err(_) => {
+          error!("Invalid number of entries to retain {}", r);
+          return Err(CoordinatorError::InvalidRetention);
+        },
+      },
+    };
+    let content_threshold = match args.get("CONTENT_THRESHOLD") {
+      None => DEFAULT_CONTENT_THRESHOLD,
+      Some(t) => match t.parse::<usize>() {
+        Ok(t) => t,
+        Err(_) => {
+          error!("Invalid content threshold {}", t);
+          return Err(CoordinatorError::InvalidContentStore);
+        },
+      },
+    };
+
+    let mut endorser_timeouts = Vec::new();
+    for (key, default) in [
+      ("ENDORSER_CONNECT_TIMEOUT", DEFAULT_ENDORSER_CONNECT_TIMEOUT),
+      ("ENDORSER_REQUEST_TIMEOUT", DEFAULT_ENDORSER_REQUEST_TIMEOUT),
+    ] {
+      let secs = match args.get(key) {
+        None => default,
+        Some(t) => match t.parse::<u64>() {
+          Ok(t) if t > 0 => t,
+          _ => {
+            error!("Invalid endorser timeout {}", t);
+            return Err(CoordinatorError::InvalidTimeout);
+          },
+        },
+      };
+      endorser_timeouts.push(std::time::Duration::from_secs(secs));
+    }
+
+    let append_queues = match args.get("APPEND_BATCH_SIZE") {
+      // appends are batched only when asked to
+      None => None,
+      Some(s) => match s.parse::<usize>() {
+        // batches of one append are not worth queueing
+        Ok(1) => None,
+        Ok(s) if s > 1 => Some(Arc::new(AppendQueues::new(s))),
+        _ => {
+          error!("Invalid append batch size {}", s);
+          return Err(CoordinatorError::InvalidBatchSize);
+        },
+      },
+    };
+
+    let endorser_tls = match args.get("ENDORSER_TLS_CA") {
+      None => None,
+      Some(ca) => {
+        let identity = match (args.get("ENDORSER_TLS_CERT"), args.get("ENDORSER_TLS_KEY")) {
+          (Some(cert), Some(key)) => Some((cert.as_str(), key.as_str())),
+          _ => None,
+        };
+        Some(Arc::new(EndorserTls::from_files(ca, identity)?))
+      },
+    };
+
+    let election = match args.get("LEASE_DURATION") {
+      None => None,
+      Some(d) => match d.parse::<u64>() {
+        Ok(duration) if duration > 0 => Some(Election {
+          id: match args.get("COORDINATOR_ID") {
+            Some(id) => id.clone(),
+            None => format!("{:016x}", random::<u64>()),
+          },
+          duration,
+          lease: RwLock::new(None),
+        }),
+        _ => {
+          error!("Invalid lease duration {}", d);
+          return Err(CoordinatorError::InvalidLease);
+        },
+      },
+    };
+
+    let coordinator = CoordinatorState {
+      ledger_store,
+      content_store,
+      content_threshold,
+      conn_map: Arc::new(RwLock::new(HashMap::new())),
+      verifier_state: Arc::new(RwLock::new(VerifierState::new())),
+      num_grpc_channels,
+      endorser_connect_timeout: endorser_timeouts[0],
+      endorser_request_timeout: endorser_timeouts[1],
+      endorser_tls,
+      append_gate: Arc::new(tokio::sync::RwLock::new(())),
+      retain_entries,
+      backup_dir: args.get("BACKUP_DIR").map(std::path::PathBuf::from),
+      maintenance: tokio::sync::Mutex::new(()),
+      election,
+      lagging: Arc::new(LaggingLedgers::default()),
+      endorsing: EndorsingEntries::default(),
+      reconfiguration: Mutex::new(None),
+      draining: AtomicBool::new(false),
+      append_queues,
+      updates: broadcast::channel(UPDATES_CHANNEL_BUFFER).0,
+    };
+
+    // A coordinator that has to be elected loads the view ledger only once it is the leader,
+    // since the leader may change the endorsers until then
+    if coordinator.election.is_some() {
+      if let Err(error) = coordinator.ledger_store.read_lease().await {
+        error!("The ledger store cannot hold a lease ({:?})", error);
+        return Err(CoordinatorError::InvalidLease);
+      }
+      return Ok(coordinator);
+    }
+
+    coordinator.load_view_ledger().await?;
+    Ok(coordinator)
+  }
+
+  // Connects to the endorsers of the latest view in the ledger store and replays the view
+  // ledger into the verifier state, finishing the latest view change if it was interrupted
+  async fn load_view_ledger(&self) -> Result<(), CoordinatorError> {
+    if let Ok(mut vs) = self.verifier_state.write() {
+      *vs = VerifierState::new();
+    } else {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
+    }
+    if let Ok(mut conn_map) = self.conn_map.write() {
+      conn_map.clear();
+    } else {
+      return Err(CoordinatorError::FailedToAcquireWriteLock);
+    }
+
+    let res = self.ledger_store.read_view_ledger_tail().await;
+    if res.is_err() {
+      error!("Failed to read the view ledger tail {:?}", res);
       return Err(CoordinatorError::FailedToReadViewLedger);
     }
 
@@ -491,31 +1390,28 @@ impl CoordinatorState {
       let view_ledger_head = if tail_height == 1 {
         view_ledger_tail.clone()
       } else {
-        let res = coordinator
-          .ledger_store
-          .read_view_ledger_by_index(1usize)
-          .await;
+        let res = self.ledger_store.read_view_ledger_by_index(1usize).await;
         match res {
           Ok(l) => l,
           Err(e) => {
-            eprintln!("Failed to read the view ledger head {:?}", e);
+            error!("Failed to read the view ledger head {:?}", e);
             return Err(CoordinatorError::FailedToReadViewLedger);
           },
         }
       };
-      if let Ok(mut vs) = coordinator.verifier_state.write() {
+      if let Ok(mut vs) = self.verifier_state.write() {
         vs.set_group_identity(view_ledger_head.get_block().hash());
       } else {
         return Err(CoordinatorError::FailedToAcquireWriteLock);
       }
 
       // Connect to current endorsers
-      let curr_endorsers = coordinator
+      let curr_endorsers = self
         .connect_to_existing_endorsers(&view_ledger_tail.get_block().to_bytes())
         .await?;
 
       // Check if the latest view change was completed
-      let res = if let Ok(mut vs) = coordinator.verifier_state.write() {
+      let res = if let Ok(mut vs) = self.verifier_state.write() {
         vs.apply_view_change(
           &view_ledger_tail.get_block().to_bytes(),
           &view_ledger_tail.get_receipts().to_bytes(),
@@ -527,12 +1423,12 @@ impl CoordinatorState {
       if let Err(error) = res {
         // Collect receipts again!
         if error == VerificationError::InsufficientReceipts {
-          let res = coordinator
+          let res = self
             .ledger_store
             .read_view_ledger_by_index(tail_height - 1)
             .await;
           if res.is_err() {
-            eprintln!(
+            error!(
               "Failed to read the view ledger entry at index {} ({:?})",
               tail_height - 1,
               res
@@ -540,24 +1436,32 @@ impl CoordinatorState {
             return Err(CoordinatorError::FailedToReadViewLedger);
           }
           let prev_view_ledger_entry = res.unwrap();
-          let prev_endorsers = coordinator
+          // the endorsers of both views are connected already, and stay active
+          let prev_endorsers = self
             .connect_to_existing_endorsers(&prev_view_ledger_entry.get_block().to_bytes())
             .await?;
-          let res = coordinator
+          let (prev_config, _record) =
+            decode_view_block(&prev_view_ledger_entry.get_block().to_bytes())?;
+          let (staying, joining): (EndorserHostnames, EndorserHostnames) = curr_endorsers
+            .iter()
+            .cloned()
+            .partition(|(pk, _uri)| prev_config.iter().any(|(prev_pk, _)| prev_pk == pk));
+          let res = self
             .apply_view_change(
               &prev_endorsers,
-              &curr_endorsers,
+              &staying,
+              &joining,
               &prev_view_ledger_entry,
               view_ledger_tail.get_block(),
               tail_height,
             )
             .await;
           if let Err(error) = res {
-            eprintln!("Failed to re-apply view change {:?}", error);
+            error!("Failed to re-apply view change {:?}", error);
             return Err(error);
           }
         } else {
-          eprintln!(
+          error!(
             "Failed to apply view change at the tail {} ({:?})",
             tail_height, error
           );
@@ -566,11 +1470,9 @@ impl CoordinatorState {
       }
 
       // Remove endorsers that don't have the latest view
-      let res = coordinator
-        .filter_endorsers(&curr_endorsers, tail_height)
-        .await;
+      let res = self.filter_endorsers(&curr_endorsers, tail_height).await;
       if let Err(error) = res {
-        eprintln!(
+        error!(
           "Failed to filter the endorsers with the latest view {:?}",
           error
         );
@@ -579,19 +1481,16 @@ impl CoordinatorState {
     }
 
     for idx in (1..tail_height).rev() {
-      let res = coordinator
-        .ledger_store
-        .read_view_ledger_by_index(idx)
-        .await;
+      let res = self.ledger_store.read_view_ledger_by_index(idx).await;
       if res.is_err() {
-        eprintln!(
+        error!(
           "Failed to read the view ledger entry at index {} ({:?})",
           idx, res
         );
         return Err(CoordinatorError::FailedToReadViewLedger);
       }
       let view_ledger_entry = res.unwrap();
-      if let Ok(mut vs) = coordinator.verifier_state.write() {
+      if let Ok(mut vs) = self.verifier_state.write() {
         // Set group identity
         if idx == 1 {
           vs.set_group_identity(view_ledger_entry.get_block().hash());
@@ -602,7 +1501,7 @@ impl CoordinatorState {
           None,
         );
         if res.is_err() {
-          eprintln!("Failed to apply view change at index {} ({:?})", idx, res);
+          error!("Failed to apply view change at index {} ({:?})", idx, res);
           return Err(CoordinatorError::FailedToActivate);
         }
       } else {
@@ -610,22 +1509,231 @@ impl CoordinatorState {
       }
     }
 
-    Ok(coordinator)
+    Ok(())
+  }
+
+  /// Returns whether this coordinator may serve requests, i.e., it is not drained, and
+  /// coordinators do not elect a leader or this coordinator holds a lease that is not about
+  /// to expire
+  pub fn is_leader(&self) -> bool {
+    if self.is_draining() {
+      return false;
+    }
+    match &self.election {
+      None => true,
+      Some(election) => match election.get_lease() {
+        Some(lease) => now_millis() + election.duration / LEASE_MARGIN_FRACTION < lease.expires_at,
+        None => false,
+      },
+    }
+  }
+
+  /// Returns the number of milliseconds between two rounds of an election, if coordinators
+  /// elect a leader
+  pub fn get_campaign_interval(&self) -> Option<u64> {
+    self
+      .election
+      .as_ref()
+      .map(|e| std::cmp::max(e.duration / 3, 1))
+  }
+
+  /// Runs a round of the election: the leader renews its lease, and any other coordinator
+  /// takes over the lease once it expires. A coordinator that takes over loads the view
+  /// ledger before it serves. Returns whether this coordinator is the leader.
+  pub async fn campaign(&self) -> Result<bool, CoordinatorError> {
+    let election = match &self.election {
+      None => return Ok(!self.is_draining()),
+      Some(e) => e,
+    };
+    // a drained coordinator leaves the lease to the others
+    if self.is_draining() {
+      election.set_lease(None);
+      return Ok(false);
+    }
+
+    let current = match self.ledger_store.read_lease().await {
+      Ok(l) => l,
+      Err(e) => {
+        error!("Failed to read the lease from the ledger store ({:?})", e);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+    let held = election.get_lease();
+    let now = now_millis();
+    let next = match &current {
+      // the leader renews the lease it holds
+      Some(l) if held.as_ref() == Some(l) => Lease {
+        holder: election.id.clone(),
+        epoch: l.epoch,
+        expires_at: now + election.duration,
+      },
+      // a lease that expired goes to whoever writes it first, as does the first lease
+      Some(l) if l.expires_at <= now => Lease {
+        holder: election.id.clone(),
+        epoch: l.epoch + 1,
+        expires_at: now + election.duration,
+      },
+      None => Lease {
+        holder: election.id.clone(),
+        epoch: 1,
+        expires_at: now + election.duration,
+      },
+      Some(_) => {
+        election.set_lease(None);
+        return Ok(false);
+      },
+    };
+
+    match self.ledger_store.write_lease(&next, current.as_ref()).await {
+      Ok(()) => {},
+      Err(LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData)) => {
+        // another coordinator wrote the lease since it was read
+        election.set_lease(None);
+        return Ok(false);
+      },
+      Err(e) => {
+        error!("Failed to write the lease to the ledger store ({:?})", e);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    }
+
+    if held.map(|l| l.epoch) != Some(next.epoch) {
+      info!(
+        "Coordinator {} is the leader in epoch {}",
+        election.id, next.epoch
+      );
+      // the former leader may have changed the endorsers
+      if let Err(error) = self.load_view_ledger().await {
+        error!("Failed to load the view ledger as the leader {:?}", error);
+        election.set_lease(None);
+        return Err(error);
+      }
+    }
+    election.set_lease(Some(next));
+    Ok(true)
+  }
+
+  /// Returns whether the coordinator is drained
+  pub fn is_draining(&self) -> bool {
+    self.draining.load(Ordering::SeqCst)
+  }
+
+  /// Stops serving requests. The coordinator waits for the appends and the view changes in
+  /// flight to finish, and gives up its lease, if it holds one, for another coordinator to
+  /// take over.
+  pub async fn drain(&self) -> Result<(), CoordinatorError> {
+    self.draining.store(true, Ordering::SeqCst);
+    // every operation that writes to the ledger store holds the gate
+    drop(self.append_gate.write().await);
+
+    let election = match &self.election {
+      None => return Ok(()),
+      Some(e) => e,
+    };
+    election.set_lease(None);
+    let current = match self.ledger_store.read_lease().await {
+      Ok(l) => l,
+      Err(e) => {
+        error!("Failed to read the lease from the ledger store ({:?})", e);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+    if let Some(lease) = current {
+      if lease.holder == election.id {
+        let released = Lease {
+          expires_at: now_millis(),
+          ..lease.clone()
+        };
+        match self.ledger_store.write_lease(&released, Some(&lease)).await {
+          Ok(()) => info!("Coordinator {} gave up the lease", election.id),
+          // another coordinator took the lease in the meantime
+          Err(LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData)) => {},
+          Err(e) => {
+            error!("Failed to write the lease to the ledger store ({:?})", e);
+            return Err(CoordinatorError::FailedToCallLedgerStore);
+          },
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Serves requests again after the coordinator was drained. A coordinator that elects a
+  /// leader serves once it takes the lease again.
+  pub fn resume(&self) {
+    self.draining.store(false, Ordering::SeqCst);
+  }
+
+  /// Returns the latest view change, which is in flight if it has not finished
+  pub fn get_reconfiguration(&self) -> Option<Reconfiguration> {
+    match self.reconfiguration.lock() {
+      Ok(r) => r.clone(),
+      Err(_) => None,
+    }
+  }
+
+  // Runs a view change, and records its duration, its outcome and its status
+  async fn track_view_change<T>(
+    &self,
+    initiator: &str,
+    reason: &str,
+    view_change: impl Future<Output = Result<T, CoordinatorError>>,
+  ) -> Result<T, CoordinatorError> {
+    let started_at = now_millis();
+    if let Ok(mut latest) = self.reconfiguration.lock() {
+      *latest = Some(Reconfiguration {
+        initiator: initiator.to_string(),
+        reason: reason.to_string(),
+        started_at,
+        finished_at: None,
+        error: None,
+      });
+    }
+    let mut guard = ReconfigurationGuard {
+      latest: &self.reconfiguration,
+      started_at,
+      error: Some("the view change was cancelled".to_string()),
+    };
+
+    let timer = metrics::VIEW_CHANGE_DURATION.start_timer();
+    let res = view_change.await;
+    timer.observe_duration();
+    let outcome = if res.is_ok() { "success" } else { "failure" };
+    metrics::VIEW_CHANGES.with_label_values(&[outcome]).inc();
+    guard.error = res.as_ref().err().map(|error| format!("{:?}", error));
+    res
+  }
+
+  // Confirms that the lease in the ledger store is still the one this coordinator holds, so
+  // that a former leader that has not noticed it was replaced cannot change the view
+  async fn check_lease(&self) -> Result<(), CoordinatorError> {
+    let election = match &self.election {
+      None => return Ok(()),
+      Some(e) => e,
+    };
+
+    let current = match self.ledger_store.read_lease().await {
+      Ok(l) => l,
+      Err(e) => {
+        error!("Failed to read the lease from the ledger store ({:?})", e);
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+    if current != election.get_lease() {
+      election.set_lease(None);
+      return Err(CoordinatorError::NotLeader);
+    }
+    if !self.is_leader() {
+      return Err(CoordinatorError::NotLeader);
+    }
+    Ok(())
   }
 
   async fn connect_to_existing_endorsers(
     &self,
     view_ledger_block: &[u8],
   ) -> Result<EndorserHostnames, CoordinatorError> {
-    let res = bincode::deserialize(view_ledger_block);
-    if res.is_err() {
-      eprintln!(
-        "Failed to deserialize the view ledger tail's genesis block {:?}",
-        res
-      );
-      return Err(CoordinatorError::FailedToSerde);
-    }
-    let endorser_hostnames: EndorserHostnames = res.unwrap();
+    let (endorser_hostnames, _record) = decode_view_block(view_ledger_block)?;
 
     let mut endorsers = EndorserHostnames::new();
 
@@ -633,6 +1741,13 @@ impl CoordinatorState {
       let pks = self.connect_endorsers(&[uri.clone()]).await;
       if pks.len() == 1 && pks[0].0 == *pk {
         endorsers.push((pk.clone(), uri.clone()));
+      } else if !pks.is_empty() {
+        // another endorser is at the URI now, which does not hold the recorded key
+        warn!(
+          "The endorser at {} does not have the key in the view ledger",
+          uri
+        );
+        self.disconnect_endorsers(&pks).await;
       }
     }
 
@@ -650,7 +1765,7 @@ impl CoordinatorState {
       let e = conn_map_rd.get(pk);
       match e {
         None => {
-          eprintln!("No endorser has this public key {:?}", pk);
+          error!("No endorser has this public key {:?}", pk);
           None
         },
         Some(v) => Some((
@@ -659,7 +1774,7 @@ impl CoordinatorState {
         )),
       }
     } else {
-      eprintln!("Failed to acquire read lock");
+      error!("Failed to acquire read lock");
       None
     }
   }
@@ -671,7 +1786,7 @@ impl CoordinatorState {
         .map(|(pk, _endorser)| pk.clone())
         .collect::<Vec<Vec<u8>>>()
     } else {
-      eprintln!("Failed to acquire read lock");
+      error!("Failed to acquire read lock");
       Vec::new()
     }
   }
@@ -683,19 +1798,19 @@ impl CoordinatorState {
         .map(|(_pk, endorser)| endorser.uri.clone())
         .collect::<Vec<String>>()
     } else {
-      eprintln!("Failed to acquire read lock");
+      error!("Failed to acquire read lock");
       Vec::new()
     }
   }
 
-  fn get_endorser_hostnames(&self) -> EndorserHostnames {
+  pub fn get_endorser_hostnames(&self) -> EndorserHostnames {
     if let Ok(conn_map_rd) = self.conn_map.read() {
       conn_map_rd
         .iter()
         .map(|(pk, endorser)| (pk.clone(), endorser.uri.clone()))
         .collect::<Vec<(Vec<u8>, String)>>()
     } else {
-      eprintln!("Failed to acquire read lock");
+      error!("Failed to acquire read lock");
       Vec::new()
     }
   }
@@ -717,43 +1832,54 @@ impl CoordinatorState {
       for _idx in 0..self.num_grpc_channels {
         let tx = mpsc_tx.clone();
         let endorser = hostname.clone();
+        let (connect_timeout, request_timeout) =
+          (self.endorser_connect_timeout, self.endorser_request_timeout);
+        let tls = self.endorser_tls.clone();
 
-        let _job = tokio::spawn(async move {
-          let res = Endpoint::from_shared(endorser.to_string());
-          if let Ok(endorser_endpoint) = res {
-            let endorser_endpoint = endorser_endpoint
-              .connect_timeout(std::time::Duration::from_secs(ENDORSER_CONNECT_TIMEOUT));
-            let endorser_endpoint =
-              endorser_endpoint.timeout(std::time::Duration::from_secs(ENDORSER_REQUEST_TIMEOUT));
-            let res = endorser_endpoint.connect().await;
-            if let Ok(channel) = res {
-              let mut client =
-                endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
-
-              let res =
-                get_public_key_with_retry(&mut client, endorser_proto::GetPublicKeyReq {}).await;
-              if let Ok(resp) = res {
-                let endorser_proto::GetPublicKeyResp { pk } = resp.into_inner();
-                let _ = tx.send((endorser, Ok((client, pk)))).await;
-              } else {
-                eprintln!("Failed to retrieve the public key: {:?}", res);
-                let _ = tx
-                  .send((endorser, Err(CoordinatorError::UnableToRetrievePublicKey)))
-                  .await;
-              }
-            } else {
-              eprintln!("Failed to connect to the endorser {}: {:?}", endorser, res);
-              let _ = tx
-                .send((endorser, Err(CoordinatorError::FailedToConnectToEndorser)))
-                .await;
-            }
-          } else {
-            eprintln!("Failed to resolve the endorser host name: {:?}", res);
-            let _ = tx
-              .send((endorser, Err(CoordinatorError::CannotResolveHostName)))
-              .await;
-          }
-        });
+        let _job = tokio::spawn(telemetry::propagate(async move {
+          let res =
+            match connect_endorser(&endorser, tls.as_deref(), connect_timeout, request_timeout)
+              .await
+            {
+              Ok((channel, connector)) => {
+                let mut client =
+                  endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
+
+                let res =
+                  get_public_key_with_retry(&mut client, endorser_proto::GetPublicKeyReq {}).await;
+                match res {
+                  Ok(resp) => {
+                    let endorser_proto::GetPublicKeyResp { pk, tls_signature } = resp.into_inner();
+                    // the endorser's certificate is pinned to its public key, which is what the
+                    // view ledger records, by the endorser's signature over the certificate
+                    match connector {
+                      Some(connector) => match connector.peer_certificate() {
+                        Some(cert) if verify_tls_certificate(&pk, &cert, &tls_signature) => {
+                          // a later certificate must be signed with the same key
+                          connector.pin_public_key(&pk);
+                          Ok((client, pk))
+                        },
+                        _ => {
+                          error!(
+                            "The TLS certificate of the endorser {} is not signed with its key",
+                            endorser
+                          );
+                          Err(CoordinatorError::InvalidEndorserCertificate)
+                        },
+                      },
+                      None => Ok((client, pk)),
+                    }
+                  },
+                  Err(status) => {
+                    error!("Failed to retrieve the public key: {:?}", status);
+                    Err(CoordinatorError::UnableToRetrievePublicKey)
+                  },
+                }
+              },
+              Err(error) => Err(error),
+            };
+          let _ = tx.send((endorser, res)).await;
+        }));
       }
     }
 
@@ -763,7 +1889,7 @@ impl CoordinatorState {
     while let Some((endorser, res)) = mpsc_rx.recv().await {
       if let Ok((client, pk)) = res {
         if PublicKey::from_bytes(&pk).is_err() {
-          eprintln!("Public key is invalid from endorser {:?}", endorser);
+          error!("Public key is invalid from endorser {:?}", endorser);
           continue;
         }
         if let Ok(mut conn_map_wr) = self.conn_map.write() {
@@ -783,7 +1909,7 @@ impl CoordinatorState {
             },
           };
         } else {
-          eprintln!("Failed to acquire the write lock");
+          error!("Failed to acquire the write lock");
         }
       }
     }
@@ -800,13 +1926,13 @@ impl CoordinatorState {
             let client = endorser.clients.pop();
             drop(client);
           }
-          eprintln!("Removed endorser {}", uri);
+          info!("Removed endorser {}", uri);
         } else {
-          eprintln!("Failed to find the endorser to disconnect {}", uri);
+          error!("Failed to find the endorser to disconnect {}", uri);
         }
       }
     } else {
-      eprintln!("Failed to acquire the write lock");
+      error!("Failed to acquire the write lock");
     }
   }
 
@@ -824,11 +1950,11 @@ impl CoordinatorState {
 
       let tx = mpsc_tx.clone();
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
+      let _job = tokio::spawn(telemetry::propagate(async move {
         let res =
           read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await;
         let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -844,7 +1970,7 @@ impl CoordinatorState {
               if receipt_rs.get_height() == view_ledger_height {
                 to_keep = true;
               } else {
-                eprintln!(
+                warn!(
                   "expected view ledger height={}, endorser's view ledger height={}",
                   view_ledger_height,
                   receipt_rs.get_height(),
@@ -852,12 +1978,12 @@ impl CoordinatorState {
               }
             },
             Err(error) => {
-              eprintln!("Failed to parse the metablock {:?}", error);
+              error!("Failed to parse the metablock {:?}", error);
             },
           }
         },
         Err(status) => {
-          eprintln!("Failed to get the view tail metablock {:?}", status);
+          error!("Failed to get the view tail metablock {:?}", status);
           if CoordinatorAction::RemoveEndorser != process_error(&endorser, None, &status) {
             to_keep = true;
           }
@@ -894,7 +2020,7 @@ impl CoordinatorState {
       let block_hash_copy = block_hash.to_bytes();
       let pk_bytes = pk.clone();
       let group_identity_copy = (*group_identity).to_bytes();
-      let _job = tokio::spawn(async move {
+      let _job = tokio::spawn(telemetry::propagate(async move {
         let res = initialize_state_with_retry(
           &mut endorser_client,
           group_identity_copy,
@@ -905,7 +2031,7 @@ impl CoordinatorState {
         )
         .await;
         let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -918,16 +2044,16 @@ impl CoordinatorState {
           let res = Receipt::from_bytes(&receipt);
           match res {
             Ok(receipt_rs) => receipts.add(&receipt_rs),
-            Err(error) => eprintln!("Failed to parse a receipt ({:?})", error),
+            Err(error) => error!("Failed to parse a receipt ({:?})", error),
           }
         },
         Err(status) => {
-          eprintln!(
+          error!(
             "Failed to initialize the state of endorser {} (status={:?})",
             endorser, status
           );
           if let CoordinatorAction::RemoveEndorser = process_error(&endorser, None, &status) {
-            eprintln!(
+            error!(
               "initialize_state from endorser {} received unexpected error {:?}",
               endorser, status
             );
@@ -940,6 +2066,7 @@ impl CoordinatorState {
     receipts
   }
 
+  #[instrument(skip_all)]
   async fn endorser_create_ledger(
     &self,
     endorsers: &[Vec<u8>],
@@ -959,7 +2086,7 @@ impl CoordinatorState {
       let block_hash = *ledger_block_hash;
       let block = ledger_block.clone();
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
+      let _job = tokio::spawn(telemetry::propagate(async move {
         let res = new_ledger_with_retry(
           &mut endorser_client,
           endorser_proto::NewLedgerReq {
@@ -970,7 +2097,7 @@ impl CoordinatorState {
         )
         .await;
         let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -990,18 +2117,18 @@ impl CoordinatorState {
                 }
               }
             },
-            Err(error) => eprintln!("Failed to parse a receipt ({:?})", error),
+            Err(error) => error!("Failed to parse a receipt ({:?})", error),
           }
         },
         Err(status) => {
-          eprintln!(
+          error!(
             "Failed to create a ledger {:?} in endorser {} (status={:?})",
             ledger_handle, endorser, status
           );
           if process_error(&endorser, Some(ledger_handle), &status)
             == CoordinatorAction::RemoveEndorser
           {
-            eprintln!(
+            error!(
               "create_ledger from endorser {} received unexpected error {:?}",
               endorser, status
             );
@@ -1014,6 +2141,7 @@ impl CoordinatorState {
     Ok(receipts)
   }
 
+  #[instrument(skip_all)]
   pub async fn endorser_append_ledger(
     &self,
     endorsers: &[Vec<u8>],
@@ -1037,108 +2165,37 @@ impl CoordinatorState {
       let block_copy = block.clone();
       let nonces_copy = nonces.clone();
       let pk_bytes = pk.clone();
-      let ledger_store = self.ledger_store.clone();
-      let _job = tokio::spawn(async move {
-        loop {
-          let res = append_with_retry(
-            &mut endorser_client,
-            endorser_proto::AppendReq {
-              handle: handle.to_bytes(),
-              block_hash: block_hash_copy.to_bytes(),
-              expected_height: expected_height as u64,
-              block: block_copy.to_bytes(),
-              nonces: nonces_copy.to_bytes(),
-            },
-          )
-          .await;
-          match res {
-            Ok(resp) => {
-              let endorser_proto::AppendResp { receipt } = resp.into_inner();
-              let _ = tx.send((endorser, pk_bytes, Ok(receipt))).await;
-              break;
-            },
-            Err(status) => match process_error(&endorser, Some(&handle), &status) {
-              CoordinatorAction::UpdateEndorser => {
-                let height_to_start = {
-                  if status.code() == Code::NotFound {
-                    0
-                  } else {
-                    let bytes = status.details();
-                    let ledger_height = u64::from_le_bytes(bytes[0..].try_into().unwrap()) as usize;
-                    ledger_height.checked_add(1).unwrap()
-                  }
-                };
-                let height_to_end = expected_height - 1;
-                let res = update_endorser(
-                  ledger_store.clone(),
-                  &mut endorser_client,
-                  handle,
-                  height_to_start,
-                  height_to_end,
-                )
-                .await;
-                match res {
-                  Ok(_resp) => {
-                    continue;
-                  },
-                  Err(status) => match process_error(&endorser, Some(&handle), &status) {
-                    CoordinatorAction::RemoveEndorser => {
-                      let _ = tx
-                        .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
-                        .await;
-                      break;
-                    },
-                    CoordinatorAction::IncrementReceipt => {
-                      continue;
-                    },
-                    _ => {
-                      let _ = tx
-                        .send((
-                          endorser,
-                          pk_bytes,
-                          Err(CoordinatorError::FailedToAppendLedger),
-                        ))
-                        .await;
-                      break;
-                    },
-                  },
-                }
-              },
-              CoordinatorAction::RemoveEndorser => {
-                let _ = tx
-                  .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
-                  .await;
-                break;
-              },
-              CoordinatorAction::IncrementReceipt => {
-                let _ = tx
-                  .send((
-                    endorser,
-                    pk_bytes,
-                    Err(CoordinatorError::LedgerAlreadyExists),
-                  ))
-                  .await;
-                break;
-              },
-              _ => {
-                let _ = tx
-                  .send((
-                    endorser,
-                    pk_bytes,
-                    Err(CoordinatorError::FailedToAppendLedger),
-                  ))
-                  .await;
-                break;
-              },
-            },
-          }
+      let lagging = self.lagging.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let res = append_with_retry(
+          &mut endorser_client,
+          endorser_proto::AppendReq {
+            handle: handle.to_bytes(),
+            block_hash: block_hash_copy.to_bytes(),
+            expected_height: expected_height as u64,
+            block: block_copy.to_bytes(),
+            nonces: nonces_copy.to_bytes(),
+          },
+        )
+        .await;
+        match res {
+          Ok(resp) => {
+            let endorser_proto::AppendResp { receipt } = resp.into_inner();
+            let _ = tx.send((endorser, pk_bytes, Ok(receipt))).await;
+          },
+          Err(status) => {
+            if let Some(error) = append_error(&endorser, &pk_bytes, &handle, &status, &lagging) {
+              let _ = tx.send((endorser, pk_bytes, Err(error))).await;
+            }
+          },
         }
-      });
+      }));
     }
 
     drop(mpsc_tx);
 
     let mut receipts = Receipts::new();
+    let mut deleted = false;
     while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
       match res {
         Ok(receipt) => match Receipt::from_bytes(&receipt) {
@@ -1151,32 +2208,56 @@ impl CoordinatorState {
             }
           },
           Err(error) => {
-            eprintln!("Failed to parse a receipt (err={:?}", error);
+            error!("Failed to parse a receipt (err={:?}", error);
           },
         },
         Err(error) => {
           if error == CoordinatorError::UnexpectedError {
-            eprintln!(
+            error!(
               "append_ledger from endorser {} received unexpected error {:?}",
               endorser, error
             );
             self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
+          } else if error == CoordinatorError::LedgerDeleted {
+            deleted = true;
           }
         },
       }
     }
 
+    if deleted {
+      return Err(CoordinatorError::LedgerDeleted);
+    }
+
     Ok(receipts)
   }
 
-  async fn endorser_update_ledger(
+  /// Appends the entries, given as the aggregated hashes, blocks and nonces, to the ledger in
+  /// the endorsers at consecutive heights from `expected_height`, and returns the receipts of
+  /// each entry. Fails unless every entry obtains a quorum of receipts.
+  #[instrument(skip_all)]
+  pub async fn endorser_append_batch(
     &self,
     endorsers: &[Vec<u8>],
     ledger_handle: &Handle,
-    max_height: usize,
-    endorser_height_map: &HashMap<String, usize>,
-  ) {
+    expected_height: usize,
+    entries: &[(NimbleDigest, Block, Nonces)],
+  ) -> Result<Vec<Receipts>, CoordinatorError> {
     let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+    let request = endorser_proto::AppendBatchReq {
+      handle: ledger_handle.to_bytes(),
+      expected_height: expected_height as u64,
+      entries: entries
+        .iter()
+        .map(
+          |(block_hash, block, nonces)| endorser_proto::AppendBatchEntry {
+            block_hash: block_hash.to_bytes(),
+            block: block.to_bytes(),
+            nonces: nonces.to_bytes(),
+          },
+        )
+        .collect(),
+    };
 
     for pk in endorsers {
       let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
@@ -1184,61 +2265,66 @@ impl CoordinatorState {
         None => continue,
       };
 
-      let height_to_start = {
-        if !endorser_height_map.contains_key(&endorser) {
-          0
-        } else {
-          endorser_height_map[&endorser].checked_add(1).unwrap()
-        }
-      };
-
-      if height_to_start > max_height {
-        continue;
-      }
-
-      let ledger_store = self.ledger_store.clone();
+      let tx = mpsc_tx.clone();
       let handle = *ledger_handle;
+      let request = request.clone();
       let pk_bytes = pk.clone();
-      let tx = mpsc_tx.clone();
-      let _job = tokio::spawn(async move {
-        let res = update_endorser(
-          ledger_store,
-          &mut endorser_client,
-          handle,
-          height_to_start,
-          max_height,
-        )
-        .await;
-        let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      let lagging = self.lagging.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let (receipts, status) = append_batch_with_retry(&mut endorser_client, request).await;
+        let error =
+          status.and_then(|status| append_error(&endorser, &pk_bytes, &handle, &status, &lagging));
+        let _ = tx.send((endorser, pk_bytes, receipts, error)).await;
+      }));
     }
 
     drop(mpsc_tx);
 
-    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
-      match res {
-        Ok(()) => {},
-        Err(status) => {
-          if process_error(&endorser, Some(ledger_handle), &status)
-            == CoordinatorAction::RemoveEndorser
-          {
-            eprintln!(
-              "update_endorser {} received unexpected error {:?}",
-              endorser, status,
-            );
-            self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
-          }
+    let mut receipts = vec![Receipts::new(); entries.len()];
+    let mut deleted = false;
+    while let Some((endorser, pk_bytes, endorser_receipts, error)) = mpsc_rx.recv().await {
+      // an endorser that failed part way through may still have signed the first entries
+      for (i, receipt) in endorser_receipts.iter().take(entries.len()).enumerate() {
+        match Receipt::from_bytes(receipt) {
+          Ok(receipt_rs) => receipts[i].add(&receipt_rs),
+          Err(error) => {
+            error!("Failed to parse a receipt (err={:?}", error);
+          },
+        }
+      }
+      if let Ok(vs) = self.verifier_state.read() {
+        if receipts.iter().all(|r| r.check_quorum(&vs).is_ok()) {
+          return Ok(receipts);
+        }
+      }
+      match error {
+        Some(CoordinatorError::UnexpectedError) => {
+          error!(
+            "append_batch from endorser {} received an unexpected error",
+            endorser
+          );
+          self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
         },
+        Some(CoordinatorError::LedgerDeleted) => deleted = true,
+        _ => {},
       }
     }
+
+    if deleted {
+      return Err(CoordinatorError::LedgerDeleted);
+    }
+
+    // some entry lacks a quorum of receipts once every endorser answered
+    Err(CoordinatorError::FailedToObtainQuorum)
   }
 
-  async fn endorser_read_ledger_tail(
+  #[instrument(skip_all)]
+  pub async fn endorser_delete_ledger(
     &self,
     endorsers: &[Vec<u8>],
     ledger_handle: &Handle,
-    client_nonce: &Nonce,
-  ) -> Result<LedgerEntry, CoordinatorError> {
+    expected_height: usize,
+  ) -> Result<Receipts, CoordinatorError> {
     let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
 
     for pk in endorsers {
@@ -1249,27 +2335,206 @@ impl CoordinatorState {
 
       let tx = mpsc_tx.clone();
       let handle = *ledger_handle;
-      let nonce = *client_nonce;
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
-        let res = read_latest_with_retry(
+      let lagging = self.lagging.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let res = delete_ledger_with_retry(
           &mut endorser_client,
-          endorser_proto::ReadLatestReq {
+          endorser_proto::DeleteLedgerReq {
             handle: handle.to_bytes(),
-            nonce: nonce.to_bytes(),
+            expected_height: expected_height as u64,
           },
         )
         .await;
         match res {
           Ok(resp) => {
-            let endorser_proto::ReadLatestResp {
-              receipt,
-              block,
-              nonces,
-            } = resp.into_inner();
-            let _ = tx
-              .send((endorser, pk_bytes, Ok((receipt, block, nonces))))
-              .await;
+            let endorser_proto::DeleteLedgerResp { receipt } = resp.into_inner();
+            let _ = tx.send((endorser, pk_bytes, Ok(receipt))).await;
+          },
+          Err(status) => match process_error(&endorser, Some(&handle), &status) {
+            CoordinatorAction::UpdateEndorser => {
+              let height_to_start = {
+                if status.code() == Code::NotFound {
+                  0
+                } else {
+                  let bytes = status.details();
+                  let ledger_height = u64::from_le_bytes(bytes[0..].try_into().unwrap()) as usize;
+                  ledger_height.checked_add(1).unwrap()
+                }
+              };
+              lagging.report(&pk_bytes, &handle, height_to_start);
+            },
+            CoordinatorAction::RemoveEndorser => {
+              let _ = tx
+                .send((endorser, pk_bytes, Err(CoordinatorError::UnexpectedError)))
+                .await;
+            },
+            CoordinatorAction::IncrementReceipt => {
+              let _ = tx
+                .send((
+                  endorser,
+                  pk_bytes,
+                  Err(CoordinatorError::LedgerAlreadyExists),
+                ))
+                .await;
+            },
+            _ => {
+              let error = if status.code() == Code::PermissionDenied {
+                CoordinatorError::LedgerDeleted
+              } else {
+                CoordinatorError::FailedToAppendLedger
+              };
+              let _ = tx.send((endorser, pk_bytes, Err(error))).await;
+            },
+          },
+        }
+      }));
+    }
+
+    drop(mpsc_tx);
+
+    let mut receipts = Receipts::new();
+    let mut deleted = false;
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(receipt) => match Receipt::from_bytes(&receipt) {
+          Ok(receipt_rs) => {
+            receipts.add(&receipt_rs);
+            if let Ok(vs) = self.verifier_state.read() {
+              if receipts.check_quorum(&vs).is_ok() {
+                return Ok(receipts);
+              }
+            }
+          },
+          Err(error) => {
+            error!("Failed to parse a receipt (err={:?}", error);
+          },
+        },
+        Err(error) => {
+          if error == CoordinatorError::UnexpectedError {
+            error!(
+              "delete_ledger from endorser {} received unexpected error {:?}",
+              endorser, error
+            );
+            self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
+          } else if error == CoordinatorError::LedgerDeleted {
+            deleted = true;
+          }
+        },
+      }
+    }
+
+    if deleted {
+      return Err(CoordinatorError::LedgerDeleted);
+    }
+
+    Ok(receipts)
+  }
+
+  #[instrument(skip_all)]
+  async fn endorser_update_ledger(
+    &self,
+    endorsers: &[Vec<u8>],
+    ledger_handle: &Handle,
+    max_height: usize,
+    endorser_height_map: &HashMap<String, usize>,
+  ) {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+
+    for pk in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let height_to_start = {
+        if !endorser_height_map.contains_key(&endorser) {
+          0
+        } else {
+          endorser_height_map[&endorser].checked_add(1).unwrap()
+        }
+      };
+
+      if height_to_start > max_height {
+        continue;
+      }
+
+      let ledger_store = self.ledger_store.clone();
+      let handle = *ledger_handle;
+      let pk_bytes = pk.clone();
+      let tx = mpsc_tx.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let res = update_endorser(
+          ledger_store,
+          &mut endorser_client,
+          handle,
+          height_to_start,
+          max_height,
+          DEFAULT_CATCH_UP_BATCH_SIZE,
+        )
+        .await;
+        let _ = tx.send((endorser, pk_bytes, res)).await;
+      }));
+    }
+
+    drop(mpsc_tx);
+
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(_num_replayed) => {},
+        Err(status) => {
+          if process_error(&endorser, Some(ledger_handle), &status)
+            == CoordinatorAction::RemoveEndorser
+          {
+            error!(
+              "update_endorser {} received unexpected error {:?}",
+              endorser, status,
+            );
+            self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
+          }
+        },
+      }
+    }
+  }
+
+  #[instrument(skip_all)]
+  async fn endorser_read_ledger_tail(
+    &self,
+    endorsers: &[Vec<u8>],
+    ledger_handle: &Handle,
+    client_nonce: &Nonce,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+
+    for pk in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let handle = *ledger_handle;
+      let nonce = *client_nonce;
+      let pk_bytes = pk.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let res = read_latest_with_retry(
+          &mut endorser_client,
+          endorser_proto::ReadLatestReq {
+            handle: handle.to_bytes(),
+            nonce: nonce.to_bytes(),
+          },
+        )
+        .await;
+        match res {
+          Ok(resp) => {
+            let endorser_proto::ReadLatestResp {
+              receipt,
+              block,
+              nonces,
+            } = resp.into_inner();
+            let _ = tx
+              .send((endorser, pk_bytes, Ok((receipt, block, nonces))))
+              .await;
           },
           Err(status) => match process_error(&endorser, Some(&handle), &status) {
             CoordinatorAction::RemoveEndorser => {
@@ -1288,7 +2553,7 @@ impl CoordinatorState {
             },
           },
         }
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -1318,12 +2583,12 @@ impl CoordinatorState {
             }
           },
           Err(error) => {
-            eprintln!("Failed to parse a receipt (err={:?}", error);
+            error!("Failed to parse a receipt (err={:?}", error);
           },
         },
         Err(error) => {
           if error == CoordinatorError::UnexpectedError {
-            eprintln!(
+            error!(
               "read_ledger from endorser {} received unexpected error {:?}",
               endorser, error
             );
@@ -1358,7 +2623,7 @@ impl CoordinatorState {
       let tx = mpsc_tx.clone();
       let block = *block_hash;
       let pk_bytes = pk.clone();
-      let _job = tokio::spawn(async move {
+      let _job = tokio::spawn(telemetry::propagate(async move {
         let res = finalize_state_with_retry(
           &mut endorser_client,
           endorser_proto::FinalizeStateReq {
@@ -1368,7 +2633,7 @@ impl CoordinatorState {
         )
         .await;
         let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -1391,7 +2656,7 @@ impl CoordinatorState {
               receipt_rs
             },
             Err(error) => {
-              eprintln!("Failed to parse a receipt ({:?})", error);
+              error!("Failed to parse a receipt ({:?})", error);
               continue;
             },
           };
@@ -1403,7 +2668,7 @@ impl CoordinatorState {
           }
         },
         Err(status) => {
-          eprintln!(
+          error!(
             "Failed to append view ledger to endorser {} (status={:?})",
             endorser, status
           );
@@ -1417,6 +2682,73 @@ impl CoordinatorState {
     (receipts, ledger_tail_maps)
   }
 
+  async fn endorser_advance_view(
+    &self,
+    endorsers: &EndorserHostnames,
+    block_hash: &NimbleDigest,
+    expected_height: usize,
+  ) -> (Receipts, Vec<endorser_proto::LedgerTailMap>) {
+    let (mpsc_tx, mut mpsc_rx) = mpsc::channel(ENDORSER_MPSC_CHANNEL_BUFFER);
+
+    for (pk, _uri) in endorsers {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+
+      let tx = mpsc_tx.clone();
+      let block = *block_hash;
+      let pk_bytes = pk.clone();
+      let _job = tokio::spawn(telemetry::propagate(async move {
+        let res = advance_view_with_retry(
+          &mut endorser_client,
+          endorser_proto::AdvanceViewReq {
+            block_hash: block.to_bytes(),
+            expected_height: expected_height as u64,
+          },
+        )
+        .await;
+        let _ = tx.send((endorser, pk_bytes, res)).await;
+      }));
+    }
+
+    drop(mpsc_tx);
+
+    let mut receipts = Receipts::new();
+    let mut ledger_tail_maps = Vec::new();
+
+    while let Some((endorser, pk_bytes, res)) = mpsc_rx.recv().await {
+      match res {
+        Ok(resp) => {
+          let endorser_proto::AdvanceViewResp {
+            receipt,
+            ledger_tail_map,
+          } = resp.into_inner();
+          match Receipt::from_bytes(&receipt) {
+            Ok(receipt_rs) => receipts.add(&receipt_rs),
+            Err(error) => {
+              error!("Failed to parse a receipt ({:?})", error);
+              continue;
+            },
+          }
+          ledger_tail_maps.push(endorser_proto::LedgerTailMap {
+            entries: ledger_tail_map,
+          });
+        },
+        Err(status) => {
+          error!(
+            "Failed to advance the view of endorser {} (status={:?})",
+            endorser, status
+          );
+          // an endorser that did not move to the new view cannot serve it
+          self.disconnect_endorsers(&vec![(pk_bytes, endorser)]).await;
+        },
+      }
+    }
+
+    (receipts, ledger_tail_maps)
+  }
+
   async fn endorser_verify_view_change(
     &self,
     endorsers: &EndorserHostnames,
@@ -1442,7 +2774,7 @@ impl CoordinatorState {
       let ledger_tail_maps_arc_copy = ledger_tail_maps_arc.clone();
       let ledger_chunks_copy = ledger_chunks.clone();
       let receipts_copy = receipts.to_bytes();
-      let _job = tokio::spawn(async move {
+      let _job = tokio::spawn(telemetry::propagate(async move {
         let res = activate_with_retry(
           &mut endorser_client,
           old_config_copy.to_bytes(),
@@ -1453,7 +2785,7 @@ impl CoordinatorState {
         )
         .await;
         let _ = tx.send((endorser, pk_bytes, res)).await;
-      });
+      }));
     }
 
     drop(mpsc_tx);
@@ -1466,7 +2798,7 @@ impl CoordinatorState {
           num_verified_endorers += 1;
         },
         Err(status) => {
-          eprintln!(
+          error!(
             "Failed to prove view change to endorser {} (status={:?})",
             endorser, status
           );
@@ -1481,6 +2813,43 @@ impl CoordinatorState {
   }
 
   pub async fn replace_endorsers(&self, hostnames: &[String]) -> Result<(), CoordinatorError> {
+    self
+      .reconfigure(
+        hostnames,
+        "operator",
+        "the endorsers were replaced on request",
+      )
+      .await
+  }
+
+  /// Moves to a new view with the endorsers at `hostnames`, and records who asked for the
+  /// view change and why in the view ledger
+  #[instrument(skip(self, hostnames))]
+  pub async fn reconfigure(
+    &self,
+    hostnames: &[String],
+    initiator: &str,
+    reason: &str,
+  ) -> Result<(), CoordinatorError> {
+    let record = ViewChangeRecord {
+      initiator: initiator.to_string(),
+      reason: reason.to_string(),
+      coordinator_id: self.election.as_ref().map(|e| e.id.clone()),
+      timestamp: now_millis(),
+      added: Vec::new(),
+      removed: Vec::new(),
+    };
+    self
+      .track_view_change(initiator, reason, self.change_view(hostnames, record))
+      .await
+  }
+
+  async fn change_view(
+    &self,
+    hostnames: &[String],
+    record: ViewChangeRecord,
+  ) -> Result<(), CoordinatorError> {
+    let _gate = self.append_gate.read().await;
     let existing_endorsers = self.get_endorser_hostnames();
 
     // Connect to new endorsers
@@ -1489,22 +2858,23 @@ impl CoordinatorState {
       return Err(CoordinatorError::NoNewEndorsers);
     }
 
-    // Package the list of endorsers into a genesis block of the view ledger
-    let view_ledger_genesis_block = {
-      let res = bincode::serialize(&new_endorsers);
-      if res.is_err() {
-        eprintln!("Failed to serialize endorser hostnames {:?}", res);
-        return Err(CoordinatorError::FailedToSerde);
-      }
-      let block_vec = res.unwrap();
-      Block::new(&block_vec)
+    // Package the list of endorsers and the record of the view change into a genesis block
+    // of the view ledger
+    let record = ViewChangeRecord {
+      added: new_endorsers.iter().map(|(_pk, uri)| uri.clone()).collect(),
+      removed: existing_endorsers
+        .iter()
+        .map(|(_pk, uri)| uri.clone())
+        .collect(),
+      ..record
     };
+    let view_ledger_genesis_block = encode_view_block(&new_endorsers, &record)?;
 
     // Read the current ledger tail
     let res = self.ledger_store.read_view_ledger_tail().await;
 
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to read from the view ledger in the ledger store ({:?})",
         res.unwrap_err()
       );
@@ -1513,13 +2883,15 @@ impl CoordinatorState {
 
     let (tail, height) = res.unwrap();
 
+    self.check_lease().await?;
+
     // Store the genesis block of the view ledger in the ledger store
     let res = self
       .ledger_store
       .append_view_ledger(&view_ledger_genesis_block, height + 1)
       .await;
     if let Err(e) = res {
-      eprintln!(
+      error!(
         "Failed to append to the view ledger in the ledger store ({:?})",
         e,
       );
@@ -1531,6 +2903,7 @@ impl CoordinatorState {
     self
       .apply_view_change(
         &existing_endorsers,
+        &EndorserHostnames::new(),
         &new_endorsers,
         &tail,
         &view_ledger_genesis_block,
@@ -1539,51 +2912,303 @@ impl CoordinatorState {
       .await
   }
 
-  async fn apply_view_change(
+  /// Adds the endorser at `hostname` to the current view, and returns its public key. The
+  /// endorsers of the view stay active, and only the new one is initialized
+  #[instrument(skip(self))]
+  pub async fn add_endorser(
     &self,
-    existing_endorsers: &EndorserHostnames,
-    new_endorsers: &EndorserHostnames,
-    view_ledger_entry: &LedgerEntry,
-    view_ledger_genesis_block: &Block,
-    view_ledger_height: usize,
-  ) -> Result<(), CoordinatorError> {
-    // Retrieve the view tail metablock
-    let view_tail_receipts = view_ledger_entry.get_receipts();
-    let view_tail_metablock = if view_tail_receipts.is_empty() {
-      if view_ledger_height != 1 {
-        eprintln!(
-          "cannot get view tail metablock from empty receipts (height = {}",
-          view_ledger_height
-        );
-        return Err(CoordinatorError::UnexpectedError);
-      } else {
-        MetaBlock::default()
-      }
-    } else {
-      let res = view_tail_receipts.get_metablock();
-      match res {
-        Ok(metablock) => metablock,
-        Err(_e) => {
-          eprintln!("faield to retrieve metablock from view receipts");
-          return Err(CoordinatorError::UnexpectedError);
-        },
-      }
-    };
-
-    let (finalize_receipts, ledger_tail_maps) = if existing_endorsers.is_empty() {
-      assert!(view_ledger_height == 1);
+    hostname: &str,
+    initiator: &str,
+  ) -> Result<Vec<u8>, CoordinatorError> {
+    let reason = format!("{} joined the view", hostname);
+    self
+      .track_view_change(
+        initiator,
+        &reason,
+        self.change_members(Some(hostname), None, initiator, &reason),
+      )
+      .await
+  }
 
-      (Receipts::new(), Vec::new())
-    } else {
+  /// Removes the endorser at `hostname` from the current view, and returns its public key.
+  /// Only that endorser is finalized, and the others stay active
+  #[instrument(skip(self))]
+  pub async fn remove_endorser(
+    &self,
+    hostname: &str,
+    initiator: &str,
+  ) -> Result<Vec<u8>, CoordinatorError> {
+    let reason = format!("{} left the view", hostname);
+    self
+      .track_view_change(
+        initiator,
+        &reason,
+        self.change_members(None, Some(hostname), initiator, &reason),
+      )
+      .await
+  }
+
+  async fn change_members(
+    &self,
+    joining: Option<&str>,
+    leaving: Option<&str>,
+    initiator: &str,
+    reason: &str,
+  ) -> Result<Vec<u8>, CoordinatorError> {
+    // Appends are held back, so that the endorsers that stay move to the new view with the
+    // same state
+    let _gate = self.append_gate.write().await;
+
+    let (tail, height) = match self.ledger_store.read_view_ledger_tail().await {
+      Ok(res) => res,
+      Err(error) => {
+        error!("Failed to read the view ledger tail {:?}", error);
+        return Err(CoordinatorError::FailedToReadViewLedger);
+      },
+    };
+    if height == 0 {
+      return Err(CoordinatorError::EmptyLedgerViews);
+    }
+    let (existing_endorsers, _record) = decode_view_block(&tail.get_block().to_bytes())?;
+
+    let mut new_endorsers = existing_endorsers.clone();
+    let mut joined = EndorserHostnames::new();
+    let mut left = EndorserHostnames::new();
+    if let Some(hostname) = joining {
+      if existing_endorsers.iter().any(|(_pk, uri)| uri == hostname) {
+        return Err(CoordinatorError::EndorserAlreadyInView);
+      }
+      joined = self.connect_endorsers(&[hostname.to_string()]).await;
+      if joined.is_empty() {
+        return Err(CoordinatorError::NoNewEndorsers);
+      }
+      new_endorsers.extend(joined.iter().cloned());
+    }
+    if let Some(hostname) = leaving {
+      match existing_endorsers
+        .iter()
+        .position(|(_pk, uri)| uri == hostname)
+      {
+        Some(index) => left.push(new_endorsers.remove(index)),
+        None => return Err(CoordinatorError::InvalidEndorserUri),
+      }
+      if new_endorsers.is_empty() {
+        return Err(CoordinatorError::LastEndorserInView);
+      }
+    }
+    let staying = existing_endorsers
+      .iter()
+      .filter(|endorser| !left.contains(endorser))
+      .cloned()
+      .collect::<EndorserHostnames>();
+
+    let res = self
+      .synchronize_endorsers(&existing_endorsers, &staying)
+      .await;
+    if let Err(error) = res {
+      self.disconnect_endorsers(&joined).await;
+      return Err(error);
+    }
+
+    let record = ViewChangeRecord {
+      initiator: initiator.to_string(),
+      reason: reason.to_string(),
+      coordinator_id: self.election.as_ref().map(|e| e.id.clone()),
+      timestamp: now_millis(),
+      added: joined.iter().map(|(_pk, uri)| uri.clone()).collect(),
+      removed: left.iter().map(|(_pk, uri)| uri.clone()).collect(),
+    };
+    let view_ledger_genesis_block = encode_view_block(&new_endorsers, &record)?;
+
+    if let Err(error) = self.check_lease().await {
+      self.disconnect_endorsers(&joined).await;
+      return Err(error);
+    }
+
+    let view_ledger_height = match self
+      .ledger_store
+      .append_view_ledger(&view_ledger_genesis_block, height + 1)
+      .await
+    {
+      Ok(view_ledger_height) => view_ledger_height,
+      Err(error) => {
+        error!(
+          "Failed to append to the view ledger in the ledger store ({:?})",
+          error,
+        );
+        self.disconnect_endorsers(&joined).await;
+        return Err(CoordinatorError::FailedToCallLedgerStore);
+      },
+    };
+
+    // A leaving endorser that is down cannot be finalized, but it is no longer in the view
+    let connected_pks = self.get_endorser_pks();
+    let leaving = left
+      .iter()
+      .filter(|(pk, _uri)| connected_pks.contains(pk))
+      .cloned()
+      .collect::<EndorserHostnames>();
+    self
+      .apply_view_change(
+        &leaving,
+        &staying,
+        &joined,
+        &tail,
+        &view_ledger_genesis_block,
+        view_ledger_height,
+      )
+      .await?;
+
+    let (pk, _uri) = joined.into_iter().chain(left).next().unwrap();
+    Ok(pk)
+  }
+
+  // Reads the state of each of `endorsers`, and returns the ledger tail maps of the active ones
+  async fn read_endorser_states(&self, endorsers: &EndorserHostnames) -> Vec<EndorserState> {
+    let mut jobs = Vec::new();
+    for (pk, _uri) in endorsers {
+      let (mut client, uri) = match self.get_endorser_client(pk) {
+        Some(res) => res,
+        None => continue,
+      };
+      let job = tokio::spawn(telemetry::propagate(async move {
+        let res = read_state_with_retry(&mut client, endorser_proto::ReadStateReq {}).await;
+        (client, uri, res)
+      }));
+      jobs.push((pk.clone(), job));
+    }
+
+    let mut states = Vec::new();
+    for (pk, job) in jobs {
+      let (client, uri, res) = match job.await {
+        Ok(res) => res,
+        Err(error) => {
+          error!("Failed to read the state of an endorser {:?}", error);
+          continue;
+        },
+      };
+      match res {
+        Ok(resp) => {
+          let endorser_proto::ReadStateResp {
+            mode,
+            ledger_tail_map,
+            ..
+          } = resp.into_inner();
+          if mode == endorser_proto::EndorserMode::Active as i32 {
+            states.push((pk, client, uri, ledger_tail_map));
+          }
+        },
+        Err(status) => {
+          error!(
+            "Failed to read the state of endorser {} (status={:?})",
+            uri, status
+          );
+        },
+      }
+    }
+    states
+  }
+
+  // Brings each of the `staying` endorsers up to the max cut of the states of `endorsers`,
+  // since every endorser that stays must attest to the same state when it moves to the next
+  // view. Endorsers that cannot be read are left out, as they could not sign anyway
+  async fn synchronize_endorsers(
+    &self,
+    endorsers: &EndorserHostnames,
+    staying: &EndorserHostnames,
+  ) -> Result<(), CoordinatorError> {
+    let states = self.read_endorser_states(endorsers).await;
+    let max_cut = compute_max_cut(&tail_maps(&states));
+
+    for (pk, mut client, uri, entries) in states {
+      if !staying.iter().any(|(staying_pk, _)| *staying_pk == pk) {
+        continue;
+      }
+      for (handle, start, end) in lagging_ledgers(&entries, &max_cut) {
+        let res = update_endorser(
+          self.ledger_store.clone(),
+          &mut client,
+          handle,
+          start,
+          end,
+          DEFAULT_CATCH_UP_BATCH_SIZE,
+        )
+        .await;
+        if let Err(status) = res {
+          error!(
+            "Failed to bring endorser {} up to date (status={:?})",
+            uri, status
+          );
+          return Err(CoordinatorError::EndorsersNotInSync);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Moves to the view of `view_ledger_genesis_block`: the `leaving` endorsers are finalized,
+  // the `staying` ones move to the new view as they are, and the `joining` ones are
+  // initialized with the max cut of the others and activated
+  async fn apply_view_change(
+    &self,
+    leaving: &EndorserHostnames,
+    staying: &EndorserHostnames,
+    joining: &EndorserHostnames,
+    view_ledger_entry: &LedgerEntry,
+    view_ledger_genesis_block: &Block,
+    view_ledger_height: usize,
+  ) -> Result<(), CoordinatorError> {
+    // Retrieve the view tail metablock
+    let view_tail_receipts = view_ledger_entry.get_receipts();
+    let view_tail_metablock = if view_tail_receipts.is_empty() {
+      if view_ledger_height != 1 {
+        error!(
+          "cannot get view tail metablock from empty receipts (height = {}",
+          view_ledger_height
+        );
+        return Err(CoordinatorError::UnexpectedError);
+      } else {
+        MetaBlock::default()
+      }
+    } else {
+      let res = view_tail_receipts.get_metablock();
+      match res {
+        Ok(metablock) => metablock,
+        Err(_e) => {
+          error!("faield to retrieve metablock from view receipts");
+          return Err(CoordinatorError::UnexpectedError);
+        },
+      }
+    };
+
+    let (finalize_receipts, mut ledger_tail_maps) = if leaving.is_empty() && staying.is_empty() {
+      assert!(view_ledger_height == 1);
+
+      (Receipts::new(), Vec::new())
+    } else {
       self
         .endorser_finalize_state(
-          existing_endorsers,
+          leaving,
           &view_ledger_genesis_block.hash(),
           view_ledger_height,
         )
         .await
     };
 
+    let (advance_receipts, advanced_tail_maps) = self
+      .endorser_advance_view(
+        staying,
+        &view_ledger_genesis_block.hash(),
+        view_ledger_height,
+      )
+      .await;
+    for ledger_tail_map in advanced_tail_maps {
+      if !ledger_tail_maps.contains(&ledger_tail_map) {
+        ledger_tail_maps.push(ledger_tail_map);
+      }
+    }
+
     // Compute the max cut
     let max_cut = compute_max_cut(&ledger_tail_maps);
 
@@ -1606,7 +3231,7 @@ impl CoordinatorState {
     let initialize_receipts = self
       .endorser_initialize_state(
         &group_identity,
-        new_endorsers,
+        joining,
         max_cut,
         &view_tail_metablock,
         &view_ledger_genesis_block.hash(),
@@ -1617,13 +3242,14 @@ impl CoordinatorState {
     // Store the receipts in the view ledger
     let mut receipts = Receipts::new();
     receipts.merge_receipts(&finalize_receipts);
+    receipts.merge_receipts(&advance_receipts);
     receipts.merge_receipts(&initialize_receipts);
     let res = self
       .ledger_store
       .attach_view_ledger_receipts(view_ledger_height, &receipts)
       .await;
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to attach view ledger receipt in the ledger store ({:?})",
         res.unwrap_err()
       );
@@ -1646,12 +3272,12 @@ impl CoordinatorState {
           .read_ledger_by_index(&h, index as usize)
           .await;
         if let Err(e) = res {
-          eprintln!("Failed to read the ledger store {:?}", e);
+          error!("Failed to read the ledger store {:?}", e);
           return Err(CoordinatorError::FailedToCallLedgerStore);
         }
         let ledger_entry = res.unwrap();
         let block_hash = compute_aggregated_block_hash(
-          &ledger_entry.get_block().hash().to_bytes(),
+          &content_hash(ledger_entry.get_block()).to_bytes(),
           &ledger_entry.get_nonces().hash().to_bytes(),
         );
         block_hashes.push(block_hash.to_bytes());
@@ -1664,9 +3290,10 @@ impl CoordinatorState {
       });
     }
 
+    // The endorsers that stay are active already
     let num_verified_endorsers = self
       .endorser_verify_view_change(
-        new_endorsers,
+        joining,
         view_ledger_entry.get_block().clone(),
         view_ledger_genesis_block.clone(),
         ledger_tail_maps,
@@ -1674,11 +3301,11 @@ impl CoordinatorState {
         &receipts,
       )
       .await;
-    if num_verified_endorsers * 2 <= new_endorsers.len() {
-      eprintln!(
+    if !joining.is_empty() && num_verified_endorsers * 2 <= joining.len() {
+      warn!(
         "insufficient verified endorsers {} * 2 <= {}",
         num_verified_endorsers,
-        new_endorsers.len()
+        joining.len()
       );
     }
 
@@ -1689,18 +3316,80 @@ impl CoordinatorState {
         &receipts.to_bytes(),
         Some(ATTESTATION_STR.as_bytes()),
       ) {
-        eprintln!("Failed to apply view change: {:?}", e);
+        error!("Failed to apply view change: {:?}", e);
       }
     } else {
       return Err(CoordinatorError::FailedToAcquireWriteLock);
     }
 
     // Disconnect existing endorsers
-    self.disconnect_endorsers(existing_endorsers).await;
+    self.disconnect_endorsers(leaving).await;
 
     Ok(())
   }
 
+  /// Moves `block` into the content store if it is large enough (or if it could be
+  /// mistaken for a reference block) and returns the block to keep in the ledger store
+  /// and the endorsers in its place
+  async fn store_block(&self, block: Block) -> Result<Block, CoordinatorError> {
+    let content_store = match &self.content_store {
+      Some(content_store) => content_store,
+      None => return Ok(block),
+    };
+
+    if block.len() <= self.content_threshold && decode_content_ref(&block).is_none() {
+      return Ok(block);
+    }
+
+    match content_store.put(&block.to_bytes()).await {
+      Ok(handle) => Ok(encode_content_ref(&handle)),
+      Err(error) => {
+        error!("Failed to put a block in the content store {:?}", error);
+        Err(CoordinatorError::FailedToCallContentStore)
+      },
+    }
+  }
+
+  /// Replaces a reference block in `ledger_entry` with the content it refers to
+  async fn load_block(
+    &self,
+    mut ledger_entry: LedgerEntry,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    let handle = match decode_content_ref(ledger_entry.get_block()) {
+      Some(handle) => handle,
+      None => return Ok(ledger_entry),
+    };
+
+    let content_store = match &self.content_store {
+      Some(content_store) => content_store,
+      None => {
+        error!("Found a reference block but no content store is configured");
+        return Err(CoordinatorError::FailedToCallContentStore);
+      },
+    };
+
+    match content_store.get(&handle).await {
+      Ok(data) => {
+        if NimbleDigest::digest(&data) != handle {
+          error!(
+            "Content store returned data that does not match {:?}",
+            handle
+          );
+          return Err(CoordinatorError::FailedToCallContentStore);
+        }
+        ledger_entry.set_block(Block::new(&data));
+        Ok(ledger_entry)
+      },
+      Err(error) => {
+        error!(
+          "Failed to get block {:?} from the content store {:?}",
+          handle, error
+        );
+        Err(CoordinatorError::FailedToCallContentStore)
+      },
+    }
+  }
+
   pub async fn reset_ledger_store(&self) {
     let res = self.ledger_store.reset_store().await;
     assert!(res.is_ok());
@@ -1712,19 +3401,40 @@ impl CoordinatorState {
     handle_bytes: &[u8],
     block_bytes: &[u8],
   ) -> Result<Receipts, CoordinatorError> {
-    let handle = NimbleDigest::digest(handle_bytes);
+    self
+      .create_ledger_with_request(endorsers_opt, handle_bytes, block_bytes, &[])
+      .await
+  }
+
+  /// Creates a ledger, and keeps `request_id`, the ID the client gave the request if any, with
+  /// its genesis entry, which is how a retry of the request is recognized
+  #[instrument(skip_all)]
+  pub async fn create_ledger_with_request(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    request_id: &[u8],
+  ) -> Result<Receipts, CoordinatorError> {
     let genesis_block = Block::new(block_bytes);
 
+    let _gate = self.append_gate.read().await;
+    let handle = NimbleDigest::digest(handle_bytes);
+    let _endorsing = self.endorsing.mark(&handle, 0..1);
+
     let hash_block = genesis_block.hash();
     let hash_nonces = Nonces::new().hash();
     let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
 
+    // From here on, the ledger store and the endorsers only see the stored block
+    let genesis_block = self.store_block(genesis_block).await?;
+
     let res = self
       .ledger_store
-      .create_ledger(&handle, genesis_block.clone())
+      .create_ledger_with_request(&handle, genesis_block.clone(), request_id)
       .await;
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to create ledger in the ledger store ({:?})",
         res.unwrap_err()
       );
@@ -1741,7 +3451,7 @@ impl CoordinatorState {
         .endorser_create_ledger(&endorsers, &handle, &block_hash, genesis_block)
         .await;
       if res.is_err() {
-        eprintln!("Failed to create ledger in endorsers ({:?})", res);
+        error!("Failed to create ledger in endorsers ({:?})", res);
         return Err(res.unwrap_err());
       }
       res.unwrap()
@@ -1753,36 +3463,116 @@ impl CoordinatorState {
       .attach_ledger_receipts(&handle, 0, &receipts)
       .await;
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to attach ledger receipt to the ledger store ({:?})",
         res
       );
       return Err(CoordinatorError::FailedToAttachReceipt);
     }
+    let _ = self.updates.send(handle);
 
     Ok(receipts)
   }
 
   pub async fn append_ledger(
-    &self,
+    self: &Arc<Self>,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    block_bytes: &[u8],
+    expected_height: usize,
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
+    self
+      .append_ledger_with_request(
+        endorsers_opt,
+        handle_bytes,
+        block_bytes,
+        expected_height,
+        &[],
+      )
+      .await
+  }
+
+  /// Appends a block to a ledger, and keeps `request_id` with the new entry as
+  /// `create_ledger_with_request` does
+  #[instrument(skip_all)]
+  pub async fn append_ledger_with_request(
+    self: &Arc<Self>,
     endorsers_opt: Option<Vec<Vec<u8>>>,
     handle_bytes: &[u8],
     block_bytes: &[u8],
     expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
     if expected_height == 0 {
       return Err(CoordinatorError::InvalidHeight);
     }
 
     let handle = NimbleDigest::digest(handle_bytes);
+    // appends to chosen endorsers are not batched with the others
+    let queues = match (&self.append_queues, &endorsers_opt) {
+      (Some(queues), None) => queues,
+      _ => {
+        return self
+          .append_entry(
+            endorsers_opt,
+            &handle,
+            block_bytes,
+            expected_height,
+            request_id,
+          )
+          .await
+      },
+    };
+
+    let result = queues.push(
+      &handle,
+      expected_height,
+      block_bytes.to_vec(),
+      request_id.to_vec(),
+    );
+    // the queue is served by a task of its own, so that this call neither waits for the
+    // appends of others nor leaves them half done if its client goes away
+    if let Some(serving) = queues.claim(&handle) {
+      let state = self.clone();
+      let queues = queues.clone();
+      tokio::spawn(telemetry::propagate(async move {
+        state.serve_appends(&queues, &handle).await;
+        serving.finish();
+      }));
+    }
+    result
+      .await
+      .unwrap_or(Err(CoordinatorError::FailedToAppendLedger))
+  }
+
+  // Appends a block to the ledger on its own
+  async fn append_entry(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle: &Handle,
+    block_bytes: &[u8],
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(NimbleDigest, Receipts), CoordinatorError> {
     let data_block = Block::new(block_bytes);
+    let _gate = self.append_gate.read().await;
+    let _endorsing = self
+      .endorsing
+      .mark(handle, expected_height..expected_height + 1);
+    let hash_block = data_block.hash();
+
+    // a deleted ledger ends with its tombstone, which endorsers never append to
+    self.check_not_deleted(handle, expected_height - 1).await?;
+
+    // From here on, the ledger store and the endorsers only see the stored block
+    let data_block = self.store_block(data_block).await?;
 
     let res = self
       .ledger_store
-      .append_ledger(&handle, &data_block, expected_height)
+      .append_ledger_with_request(handle, &data_block, expected_height, request_id)
       .await;
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to append to the ledger in the ledger store {:?}",
         res.unwrap_err()
       );
@@ -1792,7 +3582,6 @@ impl CoordinatorState {
     let (actual_height, nonces) = res.unwrap();
     assert!(actual_height == expected_height);
 
-    let hash_block = data_block.hash();
     let hash_nonces = nonces.hash();
     let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
 
@@ -1804,7 +3593,7 @@ impl CoordinatorState {
       let res = self
         .endorser_append_ledger(
           &endorsers,
-          &handle,
+          handle,
           &block_hash,
           actual_height,
           data_block,
@@ -1812,7 +3601,7 @@ impl CoordinatorState {
         )
         .await;
       if res.is_err() {
-        eprintln!("Failed to append to the ledger in endorsers {:?}", res);
+        error!("Failed to append to the ledger in endorsers {:?}", res);
         return Err(res.unwrap_err());
       }
       res.unwrap()
@@ -1820,75 +3609,418 @@ impl CoordinatorState {
 
     let res = self
       .ledger_store
-      .attach_ledger_receipts(&handle, expected_height, &receipts)
+      .attach_ledger_receipts(handle, expected_height, &receipts)
       .await;
     if res.is_err() {
-      eprintln!(
+      error!(
         "Failed to attach ledger receipt to the ledger store ({:?})",
         res.unwrap_err()
       );
       return Err(CoordinatorError::FailedToAttachReceipt);
     }
+    let _ = self.updates.send(*handle);
 
     Ok((hash_nonces, receipts))
   }
 
-  async fn read_ledger_tail_internal(
-    &self,
-    handle: &NimbleDigest,
-    nonce: &Nonce,
-  ) -> Result<LedgerEntry, CoordinatorError> {
-    let endorsers = self.get_endorser_pks();
-    self
-      .endorser_read_ledger_tail(&endorsers, handle, nonce)
-      .await
-  }
-
-  async fn read_ledger_by_index_internal(
-    &self,
-    handle: &NimbleDigest,
-    height: usize,
-  ) -> Result<LedgerEntry, CoordinatorError> {
-    let res = self.ledger_store.read_ledger_by_index(handle, height).await;
-    match res {
-      Ok(ledger_entry) => Ok(ledger_entry),
-      Err(error) => match error {
-        LedgerStoreError::LedgerError(StorageError::InvalidIndex) => {
-          Err(CoordinatorError::InvalidHeight)
+  // Serves the ledger's queue of appends until it is empty. Pipelining clients may send
+  // appends in any order, so appends that arrive ahead of the height after the ledger's tail
+  // wait a little for the appends in between.
+  async fn serve_appends(&self, queues: &AppendQueues, handle: &Handle) {
+    let mut next = None;
+    let mut deadline = None;
+    loop {
+      let wait = deadline.is_none_or(|d| tokio::time::Instant::now() < d);
+      match queues.take_batch(handle, next, wait) {
+        Batch::Done => return,
+        Batch::Waiting(arrived) => {
+          let d = *deadline.get_or_insert_with(|| {
+            tokio::time::Instant::now() + std::time::Duration::from_millis(APPEND_GAP_WAIT)
+          });
+          let _ = tokio::time::timeout_at(d, arrived.notified()).await;
         },
-        _ => Err(CoordinatorError::FailedToCallLedgerStore),
-      },
+        Batch::Ready(batch) => {
+          let (height, early) = self.append_batch(handle, batch, wait).await;
+          if early.is_empty() {
+            deadline = None;
+          }
+          queues.requeue(handle, early);
+          next = height;
+        },
+      }
     }
   }
 
-  pub async fn read_ledger_tail(
+  // Appends a batch of blocks at consecutive heights to the ledger, and answers each of their
+  // callers. Returns the height after the ledger's tail, if it is known, along with the
+  // appends that arrived ahead of it if they may wait.
+  async fn append_batch(
     &self,
-    handle_bytes: &[u8],
-    nonce_bytes: &[u8],
-  ) -> Result<LedgerEntry, CoordinatorError> {
-    let nonce = {
-      let nonce_op = Nonce::new(nonce_bytes);
-      if nonce_op.is_err() {
-        eprintln!("Nonce is invalide");
-        return Err(CoordinatorError::InvalidNonce);
+    handle: &Handle,
+    batch: Vec<PendingAppend>,
+    may_wait: bool,
+  ) -> (Option<usize>, Vec<PendingAppend>) {
+    let _gate = self.append_gate.read().await;
+    if let Err(error) = self.check_not_deleted(handle, batch[0].height - 1).await {
+      for append in batch {
+        append.reply(Err(error.clone()));
       }
-      nonce_op.unwrap().to_owned()
+      return (None, Vec::new());
+    }
+    let _endorsing = self
+      .endorsing
+      .mark(handle, batch[0].height..batch[batch.len() - 1].height + 1);
+
+    // Since the ledger store appends at the expected height only, the blocks it appends
+    // follow one another
+    let mut appended = Vec::new();
+    let mut entries = Vec::new();
+    let mut early = Vec::new();
+    let mut next = None;
+    for append in batch {
+      let data_block = Block::new(&append.block);
+      let hash_block = data_block.hash();
+      let data_block = match self.store_block(data_block).await {
+        Ok(data_block) => data_block,
+        Err(error) => {
+          append.reply(Err(error));
+          continue;
+        },
+      };
+      match self
+        .ledger_store
+        .append_ledger_with_request(handle, &data_block, append.height, &append.request_id)
+        .await
+      {
+        Ok((actual_height, nonces)) => {
+          assert!(actual_height == append.height);
+          let hash_nonces = nonces.hash();
+          let block_hash =
+            compute_aggregated_block_hash(&hash_block.to_bytes(), &hash_nonces.to_bytes());
+          appended.push((append, hash_nonces));
+          entries.push((block_hash, data_block, nonces));
+        },
+        Err(error) => {
+          if may_wait && appended.is_empty() {
+            if next.is_none() {
+              next = self
+                .ledger_store
+                .ledger_height(handle)
+                .await
+                .ok()
+                .map(|height| height + 1);
+            }
+            if next.is_some_and(|next| append.height > next) {
+              early.push(append);
+              continue;
+            }
+          }
+          error!(
+            "Failed to append to the ledger in the ledger store {:?}",
+            error
+          );
+          append.reply(Err(CoordinatorError::FailedToAppendLedger));
+        },
+      }
+    }
+    let first = match appended.first() {
+      Some((append, _)) => append.height,
+      None => return (next, early),
     };
+    metrics::APPEND_BATCH_SIZE.observe(appended.len() as f64);
 
-    let handle = NimbleDigest::digest(handle_bytes);
-
-    let mut nonce_attached = false;
-    let mut nonce_attached_height = 0;
-
-    loop {
-      match self.read_ledger_tail_internal(&handle, &nonce).await {
-        Ok(ledger_entry) => return Ok(ledger_entry),
-        Err(error) => match error {
-          CoordinatorError::FailedToObtainQuorum => {
+    // a lone append goes to the endorsers the way it always did
+    let endorsers = self.get_endorser_pks();
+    let res = if entries.len() == 1 {
+      let (block_hash, data_block, nonces) = entries.pop().unwrap();
+      self
+        .endorser_append_ledger(&endorsers, handle, &block_hash, first, data_block, nonces)
+        .await
+        .map(|receipts| vec![receipts])
+    } else {
+      self
+        .endorser_append_batch(&endorsers, handle, first, &entries)
+        .await
+    };
+    let res = match res {
+      Ok(receipts) => match self
+        .ledger_store
+        .attach_ledger_receipts_batch(handle, first, &receipts)
+        .await
+      {
+        Ok(()) => Ok(receipts),
+        Err(error) => {
+          error!(
+            "Failed to attach ledger receipts to the ledger store ({:?})",
+            error
+          );
+          Err(CoordinatorError::FailedToAttachReceipt)
+        },
+      },
+      Err(error) => {
+        error!("Failed to append to the ledger in endorsers {:?}", error);
+        Err(error)
+      },
+    };
+
+    let next = first + appended.len();
+    match res {
+      Ok(receipts) => {
+        let _ = self.updates.send(*handle);
+        for ((append, hash_nonces), receipts) in appended.into_iter().zip(receipts) {
+          append.reply(Ok((hash_nonces, receipts)));
+        }
+      },
+      Err(error) => {
+        for (append, _) in appended {
+          append.reply(Err(error.clone()));
+        }
+      },
+    }
+    (Some(next), early)
+  }
+
+  /// Returns the hash of the nonces and the receipts of the entry at `index` if the request
+  /// with the ID added it with `block_bytes`, which is how a retried request finds that its
+  /// block was added already. Nothing is returned if the ledger has no such entry, or has an
+  /// entry there that another request added.
+  pub async fn read_retried_entry(
+    &self,
+    handle_bytes: &[u8],
+    index: usize,
+    block_bytes: &[u8],
+    request_id: &[u8],
+  ) -> Result<Option<(NimbleDigest, Receipts)>, CoordinatorError> {
+    let handle = NimbleDigest::digest(handle_bytes);
+    let ledger_entry = match self.ledger_store.read_ledger_by_index(&handle, index).await {
+      Ok(ledger_entry) => ledger_entry,
+      // an entry that was pruned is long past retrying
+      Err(_) => return Ok(None),
+    };
+    if request_id.is_empty()
+      || ledger_entry.get_request_id() != request_id
+      || content_hash(ledger_entry.get_block()) != Block::new(block_bytes).hash()
+    {
+      return Ok(None);
+    }
+    // the receipts are attached once the endorsers signed the entry
+    if !ledger_entry.get_receipts().is_empty() {
+      return Ok(Some((
+        ledger_entry.get_nonces().hash(),
+        ledger_entry.get_receipts().clone(),
+      )));
+    }
+    // an entry nobody is endorsing any more was left behind by a request that failed midway,
+    // so the retry finishes endorsing it
+    let _endorsing = match self.endorsing.try_mark(&handle, index) {
+      Some(endorsing) => endorsing,
+      None => return Err(CoordinatorError::RequestInProgress),
+    };
+    let ledger_entry = match self.ledger_store.read_ledger_by_index(&handle, index).await {
+      Ok(ledger_entry) => ledger_entry,
+      Err(_) => return Ok(None),
+    };
+    let hash_nonces = ledger_entry.get_nonces().hash();
+    let receipts = if ledger_entry.get_receipts().is_empty() {
+      self.finish_entry(&handle, index, ledger_entry).await?
+    } else {
+      ledger_entry.get_receipts().clone()
+    };
+    Ok(Some((hash_nonces, receipts)))
+  }
+
+  // Sends a stored entry that lacks receipts to the endorsers again and attaches the receipts
+  // they return. Endorsers that fell behind in the meantime are left to the catch-up path.
+  async fn finish_entry(
+    &self,
+    handle: &Handle,
+    index: usize,
+    ledger_entry: LedgerEntry,
+  ) -> Result<Receipts, CoordinatorError> {
+    let _gate = self.append_gate.read().await;
+    let hash_nonces = ledger_entry.get_nonces().hash();
+    let block_hash = compute_aggregated_block_hash(
+      &content_hash(ledger_entry.get_block()).to_bytes(),
+      &hash_nonces.to_bytes(),
+    );
+    let endorsers = self.get_endorser_pks();
+    let receipts = if index == 0 {
+      self
+        .endorser_create_ledger(
+          &endorsers,
+          handle,
+          &block_hash,
+          ledger_entry.get_block().clone(),
+        )
+        .await?
+    } else {
+      self
+        .endorser_append_ledger(
+          &endorsers,
+          handle,
+          &block_hash,
+          index,
+          ledger_entry.get_block().clone(),
+          ledger_entry.get_nonces().clone(),
+        )
+        .await?
+    };
+    // endorsers sign the entry again only while it is the tail of the ledger
+    if receipts.is_empty() {
+      return Err(CoordinatorError::FailedToObtainQuorum);
+    }
+
+    if let Err(error) = self
+      .ledger_store
+      .attach_ledger_receipts(handle, index, &receipts)
+      .await
+    {
+      error!(
+        "Failed to attach ledger receipt to the ledger store ({:?})",
+        error
+      );
+      return Err(CoordinatorError::FailedToAttachReceipt);
+    }
+    let _ = self.updates.send(*handle);
+
+    Ok(receipts)
+  }
+
+  async fn check_not_deleted(
+    &self,
+    handle: &Handle,
+    height: usize,
+  ) -> Result<(), CoordinatorError> {
+    match self.ledger_store.read_ledger_by_index(handle, height).await {
+      Ok(ledger_entry) if ledger_entry.is_tombstone() => Err(CoordinatorError::LedgerDeleted),
+      // anything else is left to the ledger store and the endorsers
+      _ => Ok(()),
+    }
+  }
+
+  /// Deletes a ledger by appending a tombstone at `expected_height`. The endorsers sign
+  /// the tombstone and drop the tail of the ledger, after which it can be neither appended
+  /// to nor created again. Its tail reads as the tombstone.
+  #[instrument(skip_all)]
+  pub async fn delete_ledger(
+    &self,
+    endorsers_opt: Option<Vec<Vec<u8>>>,
+    handle_bytes: &[u8],
+    expected_height: usize,
+  ) -> Result<Receipts, CoordinatorError> {
+    if expected_height == 0 {
+      return Err(CoordinatorError::InvalidHeight);
+    }
+
+    let _gate = self.append_gate.read().await;
+    let handle = NimbleDigest::digest(handle_bytes);
+    self.check_not_deleted(&handle, expected_height - 1).await?;
+
+    let res = self
+      .ledger_store
+      .append_tombstone(&handle, expected_height)
+      .await;
+    if let Err(error) = res {
+      error!(
+        "Failed to append a tombstone to the ledger in the ledger store {:?}",
+        error
+      );
+      return Err(CoordinatorError::FailedToAppendLedger);
+    }
+
+    let receipts = {
+      let endorsers = match endorsers_opt {
+        Some(endorsers) => endorsers,
+        None => self.get_endorser_pks(),
+      };
+      match self
+        .endorser_delete_ledger(&endorsers, &handle, expected_height)
+        .await
+      {
+        Ok(receipts) => receipts,
+        Err(error) => {
+          error!("Failed to delete the ledger in endorsers {:?}", error);
+          return Err(error);
+        },
+      }
+    };
+
+    let res = self
+      .ledger_store
+      .attach_ledger_receipts(&handle, expected_height, &receipts)
+      .await;
+    if let Err(error) = res {
+      error!(
+        "Failed to attach ledger receipt to the ledger store ({:?})",
+        error
+      );
+      return Err(CoordinatorError::FailedToAttachReceipt);
+    }
+    let _ = self.updates.send(handle);
+
+    Ok(receipts)
+  }
+
+  async fn read_ledger_tail_internal(
+    &self,
+    handle: &NimbleDigest,
+    nonce: &Nonce,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    let endorsers = self.get_endorser_pks();
+    self
+      .endorser_read_ledger_tail(&endorsers, handle, nonce)
+      .await
+  }
+
+  async fn read_ledger_by_index_internal(
+    &self,
+    handle: &NimbleDigest,
+    height: usize,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    let res = self.ledger_store.read_ledger_by_index(handle, height).await;
+    match res {
+      Ok(ledger_entry) => Ok(ledger_entry),
+      Err(error) => match error {
+        LedgerStoreError::LedgerError(StorageError::InvalidIndex) => {
+          Err(CoordinatorError::InvalidHeight)
+        },
+        LedgerStoreError::LedgerError(StorageError::PrunedIndex) => {
+          Err(CoordinatorError::PrunedIndex)
+        },
+        _ => Err(CoordinatorError::FailedToCallLedgerStore),
+      },
+    }
+  }
+
+  #[instrument(skip_all)]
+  pub async fn read_ledger_tail(
+    &self,
+    handle_bytes: &[u8],
+    nonce_bytes: &[u8],
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    let nonce = {
+      let nonce_op = Nonce::new(nonce_bytes);
+      if nonce_op.is_err() {
+        warn!("Nonce is invalide");
+        return Err(CoordinatorError::InvalidNonce);
+      }
+      nonce_op.unwrap().to_owned()
+    };
+
+    let handle = NimbleDigest::digest(handle_bytes);
+
+    let mut nonce_attached = false;
+    let mut nonce_attached_height = 0;
+
+    loop {
+      match self.read_ledger_tail_internal(&handle, &nonce).await {
+        Ok(ledger_entry) => return self.load_block(ledger_entry).await,
+        Err(error) => match error {
+          CoordinatorError::FailedToObtainQuorum => {
             if !nonce_attached {
               let res = self.ledger_store.attach_ledger_nonce(&handle, &nonce).await;
               if res.is_err() {
-                eprintln!(
+                error!(
                   "Failed to attach the nonce for reading ledger tail {:?}",
                   res.unwrap_err()
                 );
@@ -1901,7 +4033,7 @@ impl CoordinatorState {
               .read_ledger_by_index_internal(&handle, nonce_attached_height)
               .await
             {
-              Ok(ledger_entry) => return Ok(ledger_entry),
+              Ok(ledger_entry) => return self.load_block(ledger_entry).await,
               Err(error) => match error {
                 CoordinatorError::FailedToObtainQuorum | CoordinatorError::InvalidHeight => {
                   continue;
@@ -1920,6 +4052,7 @@ impl CoordinatorState {
     }
   }
 
+  #[instrument(skip_all)]
   pub async fn read_ledger_by_index(
     &self,
     handle_bytes: &[u8],
@@ -1928,9 +4061,12 @@ impl CoordinatorState {
     let handle = NimbleDigest::digest(handle_bytes);
 
     match self.ledger_store.read_ledger_by_index(&handle, index).await {
-      Ok(ledger_entry) => Ok(ledger_entry),
+      Ok(ledger_entry) => self.load_block(ledger_entry).await,
+      Err(LedgerStoreError::LedgerError(StorageError::PrunedIndex)) => {
+        Err(CoordinatorError::PrunedIndex)
+      },
       Err(error) => {
-        eprintln!(
+        error!(
           "Failed to read ledger by index from the ledger store {:?}",
           error,
         );
@@ -1939,6 +4075,90 @@ impl CoordinatorState {
     }
   }
 
+  /// Reads the entry at `index` of the ledger with the given handle, as kept in the ledger
+  /// store, with its block loaded from the content store if it is a reference
+  pub async fn read_ledger_entry(
+    &self,
+    handle: &Handle,
+    index: usize,
+  ) -> Result<LedgerEntry, CoordinatorError> {
+    match self.ledger_store.read_ledger_by_index(handle, index).await {
+      Ok(ledger_entry) => self.load_block(ledger_entry).await,
+      Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      | Err(LedgerStoreError::LedgerError(StorageError::InvalidKey)) => {
+        Err(CoordinatorError::InvalidHandle)
+      },
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex)) => {
+        Err(CoordinatorError::InvalidHeight)
+      },
+      Err(LedgerStoreError::LedgerError(StorageError::PrunedIndex)) => {
+        Err(CoordinatorError::PrunedIndex)
+      },
+      Err(error) => {
+        error!(
+          "Failed to read ledger by index from the ledger store {:?}",
+          error,
+        );
+        Err(CoordinatorError::FailedToReadLedger)
+      },
+    }
+  }
+
+  /// Subscribes to the handles of the ledgers whose new entries got their receipts. A
+  /// subscriber reads the entries from the ledger store, and re-reads them if it lagged.
+  pub fn watch_updates(&self) -> broadcast::Receiver<Handle> {
+    self.updates.subscribe()
+  }
+
+  pub async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, CoordinatorError> {
+    match self.ledger_store.list_ledgers(cursor, limit).await {
+      Ok(handles) => Ok(handles),
+      Err(error) => {
+        error!("Failed to list ledgers in the ledger store {:?}", error);
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  pub async fn get_ledger_height(&self, handle: &Handle) -> Result<usize, CoordinatorError> {
+    match self.ledger_store.ledger_height(handle).await {
+      Ok(height) => Ok(height),
+      Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      | Err(LedgerStoreError::LedgerError(StorageError::InvalidKey)) => {
+        Err(CoordinatorError::InvalidHandle)
+      },
+      Err(error) => {
+        error!(
+          "Failed to read the ledger height from the ledger store {:?}",
+          error
+        );
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  pub async fn get_ledger_checkpoint(&self, handle: &Handle) -> Result<usize, CoordinatorError> {
+    match self.ledger_store.ledger_checkpoint(handle).await {
+      Ok(checkpoint) => Ok(checkpoint),
+      Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      | Err(LedgerStoreError::LedgerError(StorageError::InvalidKey)) => {
+        Err(CoordinatorError::InvalidHandle)
+      },
+      Err(error) => {
+        error!(
+          "Failed to read the ledger checkpoint from the ledger store {:?}",
+          error
+        );
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  #[instrument(skip_all)]
   pub async fn read_view_by_index(&self, index: usize) -> Result<LedgerEntry, CoordinatorError> {
     let ledger_entry = {
       let res = self.ledger_store.read_view_ledger_by_index(index).await;
@@ -1951,10 +4171,11 @@ impl CoordinatorState {
     Ok(ledger_entry)
   }
 
+  #[instrument(skip_all)]
   pub async fn read_view_tail(&self) -> Result<(LedgerEntry, usize, Vec<u8>), CoordinatorError> {
     let res = self.ledger_store.read_view_ledger_tail().await;
     if let Err(error) = res {
-      eprintln!(
+      error!(
         "Failed to read the view ledger tail from the ledger store {:?}",
         error,
       );
@@ -1964,4 +4185,490 @@ impl CoordinatorState {
     let (ledger_entry, height) = res.unwrap();
     Ok((ledger_entry, height, ATTESTATION_STR.as_bytes().to_vec()))
   }
+
+  /// Returns the endorsers of every view, along with the record of the view change if there
+  /// is one, by the height of the view in the view ledger
+  pub async fn read_view_changes(
+    &self,
+  ) -> Result<Vec<(usize, EndorserHostnames, Option<ViewChangeRecord>)>, CoordinatorError> {
+    let (_tail, height) = match self.ledger_store.read_view_ledger_tail().await {
+      Ok(res) => res,
+      Err(error) => {
+        error!("Failed to read the view ledger tail {:?}", error);
+        return Err(CoordinatorError::FailedToReadViewLedger);
+      },
+    };
+    let mut views = Vec::with_capacity(height);
+    for index in 1..=height {
+      let entry = self.read_view_by_index(index).await?;
+      let (endorsers, record) = decode_view_block(&entry.get_block().to_bytes())?;
+      views.push((index, endorsers, record));
+    }
+    Ok(views)
+  }
+
+  /// Probes each endorser of the current view with `ReadState`, and returns its URI along
+  /// with whether it is connected and active
+  pub async fn probe_endorsers(&self) -> Result<Vec<(String, bool)>, CoordinatorError> {
+    let (tail, height) = match self.ledger_store.read_view_ledger_tail().await {
+      Ok(res) => res,
+      Err(error) => {
+        error!("Failed to read the view ledger tail {:?}", error);
+        return Err(CoordinatorError::FailedToReadViewLedger);
+      },
+    };
+    if height == 0 {
+      return Ok(Vec::new());
+    }
+    let (endorsers, _record) = decode_view_block(&tail.get_block().to_bytes())?;
+
+    let modes = self.read_endorser_modes(&endorsers).await;
+    let health = endorsers
+      .into_iter()
+      .zip(modes)
+      .map(|((_pk, uri), mode)| (uri, mode == Some(endorser_proto::EndorserMode::Active)))
+      .collect();
+    Ok(health)
+  }
+
+  /// Returns the mode each of `endorsers` reports with `ReadState`, or `None` for those that
+  /// the coordinator is not connected to or that cannot be read
+  pub async fn read_endorser_modes(
+    &self,
+    endorsers: &EndorserHostnames,
+  ) -> Vec<Option<endorser_proto::EndorserMode>> {
+    let mut jobs = Vec::new();
+    for (pk, uri) in endorsers {
+      // an endorser that the coordinator disconnected from is down for good
+      let client = if self.get_endorser_pk(uri).as_ref() == Some(pk) {
+        self.get_endorser_client(pk)
+      } else {
+        None
+      };
+      let job = tokio::spawn(telemetry::propagate(async move {
+        let (mut client, uri) = client?;
+        match read_state_with_retry(&mut client, endorser_proto::ReadStateReq {}).await {
+          Ok(resp) => endorser_proto::EndorserMode::from_i32(resp.into_inner().mode),
+          Err(status) => {
+            process_error(&uri, None, &status);
+            None
+          },
+        }
+      }));
+      jobs.push(job);
+    }
+
+    let mut modes = Vec::with_capacity(jobs.len());
+    for job in jobs {
+      modes.push(job.await.unwrap_or(None));
+    }
+    modes
+  }
+
+  /// Returns, for each of `endorsers`, the number of ledgers it lags behind the others on and
+  /// the number of entries it misses on them, or `None` if its state cannot be read or it is
+  /// not active
+  pub async fn read_endorser_lag(
+    &self,
+    endorsers: &EndorserHostnames,
+  ) -> Vec<Option<(usize, usize)>> {
+    let states = self.read_endorser_states(endorsers).await;
+    let max_cut = compute_max_cut(&tail_maps(&states));
+    let lag = states
+      .iter()
+      .map(|(pk, _client, _uri, entries)| {
+        let ledgers = lagging_ledgers(entries, &max_cut);
+        let num_entries = ledgers
+          .iter()
+          .map(|(_handle, start, end)| end + 1 - start)
+          .sum::<usize>();
+        (pk.clone(), (ledgers.len(), num_entries))
+      })
+      .collect::<HashMap<Vec<u8>, (usize, usize)>>();
+    endorsers
+      .iter()
+      .map(|(pk, _uri)| lag.get(pk).cloned())
+      .collect()
+  }
+
+  /// Reads the tail of the view ledger, and returns how long the ledger store took, so as to
+  /// tell whether the store is reachable
+  pub async fn check_ledger_store(&self) -> Result<std::time::Duration, CoordinatorError> {
+    let start = std::time::Instant::now();
+    match self.ledger_store.read_view_ledger_tail().await {
+      Ok(_) => Ok(start.elapsed()),
+      Err(error) => {
+        error!("Failed to read the view ledger tail {:?}", error);
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  /// Returns the number of ledgers in the ledger store, by listing them all
+  pub async fn count_ledgers(&self) -> Result<usize, CoordinatorError> {
+    let mut num_ledgers = 0;
+    let mut cursor = None;
+    loop {
+      let handles = self
+        .list_ledgers(cursor.as_ref(), COUNT_LIST_PAGE_SIZE)
+        .await?;
+      num_ledgers += handles.len();
+      if handles.len() < COUNT_LIST_PAGE_SIZE {
+        return Ok(num_ledgers);
+      }
+      cursor = handles.last().cloned();
+    }
+  }
+
+  /// Returns the endorsers among `hostnames` that are up and have not joined a view yet
+  pub async fn probe_spare_endorsers(&self, hostnames: &[String]) -> Vec<String> {
+    let mut jobs = Vec::new();
+    for hostname in hostnames {
+      let uri = hostname.clone();
+      let tls = self.endorser_tls.clone();
+      let (connect_timeout, request_timeout) =
+        (self.endorser_connect_timeout, self.endorser_request_timeout);
+      let job = tokio::spawn(telemetry::propagate(async move {
+        let (channel, _connector) =
+          connect_endorser(&uri, tls.as_deref(), connect_timeout, request_timeout)
+            .await
+            .ok()?;
+        let mut client = endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
+        let resp = read_state_with_retry(&mut client, endorser_proto::ReadStateReq {})
+          .await
+          .ok()?;
+        Some(resp.into_inner().mode == endorser_proto::EndorserMode::Uninitialized as i32)
+      }));
+      jobs.push((hostname.clone(), job));
+    }
+
+    let mut spares = Vec::new();
+    for (hostname, job) in jobs {
+      if matches!(job.await, Ok(Some(true))) {
+        spares.push(hostname);
+      }
+    }
+    spares
+  }
+
+  /// Writes a backup of the view ledger and every ledger to `path`.
+  ///
+  /// Appends and view changes are held back only while the heights of the cut are read.
+  /// Entries up to those heights are written afterwards with their blocks loaded from the
+  /// content store, so the backup does not depend on it.
+  #[instrument(skip_all)]
+  pub async fn backup(&self, name: &str) -> Result<BackupManifest, CoordinatorError> {
+    // Backups are only written as files of the backup directory, so that callers cannot
+    // overwrite other files of the coordinator's machine
+    let dir = match &self.backup_dir {
+      Some(dir) => dir,
+      None => {
+        error!("Backups are disabled since no backup directory is configured");
+        return Err(CoordinatorError::InvalidBackupName);
+      },
+    };
+    let mut components = std::path::Path::new(name).components();
+    match (components.next(), components.next()) {
+      (Some(std::path::Component::Normal(n)), None) if n == name => (),
+      _ => {
+        error!("The backup name {} is not the name of a file", name);
+        return Err(CoordinatorError::InvalidBackupName);
+      },
+    }
+    let path = dir.join(name);
+
+    let _maintenance = self.maintenance.lock().await;
+    let (view_height, ledgers) = {
+      let _gate = self.append_gate.write().await;
+      let (_tail, view_height, _attestation) = self.read_view_tail().await?;
+      let mut ledgers = Vec::new();
+      let mut cursor = None;
+      loop {
+        let handles = self
+          .list_ledgers(cursor.as_ref(), BACKUP_LIST_PAGE_SIZE)
+          .await?;
+        if handles.is_empty() {
+          break;
+        }
+        for handle in &handles {
+          ledgers.push((*handle, self.get_ledger_height(handle).await?));
+        }
+        cursor = handles.last().cloned();
+      }
+      (view_height, ledgers)
+    };
+
+    // Write to a temporary file so that an interrupted backup is never mistaken for one. The
+    // file must be new, so that the backup never writes through a file that is already there.
+    let tmp_path = dir.join(format!(".{}.{:016x}.tmp", name, random::<u64>()));
+    let file = match std::fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(&tmp_path)
+    {
+      Ok(f) => f,
+      Err(error) => {
+        error!(
+          "Failed to create the backup file {:?} ({:?})",
+          tmp_path, error
+        );
+        return Err(CoordinatorError::FailedToWriteBackup);
+      },
+    };
+    let backup_err = |error| {
+      error!("Failed to write the backup {:?}", error);
+      CoordinatorError::FailedToWriteBackup
+    };
+
+    let mut writer = BackupWriter::new(std::io::BufWriter::new(file)).map_err(backup_err)?;
+    for idx in 1..=view_height {
+      let entry = self.read_view_by_index(idx).await?;
+      writer.write_view_entry(idx, &entry).map_err(backup_err)?;
+    }
+    for (handle, height) in &ledgers {
+      let checkpoint = self.get_ledger_checkpoint(handle).await?;
+      for idx in checkpoint..=*height {
+        let entry = self.read_ledger_by_index_internal(handle, idx).await?;
+        let entry = self.load_block(entry).await?;
+        writer
+          .write_ledger_entry(handle, idx, &entry)
+          .map_err(backup_err)?;
+      }
+    }
+    let (manifest, _writer) = writer.finish().map_err(backup_err)?;
+
+    if let Err(error) = std::fs::rename(&tmp_path, &path) {
+      error!("Failed to move the backup to {:?} ({:?})", path, error);
+      return Err(CoordinatorError::FailedToWriteBackup);
+    }
+
+    Ok(manifest)
+  }
+
+  /// Back-fills the replicas of a replicated ledger store that missed writes, and returns
+  /// the number of entries written
+  pub async fn repair_ledger_store(&self) -> Result<usize, CoordinatorError> {
+    match self.ledger_store.repair().await {
+      Ok(n) => Ok(n),
+      Err(error) => {
+        error!("Failed to repair the ledger store {:?}", error);
+        Err(CoordinatorError::FailedToCallLedgerStore)
+      },
+    }
+  }
+
+  /// Returns the number of ledgers, across endorsers, waiting for an endorser to be caught up
+  pub fn get_num_lagging_ledgers(&self) -> usize {
+    match self.lagging.ledgers.lock() {
+      Ok(ledgers) => ledgers.len(),
+      Err(_) => 0,
+    }
+  }
+
+  /// Waits until an endorser is found to lag behind on a ledger
+  pub async fn lagging_endorsers_reported(&self) {
+    self.lagging.reported.notified().await
+  }
+
+  /// Compares the ledger tail maps of the connected endorsers, and queues each ledger that an
+  /// endorser lags behind the others on to be caught up. Returns the number of ledgers queued.
+  pub async fn find_lagging_endorsers(&self) -> usize {
+    let states = self
+      .read_endorser_states(&self.get_endorser_hostnames())
+      .await;
+    let max_cut = compute_max_cut(&tail_maps(&states));
+    let mut num_lagging = 0;
+    for (pk, _client, _uri, entries) in &states {
+      for (handle, start, _end) in lagging_ledgers(entries, &max_cut) {
+        self.lagging.report(pk, &handle, start);
+        num_lagging += 1;
+      }
+    }
+    num_lagging
+  }
+
+  /// Brings the endorsers up to date on the ledgers they were found to lag behind on, and
+  /// returns the number of entries replayed to them. An endorser is sent the entries it
+  /// misses up to the tail of each ledger in the ledger store, `batch_size` at a time, and
+  /// the endorsers are caught up in parallel.
+  #[instrument(skip_all)]
+  pub async fn catch_up_endorsers(&self, batch_size: usize) -> usize {
+    let mut ledgers_by_endorser: HashMap<Vec<u8>, Vec<(Handle, usize)>> = HashMap::new();
+    for ((pk, handle), start) in self.lagging.take() {
+      ledgers_by_endorser
+        .entry(pk)
+        .or_default()
+        .push((handle, start));
+    }
+
+    let mut jobs = Vec::new();
+    for (pk, ledgers) in ledgers_by_endorser {
+      let (mut client, uri) = match self.get_endorser_client(&pk) {
+        Some(res) => res,
+        None => continue,
+      };
+      let ledger_store = self.ledger_store.clone();
+      let append_gate = self.append_gate.clone();
+      let job = tokio::spawn(telemetry::propagate(async move {
+        let mut num_replayed = 0;
+        for (handle, start) in ledgers {
+          let end = match ledger_store.ledger_height(&handle).await {
+            Ok(height) => height,
+            Err(error) => {
+              error!("Failed to read the height of a ledger {:?}", error);
+              continue;
+            },
+          };
+          let mut batch_start = start;
+          while batch_start <= end {
+            let batch_end = std::cmp::min(end, batch_start + batch_size - 1);
+            // a batch attaches receipts to the ledger store, so it holds the gate as an
+            // append does, but only for as long as the batch takes
+            let _gate = append_gate.read().await;
+            let res = update_endorser(
+              ledger_store.clone(),
+              &mut client,
+              handle,
+              batch_start,
+              batch_end,
+              batch_size,
+            )
+            .await;
+            match res {
+              Ok(n) => num_replayed += n,
+              Err(status) => return (pk, uri, num_replayed, Err(status)),
+            }
+            batch_start = batch_end + 1;
+          }
+        }
+        (pk, uri, num_replayed, Ok(()))
+      }));
+      jobs.push(job);
+    }
+
+    let mut num_replayed = 0;
+    for job in jobs {
+      let (pk, uri, n, res) = match job.await {
+        Ok(res) => res,
+        Err(error) => {
+          error!("Failed to catch up an endorser {:?}", error);
+          continue;
+        },
+      };
+      num_replayed += n;
+      metrics::CAUGHT_UP_ENTRIES.inc_by(n as u64);
+      if let Err(status) = res {
+        error!(
+          "Failed to bring endorser {} up to date (status={:?})",
+          uri, status
+        );
+        if process_error(&uri, None, &status) == CoordinatorAction::RemoveEndorser {
+          self.disconnect_endorsers(&vec![(pk, uri)]).await;
+        }
+      }
+    }
+    num_replayed
+  }
+
+  // Returns the height of each ledger at the endorser that is furthest behind on it.
+  // Entries above that height may still have to be replayed to the endorser.
+  async fn endorser_ledger_heights(&self) -> Result<HashMap<Handle, usize>, CoordinatorError> {
+    let mut heights: Option<HashMap<Handle, usize>> = None;
+    for pk in self.get_endorser_pks() {
+      let (mut endorser_client, endorser) = match self.get_endorser_client(&pk) {
+        Some((client, endorser)) => (client, endorser),
+        None => continue,
+      };
+      let resp =
+        match read_state_with_retry(&mut endorser_client, endorser_proto::ReadStateReq {}).await {
+          Ok(resp) => resp.into_inner(),
+          Err(status) => {
+            error!(
+              "Failed to read the state of endorser {} ({:?})",
+              endorser, status
+            );
+            return Err(CoordinatorError::FailedToReadLatestState);
+          },
+        };
+      let endorser_heights = resp
+        .ledger_tail_map
+        .into_iter()
+        .filter_map(|entry| {
+          NimbleDigest::from_bytes(&entry.handle)
+            .ok()
+            .map(|handle| (handle, entry.height as usize))
+        })
+        .collect::<HashMap<Handle, usize>>();
+      // a ledger that an endorser does not hold may be replayed to it from the start
+      heights = Some(match heights {
+        None => endorser_heights,
+        Some(h) => h
+          .into_iter()
+          .filter_map(|(handle, height)| {
+            endorser_heights
+              .get(&handle)
+              .map(|e| (handle, std::cmp::min(height, *e)))
+          })
+          .collect(),
+      });
+    }
+    Ok(heights.unwrap_or_default())
+  }
+
+  /// Prunes each ledger so that it keeps its most recent entries, as many as configured,
+  /// after its checkpoint, and returns the number of entries deleted.
+  ///
+  /// The checkpoint is the newest entry that is old enough and whose receipts form a
+  /// quorum. A ledger is never pruned past the height any endorser holds it at, since the
+  /// entries above that height may have to be replayed to the endorser.
+  #[instrument(skip_all)]
+  pub async fn prune_ledgers(&self) -> Result<usize, CoordinatorError> {
+    let retain_entries = match self.retain_entries {
+      Some(r) => r,
+      None => return Ok(0),
+    };
+    let _maintenance = self.maintenance.lock().await;
+    let endorser_heights = self.endorser_ledger_heights().await?;
+
+    let mut num_pruned = 0;
+    let mut cursor = None;
+    loop {
+      let handles = self
+        .list_ledgers(cursor.as_ref(), PRUNE_LIST_PAGE_SIZE)
+        .await?;
+      if handles.is_empty() {
+        break;
+      }
+      for handle in &handles {
+        let endorsed = match endorser_heights.get(handle) {
+          Some(h) => *h,
+          None => continue,
+        };
+        let height = self.get_ledger_height(handle).await?;
+        let target = std::cmp::min(height.saturating_sub(retain_entries), endorsed);
+        let checkpoint = self.get_ledger_checkpoint(handle).await?;
+        for idx in (checkpoint + 1..=target).rev() {
+          let entry = self.read_ledger_by_index_internal(handle, idx).await?;
+          let has_quorum = match self.verifier_state.read() {
+            Ok(vs) => entry.get_receipts().check_quorum(&vs).is_ok(),
+            Err(_) => return Err(CoordinatorError::FailedToAcquireReadLock),
+          };
+          if !has_quorum {
+            continue;
+          }
+          match self.ledger_store.prune_ledger(handle, idx).await {
+            Ok(n) => num_pruned += n,
+            Err(error) => {
+              error!("Failed to prune a ledger in the ledger store {:?}", error);
+              return Err(CoordinatorError::FailedToCallLedgerStore);
+            },
+          }
+          break;
+        }
+      }
+      cursor = handles.last().cloned();
+    }
+
+    Ok(num_pruned)
+  }
 }
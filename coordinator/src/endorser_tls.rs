@@ -0,0 +1,253 @@
+//! TLS for the connections to endorsers. The coordinator checks the endorser's certificate
+//! against a CA and presents its own if the endorsers require one. It keeps the certificate the
+//! endorser presented, which the endorser signs with its key, so that the certificate can be
+//! pinned to the public key recorded in the view ledger. A renewed certificate is accepted
+//! when the endorser signed it with the same key.
+
+use crate::errors::CoordinatorError;
+use ledger::{
+  endorser_proto,
+  signature::{PublicKey, PublicKeyTrait, Signature, SignatureTrait},
+  tls_certificate_hash,
+};
+use std::{
+  convert::TryFrom,
+  future::Future,
+  io,
+  pin::Pin,
+  sync::{Arc, Mutex},
+  task::{Context, Poll},
+  time::Duration,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+  client::TlsStream,
+  rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName},
+  TlsConnector,
+};
+use tonic::transport::{Endpoint, Uri};
+use tower::Service;
+use tracing::{error, info};
+
+const DEFAULT_TLS_PORT: u16 = 443;
+
+/// The TLS configuration for connecting to endorsers
+pub struct EndorserTls {
+  config: Arc<ClientConfig>,
+}
+
+impl EndorserTls {
+  /// Loads the CA certificate that endorser certificates are signed by and, if endorsers
+  /// require coordinators to present one, the coordinator's certificate and key, from PEM files
+  pub fn from_files(ca: &str, identity: Option<(&str, &str)>) -> Result<Self, CoordinatorError> {
+    let invalid = |path: &str, reason: String| {
+      error!("Invalid endorser TLS file {}: {}", path, reason);
+      CoordinatorError::InvalidEndorserTls
+    };
+    let read_certs = |path: &str| -> Result<Vec<Certificate>, CoordinatorError> {
+      let pem = std::fs::read(path).map_err(|e| invalid(path, e.to_string()))?;
+      match rustls_pemfile::certs(&mut pem.as_slice()) {
+        Ok(certs) if !certs.is_empty() => Ok(certs.into_iter().map(Certificate).collect()),
+        _ => Err(invalid(path, "no certificate".to_string())),
+      }
+    };
+
+    let mut roots = RootCertStore::empty();
+    for cert in read_certs(ca)? {
+      roots.add(&cert).map_err(|e| invalid(ca, e.to_string()))?;
+    }
+    let builder = ClientConfig::builder()
+      .with_safe_defaults()
+      .with_root_certificates(roots);
+    let mut config = match identity {
+      Some((cert, key)) => {
+        let pem = std::fs::read(key).map_err(|e| invalid(key, e.to_string()))?;
+        let key_der = rustls_pemfile::read_all(&mut pem.as_slice())
+          .map_err(|e| invalid(key, e.to_string()))?
+          .into_iter()
+          .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(der)
+            | rustls_pemfile::Item::RSAKey(der)
+            | rustls_pemfile::Item::ECKey(der) => Some(der),
+            _ => None,
+          })
+          .ok_or_else(|| invalid(key, "no private key".to_string()))?;
+        builder
+          .with_single_cert(read_certs(cert)?, PrivateKey(key_der))
+          .map_err(|e| invalid(cert, e.to_string()))?
+      },
+      None => builder.with_no_client_auth(),
+    };
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(EndorserTls {
+      config: Arc::new(config),
+    })
+  }
+
+  /// Returns a connector for one endorser, which gives up on checking a new certificate
+  /// of the endorser after `timeout`
+  pub fn connector(&self, timeout: Duration) -> EndorserConnector {
+    EndorserConnector {
+      config: self.config.clone(),
+      pinned: Arc::new(Mutex::new(Pinned::default())),
+      timeout,
+    }
+  }
+}
+
+/// Checks that the endorser with the public key signed the TLS certificate it presented
+pub fn verify_tls_certificate(pk: &[u8], cert: &[u8], signature: &[u8]) -> bool {
+  match (PublicKey::from_bytes(pk), Signature::from_bytes(signature)) {
+    (Ok(pk), Ok(signature)) => signature
+      .verify(&pk, &tls_certificate_hash(cert).to_bytes())
+      .is_ok(),
+    _ => false,
+  }
+}
+
+#[derive(Default)]
+struct Pinned {
+  // the certificate, in DER, that the endorser presented last
+  cert: Option<Vec<u8>>,
+  // the endorser's public key, once it is known to have signed the certificate
+  pk: Option<Vec<u8>>,
+}
+
+/// Connects to an endorser over TLS. The connector remembers the certificate the endorser
+/// presented. Once the endorser's key is pinned, the endorser may reconnect with another
+/// certificate only if it signed that certificate with the same key; until then, and for
+/// a certificate it did not sign, the connection is refused.
+#[derive(Clone)]
+pub struct EndorserConnector {
+  config: Arc<ClientConfig>,
+  pinned: Arc<Mutex<Pinned>>,
+  timeout: Duration,
+}
+
+impl EndorserConnector {
+  /// Returns the certificate, in DER, that the endorser presented
+  pub fn peer_certificate(&self) -> Option<Vec<u8>> {
+    self
+      .pinned
+      .lock()
+      .ok()
+      .and_then(|pinned| pinned.cert.clone())
+  }
+
+  /// Pins the public key of the endorser, which was checked to have signed the certificate
+  /// it presented
+  pub fn pin_public_key(&self, pk: &[u8]) {
+    if let Ok(mut pinned) = self.pinned.lock() {
+      pinned.pk = Some(pk.to_vec());
+    }
+  }
+
+  // Checks a certificate the endorser presented in place of the pinned one, by asking the
+  // endorser over a connection of its own for its public key and its signature over the
+  // certificate it is served with
+  async fn verify_new_certificate(&self, uri: Uri, pk: &[u8], cert: &[u8]) -> io::Result<()> {
+    let refused =
+      |reason: &str| io::Error::new(io::ErrorKind::PermissionDenied, reason.to_string());
+    let connector = EndorserConnector {
+      config: self.config.clone(),
+      pinned: Arc::new(Mutex::new(Pinned::default())),
+      timeout: self.timeout,
+    };
+    let channel = Endpoint::from(uri)
+      .connect_timeout(self.timeout)
+      .timeout(self.timeout)
+      .connect_with_connector(connector.clone())
+      .await
+      .map_err(io::Error::other)?;
+    let endorser_proto::GetPublicKeyResp {
+      pk: new_pk,
+      tls_signature,
+    } = endorser_proto::endorser_call_client::EndorserCallClient::new(channel)
+      .get_public_key(endorser_proto::GetPublicKeyReq {})
+      .await
+      .map_err(io::Error::other)?
+      .into_inner();
+
+    if new_pk != pk {
+      return Err(refused("the endorser presented another public key"));
+    }
+    // the endorser may have been served with yet another certificate in between
+    if connector.peer_certificate().as_deref() != Some(cert) {
+      return Err(refused("the endorser changed its certificate again"));
+    }
+    if !verify_tls_certificate(pk, cert, &tls_signature) {
+      return Err(refused(
+        "the endorser presented another certificate, which is not signed with its key",
+      ));
+    }
+    Ok(())
+  }
+}
+
+impl Service<Uri> for EndorserConnector {
+  type Response = TlsStream<TcpStream>;
+  type Error = io::Error;
+  type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+  fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn call(&mut self, uri: Uri) -> Self::Future {
+    let connector = TlsConnector::from(self.config.clone());
+    let this = self.clone();
+    Box::pin(async move {
+      let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, reason.to_string());
+      let host = uri
+        .host()
+        .ok_or_else(|| invalid("the endorser URI has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+      let port = uri.port_u16().unwrap_or(DEFAULT_TLS_PORT);
+      let server_name =
+        ServerName::try_from(host.as_str()).map_err(|_| invalid("invalid endorser host name"))?;
+
+      let tcp = TcpStream::connect((host.as_str(), port)).await?;
+      tcp.set_nodelay(true)?;
+      let stream = connector.connect(server_name, tcp).await?;
+
+      let cert = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .map(|cert| cert.0.clone())
+        .ok_or_else(|| invalid("the endorser presented no certificate"))?;
+      let pk = {
+        let mut pinned = this
+          .pinned
+          .lock()
+          .map_err(|_| io::Error::other("poisoned lock"))?;
+        match (&pinned.cert, &pinned.pk) {
+          (Some(pinned_cert), Some(pk)) if *pinned_cert != cert => pk.clone(),
+          (Some(pinned_cert), None) if *pinned_cert != cert => {
+            return Err(io::Error::new(
+              io::ErrorKind::PermissionDenied,
+              "the endorser presented another certificate before its key was pinned",
+            ));
+          },
+          _ => {
+            pinned.cert = Some(cert);
+            return Ok(stream);
+          },
+        }
+      };
+
+      this.verify_new_certificate(uri, &pk, &cert).await?;
+      info!(
+        "The endorser {}:{} presented a new certificate signed with its key",
+        host, port
+      );
+      if let Ok(mut pinned) = this.pinned.lock() {
+        pinned.cert = Some(cert);
+      }
+      Ok(stream)
+    })
+  }
+}
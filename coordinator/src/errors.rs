@@ -64,4 +64,85 @@ pub enum CoordinatorError {
   FailedToObtainQuorum,
   /// returned if failed to verify view change
   FailedToActivate,
+  /// returned if the content store type or its arguments are invalid
+  InvalidContentStore,
+  /// returned if a call to the content store fails
+  FailedToCallContentStore,
+  /// returned if the ledger cache size is not a positive integer
+  InvalidLedgerCacheSize,
+  /// returned if the key for encrypting the ledger store cannot be read
+  InvalidLedgerKey,
+  /// returned if a backup of the ledger store cannot be written
+  FailedToWriteBackup,
+  /// returned if backups are disabled or the backup name is not a file name
+  InvalidBackupName,
+  /// returned if the replicas of a replicated ledger store are invalid
+  InvalidReplicas,
+  /// returned if the requested entry was pruned from the ledger
+  PrunedIndex,
+  /// returned if the number of entries to retain in each ledger is not an integer
+  InvalidRetention,
+  /// returned if the ledger was deleted
+  LedgerDeleted,
+  /// returned if the lease duration is invalid or the ledger store cannot hold a lease
+  InvalidLease,
+  /// returned if another coordinator is the leader
+  NotLeader,
+  /// returned if a timeout for calls to endorsers is not a positive number of seconds
+  InvalidTimeout,
+  /// returned if the endorser to add is already in the current view
+  EndorserAlreadyInView,
+  /// returned if the endorser to remove is the last one in the current view
+  LastEndorserInView,
+  /// returned if the CA, certificate or key for connecting to endorsers over TLS is invalid
+  InvalidEndorserTls,
+  /// returned if the endorser's TLS certificate is not signed with the endorser's key
+  InvalidEndorserCertificate,
+  /// returned if the block of a retried request was stored, but is not yet endorsed
+  RequestInProgress,
+  /// returned if the maximum number of appends to a ledger in a batch is not positive
+  InvalidBatchSize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigError {
+  /// returned if the configuration file cannot be read
+  FailedToReadConfig(String),
+  /// returned if the configuration file is not valid TOML or YAML, or has unknown settings
+  InvalidConfigFile(String),
+  /// returned if a setting is unknown or its value is invalid
+  InvalidSetting(String, String),
+  /// returned if a setting that another one depends on is missing
+  MissingSetting(String),
+  /// returned if a secret is given both inline and in a file, or its file cannot be read
+  InvalidSecret(String),
+  /// returned if the authorization policy cannot be read or a client policy in it is invalid
+  InvalidPolicy(String),
+}
+
+impl std::fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConfigError::FailedToReadConfig(reason) => {
+        write!(f, "cannot read the configuration file ({})", reason)
+      },
+      ConfigError::InvalidConfigFile(reason) => {
+        write!(f, "invalid configuration file ({})", reason)
+      },
+      ConfigError::InvalidSetting(key, value) => write!(f, "invalid value {:?} for {}", value, key),
+      ConfigError::MissingSetting(key) => write!(f, "{} must be set", key),
+      ConfigError::InvalidSecret(reason) => write!(f, "invalid secret ({})", reason),
+      ConfigError::InvalidPolicy(reason) => write!(f, "invalid authorization policy ({})", reason),
+    }
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AuthorizationError {
+  /// returned if the client presented no certificate and no policy applies to every client
+  Unauthenticated,
+  /// returned if no policy lets the client make the call on the ledger
+  PermissionDenied,
 }
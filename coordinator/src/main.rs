@@ -1,10 +1,30 @@
+mod append_queue;
+mod authorizer;
+mod catchup;
+mod config;
 mod coordinator_state;
+mod endorser_tls;
 mod errors;
-
-use crate::coordinator_state::CoordinatorState;
-use ledger::CustomSerde;
-use std::{collections::HashMap, sync::Arc};
-use tonic::{transport::Server, Request, Response, Status};
+mod metrics;
+mod supervisor;
+
+use crate::{
+  authorizer::{AllowAll, Authorizer, ClientIdentity, Operation, PolicyAuthorizer},
+  catchup::CatchUp,
+  config::{CatchUpConfig, CoordinatorConfig},
+  coordinator_state::{decode_view_block, CoordinatorState, ViewChangeRecord},
+  errors::{AuthorizationError, CoordinatorError},
+  metrics::MeteredCallService,
+  supervisor::Supervisor,
+};
+use ledger::{CustomSerde, EndorserHostnames, NimbleDigest, NimbleHashTrait, Receipts};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+  transport::{Certificate, Identity, Server, ServerTlsConfig},
+  Request, Response, Status,
+};
 
 #[allow(clippy::derive_partial_eq_without_eq)]
 pub mod coordinator_proto {
@@ -14,57 +34,243 @@ pub mod coordinator_proto {
 use clap::{App, Arg};
 use coordinator_proto::{
   call_server::{Call, CallServer},
-  AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadByIndexReq, ReadByIndexResp,
-  ReadLatestReq, ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq,
-  ReadViewTailResp,
+  AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp, NewLedgerReq, NewLedgerResp,
+  ReadByIndexReq, ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewByIndexReq,
+  ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp, WatchReq, WatchResp,
 };
 
 use axum::{
-  extract::{Extension, Path},
-  http::StatusCode,
+  extract::{Extension, Path, Query},
+  http::{header, Request as HttpRequest, StatusCode},
+  middleware::{self, Next},
   response::IntoResponse,
-  routing::get,
+  routing::{get, post, put},
   Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tower::ServiceBuilder;
+use tracing::{error, info, warn};
+
+const MAX_REQUEST_ID_SIZE: usize = 64; // bytes: the longest request ID a client may give
+const WATCH_CHANNEL_BUFFER: usize = 16; // entries sent ahead of a watcher that reads slowly
+const WATCH_POLL_INTERVAL: u64 = 1; // seconds: the longest a watcher waits before re-reading
 
 pub struct CoordinatorServiceState {
   state: Arc<CoordinatorState>,
+  authorizer: Arc<dyn Authorizer>,
 }
 
 impl CoordinatorServiceState {
   pub fn new(coordinator: Arc<CoordinatorState>) -> Self {
-    CoordinatorServiceState { state: coordinator }
+    CoordinatorServiceState {
+      state: coordinator,
+      authorizer: Arc::new(AllowAll),
+    }
+  }
+
+  /// Serves only the calls that `authorizer` lets each client make
+  pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+    self.authorizer = authorizer;
+    self
   }
 
   #[cfg(test)]
-  pub fn get_state(&self) -> &CoordinatorState {
+  pub fn get_state(&self) -> &Arc<CoordinatorState> {
     &self.state
   }
+
+  // Only the leader serves when coordinators elect one; the others answer `Unavailable`, so
+  // that clients move on to another coordinator
+  #[allow(clippy::result_large_err)]
+  fn check_leader(&self) -> Result<(), Status> {
+    if self.state.is_leader() {
+      Ok(())
+    } else {
+      Err(Status::unavailable("The coordinator is not the leader"))
+    }
+  }
+
+  // Checks that the client that sent the request, as identified by the certificate it
+  // presented, may make the call on the ledger
+  #[allow(clippy::result_large_err)]
+  fn authorize<T>(
+    &self,
+    request: &Request<T>,
+    operation: Operation,
+    handle: &[u8],
+  ) -> Result<(), Status> {
+    let client = ClientIdentity::new(request.peer_certs());
+    match self.authorizer.authorize(&client, operation, handle) {
+      Ok(()) => Ok(()),
+      Err(AuthorizationError::Unauthenticated) => Err(Status::unauthenticated(
+        "The client presented no certificate",
+      )),
+      Err(AuthorizationError::PermissionDenied) => {
+        warn!(
+          "refused a {:?} call from the client {:?}",
+          operation,
+          client.fingerprint()
+        );
+        Err(Status::permission_denied(
+          "The client may not make this call on the ledger",
+        ))
+      },
+    }
+  }
+
+  // Returns the hash of the nonces and the receipts of the entry at `index` if the request
+  // with the ID is a retry of one that added its block there already, i.e., if the entry was
+  // stored with the same ID
+  async fn read_retried_entry(
+    &self,
+    handle: &[u8],
+    index: usize,
+    block: &[u8],
+    request_id: &[u8],
+  ) -> Result<Option<(NimbleDigest, Receipts)>, Status> {
+    if request_id.is_empty() {
+      return Ok(None);
+    }
+    if request_id.len() > MAX_REQUEST_ID_SIZE {
+      return Err(Status::invalid_argument(format!(
+        "The request ID is longer than {} bytes",
+        MAX_REQUEST_ID_SIZE
+      )));
+    }
+    match self
+      .state
+      .read_retried_entry(handle, index, block, request_id)
+      .await
+    {
+      Ok(Some(entry)) => {
+        info!(
+          "Answered the retried request {} with its original receipts",
+          hex::encode(request_id)
+        );
+        metrics::RETRIED_REQUESTS.inc();
+        Ok(Some(entry))
+      },
+      Ok(None) => Ok(None),
+      Err(CoordinatorError::RequestInProgress) => {
+        Err(Status::unavailable("The request is still in progress"))
+      },
+      Err(CoordinatorError::FailedToObtainQuorum) => Err(Status::aborted(
+        "Failed to obtain a quorum of receipts for the stored entry",
+      )),
+      Err(_) => Err(Status::aborted("Failed to read the ledger")),
+    }
+  }
+}
+
+// Sends the entries of a ledger from `height` on, each once its receipts are attached, until
+// the ledger is deleted, the watcher goes away, or this coordinator is no longer the leader
+async fn watch_ledger(
+  state: Arc<CoordinatorState>,
+  handle_bytes: Vec<u8>,
+  mut height: usize,
+  tx: mpsc::Sender<Result<WatchResp, Status>>,
+) {
+  let handle = NimbleDigest::digest(&handle_bytes);
+  // subscribe before reading, so that no entry is missed in between
+  let mut updates = state.watch_updates();
+  loop {
+    loop {
+      let status = match state.read_ledger_entry(&handle, height).await {
+        // the endorsers have not signed the entry yet
+        Ok(ledger_entry) if ledger_entry.get_receipts().is_empty() => break,
+        Ok(ledger_entry) => {
+          let deleted = ledger_entry.is_tombstone();
+          let resp = WatchResp {
+            height: height as u64,
+            block: ledger_entry.get_block().to_bytes(),
+            hash_nonces: ledger_entry.get_nonces().hash().to_bytes(),
+            receipts: ledger_entry.get_receipts().to_bytes(),
+          };
+          if tx.send(Ok(resp)).await.is_err() || deleted {
+            return;
+          }
+          height += 1;
+          continue;
+        },
+        Err(CoordinatorError::InvalidHeight) => break,
+        Err(CoordinatorError::InvalidHandle) => Status::not_found("The ledger does not exist"),
+        Err(CoordinatorError::PrunedIndex) => {
+          Status::out_of_range("The entry was pruned from the ledger")
+        },
+        Err(_) => Status::aborted("Failed to read a ledger"),
+      };
+      let _ = tx.send(Err(status)).await;
+      return;
+    }
+
+    let updated = async {
+      loop {
+        match updates.recv().await {
+          Ok(updated) if updated == handle => return,
+          Ok(_) => continue,
+          // missed updates may include this ledger's
+          Err(broadcast::error::RecvError::Lagged(_)) => return,
+          Err(broadcast::error::RecvError::Closed) => std::future::pending::<()>().await,
+        }
+      }
+    };
+    // another coordinator sharing the ledger store may have appended in the meantime
+    tokio::select! {
+      _ = tx.closed() => return,
+      _ = tokio::time::timeout(Duration::from_secs(WATCH_POLL_INTERVAL), updated) => {},
+    }
+    if !state.is_leader() {
+      let _ = tx
+        .send(Err(Status::unavailable(
+          "The coordinator is not the leader",
+        )))
+        .await;
+      return;
+    }
+  }
 }
 
 #[tonic::async_trait]
 impl Call for CoordinatorServiceState {
+  type WatchStream = ReceiverStream<Result<WatchResp, Status>>;
+
   async fn new_ledger(
     &self,
     req: Request<NewLedgerReq>,
   ) -> Result<Response<NewLedgerResp>, Status> {
+    self.check_leader()?;
+    self.authorize(&req, Operation::NewLedger, &req.get_ref().handle)?;
+
     let NewLedgerReq {
       handle: handle_bytes,
       block: block_bytes,
+      request_id,
     } = req.into_inner();
 
-    let res = self
-      .state
-      .create_ledger(None, &handle_bytes, &block_bytes)
-      .await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to create a new ledger"));
-    }
+    // a retry of a request that created the ledger already gets the original receipts, which
+    // are looked for again if the ledger is found to exist meanwhile
+    let retried = self
+      .read_retried_entry(&handle_bytes, 0, &block_bytes, &request_id)
+      .await?;
+    let receipts = match retried {
+      Some((_hash_nonces, receipts)) => receipts,
+      None => match self
+        .state
+        .create_ledger_with_request(None, &handle_bytes, &block_bytes, &request_id)
+        .await
+      {
+        Ok(receipts) => receipts,
+        Err(_) => match self
+          .read_retried_entry(&handle_bytes, 0, &block_bytes, &request_id)
+          .await?
+        {
+          Some((_hash_nonces, receipts)) => receipts,
+          None => return Err(Status::aborted("Failed to create a new ledger")),
+        },
+      },
+    };
 
-    let receipts = res.unwrap();
     let reply = NewLedgerResp {
       receipts: receipts.to_bytes(),
     };
@@ -72,21 +278,42 @@ impl Call for CoordinatorServiceState {
   }
 
   async fn append(&self, request: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    self.check_leader()?;
+    self.authorize(&request, Operation::Append, &request.get_ref().handle)?;
+
     let AppendReq {
       handle: handle_bytes,
       block: block_bytes,
       expected_height,
+      request_id,
     } = request.into_inner();
-
-    let res = self
-      .state
-      .append_ledger(None, &handle_bytes, &block_bytes, expected_height as usize)
-      .await;
-    if res.is_err() {
-      return Err(Status::aborted("Failed to append to a ledger"));
-    }
-
-    let (hash_nonces, receipts) = res.unwrap();
+    let index = expected_height as usize;
+
+    // a retry of a request that added the block already gets the original receipts, which
+    // are looked for again if the append finds the block added meanwhile
+    let retried = self
+      .read_retried_entry(&handle_bytes, index, &block_bytes, &request_id)
+      .await?;
+    let (hash_nonces, receipts) = match retried {
+      Some(entry) => entry,
+      None => match self
+        .state
+        .append_ledger_with_request(None, &handle_bytes, &block_bytes, index, &request_id)
+        .await
+      {
+        Ok(entry) => entry,
+        Err(CoordinatorError::LedgerDeleted) => {
+          return Err(Status::failed_precondition("The ledger is deleted"));
+        },
+        Err(_) => match self
+          .read_retried_entry(&handle_bytes, index, &block_bytes, &request_id)
+          .await?
+        {
+          Some(entry) => entry,
+          None => return Err(Status::aborted("Failed to append to a ledger")),
+        },
+      },
+    };
     let reply = AppendResp {
       hash_nonces: hash_nonces.to_bytes(),
       receipts: receipts.to_bytes(),
@@ -95,10 +322,43 @@ impl Call for CoordinatorServiceState {
     Ok(Response::new(reply))
   }
 
+  async fn delete_ledger(
+    &self,
+    request: Request<DeleteLedgerReq>,
+  ) -> Result<Response<DeleteLedgerResp>, Status> {
+    self.check_leader()?;
+    self.authorize(&request, Operation::Delete, &request.get_ref().handle)?;
+
+    let DeleteLedgerReq {
+      handle: handle_bytes,
+      expected_height,
+    } = request.into_inner();
+
+    let res = self
+      .state
+      .delete_ledger(None, &handle_bytes, expected_height as usize)
+      .await;
+    match res {
+      Ok(receipts) => {
+        let reply = DeleteLedgerResp {
+          receipts: receipts.to_bytes(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(CoordinatorError::LedgerDeleted) => {
+        Err(Status::failed_precondition("The ledger is already deleted"))
+      },
+      Err(_) => Err(Status::aborted("Failed to delete a ledger")),
+    }
+  }
+
   async fn read_latest(
     &self,
     request: Request<ReadLatestReq>,
   ) -> Result<Response<ReadLatestResp>, Status> {
+    self.check_leader()?;
+    self.authorize(&request, Operation::Read, &request.get_ref().handle)?;
+
     let ReadLatestReq {
       handle: handle_bytes,
       nonce: nonce_bytes,
@@ -126,6 +386,9 @@ impl Call for CoordinatorServiceState {
     &self,
     request: Request<ReadByIndexReq>,
   ) -> Result<Response<ReadByIndexResp>, Status> {
+    self.check_leader()?;
+    self.authorize(&request, Operation::Read, &request.get_ref().handle)?;
+
     let ReadByIndexReq {
       handle: handle_bytes,
       index,
@@ -144,6 +407,9 @@ impl Call for CoordinatorServiceState {
         };
         Ok(Response::new(reply))
       },
+      Err(CoordinatorError::PrunedIndex) => {
+        Err(Status::out_of_range("The entry was pruned from the ledger"))
+      },
       Err(_) => return Err(Status::aborted("Failed to read a ledger")),
     }
   }
@@ -152,6 +418,8 @@ impl Call for CoordinatorServiceState {
     &self,
     request: Request<ReadViewByIndexReq>,
   ) -> Result<Response<ReadViewByIndexResp>, Status> {
+    self.check_leader()?;
+
     let ReadViewByIndexReq { index } = request.into_inner();
 
     let res = self.state.read_view_by_index(index as usize).await;
@@ -172,6 +440,8 @@ impl Call for CoordinatorServiceState {
     &self,
     _request: Request<ReadViewTailReq>,
   ) -> Result<Response<ReadViewTailResp>, Status> {
+    self.check_leader()?;
+
     let res = self.state.read_view_tail().await;
     if res.is_err() {
       return Err(Status::aborted("Failed to read the view ledger tail"));
@@ -187,6 +457,37 @@ impl Call for CoordinatorServiceState {
 
     Ok(Response::new(reply))
   }
+
+  async fn watch(&self, request: Request<WatchReq>) -> Result<Response<Self::WatchStream>, Status> {
+    self.check_leader()?;
+    self.authorize(&request, Operation::Read, &request.get_ref().handle)?;
+
+    let WatchReq {
+      handle: handle_bytes,
+      from_height,
+    } = request.into_inner();
+
+    match self
+      .state
+      .get_ledger_height(&NimbleDigest::digest(&handle_bytes))
+      .await
+    {
+      Ok(_) => {},
+      Err(CoordinatorError::InvalidHandle) => {
+        return Err(Status::not_found("The ledger does not exist"))
+      },
+      Err(_) => return Err(Status::aborted("Failed to read a ledger")),
+    }
+
+    let (tx, rx) = mpsc::channel(WATCH_CHANNEL_BUFFER);
+    tokio::spawn(watch_ledger(
+      self.state.clone(),
+      handle_bytes,
+      from_height as usize,
+      tx,
+    ));
+    Ok(Response::new(ReceiverStream::new(rx)))
+  }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -201,14 +502,14 @@ async fn get_endorser(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&uri);
   if res.is_err() {
-    eprintln!("received a bad endorser uri {:?}", res);
+    warn!("received a bad endorser uri {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let endorser_uri = res.unwrap();
 
   let res = std::str::from_utf8(&endorser_uri);
   if res.is_err() {
-    eprintln!(
+    error!(
       "cannot convert the endorser uri {:?} to string {:?}",
       endorser_uri, res
     );
@@ -219,7 +520,7 @@ async fn get_endorser(
   let res = state.get_endorser_pk(endorser_uri_str);
   match res {
     None => {
-      eprintln!(
+      error!(
         "failed to delete the endorser {} ({:?})",
         endorser_uri_str, res
       );
@@ -240,14 +541,14 @@ async fn new_endorser(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&uri);
   if res.is_err() {
-    eprintln!("received a bad endorser uri {:?}", res);
+    warn!("received a bad endorser uri {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let endorser_uri = res.unwrap();
 
   let res = String::from_utf8(endorser_uri.clone());
   if res.is_err() {
-    eprintln!(
+    error!(
       "cannot convert the endorser uri {:?} to string {:?}",
       endorser_uri, res
     );
@@ -263,7 +564,7 @@ async fn new_endorser(
 
   let res = state.replace_endorsers(&endorsers).await;
   if res.is_err() {
-    eprintln!("failed to add the endorser ({:?})", res);
+    error!("failed to add the endorser ({:?})", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
 
@@ -284,14 +585,14 @@ async fn delete_endorser(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&uri);
   if res.is_err() {
-    eprintln!("received a bad endorser uri {:?}", res);
+    warn!("received a bad endorser uri {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let endorser_uri = res.unwrap();
 
   let res = std::str::from_utf8(&endorser_uri);
   if res.is_err() {
-    eprintln!(
+    error!(
       "cannot convert the endorser uri {:?} to string {:?}",
       endorser_uri, res
     );
@@ -302,7 +603,7 @@ async fn delete_endorser(
   let res = state.get_endorser_pk(endorser_uri_str);
   let pk = match res {
     None => {
-      eprintln!(
+      error!(
         "failed to find the endorser {} ({:?})",
         endorser_uri_str, res
       );
@@ -322,872 +623,3196 @@ async fn delete_endorser(
   (StatusCode::OK, Json(json!(resp)))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-  let config = App::new("coordinator")
-    .arg(
-      Arg::with_name("nimbledb")
-        .short("n")
-        .long("nimbledb")
-        .help("The database name")
-        .default_value("nimble_cosmosdb"),
-    )
-    .arg(
-      Arg::with_name("cosmosurl")
-        .short("c")
-        .long("cosmosurl")
-        .takes_value(true)
-        .help("The COSMOS URL"),
-    )
-    .arg(
-      Arg::with_name("storage_account")
-        .short("a")
-        .long("storage_account")
-        .takes_value(true)
-        .help("The storage account name"),
-    )
-    .arg(
-      Arg::with_name("storage_master_key")
-        .short("k")
-        .long("storage_master_key")
-        .takes_value(true)
-        .help("The storage master key"),
-    )
-    .arg(
-      Arg::with_name("store")
-        .short("s")
-        .long("store")
-        .help("The type of store used by the service.")
-        .default_value("memory"),
-    )
-    .arg(
-      Arg::with_name("host")
-        .short("t")
-        .long("host")
-        .help("The hostname to run the service on.")
-        .default_value("[::1]"),
-    )
-    .arg(
-      Arg::with_name("port")
-        .short("p")
-        .long("port")
-        .help("The port number to run the coordinator service on.")
-        .default_value("8080"),
-    )
-    .arg(
-      Arg::with_name("ctrl")
-        .short("r")
-        .long("ctrl")
-        .help("The port number to run the coordinator control service on.")
-        .default_value("8090"),
-    )
-    .arg(
-      Arg::with_name("endorser")
-        .short("e")
-        .long("endorser")
-        .help("List of URLs to Endorser Services")
-        .use_delimiter(true)
-        .default_value("http://[::1]:9090"),
-    )
-    .arg(
-      Arg::with_name("channels")
-        .short("l")
-        .long("channels")
-        .takes_value(true)
-        .help("The number of grpc channels"),
-    );
+// Adds the endorser to the current view, keeping the endorsers of the view active
+async fn add_member(
+  Path(uri): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  change_member(uri, state, true).await
+}
 
-  let cli_matches = config.get_matches();
-  let hostname = cli_matches.value_of("host").unwrap();
-  let port_number = cli_matches.value_of("port").unwrap();
-  let ctrl_port = cli_matches.value_of("ctrl").unwrap();
-  let store = cli_matches.value_of("store").unwrap();
-  let addr = format!("{}:{}", hostname, port_number).parse()?;
-  let str_vec: Vec<&str> = cli_matches.values_of("endorser").unwrap().collect();
-  let endorser_hostnames = str_vec
-    .iter()
-    .filter(|e| !e.is_empty())
-    .map(|e| e.to_string())
-    .collect::<Vec<String>>();
+// Removes the endorser from the current view, keeping the other endorsers active
+async fn remove_member(
+  Path(uri): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  change_member(uri, state, false).await
+}
 
-  let mut ledger_store_args = HashMap::<String, String>::new();
-  if let Some(x) = cli_matches.value_of("cosmosurl") {
-    ledger_store_args.insert(String::from("COSMOS_URL"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("nimbledb") {
-    ledger_store_args.insert(String::from("NIMBLE_DB"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("storage_account") {
-    ledger_store_args.insert(String::from("STORAGE_ACCOUNT"), x.to_string());
-  }
-  if let Some(x) = cli_matches.value_of("storage_master_key") {
-    ledger_store_args.insert(String::from("STORAGE_MASTER_KEY"), x.to_string());
-  }
-  let num_grpc_channels: Option<usize> = if let Some(x) = cli_matches.value_of("channels") {
-    match x.to_string().parse() {
-      Ok(v) => Some(v),
-      Err(_) => panic!("Failed to parse the number of grpc channels"),
-    }
-  } else {
-    None
+async fn change_member(
+  uri: String,
+  state: Arc<CoordinatorState>,
+  add: bool,
+) -> (StatusCode, Json<serde_json::Value>) {
+  let endorser_uri = match base64_url::decode(&uri).map(String::from_utf8) {
+    Ok(Ok(endorser_uri)) => endorser_uri,
+    res => {
+      warn!("received a bad endorser uri {:?}", res);
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
   };
-  let res = CoordinatorState::new(store, &ledger_store_args, num_grpc_channels).await;
-  assert!(res.is_ok());
-  let coordinator = res.unwrap();
 
-  if !endorser_hostnames.is_empty() {
-    let _ = coordinator.replace_endorsers(&endorser_hostnames).await;
-  }
-  if coordinator.get_endorser_pks().is_empty() {
-    panic!("No endorsers are available!");
+  let res = if add {
+    state.add_endorser(&endorser_uri, "operator").await
+  } else {
+    state.remove_endorser(&endorser_uri, "operator").await
+  };
+  match res {
+    Ok(pk) => {
+      let resp = EndorserOpResponse {
+        pk: base64_url::encode(&pk),
+      };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      error!(
+        "failed to change the membership of the endorser {} ({:?})",
+        endorser_uri, error
+      );
+      let status = match error {
+        CoordinatorError::EndorserAlreadyInView => StatusCode::CONFLICT,
+        CoordinatorError::InvalidEndorserUri => StatusCode::NOT_FOUND,
+        CoordinatorError::NoNewEndorsers | CoordinatorError::LastEndorserInView => {
+          StatusCode::BAD_REQUEST
+        },
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+      };
+      (status, Json(json!({})))
+    },
   }
-  println!("Endorser URIs: {:?}", coordinator.get_endorser_uris());
-
-  let coordinator_ref = Arc::new(coordinator);
-
-  let server = CoordinatorServiceState::new(coordinator_ref.clone());
+}
 
-  // Start the REST server for management
-  let control_server = Router::new()
-      .route("/endorsers/:uri", get(get_endorser).put(new_endorser).delete(delete_endorser))
-      // Add middleware to all routes
-      .layer(
-          ServiceBuilder::new()
-              // Handle errors from middleware
-              .layer(Extension(coordinator_ref.clone()))
-              .into_inner(),
-      );
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewInfo {
+  #[serde(rename = "Height")]
+  pub height: usize,
+  #[serde(rename = "Endorsers")]
+  pub endorsers: Vec<String>,
+  #[serde(rename = "Initiator")]
+  pub initiator: Option<String>,
+  #[serde(rename = "Reason")]
+  pub reason: Option<String>,
+  #[serde(rename = "CoordinatorId")]
+  pub coordinator_id: Option<String>,
+  #[serde(rename = "Timestamp")]
+  pub timestamp: Option<u64>,
+  #[serde(rename = "Added")]
+  pub added: Option<Vec<String>>,
+  #[serde(rename = "Removed")]
+  pub removed: Option<Vec<String>>,
+}
 
-  let ctrl_addr = format!("{}:{}", hostname, ctrl_port).parse()?;
-  let _job = tokio::spawn(async move {
-    println!("Running control service at {}", ctrl_addr);
-    let _res = axum::Server::bind(&ctrl_addr)
-      .serve(control_server.into_make_service())
-      .await;
-  });
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewListResponse {
+  #[serde(rename = "Views")]
+  pub views: Vec<ViewInfo>,
+}
 
-  let job2 = tokio::spawn(async move {
-    println!("Running gRPC Coordinator Service at {:?}", addr);
-    let _ = Server::builder()
-      .add_service(CallServer::new(server))
-      .serve(addr)
-      .await;
-  });
+fn view_info(
+  height: usize,
+  endorsers: &EndorserHostnames,
+  record: Option<ViewChangeRecord>,
+) -> ViewInfo {
+  ViewInfo {
+    height,
+    endorsers: endorsers.iter().map(|(_pk, uri)| uri.clone()).collect(),
+    initiator: record.as_ref().map(|r| r.initiator.clone()),
+    reason: record.as_ref().map(|r| r.reason.clone()),
+    coordinator_id: record.as_ref().and_then(|r| r.coordinator_id.clone()),
+    timestamp: record.as_ref().map(|r| r.timestamp),
+    added: record.as_ref().map(|r| r.added.clone()),
+    removed: record.map(|r| r.removed),
+  }
+}
 
-  job2.await?;
+// Lists the views in the view ledger with the reasons for their view changes, which make up
+// the audit trail of the endorsers
+async fn list_views(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let views = match state.read_view_changes().await {
+    Ok(views) => views,
+    Err(error) => {
+      error!("failed to read the views ({:?})", error);
+      return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+    },
+  };
+  let views = views
+    .into_iter()
+    .map(|(height, endorsers, record)| view_info(height, &endorsers, record))
+    .collect();
+  (StatusCode::OK, Json(json!(ViewListResponse { views })))
+}
 
-  Ok(())
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewEntryResponse {
+  #[serde(rename = "Height")]
+  pub height: usize,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+  // the attestations the receipts of the latest view are checked against, which only the
+  // tail of the view ledger is returned with
+  #[serde(rename = "Attestations")]
+  pub attestations: Option<String>,
 }
 
-#[cfg(test)]
-mod tests {
-  use crate::{
-    coordinator_proto::{
-      call_server::Call, AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadByIndexReq,
-      ReadByIndexResp, ReadLatestReq, ReadLatestResp, ReadViewTailReq, ReadViewTailResp,
+// Returns a view with its receipts, so that it can be verified the way endpoints do
+async fn get_view_entry(
+  Path(index): Path<usize>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let (tail, height, attestations) = match state.read_view_tail().await {
+    Ok(res) => res,
+    Err(error) => {
+      error!("failed to read the view ledger tail ({:?})", error);
+      return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
     },
-    CoordinatorServiceState, CoordinatorState,
   };
-  use ledger::{Block, CustomSerde, NimbleDigest, VerifierState};
-  use rand::Rng;
-  use std::{
-    collections::HashMap,
-    ffi::OsString,
-    io::{BufRead, BufReader},
-    process::{Child, Command, Stdio},
-    sync::Arc,
+  if index == 0 || index > height {
+    return (StatusCode::NOT_FOUND, Json(json!({})));
+  }
+  let (entry, attestations) = if index == height {
+    (tail, Some(base64_url::encode(&attestations)))
+  } else {
+    match state.read_view_by_index(index).await {
+      Ok(entry) => (entry, None),
+      Err(error) => {
+        error!("failed to read the view at {} ({:?})", index, error);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+      },
+    }
+  };
+  let resp = ViewEntryResponse {
+    height: index,
+    block: base64_url::encode(&entry.get_block().to_bytes()),
+    receipts: base64_url::encode(&entry.get_receipts().to_bytes()),
+    attestations,
   };
+  (StatusCode::OK, Json(json!(resp)))
+}
 
-  struct BoxChild {
-    pub child: Child,
-  }
+const DEFAULT_LIST_LIMIT: usize = 100; // the default number of ledgers returned by a list request
+const MAX_LIST_LIMIT: usize = 1000; // the maximum number of ledgers returned by a list request
 
-  impl Drop for BoxChild {
-    fn drop(&mut self) {
-      self.child.kill().expect("failed to kill a child process");
-    }
-  }
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerInfo {
+  #[serde(rename = "Handle")]
+  pub handle: String,
+  #[serde(rename = "Height")]
+  pub height: usize,
+}
 
-  fn launch_endorser(cmd: &OsString, args: String) -> BoxChild {
-    let mut endorser = BoxChild {
-      child: Command::new(cmd)
-        .args(args.split_whitespace())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("endorser failed to start"),
-    };
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerListResponse {
+  #[serde(rename = "Ledgers")]
+  pub ledgers: Vec<LedgerInfo>,
+  #[serde(rename = "Cursor")]
+  pub cursor: Option<String>,
+}
 
-    let mut buf_reader = BufReader::new(endorser.child.stdout.take().unwrap());
-    let mut endorser_output = String::new();
-    while let Ok(buflen) = buf_reader.read_line(&mut endorser_output) {
-      if buflen == 0 {
-        break;
-      }
-      if endorser_output.contains("listening on") {
-        break;
-      }
-    }
+#[derive(Debug, Deserialize)]
+struct LedgerListParams {
+  pub cursor: Option<String>,
+  pub limit: Option<usize>,
+}
 
-    endorser
+fn decode_handle(handle: &str) -> Option<NimbleDigest> {
+  match base64_url::decode(handle) {
+    Ok(bytes) => NimbleDigest::from_bytes(&bytes).ok(),
+    Err(_) => None,
   }
+}
 
-  #[tokio::test]
-  #[ignore]
-  async fn test_coordinator() {
-    if std::env::var_os("ENDORSER_CMD").is_none() {
-      panic!("The ENDORSER_CMD environment variable is not specified");
+async fn list_ledgers(
+  Query(params): Query<LedgerListParams>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let cursor = match params.cursor {
+    None => None,
+    Some(c) => match decode_handle(&c) {
+      Some(h) => Some(h),
+      None => {
+        warn!("received a bad cursor {}", c);
+        return (StatusCode::BAD_REQUEST, Json(json!({})));
+      },
+    },
+  };
+  let limit = std::cmp::min(params.limit.unwrap_or(DEFAULT_LIST_LIMIT), MAX_LIST_LIMIT);
+
+  let res = state.list_ledgers(cursor.as_ref(), limit).await;
+  if res.is_err() {
+    error!("failed to list ledgers ({:?})", res);
+    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+  }
+  let handles = res.unwrap();
+
+  let mut ledgers = Vec::with_capacity(handles.len());
+  for handle in &handles {
+    match state.get_ledger_height(handle).await {
+      Ok(height) => ledgers.push(LedgerInfo {
+        handle: base64_url::encode(&handle.to_bytes()),
+        height,
+      }),
+      // the ledger may have been removed after it was listed
+      Err(CoordinatorError::InvalidHandle) => continue,
+      Err(error) => {
+        error!(
+          "failed to read the height of ledger {:?} ({:?})",
+          handle, error
+        );
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+      },
     }
-    let endorser_cmd = {
-      match std::env::var_os("ENDORSER_CMD") {
-        None => panic!("The ENDORSER_CMD environment variable is not specified"),
-        Some(x) => x,
-      }
-    };
+  }
 
-    let endorser_args = {
-      match std::env::var_os("ENDORSER_ARGS") {
-        None => String::from(""),
-        Some(x) => x.into_string().unwrap(),
-      }
-    };
+  // a full page means there may be more ledgers after the last one
+  let cursor = if limit > 0 && handles.len() == limit {
+    handles.last().map(|h| base64_url::encode(&h.to_bytes()))
+  } else {
+    None
+  };
 
-    let store = {
-      match std::env::var_os("LEDGER_STORE") {
-        None => String::from("memory"),
-        Some(x) => x.into_string().unwrap(),
-      }
-    };
+  let resp = LedgerListResponse { ledgers, cursor };
+  (StatusCode::OK, Json(json!(resp)))
+}
 
-    let mut ledger_store_args = HashMap::<String, String>::new();
-    if std::env::var_os("COSMOS_URL").is_some() {
-      ledger_store_args.insert(
-        String::from("COSMOS_URL"),
-        std::env::var_os("COSMOS_URL")
-          .unwrap()
-          .into_string()
-          .unwrap(),
-      );
-    }
+async fn get_ledger(
+  Path(handle): Path<String>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let handle_rs = match decode_handle(&handle) {
+    Some(h) => h,
+    None => {
+      warn!("received a bad ledger handle {}", handle);
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
+  };
 
-    if std::env::var_os("STORAGE_ACCOUNT").is_some() {
-      ledger_store_args.insert(
-        String::from("STORAGE_ACCOUNT"),
-        std::env::var_os("STORAGE_ACCOUNT")
-          .unwrap()
-          .into_string()
-          .unwrap(),
+  match state.get_ledger_height(&handle_rs).await {
+    Ok(height) => {
+      let resp = LedgerInfo { handle, height };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(CoordinatorError::InvalidHandle) => (StatusCode::NOT_FOUND, Json(json!({}))),
+    Err(error) => {
+      error!(
+        "failed to read the height of ledger {} ({:?})",
+        handle, error
       );
-    }
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
 
-    if std::env::var_os("STORAGE_MASTER_KEY").is_some() {
-      ledger_store_args.insert(
-        String::from("STORAGE_MASTER_KEY"),
-        std::env::var_os("STORAGE_MASTER_KEY")
-          .unwrap()
-          .into_string()
-          .unwrap(),
-      );
-    }
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerEntryResponse {
+  #[serde(rename = "Handle")]
+  pub handle: String,
+  #[serde(rename = "Index")]
+  pub index: usize,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Nonces")]
+  pub nonces: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+}
 
-    if std::env::var_os("NIMBLE_DB").is_some() {
-      ledger_store_args.insert(
-        String::from("NIMBLE_DB"),
-        std::env::var_os("NIMBLE_DB")
-          .unwrap()
-          .into_string()
-          .unwrap(),
-      );
-    }
+// Returns an entry of a ledger with its receipts, so that they can be verified against the
+// view ledger
+async fn get_ledger_entry(
+  Path((handle, index)): Path<(String, usize)>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  let handle_rs = match decode_handle(&handle) {
+    Some(h) => h,
+    None => {
+      warn!("received a bad ledger handle {}", handle);
+      return (StatusCode::BAD_REQUEST, Json(json!({})));
+    },
+  };
 
-    if std::env::var_os("NIMBLE_FSTORE_DIR").is_some() {
-      ledger_store_args.insert(
-        String::from("NIMBLE_FSTORE_DIR"),
-        std::env::var_os("NIMBLE_FSTORE_DIR")
-          .unwrap()
-          .into_string()
-          .unwrap(),
+  match state.read_ledger_entry(&handle_rs, index).await {
+    Ok(entry) => {
+      let resp = LedgerEntryResponse {
+        handle,
+        index,
+        block: base64_url::encode(&entry.get_block().to_bytes()),
+        nonces: base64_url::encode(&entry.get_nonces().to_bytes()),
+        receipts: base64_url::encode(&entry.get_receipts().to_bytes()),
+      };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(CoordinatorError::InvalidHandle) | Err(CoordinatorError::InvalidHeight) => {
+      (StatusCode::NOT_FOUND, Json(json!({})))
+    },
+    Err(CoordinatorError::PrunedIndex) => (StatusCode::GONE, Json(json!({}))),
+    Err(error) => {
+      error!(
+        "failed to read ledger {} at {} ({:?})",
+        handle, index, error
       );
-    }
-
-    // Launch the endorser
-    let endorser = launch_endorser(&endorser_cmd, endorser_args.clone());
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
 
-    // Create the coordinator
-    let coordinator = Arc::new(
-      CoordinatorState::new(&store, &ledger_store_args, None)
-        .await
-        .unwrap(),
-    );
+#[derive(Debug, Deserialize)]
+struct BackupParams {
+  pub name: String,
+}
 
-    let res = coordinator
-      .replace_endorsers(&["http://[::1]:9090".to_string()])
-      .await;
-    assert!(res.is_ok());
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupResponse {
+  #[serde(rename = "Name")]
+  pub name: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: u64,
+  #[serde(rename = "NumLedgers")]
+  pub num_ledgers: usize,
+  #[serde(rename = "Digest")]
+  pub digest: String,
+}
 
-    let server = CoordinatorServiceState::new(coordinator);
+async fn backup(
+  Query(params): Query<BackupParams>,
+  Extension(state): Extension<Arc<CoordinatorState>>,
+) -> impl IntoResponse {
+  match state.backup(&params.name).await {
+    Ok(manifest) => {
+      let resp = BackupResponse {
+        name: params.name,
+        view_height: manifest.view_height,
+        num_ledgers: manifest.ledgers.len(),
+        digest: base64_url::encode(&manifest.digest),
+      };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(CoordinatorError::InvalidBackupName) => (StatusCode::BAD_REQUEST, Json(json!({}))),
+    Err(error) => {
+      error!("failed to back up to {} ({:?})", params.name, error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
 
-    // Initialization: Fetch view ledger to build VerifierState
-    let mut vs = VerifierState::new();
+#[derive(Debug, Serialize, Deserialize)]
+struct RepairResponse {
+  #[serde(rename = "NumEntries")]
+  pub num_entries: usize,
+}
 
-    let req = tonic::Request::new(ReadViewTailReq {});
-    let res = server.read_view_tail(req).await;
-    assert!(res.is_ok());
-    let ReadViewTailResp {
-      block,
-      receipts,
-      height: view_height,
-      attestations,
-    } = res.unwrap().into_inner();
+async fn repair(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.repair_ledger_store().await {
+    Ok(num_entries) => {
+      let resp = RepairResponse { num_entries };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      error!("failed to repair the ledger store ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
 
-    assert!(view_height == 1);
-    vs.set_group_identity(NimbleDigest::digest(&block));
+#[derive(Debug, Serialize, Deserialize)]
+struct PruneResponse {
+  #[serde(rename = "NumEntries")]
+  pub num_entries: usize,
+}
 
-    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
-    assert!(res.is_ok());
+async fn prune(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.prune_ledgers().await {
+    Ok(num_entries) => {
+      let resp = PruneResponse { num_entries };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      error!("failed to prune the ledgers ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
 
-    // Step 0: Create some app data
-    let block_bytes: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserInfo {
+  #[serde(rename = "Uri")]
+  pub uri: String,
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  // the mode the endorser reports, if the coordinator is connected to it and can read it
+  #[serde(rename = "Mode")]
+  pub mode: Option<String>,
+}
 
-    // Step 1: NewLedger Request (With Application Data Embedded)
-    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
-    let request = tonic::Request::new(NewLedgerReq {
-      handle: handle_bytes.to_vec(),
-      block: block_bytes.to_vec(),
-    });
-    let NewLedgerResp { receipts } = server.new_ledger(request).await.unwrap().into_inner();
-    let res = vs.verify_new_ledger(&handle_bytes, block_bytes.as_ref(), &receipts);
-    println!("NewLedger (WithAppData) : {:?}", res);
-    assert!(res.is_ok());
+#[derive(Debug, Serialize, Deserialize)]
+struct CurrentViewResponse {
+  #[serde(rename = "View")]
+  pub view: ViewInfo,
+  #[serde(rename = "Endorsers")]
+  pub endorsers: Vec<EndorserInfo>,
+}
 
-    let handle = handle_bytes.to_vec();
+// Describes the current view, with the mode each of its endorsers is in
+async fn get_view(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let (tail, height, _attestations) = match state.read_view_tail().await {
+    Ok(res) => res,
+    Err(error) => {
+      error!("failed to read the view ledger tail ({:?})", error);
+      return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+    },
+  };
+  if height == 0 {
+    return (StatusCode::NOT_FOUND, Json(json!({})));
+  }
+  let (endorsers, record) = match decode_view_block(&tail.get_block().to_bytes()) {
+    Ok(res) => res,
+    Err(error) => {
+      error!("failed to decode the current view ({:?})", error);
+      return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})));
+    },
+  };
 
-    // Step 2: Read At Index
-    let req = tonic::Request::new(ReadByIndexReq {
-      handle: handle.clone(),
-      index: 0,
-    });
+  let modes = state.read_endorser_modes(&endorsers).await;
+  let view = view_info(height, &endorsers, record);
+  let endorsers = endorsers
+    .into_iter()
+    .zip(modes)
+    .map(|((pk, uri), mode)| EndorserInfo {
+      uri,
+      pk: base64_url::encode(&pk),
+      mode: mode.map(|m| format!("{:?}", m)),
+    })
+    .collect();
+  let resp = CurrentViewResponse { view, endorsers };
+  (StatusCode::OK, Json(json!(resp)))
+}
 
-    let ReadByIndexResp {
-      block,
-      nonces,
-      receipts,
-    } = server.read_by_index(req).await.unwrap().into_inner();
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserStatus {
+  #[serde(rename = "Uri")]
+  pub uri: String,
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Mode")]
+  pub mode: Option<String>,
+  // how far the endorser is behind the others, if it is active and its state can be read
+  #[serde(rename = "LaggingLedgers")]
+  pub lagging_ledgers: Option<usize>,
+  #[serde(rename = "MissingEntries")]
+  pub missing_entries: Option<usize>,
+}
 
-    let res = vs.verify_read_by_index(&handle, &block, &nonces, 0, &receipts);
-    println!("ReadByIndex: {:?}", res.is_ok());
-    assert!(res.is_ok());
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserListResponse {
+  #[serde(rename = "Endorsers")]
+  pub endorsers: Vec<EndorserStatus>,
+}
 
-    // Step 3: Read Latest with the Nonce generated
-    let nonce = rand::thread_rng().gen::<[u8; 16]>();
-    let req = tonic::Request::new(ReadLatestReq {
-      handle: handle.clone(),
-      nonce: nonce.to_vec(),
-    });
+// Lists the connected endorsers with their modes and how far each lags behind the others
+async fn list_endorsers(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let endorsers = state.get_endorser_hostnames();
+  let modes = state.read_endorser_modes(&endorsers).await;
+  let lag = state.read_endorser_lag(&endorsers).await;
+  let endorsers = endorsers
+    .into_iter()
+    .zip(modes.into_iter().zip(lag))
+    .map(|((pk, uri), (mode, lag))| EndorserStatus {
+      uri,
+      pk: base64_url::encode(&pk),
+      mode: mode.map(|m| format!("{:?}", m)),
+      lagging_ledgers: lag.map(|(ledgers, _entries)| ledgers),
+      missing_entries: lag.map(|(_ledgers, entries)| entries),
+    })
+    .collect();
+  let resp = EndorserListResponse { endorsers };
+  (StatusCode::OK, Json(json!(resp)))
+}
 
-    let ReadLatestResp {
-      block,
-      nonces,
-      receipts,
-    } = server.read_latest(req).await.unwrap().into_inner();
+#[derive(Debug, Serialize, Deserialize)]
+struct ReconfigurationInfo {
+  #[serde(rename = "Initiator")]
+  pub initiator: String,
+  #[serde(rename = "Reason")]
+  pub reason: String,
+  #[serde(rename = "StartedAt")]
+  pub started_at: u64,
+  #[serde(rename = "FinishedAt")]
+  pub finished_at: Option<u64>,
+  #[serde(rename = "Error")]
+  pub error: Option<String>,
+}
 
-    let res = vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
-    println!("Read Latest : {:?}", res.is_ok());
-    assert!(res.is_ok());
+#[derive(Debug, Serialize, Deserialize)]
+struct StoreHealth {
+  #[serde(rename = "Healthy")]
+  pub healthy: bool,
+  #[serde(rename = "LatencyMs")]
+  pub latency_ms: Option<u64>,
+}
 
-    // Step 4: Append
-    let b1: Vec<u8> = "data_block_example_1".as_bytes().to_vec();
-    let b2: Vec<u8> = "data_block_example_2".as_bytes().to_vec();
-    let b3: Vec<u8> = "data_block_example_3".as_bytes().to_vec();
-    let blocks = vec![&b1, &b2, &b3].to_vec();
+#[derive(Debug, Serialize, Deserialize)]
+struct StatusResponse {
+  #[serde(rename = "Leader")]
+  pub leader: bool,
+  #[serde(rename = "Draining")]
+  pub draining: bool,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: Option<usize>,
+  #[serde(rename = "NumEndorsers")]
+  pub num_endorsers: usize,
+  #[serde(rename = "LaggingLedgers")]
+  pub lagging_ledgers: usize,
+  #[serde(rename = "Store")]
+  pub store: StoreHealth,
+  // the latest view change, which is in flight if it has not finished
+  #[serde(rename = "Reconfiguration")]
+  pub reconfiguration: Option<ReconfigurationInfo>,
+}
 
-    let mut expected_height = 0;
+// Reports the health of the coordinator, which it does whether or not it leads
+async fn get_status(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  let store = match state.check_ledger_store().await {
+    Ok(latency) => StoreHealth {
+      healthy: true,
+      latency_ms: Some(latency.as_millis() as u64),
+    },
+    Err(_) => StoreHealth {
+      healthy: false,
+      latency_ms: None,
+    },
+  };
+  let view_height = if store.healthy {
+    state.read_view_tail().await.ok().map(|(_, h, _)| h)
+  } else {
+    None
+  };
+  let resp = StatusResponse {
+    leader: state.is_leader(),
+    draining: state.is_draining(),
+    view_height,
+    num_endorsers: state.get_endorser_pks().len(),
+    lagging_ledgers: state.get_num_lagging_ledgers(),
+    store,
+    reconfiguration: state.get_reconfiguration().map(|r| ReconfigurationInfo {
+      initiator: r.initiator,
+      reason: r.reason,
+      started_at: r.started_at,
+      finished_at: r.finished_at,
+      error: r.error,
+    }),
+  };
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StatsResponse {
+  #[serde(rename = "NumLedgers")]
+  pub num_ledgers: usize,
+}
+
+// Counts the ledgers, which takes a listing of the whole ledger store
+async fn get_stats(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.count_ledgers().await {
+    Ok(num_ledgers) => {
+      let resp = StatsResponse { num_ledgers };
+      (StatusCode::OK, Json(json!(resp)))
+    },
+    Err(error) => {
+      error!("failed to count the ledgers ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
+
+// Stops serving until the coordinator is resumed, once the requests in flight are done
+async fn drain(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  match state.drain().await {
+    Ok(()) => (StatusCode::OK, Json(json!({}))),
+    Err(error) => {
+      error!("failed to drain the coordinator ({:?})", error);
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({})))
+    },
+  }
+}
+
+async fn resume(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  state.resume();
+  (StatusCode::OK, Json(json!({})))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ResyncResponse {
+  #[serde(rename = "NumEntries")]
+  pub num_entries: usize,
+}
+
+// Looks for endorsers that lag behind, and brings them up to date before it responds
+async fn resync(
+  Extension(state): Extension<Arc<CoordinatorState>>,
+  Extension(config): Extension<CatchUpConfig>,
+) -> impl IntoResponse {
+  let num_entries = CatchUp::new(state, config).catch_up(true).await;
+  let resp = ResyncResponse { num_entries };
+  (StatusCode::OK, Json(json!(resp)))
+}
+
+async fn get_metrics(Extension(state): Extension<Arc<CoordinatorState>>) -> impl IntoResponse {
+  metrics::ENDORSERS.set(state.get_endorser_pks().len() as i64);
+  metrics::LEADER.set(state.is_leader() as i64);
+  metrics::render()
+}
+
+// Only the leader serves control requests, since the others do not track the endorsers
+async fn require_leader<B>(req: HttpRequest<B>, next: Next<B>) -> impl IntoResponse {
+  match req.extensions().get::<Arc<CoordinatorState>>() {
+    Some(state) if !state.is_leader() => Err(StatusCode::SERVICE_UNAVAILABLE),
+    _ => Ok(next.run(req).await),
+  }
+}
+
+#[derive(Clone)]
+struct ControlToken(Option<String>);
+
+// Compares in time that does not depend on where the two differ
+fn tokens_match(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Serves only the control requests that carry the token, if one is set
+async fn require_token<B>(req: HttpRequest<B>, next: Next<B>) -> impl IntoResponse {
+  let token = match req.extensions().get::<ControlToken>() {
+    Some(ControlToken(Some(token))) => token,
+    _ => return Ok(next.run(req).await),
+  };
+  let bearer = req
+    .headers()
+    .get(header::AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.strip_prefix("Bearer "));
+  match bearer {
+    Some(bearer) if tokens_match(bearer.as_bytes(), token.as_bytes()) => Ok(next.run(req).await),
+    _ => {
+      warn!("refused a control request without a valid token");
+      Err(StatusCode::UNAUTHORIZED)
+    },
+  }
+}
+
+fn control_router(
+  state: Arc<CoordinatorState>,
+  catch_up: CatchUpConfig,
+  token: Option<String>,
+) -> Router {
+  Router::new()
+    .route(
+      "/endorsers/:uri",
+      get(get_endorser).put(new_endorser).delete(delete_endorser),
+    )
+    .route("/endorsers", get(list_endorsers))
+    .route("/ledgers", get(list_ledgers))
+    .route("/view", get(get_view))
+    .route("/views", get(list_views))
+    .route("/views/:index", get(get_view_entry))
+    .route("/members/:uri", put(add_member).delete(remove_member))
+    .route("/ledgers/:handle", get(get_ledger))
+    .route("/ledgers/:handle/:index", get(get_ledger_entry))
+    .route("/stats", get(get_stats))
+    .route("/backup", post(backup))
+    .route("/repair", post(repair))
+    .route("/prune", post(prune))
+    .route("/resync", post(resync))
+    .route_layer(middleware::from_fn(require_leader))
+    // Every coordinator reports its status and its metrics, and can be drained, whether or
+    // not it leads
+    .route("/status", get(get_status))
+    .route("/drain", post(drain).delete(resume))
+    .route("/metrics", get(get_metrics))
+    .route_layer(middleware::from_fn(require_token))
+    // Add middleware to all routes
+    .layer(
+      ServiceBuilder::new()
+        .layer(Extension(state))
+        .layer(Extension(catch_up))
+        .layer(Extension(ControlToken(token)))
+        .into_inner(),
+    )
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+  let config = App::new("coordinator")
+    .arg(
+      Arg::with_name("config")
+        .long("config")
+        .takes_value(true)
+        .help("A TOML or YAML configuration file, whose settings the options below override"),
+    )
+    .arg(
+      Arg::with_name("nimbledb")
+        .short("n")
+        .long("nimbledb")
+        .takes_value(true)
+        .help("The database name [default: nimble_cosmosdb]"),
+    )
+    .arg(
+      Arg::with_name("cosmosurl")
+        .short("c")
+        .long("cosmosurl")
+        .takes_value(true)
+        .help("The COSMOS URL"),
+    )
+    .arg(
+      Arg::with_name("storage_account")
+        .short("a")
+        .long("storage_account")
+        .takes_value(true)
+        .help("The storage account name"),
+    )
+    .arg(
+      Arg::with_name("storage_master_key")
+        .short("k")
+        .long("storage_master_key")
+        .takes_value(true)
+        .help("The storage master key"),
+    )
+    .arg(
+      Arg::with_name("store")
+        .short("s")
+        .long("store")
+        .takes_value(true)
+        .help("The type of store used by the service. [default: memory]"),
+    )
+    .arg(
+      Arg::with_name("replicas")
+        .long("replicas")
+        .takes_value(true)
+        .help("Replicas of the \"replicated\" store, as type[:KEY=VALUE,...] separated by ';'"),
+    )
+    .arg(
+      Arg::with_name("host")
+        .short("t")
+        .long("host")
+        .takes_value(true)
+        .help("The hostname to run the service on. [default: [::1]]"),
+    )
+    .arg(
+      Arg::with_name("port")
+        .short("p")
+        .long("port")
+        .takes_value(true)
+        .help("The port number to run the coordinator service on. [default: 8080]"),
+    )
+    .arg(
+      Arg::with_name("ctrl")
+        .short("r")
+        .long("ctrl")
+        .takes_value(true)
+        .help("The port number to run the coordinator control service on. [default: 8090]"),
+    )
+    .arg(
+      Arg::with_name("endorser")
+        .short("e")
+        .long("endorser")
+        .help("List of URLs to Endorser Services [default: http://[::1]:9090]")
+        .use_delimiter(true),
+    )
+    .arg(
+      Arg::with_name("spare_endorsers")
+        .long("spare_endorsers")
+        .help("List of URLs to spare Endorser Services, which replace the endorsers if they fail")
+        .use_delimiter(true),
+    )
+    .arg(
+      Arg::with_name("channels")
+        .short("l")
+        .long("channels")
+        .takes_value(true)
+        .help("The number of grpc channels"),
+    )
+    .arg(
+      Arg::with_name("content_store")
+        .long("content_store")
+        .takes_value(true)
+        .possible_values(&["memory", "filestore", "s3"])
+        .help("The type of store for keeping large blocks out of the ledger store"),
+    )
+    .arg(
+      Arg::with_name("content_threshold")
+        .long("content_threshold")
+        .takes_value(true)
+        .help("Blocks larger than this many bytes are kept in the content store"),
+    )
+    .arg(
+      Arg::with_name("content_dir")
+        .long("content_dir")
+        .takes_value(true)
+        .help("The directory used by the filestore content store"),
+    )
+    .arg(
+      Arg::with_name("s3_endpoint")
+        .long("s3_endpoint")
+        .takes_value(true)
+        .help("The endpoint URL of the S3-compatible content store"),
+    )
+    .arg(
+      Arg::with_name("s3_bucket")
+        .long("s3_bucket")
+        .takes_value(true)
+        .help("The bucket used by the S3-compatible content store"),
+    )
+    .arg(
+      Arg::with_name("s3_region")
+        .long("s3_region")
+        .takes_value(true)
+        .help("The region of the S3-compatible content store"),
+    )
+    .arg(
+      Arg::with_name("s3_access_key")
+        .long("s3_access_key")
+        .takes_value(true)
+        .help("The access key of the S3-compatible content store"),
+    )
+    .arg(
+      Arg::with_name("s3_secret_key")
+        .long("s3_secret_key")
+        .takes_value(true)
+        .help("The secret key of the S3-compatible content store"),
+    )
+    .arg(
+      Arg::with_name("ledger_cache")
+        .long("ledger_cache")
+        .takes_value(true)
+        .help("Cache up to this many ledger tails and entries in front of the ledger store"),
+    )
+    .arg(
+      Arg::with_name("ledger_key_file")
+        .long("ledger_key_file")
+        .takes_value(true)
+        .help(
+          "File with a base64url-encoded 32-byte key for encrypting blocks in the ledger store",
+        ),
+    )
+    .arg(
+      Arg::with_name("backup_dir")
+        .long("backup_dir")
+        .takes_value(true)
+        .help("The directory backups are written to; backups are disabled without it"),
+    )
+    .arg(
+      Arg::with_name("retain_entries")
+        .long("retain_entries")
+        .takes_value(true)
+        .help("Prune each ledger down to this many entries after its checkpoint"),
+    )
+    .arg(
+      Arg::with_name("prune_interval")
+        .long("prune_interval")
+        .takes_value(true)
+        .help("The number of seconds between two rounds of pruning [default: 3600]"),
+    )
+    .arg(
+      Arg::with_name("lease_duration")
+        .long("lease_duration")
+        .takes_value(true)
+        .help(
+          "Elect a leader among the coordinators sharing the store, with a lease of this many milliseconds",
+        ),
+    )
+    .arg(
+      Arg::with_name("coordinator_id")
+        .long("coordinator_id")
+        .takes_value(true)
+        .help("The name this coordinator holds the lease under (defaults to host:port)"),
+    )
+    .arg(
+      Arg::with_name("otlp_endpoint")
+        .long("otlp_endpoint")
+        .takes_value(true)
+        .help("Export spans to the OTLP collector at this URI (e.g., http://localhost:4317)"),
+    );
+
+  let cli_matches = config.get_matches();
+  let mut overrides = Vec::new();
+  for (arg, key) in [
+    ("host", "host"),
+    ("port", "port"),
+    ("ctrl", "ctrl_port"),
+    ("channels", "channels"),
+    ("coordinator_id", "coordinator_id"),
+    ("lease_duration", "lease_duration_ms"),
+    ("backup_dir", "backup_dir"),
+    ("retain_entries", "retain_entries"),
+    ("prune_interval", "prune_interval_secs"),
+    ("otlp_endpoint", "otlp_endpoint"),
+    ("store", "store.type"),
+    ("nimbledb", "store.nimble_db"),
+    ("cosmosurl", "store.cosmos_url"),
+    ("storage_account", "store.storage_account"),
+    ("storage_master_key", "store.storage_master_key"),
+    ("replicas", "store.replicas"),
+    ("ledger_cache", "store.cache_size"),
+    ("ledger_key_file", "store.key_file"),
+    ("content_store", "content_store.type"),
+    ("content_threshold", "content_store.threshold"),
+    ("content_dir", "content_store.dir"),
+    ("s3_endpoint", "content_store.s3_endpoint"),
+    ("s3_bucket", "content_store.s3_bucket"),
+    ("s3_region", "content_store.s3_region"),
+    ("s3_access_key", "content_store.s3_access_key"),
+    ("s3_secret_key", "content_store.s3_secret_key"),
+  ] {
+    if let Some(x) = cli_matches.value_of(arg) {
+      overrides.push((key, x.to_string()));
+    }
+  }
+  for (arg, key) in [
+    ("endorser", "endorsers"),
+    ("spare_endorsers", "supervisor.spare_endorsers"),
+  ] {
+    if let Some(x) = cli_matches.values_of(arg) {
+      overrides.push((key, x.collect::<Vec<&str>>().join(",")));
+    }
+  }
+  let config = match CoordinatorConfig::load(
+    cli_matches.value_of("config"),
+    |var| std::env::var(var).ok(),
+    &overrides,
+  ) {
+    Ok(config) => config,
+    Err(error) => {
+      eprintln!("Invalid configuration: {}", error);
+      std::process::exit(1);
+    },
+  };
+
+  if let Err(error) = telemetry::init("coordinator", config.otlp_endpoint.as_deref()) {
+    eprintln!("Failed to set up tracing ({:?})", error);
+  }
+  let addr = format!("{}:{}", config.host, config.port).parse()?;
+  let endorser_hostnames = config.endorsers.clone();
+  let prune_interval = config.prune_interval_secs;
+  let tls_config = match (&config.tls.cert, &config.tls.key) {
+    (Some(cert), Some(key)) => {
+      let tls_config = ServerTlsConfig::new().identity(Identity::from_pem(
+        std::fs::read(cert)?,
+        std::fs::read(key)?,
+      ));
+      // clients must then present a certificate signed by the CA
+      match &config.tls.client_ca {
+        Some(ca) => Some(tls_config.client_ca_root(Certificate::from_pem(std::fs::read(ca)?))),
+        None => Some(tls_config),
+      }
+    },
+    _ => None,
+  };
+  let res = CoordinatorState::new(
+    &config.store.store_type,
+    &config.store_args(),
+    Some(config.channels),
+  )
+  .await;
+  assert!(res.is_ok());
+  let coordinator = res.unwrap();
+
+  let campaign_interval = coordinator.get_campaign_interval();
+  if campaign_interval.is_none() {
+    if !endorser_hostnames.is_empty() {
+      let _ = coordinator.replace_endorsers(&endorser_hostnames).await;
+    }
+    if coordinator.get_endorser_pks().is_empty() {
+      panic!("No endorsers are available!");
+    }
+    println!("Endorser URIs: {:?}", coordinator.get_endorser_uris());
+  }
+
+  let coordinator_ref = Arc::new(coordinator);
+
+  // A coordinator that has to be elected serves only while it holds the lease. The first
+  // leader of a new store sets up the endorsers; later leaders use the endorsers of the
+  // latest view.
+  if let Some(interval_ms) = campaign_interval {
+    let coordinator = coordinator_ref.clone();
+    let _campaign = tokio::spawn(async move {
+      let mut interval = tokio::time::interval(std::time::Duration::from_millis(interval_ms));
+      let mut leading = false;
+      loop {
+        interval.tick().await;
+        match coordinator.campaign().await {
+          Ok(true) => {
+            if !leading
+              && coordinator.get_endorser_pks().is_empty()
+              && !endorser_hostnames.is_empty()
+            {
+              let _ = coordinator.replace_endorsers(&endorser_hostnames).await;
+            }
+            if !leading {
+              println!("Endorser URIs: {:?}", coordinator.get_endorser_uris());
+            }
+            leading = true;
+          },
+          Ok(false) => leading = false,
+          Err(error) => error!("failed to run a round of the election ({:?})", error),
+        }
+      }
+    });
+  }
+
+  let mut server = CoordinatorServiceState::new(coordinator_ref.clone());
+  if let Some(path) = &config.authorization.policy_file {
+    match PolicyAuthorizer::from_file(path) {
+      Ok(authorizer) => server = server.with_authorizer(Arc::new(authorizer)),
+      Err(error) => {
+        eprintln!("Invalid configuration: {}", error);
+        std::process::exit(1);
+      },
+    }
+  } else if config.tls.client_ca.is_some() {
+    warn!("every client with a certificate may make every call, as no policy is set");
+  }
+
+  // Start the REST server for management
+  if config.control.token.is_none() {
+    warn!("the control service accepts requests without a token");
+  }
+  let control_server = control_router(
+    coordinator_ref.clone(),
+    config.catch_up.clone(),
+    config.control.token.clone(),
+  );
+
+  let ctrl_addr = format!("{}:{}", config.host, config.ctrl_port).parse()?;
+  let _job = tokio::spawn(async move {
+    println!("Running control service at {}", ctrl_addr);
+    let _res = axum::Server::bind(&ctrl_addr)
+      .serve(control_server.into_make_service())
+      .await;
+  });
+
+  if config.retain_entries.is_some() {
+    let coordinator = coordinator_ref.clone();
+    let _pruner = tokio::spawn(async move {
+      let mut interval = tokio::time::interval(std::time::Duration::from_secs(prune_interval));
+      loop {
+        interval.tick().await;
+        if !coordinator.is_leader() {
+          continue;
+        }
+        match coordinator.prune_ledgers().await {
+          Ok(num_entries) => println!("Pruned {} ledger entries", num_entries),
+          Err(error) => error!("failed to prune the ledgers ({:?})", error),
+        }
+      }
+    });
+  }
+
+  let catch_up = CatchUp::new(coordinator_ref.clone(), config.catch_up.clone());
+  let _catch_up = tokio::spawn(catch_up.run());
+
+  if !config.supervisor.spare_endorsers.is_empty() {
+    let supervisor = Supervisor::new(coordinator_ref.clone(), config.supervisor.clone());
+    let _supervisor = tokio::spawn(supervisor.run());
+  }
+
+  let job2 = tokio::spawn(async move {
+    println!("Running gRPC Coordinator Service at {:?}", addr);
+    let mut builder = Server::builder();
+    if let Some(tls_config) = tls_config {
+      builder = match builder.tls_config(tls_config) {
+        Ok(builder) => builder,
+        Err(error) => panic!("Failed to set up TLS ({:?})", error),
+      };
+    }
+    let _ = builder
+      .add_service(CallServer::new(MeteredCallService::new(server)))
+      .serve(addr)
+      .await;
+  });
+
+  job2.await?;
+  telemetry::shutdown();
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    catchup::CatchUp,
+    config::{CatchUpConfig, SupervisorConfig},
+    control_router,
+    coordinator_proto::{
+      call_server::Call, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp, NewLedgerReq,
+      NewLedgerResp, ReadByIndexReq, ReadByIndexResp, ReadLatestReq, ReadLatestResp,
+      ReadViewTailReq, ReadViewTailResp, WatchReq, WatchResp,
+    },
+    errors::CoordinatorError,
+    metrics,
+    supervisor::Supervisor,
+    CoordinatorServiceState, CoordinatorState, EndorserListResponse, LedgerEntryResponse,
+    StatusResponse, MAX_REQUEST_ID_SIZE,
+  };
+  use axum::{
+    body::Body,
+    http::{header, Request as HttpRequest, StatusCode},
+  };
+  use ledger::{
+    compute_aggregated_block_hash, Block, CustomSerde, NimbleDigest, NimbleHashTrait, VerifierState,
+  };
+  use rand::Rng;
+  use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::Arc,
+  };
+  use store::{
+    content::decode_content_ref,
+    errors::StorageError,
+    ledger::faulty::{Fault, FaultRule, LedgerOp},
+  };
+  use tokio_stream::StreamExt;
+  use tower::ServiceExt;
+
+  struct BoxChild {
+    pub child: Child,
+  }
+
+  impl Drop for BoxChild {
+    fn drop(&mut self) {
+      self.child.kill().expect("failed to kill a child process");
+    }
+  }
+
+  fn launch_endorser(cmd: &OsString, args: String) -> BoxChild {
+    let mut endorser = BoxChild {
+      child: Command::new(cmd)
+        .args(args.split_whitespace())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("endorser failed to start"),
+    };
+
+    let mut buf_reader = BufReader::new(endorser.child.stdout.take().unwrap());
+    let mut endorser_output = String::new();
+    while let Ok(buflen) = buf_reader.read_line(&mut endorser_output) {
+      if buflen == 0 {
+        break;
+      }
+      if endorser_output.contains("listening on") {
+        break;
+      }
+    }
+
+    endorser
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator() {
+    if std::env::var_os("ENDORSER_CMD").is_none() {
+      panic!("The ENDORSER_CMD environment variable is not specified");
+    }
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+
+    let endorser_args = {
+      match std::env::var_os("ENDORSER_ARGS") {
+        None => String::from(""),
+        Some(x) => x.into_string().unwrap(),
+      }
+    };
+
+    let store = {
+      match std::env::var_os("LEDGER_STORE") {
+        None => String::from("memory"),
+        Some(x) => x.into_string().unwrap(),
+      }
+    };
+
+    let mut ledger_store_args = HashMap::<String, String>::new();
+    if std::env::var_os("COSMOS_URL").is_some() {
+      ledger_store_args.insert(
+        String::from("COSMOS_URL"),
+        std::env::var_os("COSMOS_URL")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    if std::env::var_os("STORAGE_ACCOUNT").is_some() {
+      ledger_store_args.insert(
+        String::from("STORAGE_ACCOUNT"),
+        std::env::var_os("STORAGE_ACCOUNT")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    if std::env::var_os("STORAGE_MASTER_KEY").is_some() {
+      ledger_store_args.insert(
+        String::from("STORAGE_MASTER_KEY"),
+        std::env::var_os("STORAGE_MASTER_KEY")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    if std::env::var_os("NIMBLE_DB").is_some() {
+      ledger_store_args.insert(
+        String::from("NIMBLE_DB"),
+        std::env::var_os("NIMBLE_DB")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    if std::env::var_os("NIMBLE_FSTORE_DIR").is_some() {
+      ledger_store_args.insert(
+        String::from("NIMBLE_FSTORE_DIR"),
+        std::env::var_os("NIMBLE_FSTORE_DIR")
+          .unwrap()
+          .into_string()
+          .unwrap(),
+      );
+    }
+
+    // Optionally keep large blocks in a content store, cache, and encrypt ledger entries
+    for key in [
+      "CONTENT_STORE",
+      "CONTENT_THRESHOLD",
+      "NIMBLE_CONTENT_DIR",
+      "S3_ENDPOINT",
+      "S3_BUCKET",
+      "S3_REGION",
+      "S3_ACCESS_KEY",
+      "S3_SECRET_KEY",
+      "LEDGER_CACHE_SIZE",
+      "LEDGER_KEY_FILE",
+    ] {
+      if let Some(x) = std::env::var_os(key) {
+        ledger_store_args.insert(String::from(key), x.into_string().unwrap());
+      }
+    }
+
+    // Launch the endorser
+    let endorser = launch_endorser(&endorser_cmd, endorser_args.clone());
+
+    // Create the coordinator
+    let coordinator = Arc::new(
+      CoordinatorState::new(&store, &ledger_store_args, None)
+        .await
+        .unwrap(),
+    );
+
+    let res = coordinator
+      .replace_endorsers(&["http://[::1]:9090".to_string()])
+      .await;
+    assert!(res.is_ok());
+
+    let server = CoordinatorServiceState::new(coordinator);
+
+    // Initialization: Fetch view ledger to build VerifierState
+    let mut vs = VerifierState::new();
+
+    let req = tonic::Request::new(ReadViewTailReq {});
+    let res = server.read_view_tail(req).await;
+    assert!(res.is_ok());
+    let ReadViewTailResp {
+      block,
+      receipts,
+      height: view_height,
+      attestations,
+    } = res.unwrap().into_inner();
+
+    assert!(view_height == 1);
+    vs.set_group_identity(NimbleDigest::digest(&block));
+
+    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
+    assert!(res.is_ok());
+
+    // Step 0: Create some app data
+    let block_bytes: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+    // Step 1: NewLedger Request (With Application Data Embedded)
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let request = tonic::Request::new(NewLedgerReq {
+      handle: handle_bytes.to_vec(),
+      block: block_bytes.to_vec(),
+      request_id: Vec::new(),
+    });
+    let NewLedgerResp { receipts } = server.new_ledger(request).await.unwrap().into_inner();
+    let res = vs.verify_new_ledger(&handle_bytes, block_bytes.as_ref(), &receipts);
+    println!("NewLedger (WithAppData) : {:?}", res);
+    assert!(res.is_ok());
+
+    let handle = handle_bytes.to_vec();
+
+    // Step 2: Read At Index
+    let req = tonic::Request::new(ReadByIndexReq {
+      handle: handle.clone(),
+      index: 0,
+    });
+
+    let ReadByIndexResp {
+      block,
+      nonces,
+      receipts,
+    } = server.read_by_index(req).await.unwrap().into_inner();
+
+    let res = vs.verify_read_by_index(&handle, &block, &nonces, 0, &receipts);
+    println!("ReadByIndex: {:?}", res.is_ok());
+    assert!(res.is_ok());
+
+    // Step 3: Read Latest with the Nonce generated
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let req = tonic::Request::new(ReadLatestReq {
+      handle: handle.clone(),
+      nonce: nonce.to_vec(),
+    });
+
+    let ReadLatestResp {
+      block,
+      nonces,
+      receipts,
+    } = server.read_latest(req).await.unwrap().into_inner();
+
+    let res = vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
+    println!("Read Latest : {:?}", res.is_ok());
+    assert!(res.is_ok());
+
+    // Step 4: Append
+    let b1: Vec<u8> = "data_block_example_1".as_bytes().to_vec();
+    let b2: Vec<u8> = "data_block_example_2".as_bytes().to_vec();
+    let b3: Vec<u8> = "data_block_example_3".as_bytes().to_vec();
+    let blocks = vec![&b1, &b2, &b3].to_vec();
+
+    let mut expected_height = 0;
     for block_to_append in blocks {
       expected_height += 1;
       let req = tonic::Request::new(AppendReq {
-        handle: handle.clone(),
-        block: block_to_append.to_vec(),
-        expected_height: expected_height as u64,
+        handle: handle.clone(),
+        block: block_to_append.to_vec(),
+        expected_height: expected_height as u64,
+        request_id: Vec::new(),
+      });
+
+      let AppendResp {
+        hash_nonces,
+        receipts,
+      } = server.append(req).await.unwrap().into_inner();
+
+      let res = vs.verify_append(
+        &handle,
+        block_to_append.as_ref(),
+        &hash_nonces,
+        expected_height,
+        &receipts,
+      );
+      println!("Append verification: {:?} {:?}", block_to_append, res);
+      assert!(res.is_ok());
+    }
+
+    // Step 4: Read Latest with the Nonce generated and check for new data
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let latest_state_query = tonic::Request::new(ReadLatestReq {
+      handle: handle.clone(),
+      nonce: nonce.to_vec(),
+    });
+
+    let ReadLatestResp {
+      block,
+      nonces,
+      receipts,
+    } = server
+      .read_latest(latest_state_query)
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(block, b3.clone());
+
+    let is_latest_valid =
+      vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
+    println!(
+      "Verifying ReadLatest Response : {:?}",
+      is_latest_valid.is_ok()
+    );
+    assert!(is_latest_valid.is_ok());
+
+    // Step 5: Read At Index
+    let req = tonic::Request::new(ReadByIndexReq {
+      handle: handle.clone(),
+      index: 1,
+    });
+
+    let ReadByIndexResp {
+      block,
+      nonces,
+      receipts,
+    } = server.read_by_index(req).await.unwrap().into_inner();
+    assert_eq!(block, b1.clone());
+
+    let res = vs.verify_read_by_index(&handle, &block, &nonces, 1, &receipts);
+    println!("Verifying ReadByIndex Response: {:?}", res.is_ok());
+    assert!(res.is_ok());
+
+    // Step 6: change the view by adding two new endorsers
+    let endorser_args2 = endorser_args.clone() + " -p 9092";
+    let endorser2 = launch_endorser(&endorser_cmd, endorser_args2);
+    let endorser_args3 = endorser_args.clone() + " -p 9093";
+    let endorser3 = launch_endorser(&endorser_cmd, endorser_args3);
+
+    let res = server
+      .get_state()
+      .replace_endorsers(&[
+        "http://[::1]:9092".to_string(),
+        "http://[::1]:9093".to_string(),
+      ])
+      .await;
+    println!("new config with 2 endorsers: {:?}", res);
+    assert!(res.is_ok());
+
+    let req = tonic::Request::new(ReadViewTailReq {});
+    let res = server.read_view_tail(req).await;
+    assert!(res.is_ok());
+    let ReadViewTailResp {
+      block,
+      receipts,
+      height: _view_height,
+      attestations,
+    } = res.unwrap().into_inner();
+
+    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
+    println!("Applying ReadViewByIndexResp Response: {:?}", res);
+    assert!(res.is_ok());
+
+    // Step 7: Append after view change
+    expected_height += 1;
+
+    let message = "data_block_append".as_bytes();
+    let req = tonic::Request::new(AppendReq {
+      handle: handle.clone(),
+      block: message.to_vec(),
+      expected_height: expected_height as u64,
+      request_id: Vec::new(),
+    });
+
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = server.append(req).await.unwrap().into_inner();
+
+    let res = vs.verify_append(&handle, message, &hash_nonces, expected_height, &receipts);
+    println!("Append verification: {:?}", res.is_ok());
+    assert!(res.is_ok());
+
+    // Step 8: Read Latest with the Nonce generated and check for new data appended without condition
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let latest_state_query = tonic::Request::new(ReadLatestReq {
+      handle: handle.clone(),
+      nonce: nonce.to_vec(),
+    });
+
+    let ReadLatestResp {
+      block,
+      nonces,
+      receipts,
+    } = server
+      .read_latest(latest_state_query)
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(block, message);
+
+    let is_latest_valid =
+      vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
+    println!(
+      "Verifying ReadLatest Response : {:?}",
+      is_latest_valid.is_ok()
+    );
+    assert!(is_latest_valid.is_ok());
+
+    // Step 9: create a ledger and append to it only on the first endorser
+    let mut endorsers = server.get_state().get_endorser_pks();
+    endorsers.remove(1);
+
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let res = server
+      .get_state()
+      .create_ledger(Some(endorsers.clone()), handle_bytes.as_ref(), &[])
+      .await;
+    println!("create_ledger with first endorser: {:?}", res);
+    assert!(res.is_ok());
+
+    let new_handle = handle_bytes.to_vec();
+
+    let message = "data_block_append 2".as_bytes();
+    let res = server
+      .get_state()
+      .append_ledger(
+        Some(endorsers.clone()),
+        &new_handle.clone(),
+        message,
+        1usize,
+      )
+      .await;
+    println!("append_ledger with first endorser: {:?}", res);
+    assert!(res.is_ok());
+
+    let handle2_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let res = server
+      .get_state()
+      .create_ledger(None, handle2_bytes.as_ref(), &[])
+      .await;
+    println!("create_ledger with first endorser: {:?}", res);
+    assert!(res.is_ok());
+
+    let new_handle2 = handle2_bytes.to_vec();
+
+    let message2 = "data_block_append 3".as_bytes();
+    let res = server
+      .get_state()
+      .append_ledger(
+        Some(endorsers.clone()),
+        &new_handle2.clone(),
+        message2,
+        1usize,
+      )
+      .await;
+    println!("append_ledger with first endorser: {:?}", res);
+    assert!(res.is_ok());
+
+    let nonce1 = rand::thread_rng().gen::<[u8; 16]>();
+    let res = server
+      .get_state()
+      .read_ledger_tail(&new_handle2, &nonce1)
+      .await;
+    assert!(res.is_ok());
+
+    let res = server
+      .get_state()
+      .append_ledger(
+        Some(endorsers.clone()),
+        &new_handle2.clone(),
+        message2,
+        2usize,
+      )
+      .await;
+    println!("append_ledger with first endorser again: {:?}", res);
+    assert!(res.is_ok());
+
+    let message3 = "data_block_append 4".as_bytes();
+    let res = server
+      .get_state()
+      .append_ledger(None, &new_handle2.clone(), message3, 3usize)
+      .await;
+    assert!(res.is_ok());
+
+    let nonce2 = rand::thread_rng().gen::<[u8; 16]>();
+    let res = server
+      .get_state()
+      .read_ledger_tail(&new_handle2, &nonce2)
+      .await;
+    assert!(res.is_ok());
+
+    let ledger_entry = res.unwrap();
+    assert_eq!(ledger_entry.get_block().to_bytes(), message3.to_vec());
+    let is_latest_valid = vs.verify_read_latest(
+      &new_handle2,
+      &ledger_entry.get_block().to_bytes(),
+      &ledger_entry.get_nonces().to_bytes(),
+      nonce2.as_ref(),
+      &ledger_entry.get_receipts().to_bytes(),
+    );
+    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
+    assert!(is_latest_valid.is_ok());
+
+    let res = server
+      .get_state()
+      .read_ledger_by_index(&new_handle2, 2usize)
+      .await;
+    assert!(res.is_ok());
+
+    let ledger_entry = res.unwrap();
+    assert_eq!(ledger_entry.get_block().to_bytes(), message2.to_vec());
+    let is_latest_valid = vs.verify_read_latest(
+      &new_handle2,
+      &ledger_entry.get_block().to_bytes(),
+      &ledger_entry.get_nonces().to_bytes(),
+      nonce1.as_ref(),
+      &ledger_entry.get_receipts().to_bytes(),
+    );
+    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
+    assert!(is_latest_valid.is_ok());
+
+    // Step 10: replace the view with three endorsers
+    let endorser_args4 = endorser_args.clone() + " -p 9094";
+    let endorser4 = launch_endorser(&endorser_cmd, endorser_args4);
+    let endorser_args5 = endorser_args.clone() + " -p 9095";
+    let endorser5 = launch_endorser(&endorser_cmd, endorser_args5);
+    let endorser_args6 = endorser_args.clone() + " -p 9096";
+    let endorser6 = launch_endorser(&endorser_cmd, endorser_args6);
+
+    let res = server
+      .get_state()
+      .replace_endorsers(&[
+        "http://[::1]:9094".to_string(),
+        "http://[::1]:9095".to_string(),
+        "http://[::1]:9096".to_string(),
+      ])
+      .await;
+    println!("new config with 3 endorsers: {:?}", res);
+    assert!(res.is_ok());
+
+    let req = tonic::Request::new(ReadViewTailReq {});
+    let res = server.read_view_tail(req).await;
+    assert!(res.is_ok());
+    let ReadViewTailResp {
+      block,
+      receipts,
+      height: _view_height,
+      attestations,
+    } = res.unwrap().into_inner();
+
+    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
+    println!("Applying ReadViewByIndexResp Response: {:?}", res);
+    assert!(res.is_ok());
+
+    // Step 11: read the latest of the new ledger
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let latest_state_query = tonic::Request::new(ReadLatestReq {
+      handle: new_handle.clone(),
+      nonce: nonce.to_vec(),
+    });
+
+    let ReadLatestResp {
+      block,
+      nonces,
+      receipts,
+    } = server
+      .read_latest(latest_state_query)
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(block, message);
+
+    let is_latest_valid =
+      vs.verify_read_latest(&new_handle, &block, &nonces, nonce.as_ref(), &receipts);
+    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
+    assert!(is_latest_valid.is_ok());
+
+    // Step 12: Append data
+    let message = "data_block_append 3".as_bytes();
+    let req = tonic::Request::new(AppendReq {
+      handle: new_handle.clone(),
+      block: message.to_vec(),
+      expected_height: 2_u64,
+      request_id: Vec::new(),
+    });
+
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = server.append(req).await.unwrap().into_inner();
+
+    let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
+    println!("Append verification: {:?}", res.is_ok());
+    assert!(res.is_ok());
+
+    if store != "memory" {
+      // set up the endorsers to be at different heights
+      let mut endorsers = server.get_state().get_endorser_pks();
+      endorsers.remove(1);
+
+      let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+      let res = server
+        .get_state()
+        .create_ledger(Some(endorsers.clone()), handle_bytes.as_ref(), &[])
+        .await;
+      println!("create_ledger with the first two endorser: {:?}", res);
+      assert!(res.is_ok());
+
+      let new_handle = handle_bytes.to_vec();
+
+      let message = "data_block_append 2".as_bytes();
+      let res = server
+        .get_state()
+        .append_ledger(
+          Some(endorsers.clone()),
+          &new_handle.clone(),
+          message,
+          1usize,
+        )
+        .await;
+      println!(
+        "append_ledger new handle1 with the first two endorsers: {:?}",
+        res
+      );
+      assert!(res.is_ok());
+
+      let handle2_bytes = rand::thread_rng().gen::<[u8; 16]>();
+      let res = server
+        .get_state()
+        .create_ledger(None, handle2_bytes.as_ref(), &[])
+        .await;
+      println!("create_ledger with all three endorser: {:?}", res);
+      assert!(res.is_ok());
+
+      let new_handle2 = handle2_bytes.to_vec();
+
+      let message2 = "data_block_append 3".as_bytes();
+      let res = server
+        .get_state()
+        .append_ledger(
+          Some(endorsers.clone()),
+          &new_handle2.clone(),
+          message2,
+          1usize,
+        )
+        .await;
+      println!(
+        "append_ledger new handle2 with the first two endorsers: {:?}",
+        res
+      );
+      assert!(res.is_ok());
+
+      // Launch three new endorsers
+      let endorser_args7 = endorser_args.clone() + " -p 9097";
+      let endorser7 = launch_endorser(&endorser_cmd, endorser_args7);
+      let endorser_args8 = endorser_args.clone() + " -p 9098";
+      let endorser8 = launch_endorser(&endorser_cmd, endorser_args8);
+      let endorser_args9 = endorser_args.clone() + " -p 9099";
+      let endorser9 = launch_endorser(&endorser_cmd, endorser_args9);
+
+      // Connect to new endorsers
+      let new_endorsers = server
+        .state
+        .connect_endorsers(&[
+          "http://[::1]:9097".to_string(),
+          "http://[::1]:9098".to_string(),
+          "http://[::1]:9099".to_string(),
+        ])
+        .await;
+      assert!(new_endorsers.len() == 3);
+
+      // Package the list of endorsers into a genesis block of the view ledger
+      let view_ledger_genesis_block = bincode::serialize(&new_endorsers).unwrap();
+
+      // Store the genesis block of the view ledger in the ledger store
+      let res = server
+        .state
+        .ledger_store
+        .append_view_ledger(&Block::new(&view_ledger_genesis_block), 4usize)
+        .await;
+      assert!(res.is_ok());
+
+      // Step 13: drop old coordinator and start a new coordinator
+      drop(server);
+
+      let coordinator2 = Arc::new(
+        CoordinatorState::new(&store, &ledger_store_args, None)
+          .await
+          .unwrap(),
+      );
+
+      let server2 = CoordinatorServiceState::new(coordinator2);
+      println!("Started a new coordinator");
+
+      let req = tonic::Request::new(ReadViewTailReq {});
+      let res = server2.read_view_tail(req).await;
+      assert!(res.is_ok());
+      let ReadViewTailResp {
+        block,
+        receipts,
+        height: _view_height,
+        attestations,
+      } = res.unwrap().into_inner();
+
+      let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
+      println!("Applying ReadViewByIndexResp Response: {:?}", res);
+      assert!(res.is_ok());
+
+      // Step 14: Append via the new coordinator
+      let message = "data_block_append 4".as_bytes();
+      let req = tonic::Request::new(AppendReq {
+        handle: new_handle.clone(),
+        block: message.to_vec(),
+        expected_height: 2_u64,
+        request_id: Vec::new(),
+      });
+
+      let AppendResp {
+        hash_nonces,
+        receipts,
+      } = server2.append(req).await.unwrap().into_inner();
+      let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
+      println!("Append verification: {:?}", res.is_ok());
+      assert!(res.is_ok());
+
+      // Step 14: Append without a condition via the new coordinator
+      let message = "data_block_append 4".as_bytes();
+      let req = tonic::Request::new(AppendReq {
+        handle: new_handle2.clone(),
+        block: message.to_vec(),
+        expected_height: 2_u64,
+        request_id: Vec::new(),
       });
 
-      let AppendResp {
-        hash_nonces,
-        receipts,
-      } = server.append(req).await.unwrap().into_inner();
+      let AppendResp {
+        hash_nonces,
+        receipts,
+      } = server2.append(req).await.unwrap().into_inner();
+      let res = vs.verify_append(&new_handle2, message, &hash_nonces, 2, &receipts);
+      println!("Append verification: {:?}", res.is_ok());
+      assert!(res.is_ok());
+
+      server2.get_state().reset_ledger_store().await;
+
+      println!("endorser7 process ID is {}", endorser7.child.id());
+      println!("endorser8 process ID is {}", endorser8.child.id());
+      println!("endorser9 process ID is {}", endorser9.child.id());
+    }
+
+    // We access endorser and endorser2 below
+    // to stop them from being dropped earlier
+    println!("endorser1 process ID is {}", endorser.child.id());
+    println!("endorser2 process ID is {}", endorser2.child.id());
+    println!("endorser3 process ID is {}", endorser3.child.id());
+    println!("endorser4 process ID is {}", endorser4.child.id());
+    println!("endorser5 process ID is {}", endorser5.child.id());
+    println!("endorser6 process ID is {}", endorser6.child.id());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_endorser_faults() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-      let res = vs.verify_append(
-        &handle,
-        block_to_append.as_ref(),
-        &hash_nonces,
-        expected_height,
-        &receipts,
+    // Each endorser misbehaves on appends in a way that leads to a different action
+    let retry = "http://[::1]:9096".to_string();
+    let endorser_retry = launch_endorser(
+      &endorser_cmd,
+      "-p 9096 --fault append:resource_exhausted:0:2".to_string(),
+    );
+    let update = "http://[::1]:9097".to_string();
+    let endorser_update = launch_endorser(
+      &endorser_cmd,
+      "-p 9097 --fault append:not_found:0:1".to_string(),
+    );
+    let remove = "http://[::1]:9098".to_string();
+    let endorser_remove =
+      launch_endorser(&endorser_cmd, "-p 9098 --fault append:internal".to_string());
+
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    let res = coordinator
+      .replace_endorsers(&[retry.clone(), update.clone(), remove.clone()])
+      .await;
+    assert!(res.is_ok());
+
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let receipts = coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+    assert!(!receipts.is_empty());
+
+    // ResourceExhausted is retried until the endorser appends
+    let pk = coordinator.get_endorser_pk(&retry).unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(Some(vec![pk]), &handle_bytes, b"block1", 1)
+      .await
+      .unwrap();
+    assert!(!receipts.is_empty());
+
+    // NotFound leaves the endorser to be brought up to date in the background, without
+    // holding up the append
+    let pk = coordinator.get_endorser_pk(&update).unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(Some(vec![pk.clone()]), &handle_bytes, b"block2", 2)
+      .await
+      .unwrap();
+    assert!(receipts.is_empty());
+
+    // the catch-up replays the genesis block, block1 and block2 to the endorser, which
+    // already holds the genesis block as its tail and signs it again
+    let catch_up = CatchUp::new(
+      coordinator.clone(),
+      CatchUpConfig {
+        scan_interval_secs: 1,
+        batch_size: 2,
+      },
+    );
+    assert_eq!(catch_up.catch_up(false).await, 3);
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(Some(vec![pk]), &handle_bytes, b"block3", 3)
+      .await
+      .unwrap();
+    assert!(!receipts.is_empty());
+
+    // Internal errors get the endorser removed
+    let pk = coordinator.get_endorser_pk(&remove).unwrap();
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(Some(vec![pk.clone()]), &handle_bytes, b"block4", 4)
+      .await
+      .unwrap();
+    assert!(receipts.is_empty());
+    assert!(!coordinator.get_endorser_pks().contains(&pk));
+    assert!(coordinator.get_endorser_pk(&retry).is_some());
+
+    // a scan finds that an endorser lags behind another one, which holds block3, and brings
+    // it up to the tail of the ledger in the ledger store, i.e., block2 to block4
+    assert_eq!(catch_up.catch_up(true).await, 3);
+    // which leaves the other one behind on block4 until the next scan
+    assert_eq!(catch_up.catch_up(true).await, 1);
+    assert_eq!(catch_up.catch_up(true).await, 0);
+
+    println!("endorser_retry process ID is {}", endorser_retry.child.id());
+    println!(
+      "endorser_update process ID is {}",
+      endorser_update.child.id()
+    );
+    println!(
+      "endorser_remove process ID is {}",
+      endorser_remove.child.id()
+    );
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_backup() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+
+    // The second append is slow, so the backup starts while it is in flight
+    let endorser = launch_endorser(
+      &endorser_cmd,
+      "-p 9099 --fault append:delay=1000:1:1".to_string(),
+    );
+
+    let dir = std::env::temp_dir().join(format!("nimble_backups_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut args = HashMap::new();
+    args.insert("BACKUP_DIR".to_string(), dir.to_str().unwrap().to_string());
+    let coordinator = Arc::new(CoordinatorState::new("memory", &args, None).await.unwrap());
+    let res = coordinator
+      .replace_endorsers(&["http://[::1]:9099".to_string()])
+      .await;
+    assert!(res.is_ok());
+
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, &handle_bytes, b"block1", 1)
+      .await
+      .unwrap();
+
+    let append = {
+      let coordinator = coordinator.clone();
+      tokio::spawn(async move {
+        coordinator
+          .append_ledger(None, &handle_bytes, b"block2", 2)
+          .await
+      })
+    };
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let manifest = coordinator.backup("nimble.backup").await.unwrap();
+    assert!(append.await.unwrap().is_ok());
+
+    // The backup waited for the append and captured it with its receipts
+    assert_eq!(manifest.view_height, 1);
+    assert_eq!(manifest.ledgers.len(), 1);
+    assert_eq!(manifest.ledgers[0].height, 2);
+
+    let file = std::fs::File::open(dir.join("nimble.backup")).unwrap();
+    let mut reader = store::backup::BackupReader::new(file).unwrap();
+    let mut num_entries = 0;
+    while let Some(entry) = reader.next_entry().unwrap() {
+      if let store::backup::BackupEntry::Ledger(_handle, _idx, entry) = entry {
+        assert!(!entry.get_receipts().is_empty());
+        num_entries += 1;
+      }
+    }
+    assert_eq!(num_entries, 3);
+    assert_eq!(reader.manifest(), Some(&manifest));
+    // only the backup is left in the directory
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    println!("endorser process ID is {}", endorser.child.id());
+  }
+
+  #[tokio::test]
+  async fn test_coordinator_backup_names() {
+    // backups are disabled without a backup directory
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    assert_eq!(
+      coordinator.backup("nimble.backup").await.err(),
+      Some(CoordinatorError::InvalidBackupName)
+    );
+
+    // and are only written as files of the directory
+    let dir = std::env::temp_dir().join(format!("nimble_backup_names_{}", std::process::id()));
+    let mut args = HashMap::new();
+    args.insert("BACKUP_DIR".to_string(), dir.to_str().unwrap().to_string());
+    let coordinator = CoordinatorState::new("memory", &args, None).await.unwrap();
+    let outside = std::env::temp_dir().join("nimble.backup");
+    for name in [
+      "",
+      ".",
+      "..",
+      "../nimble.backup",
+      "sub/nimble.backup",
+      "nimble.backup/",
+      outside.to_str().unwrap(),
+    ] {
+      assert_eq!(
+        coordinator.backup(name).await.err(),
+        Some(CoordinatorError::InvalidBackupName),
+        "{:?}",
+        name
       );
-      println!("Append verification: {:?} {:?}", block_to_append, res);
-      assert!(res.is_ok());
     }
+    assert!(!dir.exists());
+  }
 
-    // Step 4: Read Latest with the Nonce generated and check for new data
-    let nonce = rand::thread_rng().gen::<[u8; 16]>();
-    let latest_state_query = tonic::Request::new(ReadLatestReq {
-      handle: handle.clone(),
-      nonce: nonce.to_vec(),
-    });
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_delete_ledger() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+
+    let endorser = launch_endorser(&endorser_cmd, "-p 9101".to_string());
+
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    let res = coordinator
+      .replace_endorsers(&["http://[::1]:9101".to_string()])
+      .await;
+    assert!(res.is_ok());
+
+    let server = CoordinatorServiceState::new(coordinator.clone());
+    let mut vs = VerifierState::new();
+    let ReadViewTailResp {
+      block,
+      receipts,
+      height: _,
+      attestations,
+    } = server
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    vs.set_group_identity(NimbleDigest::digest(&block));
+    assert!(vs
+      .apply_view_change(&block, &receipts, Some(&attestations))
+      .is_ok());
+
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, &handle_bytes, b"block1", 1)
+      .await
+      .unwrap();
+
+    // Blocks with the same bytes as a tombstone are ordinary blocks, which do not verify as
+    // a deletion
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = server
+      .append(tonic::Request::new(AppendReq {
+        handle: handle_bytes.to_vec(),
+        block: Block::tombstone().to_bytes(),
+        expected_height: 2,
+        request_id: Vec::new(),
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(vs
+      .verify_append(
+        &handle_bytes,
+        &Block::tombstone().to_bytes(),
+        &hash_nonces,
+        2,
+        &receipts
+      )
+      .is_ok());
+    assert!(vs
+      .verify_delete_ledger(&handle_bytes, 2, &receipts)
+      .is_err());
+
+    let DeleteLedgerResp { receipts } = server
+      .delete_ledger(tonic::Request::new(DeleteLedgerReq {
+        handle: handle_bytes.to_vec(),
+        expected_height: 3,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert!(vs.verify_delete_ledger(&handle_bytes, 3, &receipts).is_ok());
+    assert!(vs
+      .verify_delete_ledger(&handle_bytes, 4, &receipts)
+      .is_err());
 
+    // The tail of the ledger proves that it was deleted
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
     let ReadLatestResp {
       block,
       nonces,
       receipts,
     } = server
-      .read_latest(latest_state_query)
+      .read_latest(tonic::Request::new(ReadLatestReq {
+        handle: handle_bytes.to_vec(),
+        nonce: nonce.to_vec(),
+      }))
       .await
       .unwrap()
       .into_inner();
-    assert_eq!(block, b3.clone());
-
-    let is_latest_valid =
-      vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
-    println!(
-      "Verifying ReadLatest Response : {:?}",
-      is_latest_valid.is_ok()
+    assert_eq!(block, Block::tombstone().to_bytes());
+    assert_eq!(
+      vs.verify_read_deleted(&handle_bytes, &nonce, &receipts),
+      Ok(3)
     );
-    assert!(is_latest_valid.is_ok());
-
-    // Step 5: Read At Index
-    let req = tonic::Request::new(ReadByIndexReq {
-      handle: handle.clone(),
-      index: 1,
-    });
+    assert!(vs
+      .verify_read_latest(&handle_bytes, &block, &nonces, &nonce, &receipts)
+      .is_err());
 
+    // The tombstone is kept in the ledger store with its receipts
     let ReadByIndexResp {
       block,
-      nonces,
+      nonces: _,
       receipts,
-    } = server.read_by_index(req).await.unwrap().into_inner();
-    assert_eq!(block, b1.clone());
+    } = server
+      .read_by_index(tonic::Request::new(ReadByIndexReq {
+        handle: handle_bytes.to_vec(),
+        index: 3,
+      }))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(block, Block::tombstone().to_bytes());
+    assert!(vs.verify_delete_ledger(&handle_bytes, 3, &receipts).is_ok());
 
-    let res = vs.verify_read_by_index(&handle, &block, &nonces, 1, &receipts);
-    println!("Verifying ReadByIndex Response: {:?}", res.is_ok());
-    assert!(res.is_ok());
+    // The ledger can be neither appended to nor deleted again
+    let res = coordinator
+      .append_ledger(None, &handle_bytes, b"block4", 4)
+      .await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::LedgerDeleted));
+    let res = coordinator.delete_ledger(None, &handle_bytes, 4).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::LedgerDeleted));
 
-    // Step 6: change the view by adding two new endorsers
-    let endorser_args2 = endorser_args.clone() + " -p 9092";
-    let endorser2 = launch_endorser(&endorser_cmd, endorser_args2);
-    let endorser_args3 = endorser_args.clone() + " -p 9093";
-    let endorser3 = launch_endorser(&endorser_cmd, endorser_args3);
+    println!("endorser process ID is {}", endorser.child.id());
+  }
 
-    let res = server
-      .get_state()
-      .replace_endorsers(&[
-        "http://[::1]:9092".to_string(),
-        "http://[::1]:9093".to_string(),
-      ])
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_prune() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+
+    let endorser = launch_endorser(&endorser_cmd, "-p 9100".to_string());
+
+    let mut args = HashMap::new();
+    args.insert("RETAIN_ENTRIES".to_string(), "2".to_string());
+    let coordinator = Arc::new(CoordinatorState::new("memory", &args, None).await.unwrap());
+    let res = coordinator
+      .replace_endorsers(&["http://[::1]:9100".to_string()])
       .await;
-    println!("new config with 2 endorsers: {:?}", res);
     assert!(res.is_ok());
 
-    let req = tonic::Request::new(ReadViewTailReq {});
-    let res = server.read_view_tail(req).await;
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+    for i in 1..=5 {
+      coordinator
+        .append_ledger(None, &handle_bytes, format!("block{}", i).as_bytes(), i)
+        .await
+        .unwrap();
+    }
+
+    // Entries 0 to 2 are deleted and entry 3 is kept as the checkpoint
+    assert_eq!(coordinator.prune_ledgers().await.unwrap(), 3);
+    assert_eq!(coordinator.prune_ledgers().await.unwrap(), 0);
+    let res = coordinator.read_ledger_by_index(&handle_bytes, 2).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::PrunedIndex));
+    let entry = coordinator
+      .read_ledger_by_index(&handle_bytes, 3)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_block().to_bytes(), b"block3".to_vec());
+
+    // Appends continue past the checkpoint
+    coordinator
+      .append_ledger(None, &handle_bytes, b"block6", 6)
+      .await
+      .unwrap();
+    assert_eq!(coordinator.prune_ledgers().await.unwrap(), 1);
+
+    println!("endorser process ID is {}", endorser.child.id());
+  }
+
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_content_store() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+
+    let endorser = launch_endorser(&endorser_cmd, "-p 9140".to_string());
+
+    let dir = std::env::temp_dir().join(format!("nimble_content_{}", std::process::id()));
+    let mut args = HashMap::new();
+    args.insert("CONTENT_STORE".to_string(), "filestore".to_string());
+    args.insert(
+      "NIMBLE_CONTENT_DIR".to_string(),
+      dir.to_str().unwrap().to_string(),
+    );
+    args.insert("CONTENT_THRESHOLD".to_string(), "16".to_string());
+    let coordinator = Arc::new(CoordinatorState::new("memory", &args, None).await.unwrap());
+    let res = coordinator
+      .replace_endorsers(&["http://[::1]:9140".to_string()])
+      .await;
     assert!(res.is_ok());
-    let ReadViewTailResp {
-      block,
-      receipts,
-      height: _view_height,
-      attestations,
-    } = res.unwrap().into_inner();
 
-    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
-    println!("Applying ReadViewByIndexResp Response: {:?}", res);
-    assert!(res.is_ok());
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let handle = NimbleDigest::digest(&handle_bytes);
+    coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+    let large = vec![7u8; 1024];
+    coordinator
+      .append_ledger(None, &handle_bytes, &large, 1)
+      .await
+      .unwrap();
+
+    // Small blocks stay in the ledger store, and large ones are replaced by a reference
+    let stored = coordinator
+      .ledger_store
+      .read_ledger_by_index(&handle, 0)
+      .await
+      .unwrap();
+    assert_eq!(stored.get_block().to_bytes(), b"genesis".to_vec());
+    let stored = coordinator
+      .ledger_store
+      .read_ledger_by_index(&handle, 1)
+      .await
+      .unwrap();
+    assert_eq!(
+      decode_content_ref(stored.get_block()),
+      Some(NimbleDigest::digest(&large))
+    );
+
+    // The block is read back through its reference
+    let entry = coordinator
+      .read_ledger_by_index(&handle_bytes, 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_block().to_bytes(), large);
+
+    // Content that does not match the hash in the reference is refused
+    std::fs::write(
+      dir.join(hex::encode(NimbleDigest::digest(&large).to_bytes())),
+      b"tampered content",
+    )
+    .unwrap();
+    let res = coordinator.read_ledger_by_index(&handle_bytes, 1).await;
+    assert_eq!(
+      res.err(),
+      Some(crate::CoordinatorError::FailedToCallContentStore)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    println!("endorser process ID is {}", endorser.child.id());
+  }
+
+  #[tokio::test]
+  async fn test_coordinator_retention() {
+    let mut args = HashMap::new();
+    args.insert("RETAIN_ENTRIES".to_string(), "-1".to_string());
+    let res = CoordinatorState::new("memory", &args, None).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::InvalidRetention));
+
+    // Without a retention policy nothing is pruned
+    let coordinator = CoordinatorState::new("memory", &HashMap::new(), None)
+      .await
+      .unwrap();
+    assert_eq!(coordinator.prune_ledgers().await.unwrap(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_coordinator_key_file_with_content_store() {
+    // blocks are not encrypted in a content store
+    let key_file = std::env::temp_dir().join(format!("nimble_ledger_key_{}", std::process::id()));
+    std::fs::write(&key_file, base64_url::encode(&[7u8; 32])).unwrap();
+    let mut args = HashMap::new();
+    args.insert(
+      "LEDGER_KEY_FILE".to_string(),
+      key_file.to_str().unwrap().to_string(),
+    );
+    args.insert("CONTENT_STORE".to_string(), "memory".to_string());
+    let res = CoordinatorState::new("memory", &args, None).await;
+    assert_eq!(
+      res.err(),
+      Some(crate::CoordinatorError::InvalidContentStore)
+    );
+    std::fs::remove_file(&key_file).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_coordinator_ledger_store_faults() {
+    let fail =
+      |op: LedgerOp| FaultRule::new(Some(op), Fault::Fail(StorageError::BadRequest)).times(1);
+    let coordinator = Arc::new(
+      CoordinatorState::new_with_faults(
+        "memory",
+        &HashMap::new(),
+        &[
+          fail(LedgerOp::CreateLedger),
+          fail(LedgerOp::AppendLedger),
+          fail(LedgerOp::AttachLedgerNonce),
+        ],
+      )
+      .await
+      .unwrap(),
+    );
+
+    // a ledger is not created if the ledger store fails to create it
+    let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let block_bytes = rand::thread_rng().gen::<[u8; 16]>();
+    let res = coordinator
+      .create_ledger(None, &handle_bytes, &block_bytes)
+      .await;
+    assert_eq!(res.err(), Some(CoordinatorError::FailedToCreateLedger));
+
+    // the fault was injected once, so the ledger store creates it now
+    let handle = NimbleDigest::digest(&handle_bytes);
+    coordinator
+      .ledger_store
+      .create_ledger(&handle, Block::new(&block_bytes))
+      .await
+      .unwrap();
+
+    // an append fails if the ledger store fails to append the block
+    let res = coordinator
+      .append_ledger(None, &handle_bytes, &block_bytes, 1)
+      .await;
+    assert_eq!(res.err(), Some(CoordinatorError::FailedToAppendLedger));
+    assert_eq!(
+      coordinator
+        .ledger_store
+        .read_ledger_tail(&handle)
+        .await
+        .unwrap()
+        .1,
+      0
+    );
+
+    // without endorsers, reading the tail attaches the nonce, which fails
+    let nonce = rand::thread_rng().gen::<[u8; 16]>();
+    let res = coordinator.read_ledger_tail(&handle_bytes, &nonce).await;
+    assert_eq!(res.err(), Some(CoordinatorError::FailedToAttachNonce));
+  }
 
-    // Step 7: Append after view change
-    expected_height += 1;
+  #[tokio::test]
+  async fn test_coordinator_control() {
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    let router = control_router(
+      coordinator.clone(),
+      CatchUpConfig::default(),
+      Some("s3cr3t".to_string()),
+    );
+    let call = |method: &str, uri: &str, token: Option<&str>| {
+      let mut req = HttpRequest::builder().method(method).uri(uri);
+      if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+      }
+      router.clone().oneshot(req.body(Body::empty()).unwrap())
+    };
+    let status = || async {
+      let resp = call("GET", "/status", Some("s3cr3t")).await.unwrap();
+      assert_eq!(resp.status(), StatusCode::OK);
+      let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+      serde_json::from_slice::<StatusResponse>(&body).unwrap()
+    };
 
-    let message = "data_block_append".as_bytes();
-    let req = tonic::Request::new(AppendReq {
-      handle: handle.clone(),
-      block: message.to_vec(),
-      expected_height: expected_height as u64,
-    });
+    // Requests without the token, or with another one, are refused
+    for token in [None, Some("other")] {
+      let resp = call("GET", "/status", token).await.unwrap();
+      assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
 
-    let AppendResp {
-      hash_nonces,
-      receipts,
-    } = server.append(req).await.unwrap().into_inner();
+    let res = status().await;
+    assert!(res.leader && !res.draining && res.store.healthy);
+    assert_eq!(res.view_height, Some(0));
+    assert!(res.reconfiguration.is_none());
+    let resp = call("GET", "/view", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = call("GET", "/stats", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = call("GET", "/endorsers", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    let res = serde_json::from_slice::<EndorserListResponse>(&body).unwrap();
+    assert!(res.endorsers.is_empty());
+    let resp = call("GET", "/views/1", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // An entry of a ledger is returned with its block, nonces and receipts
+    let handle = NimbleDigest::digest(b"control");
+    coordinator
+      .ledger_store
+      .create_ledger(&handle, Block::new(b"genesis"))
+      .await
+      .unwrap();
+    let handle = base64_url::encode(&handle.to_bytes());
+    let uri = format!("/ledgers/{}/0", handle);
+    let resp = call("GET", &uri, Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    let res = serde_json::from_slice::<LedgerEntryResponse>(&body).unwrap();
+    assert_eq!(base64_url::decode(&res.block).unwrap(), b"genesis");
+    let uri = format!("/ledgers/{}/1", handle);
+    let resp = call("GET", &uri, Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // A view change that fails is reported with its error
+    let res = coordinator
+      .reconfigure(&["http://[::1]:1".to_string()], "operator", "testing")
+      .await;
+    assert_eq!(res, Err(CoordinatorError::NoNewEndorsers));
+    let reconfiguration = status().await.reconfiguration.unwrap();
+    assert_eq!(reconfiguration.initiator, "operator");
+    assert!(reconfiguration.finished_at.is_some());
+    assert_eq!(reconfiguration.error, Some("NoNewEndorsers".to_string()));
+
+    // A drained coordinator serves neither clients nor the control requests of a leader
+    let resp = call("POST", "/drain", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(status().await.draining);
+    let resp = call("GET", "/view", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let server = CoordinatorServiceState::new(coordinator.clone());
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: b"handle".to_vec(),
+      block: Vec::new(),
+      request_id: Vec::new(),
+    });
+    let res = server.new_ledger(req).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Unavailable);
 
-    let res = vs.verify_append(&handle, message, &hash_nonces, expected_height, &receipts);
-    println!("Append verification: {:?}", res.is_ok());
-    assert!(res.is_ok());
+    let resp = call("DELETE", "/drain", Some("s3cr3t")).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(coordinator.is_leader());
+  }
 
-    // Step 8: Read Latest with the Nonce generated and check for new data appended without condition
-    let nonce = rand::thread_rng().gen::<[u8; 16]>();
-    let latest_state_query = tonic::Request::new(ReadLatestReq {
-      handle: handle.clone(),
-      nonce: nonce.to_vec(),
-    });
+  #[tokio::test]
+  async fn test_coordinator_election() {
+    let mut args = HashMap::new();
+    args.insert("LEASE_DURATION".to_string(), "0".to_string());
+    let res = CoordinatorState::new("memory", &args, None).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::InvalidLease));
+
+    args.insert("LEASE_DURATION".to_string(), "400".to_string());
+    args.insert("COORDINATOR_ID".to_string(), "a".to_string());
+    let a = CoordinatorState::new("memory", &args, None).await.unwrap();
+    args.insert("COORDINATOR_ID".to_string(), "b".to_string());
+    let mut b = CoordinatorState::new("memory", &args, None).await.unwrap();
+    // the two coordinators share one store
+    b.ledger_store = a.ledger_store.clone();
+    assert!(!a.is_leader() && !b.is_leader());
+
+    assert!(a.campaign().await.unwrap());
+    assert!(!b.campaign().await.unwrap());
+    assert!(a.campaign().await.unwrap());
+    assert!(a.is_leader() && !b.is_leader());
+    let lease = a.ledger_store.read_lease().await.unwrap().unwrap();
+    assert_eq!((lease.holder.as_str(), lease.epoch), ("a", 1));
+
+    // a standby turns clients away
+    let standby = CoordinatorServiceState::new(Arc::new(b));
+    let res = standby
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
+      .await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Unavailable);
+
+    // the leader stops serving before its lease expires, and the standby takes over once
+    // the lease has expired
+    tokio::time::sleep(std::time::Duration::from_millis(450)).await;
+    assert!(!a.is_leader());
+    assert!(standby.get_state().campaign().await.unwrap());
+    assert!(standby.get_state().is_leader());
+    let lease = a.ledger_store.read_lease().await.unwrap().unwrap();
+    assert_eq!((lease.holder.as_str(), lease.epoch), ("b", 2));
+
+    // the former leader cannot renew the lease it held
+    assert!(!a.campaign().await.unwrap());
+    assert!(!a.is_leader());
+    let lease = a.ledger_store.read_lease().await.unwrap().unwrap();
+    assert_eq!((lease.holder.as_str(), lease.epoch), ("b", 2));
+  }
 
-    let ReadLatestResp {
-      block,
-      nonces,
-      receipts,
-    } = server
-      .read_latest(latest_state_query)
+  #[tokio::test]
+  async fn test_coordinator_replicated_store() {
+    let mut args = HashMap::new();
+    let res = CoordinatorState::new("replicated", &args, None).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::InvalidReplicas));
+    args.insert("REPLICAS".to_string(), "memory;nosuchstore".to_string());
+    let res = CoordinatorState::new("replicated", &args, None).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::InvalidReplicas));
+    args.insert("REPLICAS".to_string(), "memory;memory:A".to_string());
+    let res = CoordinatorState::new("replicated", &args, None).await;
+    assert_eq!(res.err(), Some(crate::CoordinatorError::InvalidReplicas));
+
+    args.insert("REPLICAS".to_string(), "memory; memory ;memory".to_string());
+    let coordinator = CoordinatorState::new("replicated", &args, None)
       .await
-      .unwrap()
-      .into_inner();
-    assert_eq!(block, message);
+      .unwrap();
+    let handle = NimbleDigest::digest(b"replicated");
+    coordinator
+      .ledger_store
+      .create_ledger(&handle, Block::new(b"genesis"))
+      .await
+      .unwrap();
+    assert_eq!(coordinator.get_ledger_height(&handle).await.unwrap(), 0);
+    assert_eq!(coordinator.repair_ledger_store().await.unwrap(), 0);
+  }
 
-    let is_latest_valid =
-      vs.verify_read_latest(&handle, &block, &nonces, nonce.as_ref(), &receipts);
-    println!(
-      "Verifying ReadLatest Response : {:?}",
-      is_latest_valid.is_ok()
-    );
-    assert!(is_latest_valid.is_ok());
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_supervisor() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-    // Step 9: create a ledger and append to it only on the first endorser
-    let mut endorsers = server.get_state().get_endorser_pks();
-    endorsers.remove(1);
+    let mut endorsers = Vec::new();
+    let mut uris = Vec::new();
+    for port in 9110..9116 {
+      endorsers.push(Some(launch_endorser(&endorser_cmd, format!("-p {}", port))));
+      uris.push(format!("http://[::1]:{}", port));
+    }
+    let (view, spares) = uris.split_at(3);
 
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    coordinator.replace_endorsers(view).await.unwrap();
     let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
-    let res = server
-      .get_state()
-      .create_ledger(Some(endorsers.clone()), handle_bytes.as_ref(), &[])
-      .await;
-    println!("create_ledger with first endorser: {:?}", res);
-    assert!(res.is_ok());
+    coordinator
+      .create_ledger(None, &handle_bytes, b"genesis")
+      .await
+      .unwrap();
+
+    let mut supervisor = Supervisor::new(
+      coordinator.clone(),
+      SupervisorConfig {
+        spare_endorsers: spares.to_vec(),
+        failure_threshold: 1,
+        min_interval_secs: 0,
+        ..SupervisorConfig::default()
+      },
+    );
 
-    let new_handle = handle_bytes.to_vec();
+    // The view is kept while every endorser is up
+    supervisor.supervise().await.unwrap();
+    assert_eq!(coordinator.read_view_changes().await.unwrap().len(), 1);
+
+    // Once a quorum is all that is left, the view moves to the spares
+    endorsers[0] = None;
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    supervisor.supervise().await.unwrap();
+    let views = coordinator.read_view_changes().await.unwrap();
+    assert_eq!(views.len(), 2);
+    let (_height, new_endorsers, record) = &views[1];
+    let mut new_uris = new_endorsers
+      .iter()
+      .map(|(_pk, uri)| uri.clone())
+      .collect::<Vec<String>>();
+    new_uris.sort();
+    assert_eq!(new_uris, spares.to_vec());
+    let record = record.as_ref().unwrap();
+    assert_eq!(record.initiator, "supervisor");
+    assert!(record.reason.contains(&view[0]));
+    assert_eq!(views[0].2.as_ref().unwrap().initiator, "operator");
+
+    // The spares carry on with the ledgers of the old view
+    let mut sorted = coordinator.get_endorser_uris();
+    sorted.sort();
+    assert_eq!(sorted, spares.to_vec());
+    let (_hash_nonces, receipts) = coordinator
+      .append_ledger(None, &handle_bytes, b"block1", 1)
+      .await
+      .unwrap();
+    assert!(!receipts.is_empty());
+  }
 
-    let message = "data_block_append 2".as_bytes();
-    let res = server
-      .get_state()
-      .append_ledger(
-        Some(endorsers.clone()),
-        &new_handle.clone(),
-        message,
-        1usize,
-      )
-      .await;
-    println!("append_ledger with first endorser: {:?}", res);
-    assert!(res.is_ok());
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_membership() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-    let handle2_bytes = rand::thread_rng().gen::<[u8; 16]>();
-    let res = server
-      .get_state()
-      .create_ledger(None, handle2_bytes.as_ref(), &[])
-      .await;
-    println!("create_ledger with first endorser: {:?}", res);
-    assert!(res.is_ok());
+    let mut endorsers = Vec::new();
+    let mut uris = Vec::new();
+    for port in 9120..9124 {
+      endorsers.push(launch_endorser(&endorser_cmd, format!("-p {}", port)));
+      uris.push(format!("http://[::1]:{}", port));
+    }
 
-    let new_handle2 = handle2_bytes.to_vec();
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    coordinator.replace_endorsers(&uris[..2]).await.unwrap();
+    let server = CoordinatorServiceState::new(coordinator.clone());
+    let mut vs = VerifierState::new();
 
-    let message2 = "data_block_append 3".as_bytes();
-    let res = server
-      .get_state()
-      .append_ledger(
-        Some(endorsers.clone()),
-        &new_handle2.clone(),
-        message2,
-        1usize,
-      )
-      .await;
-    println!("append_ledger with first endorser: {:?}", res);
-    assert!(res.is_ok());
+    let apply_view_tail = |vs: &mut VerifierState, tail: ReadViewTailResp| {
+      vs.apply_view_change(&tail.block, &tail.receipts, Some(&tail.attestations))
+    };
+    let tail = server
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    vs.set_group_identity(NimbleDigest::digest(&tail.block));
+    apply_view_tail(&mut vs, tail).unwrap();
 
-    let nonce1 = rand::thread_rng().gen::<[u8; 16]>();
-    let res = server
-      .get_state()
-      .read_ledger_tail(&new_handle2, &nonce1)
-      .await;
-    assert!(res.is_ok());
+    let handle = rand::thread_rng().gen::<[u8; 16]>().to_vec();
+    let req = tonic::Request::new(NewLedgerReq {
+      handle: handle.clone(),
+      block: b"genesis".to_vec(),
+      request_id: Vec::new(),
+    });
+    let NewLedgerResp { receipts } = server.new_ledger(req).await.unwrap().into_inner();
+    vs.verify_new_ledger(&handle, b"genesis", &receipts)
+      .unwrap();
 
-    let res = server
-      .get_state()
-      .append_ledger(
-        Some(endorsers.clone()),
-        &new_handle2.clone(),
-        message2,
-        2usize,
-      )
-      .await;
-    println!("append_ledger with first endorser again: {:?}", res);
-    assert!(res.is_ok());
+    // Appends in each view are verified against the endorsers of that view
+    let append = |height: u64| {
+      let req = tonic::Request::new(AppendReq {
+        handle: handle.clone(),
+        block: format!("block{}", height).into_bytes(),
+        expected_height: height,
+        request_id: Vec::new(),
+      });
+      server.append(req)
+    };
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = append(1).await.unwrap().into_inner();
+    vs.verify_append(&handle, b"block1", &hash_nonces, 1, &receipts)
+      .unwrap();
 
-    let message3 = "data_block_append 4".as_bytes();
-    let res = server
-      .get_state()
-      .append_ledger(None, &new_handle2.clone(), message3, 3usize)
-      .await;
-    assert!(res.is_ok());
+    // An endorser joins while the others stay active
+    let pk = coordinator
+      .add_endorser(&uris[2], "operator")
+      .await
+      .unwrap();
+    assert_eq!(coordinator.get_endorser_pk(&uris[2]), Some(pk));
+    let mut connected = coordinator.get_endorser_uris();
+    connected.sort();
+    assert_eq!(connected, uris[..3].to_vec());
+    let tail = server
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    apply_view_tail(&mut vs, tail).unwrap();
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = append(2).await.unwrap().into_inner();
+    vs.verify_append(&handle, b"block2", &hash_nonces, 2, &receipts)
+      .unwrap();
 
-    let nonce2 = rand::thread_rng().gen::<[u8; 16]>();
-    let res = server
-      .get_state()
-      .read_ledger_tail(&new_handle2, &nonce2)
-      .await;
-    assert!(res.is_ok());
+    // An endorser leaves, and only it is finalized
+    coordinator
+      .remove_endorser(&uris[0], "operator")
+      .await
+      .unwrap();
+    let mut connected = coordinator.get_endorser_uris();
+    connected.sort();
+    assert_eq!(connected, uris[1..3].to_vec());
+    let tail = server
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
+      .await
+      .unwrap()
+      .into_inner();
+    apply_view_tail(&mut vs, tail).unwrap();
+    let AppendResp {
+      hash_nonces,
+      receipts,
+    } = append(3).await.unwrap().into_inner();
+    vs.verify_append(&handle, b"block3", &hash_nonces, 3, &receipts)
+      .unwrap();
+
+    // The view ledger records each delta
+    let views = coordinator.read_view_changes().await.unwrap();
+    assert_eq!(views.len(), 3);
+    let added = views[1].2.as_ref().unwrap();
+    assert_eq!(
+      (added.added.clone(), added.removed.len()),
+      (vec![uris[2].clone()], 0)
+    );
+    let removed = views[2].2.as_ref().unwrap();
+    assert_eq!(
+      (removed.added.len(), removed.removed.clone()),
+      (0, vec![uris[0].clone()])
+    );
+    assert_eq!(views[2].1.len(), 2);
 
-    let ledger_entry = res.unwrap();
-    assert_eq!(ledger_entry.get_block().to_bytes(), message3.to_vec());
-    let is_latest_valid = vs.verify_read_latest(
-      &new_handle2,
-      &ledger_entry.get_block().to_bytes(),
-      &ledger_entry.get_nonces().to_bytes(),
-      nonce2.as_ref(),
-      &ledger_entry.get_receipts().to_bytes(),
+    assert_eq!(
+      coordinator.add_endorser(&uris[1], "operator").await,
+      Err(CoordinatorError::EndorserAlreadyInView)
     );
-    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
-    assert!(is_latest_valid.is_ok());
+    assert_eq!(
+      coordinator.remove_endorser(&uris[3], "operator").await,
+      Err(CoordinatorError::InvalidEndorserUri)
+    );
+  }
 
-    let res = server
-      .get_state()
-      .read_ledger_by_index(&new_handle2, 2usize)
-      .await;
-    assert!(res.is_ok());
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_retried_requests() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-    let ledger_entry = res.unwrap();
-    assert_eq!(ledger_entry.get_block().to_bytes(), message2.to_vec());
-    let is_latest_valid = vs.verify_read_latest(
-      &new_handle2,
-      &ledger_entry.get_block().to_bytes(),
-      &ledger_entry.get_nonces().to_bytes(),
-      nonce1.as_ref(),
-      &ledger_entry.get_receipts().to_bytes(),
+    let endorser = launch_endorser(&endorser_cmd, "-p 9130".to_string());
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
     );
-    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
-    assert!(is_latest_valid.is_ok());
+    coordinator
+      .replace_endorsers(&["http://[::1]:9130".to_string()])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(coordinator.clone());
 
-    // Step 10: replace the view with three endorsers
-    let endorser_args4 = endorser_args.clone() + " -p 9094";
-    let endorser4 = launch_endorser(&endorser_cmd, endorser_args4);
-    let endorser_args5 = endorser_args.clone() + " -p 9095";
-    let endorser5 = launch_endorser(&endorser_cmd, endorser_args5);
-    let endorser_args6 = endorser_args.clone() + " -p 9096";
-    let endorser6 = launch_endorser(&endorser_cmd, endorser_args6);
+    let handle = rand::thread_rng().gen::<[u8; 16]>().to_vec();
+    let new_ledger = |request_id: &[u8]| {
+      server.new_ledger(tonic::Request::new(NewLedgerReq {
+        handle: handle.clone(),
+        block: b"genesis".to_vec(),
+        request_id: request_id.to_vec(),
+      }))
+    };
+    let NewLedgerResp { receipts } = new_ledger(b"create").await.unwrap().into_inner();
+
+    // A retry gets the receipts of the request that created the ledger, unless it has no ID
+    // or another request's ID
+    let retried = new_ledger(b"create").await.unwrap().into_inner();
+    assert_eq!(retried.receipts, receipts);
+    let res = new_ledger(b"").await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Aborted);
+    let res = new_ledger(b"other").await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Aborted);
+
+    let append = |block: &[u8], request_id: &[u8]| {
+      server.append(tonic::Request::new(AppendReq {
+        handle: handle.clone(),
+        block: block.to_vec(),
+        expected_height: 1,
+        request_id: request_id.to_vec(),
+      }))
+    };
+    let appended = append(b"block1", b"append").await.unwrap().into_inner();
+    let retried = append(b"block1", b"append").await.unwrap().into_inner();
+    assert_eq!(retried.hash_nonces, appended.hash_nonces);
+    assert_eq!(retried.receipts, appended.receipts);
+    assert_eq!(
+      coordinator
+        .get_ledger_height(&NimbleDigest::digest(&handle))
+        .await,
+      Ok(1)
+    );
+
+    // A request with another block at the height, or with the same block but another ID, is
+    // not a retry
+    let res = append(b"block2", b"append").await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Aborted);
+    let res = append(b"block1", b"other").await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::Aborted);
+    let res = append(b"block1", &[0u8; MAX_REQUEST_ID_SIZE + 1]).await;
+    assert_eq!(res.unwrap_err().code(), tonic::Code::InvalidArgument);
+
+    // A retry finishes endorsing an entry that was stored without its receipts, whether or
+    // not the endorsers got it before its request failed
+    let handle_digest = NimbleDigest::digest(&handle);
+    let retry_stored = |block: &[u8], height: usize| {
+      server.append(tonic::Request::new(AppendReq {
+        handle: handle.clone(),
+        block: block.to_vec(),
+        expected_height: height as u64,
+        request_id: b"stored".to_vec(),
+      }))
+    };
+    let (_, nonces) = coordinator
+      .ledger_store
+      .append_ledger_with_request(&handle_digest, &Block::new(b"block2"), 2, b"stored")
+      .await
+      .unwrap();
+    let retried = retry_stored(b"block2", 2).await.unwrap().into_inner();
+    assert_eq!(retried.hash_nonces, nonces.hash().to_bytes());
+    assert!(!retried.receipts.is_empty());
+
+    let (_, nonces) = coordinator
+      .ledger_store
+      .append_ledger_with_request(&handle_digest, &Block::new(b"block3"), 3, b"stored")
+      .await
+      .unwrap();
+    let block_hash = compute_aggregated_block_hash(
+      &Block::new(b"block3").hash().to_bytes(),
+      &nonces.hash().to_bytes(),
+    );
+    coordinator
+      .endorser_append_ledger(
+        &coordinator.get_endorser_pks(),
+        &handle_digest,
+        &block_hash,
+        3,
+        Block::new(b"block3"),
+        nonces,
+      )
+      .await
+      .unwrap();
+    let retried = retry_stored(b"block3", 3).await.unwrap().into_inner();
+    assert!(!retried.receipts.is_empty());
+    let ledger_entry = coordinator
+      .ledger_store
+      .read_ledger_by_index(&handle_digest, 3)
+      .await
+      .unwrap();
+    assert!(!ledger_entry.get_receipts().is_empty());
 
-    let res = server
-      .get_state()
-      .replace_endorsers(&[
-        "http://[::1]:9094".to_string(),
-        "http://[::1]:9095".to_string(),
-        "http://[::1]:9096".to_string(),
-      ])
-      .await;
-    println!("new config with 3 endorsers: {:?}", res);
-    assert!(res.is_ok());
+    println!("endorser process ID is {}", endorser.child.id());
+  }
 
-    let req = tonic::Request::new(ReadViewTailReq {});
-    let res = server.read_view_tail(req).await;
-    assert!(res.is_ok());
+  // Reads the view ledger into a verifier state, the way a client starts
+  async fn new_verifier_state(server: &CoordinatorServiceState) -> VerifierState {
     let ReadViewTailResp {
       block,
       receipts,
-      height: _view_height,
       attestations,
-    } = res.unwrap().into_inner();
-
-    let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
-    println!("Applying ReadViewByIndexResp Response: {:?}", res);
-    assert!(res.is_ok());
-
-    // Step 11: read the latest of the new ledger
-    let nonce = rand::thread_rng().gen::<[u8; 16]>();
-    let latest_state_query = tonic::Request::new(ReadLatestReq {
-      handle: new_handle.clone(),
-      nonce: nonce.to_vec(),
-    });
-
-    let ReadLatestResp {
-      block,
-      nonces,
-      receipts,
+      ..
     } = server
-      .read_latest(latest_state_query)
+      .read_view_tail(tonic::Request::new(ReadViewTailReq {}))
       .await
       .unwrap()
       .into_inner();
-    assert_eq!(block, message);
-
-    let is_latest_valid =
-      vs.verify_read_latest(&new_handle, &block, &nonces, nonce.as_ref(), &receipts);
-    println!("Verifying ReadLatest Response : {:?}", is_latest_valid,);
-    assert!(is_latest_valid.is_ok());
-
-    // Step 12: Append data
-    let message = "data_block_append 3".as_bytes();
-    let req = tonic::Request::new(AppendReq {
-      handle: new_handle.clone(),
-      block: message.to_vec(),
-      expected_height: 2_u64,
-    });
-
-    let AppendResp {
-      hash_nonces,
-      receipts,
-    } = server.append(req).await.unwrap().into_inner();
-
-    let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
-    println!("Append verification: {:?}", res.is_ok());
-    assert!(res.is_ok());
-
-    if store != "memory" {
-      // set up the endorsers to be at different heights
-      let mut endorsers = server.get_state().get_endorser_pks();
-      endorsers.remove(1);
-
-      let handle_bytes = rand::thread_rng().gen::<[u8; 16]>();
-      let res = server
-        .get_state()
-        .create_ledger(Some(endorsers.clone()), handle_bytes.as_ref(), &[])
-        .await;
-      println!("create_ledger with the first two endorser: {:?}", res);
-      assert!(res.is_ok());
+    let mut vs = VerifierState::new();
+    vs.set_group_identity(NimbleDigest::digest(&block));
+    assert!(vs
+      .apply_view_change(&block, &receipts, Some(&attestations))
+      .is_ok());
+    vs
+  }
 
-      let new_handle = handle_bytes.to_vec();
+  // Appends `num_appends` blocks to a new ledger, all at once if `pipelined` is set, or one
+  // after the other otherwise, and returns the time it took
+  async fn append_blocks(
+    coordinator: &Arc<CoordinatorState>,
+    vs: &VerifierState,
+    num_appends: usize,
+    pipelined: bool,
+  ) -> std::time::Duration {
+    let handle = rand::thread_rng().gen::<[u8; 16]>().to_vec();
+    let receipts = coordinator
+      .create_ledger(None, &handle, b"genesis")
+      .await
+      .unwrap();
+    assert!(vs
+      .verify_new_ledger(&handle, b"genesis", &receipts.to_bytes())
+      .is_ok());
+
+    let start = std::time::Instant::now();
+    let mut results = Vec::new();
+    let mut appends = Vec::new();
+    for height in 1..=num_appends {
+      let coordinator = coordinator.clone();
+      let handle = handle.clone();
+      let append = tokio::spawn(async move {
+        let block = format!("block {}", height).into_bytes();
+        let res = coordinator
+          .append_ledger(None, &handle, &block, height)
+          .await;
+        (block, res)
+      });
+      if pipelined {
+        appends.push(append);
+      } else {
+        results.push(append.await.unwrap());
+      }
+    }
+    for append in appends {
+      results.push(append.await.unwrap());
+    }
+    let elapsed = start.elapsed();
 
-      let message = "data_block_append 2".as_bytes();
-      let res = server
-        .get_state()
-        .append_ledger(
-          Some(endorsers.clone()),
-          &new_handle.clone(),
-          message,
-          1usize,
-        )
-        .await;
-      println!(
-        "append_ledger new handle1 with the first two endorsers: {:?}",
-        res
+    for (i, (block, res)) in results.into_iter().enumerate() {
+      let (hash_nonces, receipts) = res.unwrap();
+      let res = vs.verify_append(
+        &handle,
+        &block,
+        &hash_nonces.to_bytes(),
+        i + 1,
+        &receipts.to_bytes(),
       );
       assert!(res.is_ok());
+    }
+    elapsed
+  }
 
-      let handle2_bytes = rand::thread_rng().gen::<[u8; 16]>();
-      let res = server
-        .get_state()
-        .create_ledger(None, handle2_bytes.as_ref(), &[])
-        .await;
-      println!("create_ledger with all three endorser: {:?}", res);
-      assert!(res.is_ok());
-
-      let new_handle2 = handle2_bytes.to_vec();
-
-      let message2 = "data_block_append 3".as_bytes();
-      let res = server
-        .get_state()
-        .append_ledger(
-          Some(endorsers.clone()),
-          &new_handle2.clone(),
-          message2,
-          1usize,
-        )
-        .await;
-      println!(
-        "append_ledger new handle2 with the first two endorsers: {:?}",
-        res
-      );
-      assert!(res.is_ok());
+  #[tokio::test]
+  #[ignore]
+  async fn test_coordinator_batched_appends() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-      // Launch three new endorsers
-      let endorser_args7 = endorser_args.clone() + " -p 9097";
-      let endorser7 = launch_endorser(&endorser_cmd, endorser_args7);
-      let endorser_args8 = endorser_args.clone() + " -p 9098";
-      let endorser8 = launch_endorser(&endorser_cmd, endorser_args8);
-      let endorser_args9 = endorser_args.clone() + " -p 9099";
-      let endorser9 = launch_endorser(&endorser_cmd, endorser_args9);
+    // the second endorser acts as one that does not implement batched appends
+    let endorser = launch_endorser(&endorser_cmd, "-p 9131".to_string());
+    let legacy_endorser = launch_endorser(
+      &endorser_cmd,
+      "-p 9132 --fault append_batch:unimplemented".to_string(),
+    );
+    let args = HashMap::from([("APPEND_BATCH_SIZE".to_string(), "64".to_string())]);
+    let coordinator = Arc::new(CoordinatorState::new("memory", &args, None).await.unwrap());
+    coordinator
+      .replace_endorsers(&[
+        "http://[::1]:9131".to_string(),
+        "http://[::1]:9132".to_string(),
+      ])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(coordinator.clone());
+    let vs = new_verifier_state(&server).await;
 
-      // Connect to new endorsers
-      let new_endorsers = server
-        .state
-        .connect_endorsers(&[
-          "http://[::1]:9097".to_string(),
-          "http://[::1]:9098".to_string(),
-          "http://[::1]:9099".to_string(),
-        ])
-        .await;
-      assert!(new_endorsers.len() == 3);
+    // Appends that arrive together are sent to the endorsers in batches, and every append
+    // gets the receipts of its own entry
+    let num_batches = metrics::APPEND_BATCH_SIZE.get_sample_count();
+    append_blocks(&coordinator, &vs, 32, true).await;
+    let num_batches = metrics::APPEND_BATCH_SIZE.get_sample_count() - num_batches;
+    assert!(num_batches < 32);
 
-      // Package the list of endorsers into a genesis block of the view ledger
-      let view_ledger_genesis_block = bincode::serialize(&new_endorsers).unwrap();
+    println!(
+      "endorser process IDs are {} and {}",
+      endorser.child.id(),
+      legacy_endorser.child.id()
+    );
+  }
 
-      // Store the genesis block of the view ledger in the ledger store
-      let res = server
-        .state
-        .ledger_store
-        .append_view_ledger(&Block::new(&view_ledger_genesis_block), 4usize)
-        .await;
-      assert!(res.is_ok());
+  #[tokio::test(flavor = "multi_thread")]
+  #[ignore]
+  async fn test_coordinator_watch() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
 
-      // Step 13: drop old coordinator and start a new coordinator
-      drop(server);
+    let endorser = launch_endorser(&endorser_cmd, "-p 9135".to_string());
+    let coordinator = Arc::new(
+      CoordinatorState::new("memory", &HashMap::new(), None)
+        .await
+        .unwrap(),
+    );
+    coordinator
+      .replace_endorsers(&["http://[::1]:9135".to_string()])
+      .await
+      .unwrap();
+    let server = CoordinatorServiceState::new(coordinator.clone());
+    let vs = new_verifier_state(&server).await;
 
-      let coordinator2 = Arc::new(
-        CoordinatorState::new(&store, &ledger_store_args, None)
-          .await
-          .unwrap(),
-      );
+    let handle = rand::thread_rng().gen::<[u8; 16]>().to_vec();
+    let watch = |from_height: u64| {
+      server.watch(tonic::Request::new(WatchReq {
+        handle: handle.clone(),
+        from_height,
+      }))
+    };
 
-      let server2 = CoordinatorServiceState::new(coordinator2);
-      println!("Started a new coordinator");
+    // a ledger that does not exist cannot be watched
+    let status = watch(0).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
 
-      let req = tonic::Request::new(ReadViewTailReq {});
-      let res = server2.read_view_tail(req).await;
-      assert!(res.is_ok());
-      let ReadViewTailResp {
+    coordinator
+      .create_ledger(None, &handle, b"genesis")
+      .await
+      .unwrap();
+    coordinator
+      .append_ledger(None, &handle, b"block 1", 1)
+      .await
+      .unwrap();
+
+    // a watcher gets the entries from the height it asked for, and then each new entry as it
+    // is appended, which it verifies on its own
+    let mut updates = watch(0).await.unwrap().into_inner();
+    let mut later_updates = watch(1).await.unwrap().into_inner();
+    let appends = {
+      let coordinator = coordinator.clone();
+      let handle = handle.clone();
+      tokio::spawn(async move {
+        for height in 2..=4 {
+          let block = format!("block {}", height).into_bytes();
+          coordinator
+            .append_ledger(None, &handle, &block, height)
+            .await
+            .unwrap();
+        }
+        coordinator.delete_ledger(None, &handle, 5).await.unwrap();
+      })
+    };
+    for height in 0..=5 {
+      let WatchResp {
+        height: update_height,
         block,
-        receipts,
-        height: _view_height,
-        attestations,
-      } = res.unwrap().into_inner();
-
-      let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
-      println!("Applying ReadViewByIndexResp Response: {:?}", res);
-      assert!(res.is_ok());
-
-      // Step 14: Append via the new coordinator
-      let message = "data_block_append 4".as_bytes();
-      let req = tonic::Request::new(AppendReq {
-        handle: new_handle.clone(),
-        block: message.to_vec(),
-        expected_height: 2_u64,
-      });
-
-      let AppendResp {
         hash_nonces,
         receipts,
-      } = server2.append(req).await.unwrap().into_inner();
-      let res = vs.verify_append(&new_handle, message, &hash_nonces, 2, &receipts);
-      println!("Append verification: {:?}", res.is_ok());
+      } = updates.next().await.unwrap().unwrap();
+      assert_eq!(update_height, height as u64);
+      let res = match height {
+        0 => vs.verify_new_ledger(&handle, &block, &receipts),
+        5 => {
+          assert_eq!(block, Block::tombstone().to_bytes());
+          vs.verify_delete_ledger(&handle, height, &receipts)
+        },
+        _ => {
+          assert_eq!(block, format!("block {}", height).into_bytes());
+          vs.verify_append(&handle, &block, &hash_nonces, height, &receipts)
+        },
+      };
       assert!(res.is_ok());
+    }
+    appends.await.unwrap();
 
-      // Step 14: Append without a condition via the new coordinator
-      let message = "data_block_append 4".as_bytes();
-      let req = tonic::Request::new(AppendReq {
-        handle: new_handle2.clone(),
-        block: message.to_vec(),
-        expected_height: 2_u64,
-      });
+    // the stream ends with the tombstone
+    assert!(updates.next().await.is_none());
+    let mut heights = Vec::new();
+    while let Some(update) = later_updates.next().await {
+      heights.push(update.unwrap().height);
+    }
+    assert_eq!(heights, [1, 2, 3, 4, 5]);
 
-      let AppendResp {
-        hash_nonces,
-        receipts,
-      } = server2.append(req).await.unwrap().into_inner();
-      let res = vs.verify_append(&new_handle2, message, &hash_nonces, 2, &receipts);
-      println!("Append verification: {:?}", res.is_ok());
-      assert!(res.is_ok());
+    println!("endorser process ID is {}", endorser.child.id());
+  }
 
-      server2.get_state().reset_ledger_store().await;
+  // Compares the throughput of appends to a single ledger made one after the other, which is
+  // all that clients could do without batching, with that of pipelined appends. Endorsers
+  // further away can be emulated with, e.g., ENDORSER_ARGS="--fault *:delay=5".
+  #[tokio::test(flavor = "multi_thread")]
+  #[ignore]
+  async fn bench_coordinator_appends() {
+    let endorser_cmd = {
+      match std::env::var_os("ENDORSER_CMD") {
+        None => panic!("The ENDORSER_CMD environment variable is not specified"),
+        Some(x) => x,
+      }
+    };
+    let endorser_args = match std::env::var_os("ENDORSER_ARGS") {
+      None => String::from(""),
+      Some(x) => x.into_string().unwrap(),
+    };
+    let num_appends = match std::env::var("NUM_APPENDS") {
+      Ok(n) => n.parse().unwrap(),
+      Err(_) => 1000,
+    };
 
-      println!("endorser7 process ID is {}", endorser7.child.id());
-      println!("endorser8 process ID is {}", endorser8.child.id());
-      println!("endorser9 process ID is {}", endorser9.child.id());
-    }
+    // each coordinator initializes an endorser of its own
+    for (name, batch_size, pipelined, port) in [
+      ("sequential", 1, false, 9133),
+      ("pipelined", 64, true, 9134),
+    ] {
+      let endorser = launch_endorser(&endorser_cmd, format!("-p {} {}", port, endorser_args));
+      let args = HashMap::from([("APPEND_BATCH_SIZE".to_string(), batch_size.to_string())]);
+      let coordinator = Arc::new(CoordinatorState::new("memory", &args, None).await.unwrap());
+      coordinator
+        .replace_endorsers(&[format!("http://[::1]:{}", port)])
+        .await
+        .unwrap();
+      let vs = new_verifier_state(&CoordinatorServiceState::new(coordinator.clone())).await;
 
-    // We access endorser and endorser2 below
-    // to stop them from being dropped earlier
-    println!("endorser1 process ID is {}", endorser.child.id());
-    println!("endorser2 process ID is {}", endorser2.child.id());
-    println!("endorser3 process ID is {}", endorser3.child.id());
-    println!("endorser4 process ID is {}", endorser4.child.id());
-    println!("endorser5 process ID is {}", endorser5.child.id());
-    println!("endorser6 process ID is {}", endorser6.child.id());
+      let elapsed = append_blocks(&coordinator, &vs, num_appends, pipelined).await;
+      println!(
+        "{}: {} appends in {:?} ({:.0} appends/s)",
+        name,
+        num_appends,
+        elapsed,
+        num_appends as f64 / elapsed.as_secs_f64()
+      );
+      println!("endorser process ID is {}", endorser.child.id());
+    }
   }
 }
@@ -0,0 +1,191 @@
+use crate::coordinator_proto::{
+  call_server::Call, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp, NewLedgerReq,
+  NewLedgerResp, ReadByIndexReq, ReadByIndexResp, ReadLatestReq, ReadLatestResp,
+  ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp, WatchReq,
+};
+use lazy_static::lazy_static;
+use prometheus::{
+  register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+  register_int_gauge, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+  TextEncoder,
+};
+use std::future::Future;
+use tonic::{Code, Request, Response, Status};
+use tracing::{debug, error, field, info_span, Instrument};
+
+lazy_static! {
+  static ref REQUESTS: IntCounterVec = register_int_counter_vec!(
+    "nimble_coordinator_requests_total",
+    "The number of requests served by the coordinator, by RPC and gRPC code",
+    &["rpc", "code"]
+  )
+  .unwrap();
+  static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+    "nimble_coordinator_request_duration_seconds",
+    "The latency of requests served by the coordinator",
+    &["rpc"]
+  )
+  .unwrap();
+  pub static ref ENDORSER_ERRORS: IntCounterVec = register_int_counter_vec!(
+    "nimble_coordinator_endorser_errors_total",
+    "The number of calls to endorsers that failed, by endorser and gRPC code",
+    &["endorser", "code"]
+  )
+  .unwrap();
+  pub static ref VIEW_CHANGES: IntCounterVec = register_int_counter_vec!(
+    "nimble_coordinator_view_changes_total",
+    "The number of view changes, by outcome",
+    &["outcome"]
+  )
+  .unwrap();
+  pub static ref VIEW_CHANGE_DURATION: Histogram = register_histogram!(
+    "nimble_coordinator_view_change_duration_seconds",
+    "The time it takes to change the view",
+    vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]
+  )
+  .unwrap();
+  pub static ref ENDORSERS: IntGauge = register_int_gauge!(
+    "nimble_coordinator_endorsers",
+    "The number of endorsers the coordinator is connected to"
+  )
+  .unwrap();
+  pub static ref LIVE_ENDORSERS: IntGauge = register_int_gauge!(
+    "nimble_coordinator_live_endorsers",
+    "The number of endorsers of the current view that passed their latest probes"
+  )
+  .unwrap();
+  pub static ref LAGGING_LEDGERS: IntGauge = register_int_gauge!(
+    "nimble_coordinator_lagging_ledgers",
+    "The number of ledgers, across endorsers, waiting for an endorser to be caught up on them"
+  )
+  .unwrap();
+  pub static ref CAUGHT_UP_ENTRIES: IntCounter = register_int_counter!(
+    "nimble_coordinator_caught_up_entries_total",
+    "The number of ledger entries replayed to endorsers that lagged behind"
+  )
+  .unwrap();
+  pub static ref RETRIED_REQUESTS: IntCounter = register_int_counter!(
+    "nimble_coordinator_retried_requests_total",
+    "The number of retried requests answered with the receipts of the request that took effect"
+  )
+  .unwrap();
+  pub static ref APPEND_BATCH_SIZE: Histogram = register_histogram!(
+    "nimble_coordinator_append_batch_size",
+    "The number of appends to a ledger sent to the endorsers together",
+    vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0]
+  )
+  .unwrap();
+  pub static ref LEADER: IntGauge = register_int_gauge!(
+    "nimble_coordinator_leader",
+    "Whether the coordinator serves, i.e., it is the leader or no leader is elected"
+  )
+  .unwrap();
+}
+
+pub fn code_label(code: Code) -> String {
+  format!("{:?}", code)
+}
+
+/// Renders the metrics of the default registry in the Prometheus text format
+pub fn render() -> String {
+  let mut buffer = Vec::new();
+  if let Err(error) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+    error!("Failed to encode the metrics ({:?})", error);
+  }
+  String::from_utf8(buffer).unwrap_or_default()
+}
+
+// Serves a call in a span that carries the caller's request ID, and records its code and
+// latency
+async fn observe<R, T, F, Fut>(rpc: &str, req: Request<R>, call: F) -> Result<Response<T>, Status>
+where
+  F: FnOnce(Request<R>) -> Fut,
+  Fut: Future<Output = Result<Response<T>, Status>>,
+{
+  let span = info_span!("rpc", otel.name = rpc, rpc, request_id = field::Empty);
+  let request_id = telemetry::accept(req.metadata(), &span);
+
+  let timer = REQUEST_DURATION.with_label_values(&[rpc]).start_timer();
+  let res = telemetry::scope(request_id, call(req).instrument(span.clone())).await;
+  timer.observe_duration();
+  let code = match &res {
+    Ok(_) => Code::Ok,
+    Err(status) => status.code(),
+  };
+  REQUESTS.with_label_values(&[rpc, &code_label(code)]).inc();
+  debug!(parent: &span, ?code, "served {}", rpc);
+  res
+}
+
+/// A coordinator service that records the number and latency of the calls it forwards to
+/// another one, each in a span that carries the request ID of the caller
+pub struct MeteredCallService<T: Call> {
+  service: T,
+}
+
+impl<T: Call> MeteredCallService<T> {
+  pub fn new(service: T) -> Self {
+    MeteredCallService { service }
+  }
+}
+
+#[tonic::async_trait]
+impl<T: Call> Call for MeteredCallService<T> {
+  type WatchStream = T::WatchStream;
+
+  async fn new_ledger(
+    &self,
+    req: Request<NewLedgerReq>,
+  ) -> Result<Response<NewLedgerResp>, Status> {
+    observe("new_ledger", req, |req| self.service.new_ledger(req)).await
+  }
+
+  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    observe("append", req, |req| self.service.append(req)).await
+  }
+
+  async fn delete_ledger(
+    &self,
+    req: Request<DeleteLedgerReq>,
+  ) -> Result<Response<DeleteLedgerResp>, Status> {
+    observe("delete_ledger", req, |req| self.service.delete_ledger(req)).await
+  }
+
+  async fn read_latest(
+    &self,
+    req: Request<ReadLatestReq>,
+  ) -> Result<Response<ReadLatestResp>, Status> {
+    observe("read_latest", req, |req| self.service.read_latest(req)).await
+  }
+
+  async fn read_by_index(
+    &self,
+    req: Request<ReadByIndexReq>,
+  ) -> Result<Response<ReadByIndexResp>, Status> {
+    observe("read_by_index", req, |req| self.service.read_by_index(req)).await
+  }
+
+  async fn read_view_by_index(
+    &self,
+    req: Request<ReadViewByIndexReq>,
+  ) -> Result<Response<ReadViewByIndexResp>, Status> {
+    observe("read_view_by_index", req, |req| {
+      self.service.read_view_by_index(req)
+    })
+    .await
+  }
+
+  async fn read_view_tail(
+    &self,
+    req: Request<ReadViewTailReq>,
+  ) -> Result<Response<ReadViewTailResp>, Status> {
+    observe("read_view_tail", req, |req| {
+      self.service.read_view_tail(req)
+    })
+    .await
+  }
+
+  async fn watch(&self, req: Request<WatchReq>) -> Result<Response<Self::WatchStream>, Status> {
+    observe("watch", req, |req| self.service.watch(req)).await
+  }
+}
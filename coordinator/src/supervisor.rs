@@ -0,0 +1,140 @@
+//! A supervisor that probes the endorsers of the current view, and moves to a view of spare
+//! endorsers before too few of them are left for a quorum.
+
+use crate::{
+  config::SupervisorConfig, coordinator_state::CoordinatorState, errors::CoordinatorError, metrics,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+  time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
+
+pub struct Supervisor {
+  coordinator: Arc<CoordinatorState>,
+  config: SupervisorConfig,
+  // the number of probes each endorser has failed in a row
+  failures: HashMap<String, u32>,
+  // the spare endorsers that joined a view, and are thus no longer spare
+  used: HashSet<String>,
+  last_reconfiguration: Option<Instant>,
+}
+
+// The number of endorsers that make a quorum in a view of `n` endorsers
+fn quorum_size(n: usize) -> usize {
+  n / 2 + 1
+}
+
+impl Supervisor {
+  pub fn new(coordinator: Arc<CoordinatorState>, config: SupervisorConfig) -> Self {
+    Supervisor {
+      coordinator,
+      config,
+      failures: HashMap::new(),
+      used: HashSet::new(),
+      last_reconfiguration: None,
+    }
+  }
+
+  /// Probes the endorsers every probe interval for as long as the coordinator runs
+  pub async fn run(mut self) {
+    let mut interval = tokio::time::interval(Duration::from_secs(self.config.probe_interval_secs));
+    loop {
+      interval.tick().await;
+      // only the leader changes views, and a standby is not connected to the endorsers
+      if !self.coordinator.is_leader() {
+        self.failures.clear();
+        continue;
+      }
+      if let Err(error) = self.supervise().await {
+        error!("failed to supervise the endorsers ({:?})", error);
+      }
+    }
+  }
+
+  /// Runs a round of probes, and changes the view if it has to and is allowed to
+  pub async fn supervise(&mut self) -> Result<(), CoordinatorError> {
+    let health = self.coordinator.probe_endorsers().await?;
+    if health.is_empty() {
+      return Ok(());
+    }
+
+    let mut live = 0;
+    let mut down = Vec::new();
+    for (uri, healthy) in &health {
+      let failures = self.failures.entry(uri.clone()).or_insert(0);
+      if *healthy {
+        *failures = 0;
+      } else {
+        *failures += 1;
+      }
+      if *failures >= self.config.failure_threshold {
+        down.push(uri.clone());
+      } else {
+        live += 1;
+      }
+    }
+    metrics::LIVE_ENDORSERS.set(live as i64);
+
+    let quorum = quorum_size(health.len());
+    if down.is_empty() || live > quorum + self.config.quorum_margin {
+      return Ok(());
+    }
+    warn!(
+      "{} of {} endorsers are down ({:?}), and a quorum needs {}",
+      down.len(),
+      health.len(),
+      down,
+      quorum
+    );
+
+    if let Some(last) = self.last_reconfiguration {
+      if last.elapsed() < Duration::from_secs(self.config.min_interval_secs) {
+        warn!("the last view change was too recent to change the view again");
+        return Ok(());
+      }
+    }
+
+    // The endorsers of the current view cannot join the next one, so the new view is made of
+    // as many spare endorsers as the current one
+    let in_view = health
+      .iter()
+      .map(|(uri, _)| uri.clone())
+      .collect::<HashSet<String>>();
+    let candidates = self
+      .config
+      .spare_endorsers
+      .iter()
+      .filter(|uri| !self.used.contains(*uri) && !in_view.contains(*uri))
+      .cloned()
+      .collect::<Vec<String>>();
+    let mut spares = self.coordinator.probe_spare_endorsers(&candidates).await;
+    if spares.len() < health.len() {
+      error!(
+        "only {} spare endorsers are available to replace a view of {}",
+        spares.len(),
+        health.len()
+      );
+      return Ok(());
+    }
+    spares.truncate(health.len());
+
+    info!("moving to a view of the spare endorsers {:?}", spares);
+    self.last_reconfiguration = Some(Instant::now());
+    let reason = format!(
+      "{} of {} endorsers were down: {}",
+      down.len(),
+      health.len(),
+      down.join(", ")
+    );
+    // a spare may hold the state of the new view even if the view change fails
+    self.used.extend(spares.iter().cloned());
+    self
+      .coordinator
+      .reconfigure(&spares, "supervisor", &reason)
+      .await?;
+    self.failures.clear();
+    Ok(())
+  }
+}
@@ -1,6 +1,8 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
-use serde::{Deserialize, Serialize};
+use ledger::{CustomSerde, NimbleDigest, Receipts, VerifierState};
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::time::Instant;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,9 +11,627 @@ struct EndorserOpResponse {
   pub pk: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserStatus {
+  #[serde(rename = "Uri")]
+  pub uri: String,
+  #[serde(rename = "PublicKey")]
+  pub pk: String,
+  #[serde(rename = "Mode")]
+  pub mode: Option<String>,
+  #[serde(rename = "LaggingLedgers")]
+  pub lagging_ledgers: Option<usize>,
+  #[serde(rename = "MissingEntries")]
+  pub missing_entries: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EndorserListResponse {
+  #[serde(rename = "Endorsers")]
+  pub endorsers: Vec<EndorserStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerInfo {
+  #[serde(rename = "Handle")]
+  pub handle: String,
+  #[serde(rename = "Height")]
+  pub height: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerListResponse {
+  #[serde(rename = "Ledgers")]
+  pub ledgers: Vec<LedgerInfo>,
+  #[serde(rename = "Cursor")]
+  pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerEntryResponse {
+  #[serde(rename = "Handle")]
+  pub handle: String,
+  #[serde(rename = "Index")]
+  pub index: usize,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Nonces")]
+  pub nonces: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+  // set once the receipts are verified against the view ledger
+  #[serde(rename = "Verified", default, skip_serializing_if = "Option::is_none")]
+  pub verified: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewInfo {
+  #[serde(rename = "Height")]
+  pub height: usize,
+  #[serde(rename = "Endorsers")]
+  pub endorsers: Vec<String>,
+  #[serde(rename = "Initiator")]
+  pub initiator: Option<String>,
+  #[serde(rename = "Reason")]
+  pub reason: Option<String>,
+  #[serde(rename = "CoordinatorId")]
+  pub coordinator_id: Option<String>,
+  #[serde(rename = "Timestamp")]
+  pub timestamp: Option<u64>,
+  #[serde(rename = "Added")]
+  pub added: Option<Vec<String>>,
+  #[serde(rename = "Removed")]
+  pub removed: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewListResponse {
+  #[serde(rename = "Views")]
+  pub views: Vec<ViewInfo>,
+  // set once every view is verified the way endpoints verify them
+  #[serde(rename = "Verified", default, skip_serializing_if = "Option::is_none")]
+  pub verified: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ViewEntryResponse {
+  #[serde(rename = "Height")]
+  pub height: usize,
+  #[serde(rename = "Block")]
+  pub block: String,
+  #[serde(rename = "Receipts")]
+  pub receipts: String,
+  #[serde(rename = "Attestations")]
+  pub attestations: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReconfigurationInfo {
+  #[serde(rename = "Initiator")]
+  pub initiator: String,
+  #[serde(rename = "Reason")]
+  pub reason: String,
+  #[serde(rename = "StartedAt")]
+  pub started_at: u64,
+  #[serde(rename = "FinishedAt")]
+  pub finished_at: Option<u64>,
+  #[serde(rename = "Error")]
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoreHealth {
+  #[serde(rename = "Healthy")]
+  pub healthy: bool,
+  #[serde(rename = "LatencyMs")]
+  pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StatusResponse {
+  #[serde(rename = "Leader")]
+  pub leader: bool,
+  #[serde(rename = "Draining")]
+  pub draining: bool,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: Option<usize>,
+  #[serde(rename = "NumEndorsers")]
+  pub num_endorsers: usize,
+  #[serde(rename = "LaggingLedgers")]
+  pub lagging_ledgers: usize,
+  #[serde(rename = "Store")]
+  pub store: StoreHealth,
+  #[serde(rename = "Reconfiguration")]
+  pub reconfiguration: Option<ReconfigurationInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StatsResponse {
+  #[serde(rename = "NumLedgers")]
+  pub num_ledgers: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupResponse {
+  #[serde(rename = "Name")]
+  pub name: String,
+  #[serde(rename = "ViewHeight")]
+  pub view_height: u64,
+  #[serde(rename = "NumLedgers")]
+  pub num_ledgers: usize,
+  #[serde(rename = "Digest")]
+  pub digest: String,
+}
+
+// the response to repairing, pruning and catching up, which all replay or delete entries
+#[derive(Debug, Serialize, Deserialize)]
+struct NumEntriesResponse {
+  #[serde(rename = "NumEntries")]
+  pub num_entries: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Output {
+  Json,
+  Table,
+}
+
+struct Ctrl {
+  client: reqwest::Client,
+  coordinator: String,
+  output: Output,
+}
+
+impl Ctrl {
+  async fn call<T: DeserializeOwned>(
+    &self,
+    method: Method,
+    path: &str,
+    query: &[(&str, &str)],
+  ) -> Result<T, String> {
+    let mut url = reqwest::Url::parse(&format!("{}{}", self.coordinator, path))
+      .map_err(|e| format!("invalid coordinator address {} ({})", self.coordinator, e))?;
+    if !query.is_empty() {
+      url.query_pairs_mut().extend_pairs(query);
+    }
+    let resp = self
+      .client
+      .request(method, url)
+      .send()
+      .await
+      .map_err(|e| format!("failed to reach the coordinator ({})", e))?;
+    let status = resp.status();
+    if !status.is_success() {
+      return Err(format!("the coordinator answered {}", status));
+    }
+    resp
+      .json::<T>()
+      .await
+      .map_err(|e| format!("failed to parse the response ({})", e))
+  }
+
+  // Prints the response as it was received, or as a table
+  fn print<T: Serialize>(&self, resp: &T, table: impl FnOnce(&T)) {
+    match self.output {
+      Output::Json => println!("{}", serde_json::to_string_pretty(resp).unwrap()),
+      Output::Table => table(resp),
+    }
+  }
+}
+
+// Prints the rows under the headers, with each column as wide as its widest cell
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+  let mut widths = headers.iter().map(|h| h.len()).collect::<Vec<usize>>();
+  for row in rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = std::cmp::max(*width, cell.len());
+    }
+  }
+  let print_row = |cells: Vec<&str>| {
+    let line = cells
+      .iter()
+      .zip(&widths)
+      .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+      .collect::<Vec<String>>()
+      .join("  ");
+    println!("{}", line.trim_end());
+  };
+  print_row(headers.to_vec());
+  for row in rows {
+    print_row(row.iter().map(|c| c.as_str()).collect());
+  }
+}
+
+// Prints one name and value per line, for responses that describe a single object
+fn print_fields(fields: &[(&str, String)]) {
+  let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+  for (name, value) in fields {
+    println!("{:<width$}  {}", name, value, width = width);
+  }
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+  value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+fn join_or_dash(values: Option<&Vec<String>>) -> String {
+  match values {
+    Some(values) if !values.is_empty() => values.join(","),
+    _ => "-".to_string(),
+  }
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+  base64_url::decode(value).map_err(|e| format!("invalid {} in the response ({})", field, e))
+}
+
+// Rebuilds the verifier state from the view ledger, the way endpoints do: the genesis view
+// identifies the deployment, the latest view is checked against its attestations, and every
+// earlier view against the view after it
+async fn verify_views(ctrl: &Ctrl, height: usize) -> Result<VerifierState, String> {
+  let mut vs = VerifierState::default();
+  if height == 0 {
+    return Ok(vs);
+  }
+  let genesis: ViewEntryResponse = ctrl.call(Method::GET, "/views/1", &[]).await?;
+  vs.set_group_identity(NimbleDigest::digest(&decode("block", &genesis.block)?));
+
+  for index in (1..=height).rev() {
+    let view: ViewEntryResponse = ctrl
+      .call(Method::GET, &format!("/views/{}", index), &[])
+      .await?;
+    let attestations = match (&view.attestations, index == height) {
+      (Some(attestations), true) => Some(decode("attestations", attestations)?),
+      (None, true) => return Err("the view ledger changed while it was read".to_string()),
+      _ => None,
+    };
+    vs.apply_view_change(
+      &decode("block", &view.block)?,
+      &decode("receipts", &view.receipts)?,
+      attestations.as_deref(),
+    )
+    .map_err(|e| format!("failed to verify the view at {} ({:?})", index, e))?;
+  }
+  Ok(vs)
+}
+
+// Returns the URI path segment that stands for the endorser URIs
+fn endorser_path(prefix: &str, uris: &str) -> String {
+  format!("{}/{}", prefix, base64_url::encode(uris))
+}
+
+async fn status(ctrl: &Ctrl) -> Result<(), String> {
+  let resp: StatusResponse = ctrl.call(Method::GET, "/status", &[]).await?;
+  ctrl.print(&resp, |resp| {
+    let reconfiguration = match &resp.reconfiguration {
+      None => "-".to_string(),
+      Some(r) => {
+        let state = match (&r.finished_at, &r.error) {
+          (None, _) => "in flight".to_string(),
+          (Some(at), None) => format!("finished at {}", at),
+          (Some(at), Some(error)) => format!("failed at {}: {}", at, error),
+        };
+        format!(
+          "{} ({}), started at {}, {}",
+          r.initiator, r.reason, r.started_at, state
+        )
+      },
+    };
+    let store = match resp.store.latency_ms {
+      Some(latency) if resp.store.healthy => format!("healthy ({} ms)", latency),
+      _ => "unreachable".to_string(),
+    };
+    print_fields(&[
+      ("Leader", resp.leader.to_string()),
+      ("Draining", resp.draining.to_string()),
+      ("View height", or_dash(resp.view_height)),
+      ("Endorsers", resp.num_endorsers.to_string()),
+      ("Lagging ledgers", resp.lagging_ledgers.to_string()),
+      ("Store", store),
+      ("Reconfiguration", reconfiguration),
+    ]);
+  });
+  Ok(())
+}
+
+async fn list_endorsers(ctrl: &Ctrl) -> Result<(), String> {
+  let resp: EndorserListResponse = ctrl.call(Method::GET, "/endorsers", &[]).await?;
+  ctrl.print(&resp, |resp| {
+    let rows = resp
+      .endorsers
+      .iter()
+      .map(|e| {
+        vec![
+          e.uri.clone(),
+          or_dash(e.mode.as_ref()),
+          or_dash(e.lagging_ledgers),
+          or_dash(e.missing_entries),
+          e.pk.clone(),
+        ]
+      })
+      .collect::<Vec<_>>();
+    print_table(
+      &[
+        "URI",
+        "MODE",
+        "LAGGING LEDGERS",
+        "MISSING ENTRIES",
+        "PUBLIC KEY",
+      ],
+      &rows,
+    );
+  });
+  Ok(())
+}
+
+async fn get_endorser(ctrl: &Ctrl, uri: &str) -> Result<(), String> {
+  let resp: EndorserOpResponse = ctrl
+    .call(Method::GET, &endorser_path("/endorsers", uri), &[])
+    .await?;
+  ctrl.print(&resp, |resp| {
+    print_fields(&[("URI", uri.to_string()), ("Public key", resp.pk.clone())])
+  });
+  Ok(())
+}
+
+async fn list_ledgers(ctrl: &Ctrl, matches: &ArgMatches<'_>) -> Result<(), String> {
+  let limit = matches.value_of("limit");
+  let mut cursor = matches.value_of("cursor").map(|c| c.to_string());
+  let mut ledgers = Vec::new();
+  loop {
+    let mut query = Vec::new();
+    if let Some(cursor) = &cursor {
+      query.push(("cursor", cursor.as_str()));
+    }
+    if let Some(limit) = limit {
+      query.push(("limit", limit));
+    }
+    let resp: LedgerListResponse = ctrl.call(Method::GET, "/ledgers", &query).await?;
+    ledgers.extend(resp.ledgers);
+    cursor = resp.cursor;
+    if cursor.is_none() || !matches.is_present("all") {
+      break;
+    }
+  }
+
+  let resp = LedgerListResponse { ledgers, cursor };
+  ctrl.print(&resp, |resp| {
+    let rows = resp
+      .ledgers
+      .iter()
+      .map(|l| vec![l.handle.clone(), l.height.to_string()])
+      .collect::<Vec<_>>();
+    print_table(&["HANDLE", "HEIGHT"], &rows);
+    if let Some(cursor) = &resp.cursor {
+      println!("next cursor: {}", cursor);
+    }
+  });
+  Ok(())
+}
+
+async fn get_ledger(ctrl: &Ctrl, matches: &ArgMatches<'_>) -> Result<(), String> {
+  let handle = matches.value_of("handle").unwrap();
+  let index = match matches.value_of("index") {
+    None => {
+      let resp: LedgerInfo = ctrl
+        .call(Method::GET, &format!("/ledgers/{}", handle), &[])
+        .await?;
+      ctrl.print(&resp, |resp| {
+        print_fields(&[
+          ("Handle", resp.handle.clone()),
+          ("Height", resp.height.to_string()),
+        ])
+      });
+      return Ok(());
+    },
+    Some(index) => index
+      .parse::<usize>()
+      .map_err(|e| format!("invalid index {} ({})", index, e))?,
+  };
+
+  let mut resp: LedgerEntryResponse = ctrl
+    .call(Method::GET, &format!("/ledgers/{}/{}", handle, index), &[])
+    .await?;
+  let block = decode("block", &resp.block)?;
+  let nonces = decode("nonces", &resp.nonces)?;
+  let receipts_bytes = decode("receipts", &resp.receipts)?;
+  let receipts = Receipts::from_bytes(&receipts_bytes)
+    .map_err(|e| format!("invalid receipts in the response ({:?})", e))?;
+
+  if matches.is_present("verify") {
+    let status: StatusResponse = ctrl.call(Method::GET, "/status", &[]).await?;
+    let vs = verify_views(ctrl, status.view_height.unwrap_or(0)).await?;
+    let handle_rs = decode("handle", handle)
+      .ok()
+      .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok())
+      .ok_or_else(|| format!("invalid ledger handle {}", handle))?;
+    let res = if receipts.is_tombstone() {
+      vs.verify_ledger_tombstone(&handle_rs, index, &receipts_bytes)
+    } else {
+      vs.verify_ledger_entry(
+        &handle_rs,
+        &NimbleDigest::digest(&block),
+        &NimbleDigest::digest(&nonces),
+        index,
+        &receipts_bytes,
+      )
+    };
+    res.map_err(|e| format!("failed to verify the receipts ({:?})", e))?;
+    resp.verified = Some(true);
+  }
+
+  ctrl.print(&resp, |resp| {
+    print_fields(&[
+      ("Handle", resp.handle.clone()),
+      ("Index", resp.index.to_string()),
+      ("Block", format!("{} bytes", block.len())),
+      ("Nonces", format!("{} bytes", nonces.len())),
+      ("Verified", or_dash(resp.verified)),
+    ]);
+    println!();
+    let rows = receipts
+      .get()
+      .iter()
+      .map(|(ex_meta_block, id_sigs)| {
+        vec![
+          base64_url::encode(&ex_meta_block.get_view().to_bytes()),
+          ex_meta_block.get_metablock().get_height().to_string(),
+          id_sigs.len().to_string(),
+        ]
+      })
+      .collect::<Vec<_>>();
+    print_table(&["VIEW", "HEIGHT", "SIGNATURES"], &rows);
+  });
+  Ok(())
+}
+
+async fn list_views(ctrl: &Ctrl, verify: bool) -> Result<(), String> {
+  let mut resp: ViewListResponse = ctrl.call(Method::GET, "/views", &[]).await?;
+  if verify {
+    let height = resp.views.last().map_or(0, |v| v.height);
+    verify_views(ctrl, height).await?;
+    resp.verified = Some(true);
+  }
+  ctrl.print(&resp, |resp| {
+    let rows = resp
+      .views
+      .iter()
+      .map(|v| {
+        vec![
+          v.height.to_string(),
+          or_dash(v.initiator.as_ref()),
+          or_dash(v.reason.as_ref()),
+          or_dash(v.timestamp),
+          v.endorsers.join(","),
+          join_or_dash(v.added.as_ref()),
+          join_or_dash(v.removed.as_ref()),
+        ]
+      })
+      .collect::<Vec<_>>();
+    print_table(
+      &[
+        "HEIGHT",
+        "INITIATOR",
+        "REASON",
+        "TIMESTAMP",
+        "ENDORSERS",
+        "ADDED",
+        "REMOVED",
+      ],
+      &rows,
+    );
+    if resp.verified == Some(true) {
+      println!("verified {} views", resp.views.len());
+    }
+  });
+  Ok(())
+}
+
+// Runs a view change, which replaces, adds or removes endorsers, and reports how long it took
+async fn change_view(ctrl: &Ctrl, method: Method, path: &str) -> Result<(), String> {
+  let now = Instant::now();
+  let resp: EndorserOpResponse = ctrl.call(method, path, &[]).await?;
+  let elapsed = now.elapsed().as_millis();
+  ctrl.print(&resp, |resp| {
+    print_fields(&[
+      ("Public key", resp.pk.clone()),
+      ("Reconfiguration time", format!("{} ms", elapsed)),
+    ])
+  });
+  Ok(())
+}
+
+async fn num_entries(ctrl: &Ctrl, path: &str, done: &str) -> Result<(), String> {
+  let resp: NumEntriesResponse = ctrl.call(Method::POST, path, &[]).await?;
+  ctrl.print(&resp, |resp| {
+    println!("{} entries {}", resp.num_entries, done)
+  });
+  Ok(())
+}
+
+async fn run(ctrl: &Ctrl, matches: &ArgMatches<'_>) -> Result<(), String> {
+  match matches.subcommand() {
+    ("status", _) => status(ctrl).await,
+    ("endorsers", _) => list_endorsers(ctrl).await,
+    ("endorser", Some(m)) => get_endorser(ctrl, m.value_of("uri").unwrap()).await,
+    ("ledgers", Some(m)) => list_ledgers(ctrl, m).await,
+    ("ledger", Some(m)) => get_ledger(ctrl, m).await,
+    ("views", Some(m)) => list_views(ctrl, m.is_present("verify")).await,
+    ("stats", _) => {
+      let resp: StatsResponse = ctrl.call(Method::GET, "/stats", &[]).await?;
+      ctrl.print(&resp, |resp| {
+        print_fields(&[("Ledgers", resp.num_ledgers.to_string())])
+      });
+      Ok(())
+    },
+    ("replace", Some(m)) => {
+      let path = endorser_path("/endorsers", m.value_of("uris").unwrap());
+      change_view(ctrl, Method::PUT, &path).await
+    },
+    ("add-member", Some(m)) => {
+      let path = endorser_path("/members", m.value_of("uri").unwrap());
+      change_view(ctrl, Method::PUT, &path).await
+    },
+    ("remove-member", Some(m)) => {
+      let path = endorser_path("/members", m.value_of("uri").unwrap());
+      change_view(ctrl, Method::DELETE, &path).await
+    },
+    ("disconnect", Some(m)) => {
+      let uri = m.value_of("uri").unwrap();
+      let resp: EndorserOpResponse = ctrl
+        .call(Method::DELETE, &endorser_path("/endorsers", uri), &[])
+        .await?;
+      ctrl.print(&resp, |resp| {
+        print_fields(&[("URI", uri.to_string()), ("Public key", resp.pk.clone())])
+      });
+      Ok(())
+    },
+    ("resync", _) => num_entries(ctrl, "/resync", "replayed to lagging endorsers").await,
+    ("repair", _) => num_entries(ctrl, "/repair", "written").await,
+    ("prune", _) => num_entries(ctrl, "/prune", "deleted").await,
+    ("drain", _) | ("resume", _) => {
+      let method = if matches.subcommand_name() == Some("drain") {
+        Method::POST
+      } else {
+        Method::DELETE
+      };
+      let resp: serde_json::Value = ctrl.call(method, "/drain", &[]).await?;
+      ctrl.print(&resp, |_resp| {
+        println!("{}", matches.subcommand_name().unwrap())
+      });
+      Ok(())
+    },
+    ("backup", Some(m)) => {
+      let name = m.value_of("name").unwrap();
+      let now = Instant::now();
+      let resp: BackupResponse = ctrl
+        .call(Method::POST, "/backup", &[("name", name)])
+        .await?;
+      let elapsed = now.elapsed().as_millis();
+      ctrl.print(&resp, |resp| {
+        print_fields(&[
+          ("Name", resp.name.clone()),
+          ("View height", resp.view_height.to_string()),
+          ("Ledgers", resp.num_ledgers.to_string()),
+          ("Digest", resp.digest.clone()),
+          ("Backup time", format!("{} ms", elapsed)),
+        ])
+      });
+      Ok(())
+    },
+    _ => unreachable!("clap requires a known subcommand"),
+  }
+}
+
+fn endorser_arg(help: &'static str) -> Arg<'static, 'static> {
+  Arg::with_name("uri").required(true).help(help)
+}
+
 #[tokio::main]
 async fn main() {
-  let config = App::new("client")
+  let config = App::new("coordinator_ctrl")
+    .setting(AppSettings::SubcommandRequiredElseHelp)
+    .setting(AppSettings::VersionlessSubcommands)
     .arg(
       Arg::with_name("coordinator")
         .short("c")
@@ -20,84 +640,164 @@ async fn main() {
         .default_value("http://127.0.0.1:8090"),
     )
     .arg(
-      Arg::with_name("add")
-        .short("a")
-        .long("add")
+      Arg::with_name("token")
+        .long("token")
         .takes_value(true)
-        .help("Endorser to add"),
+        .env("NIMBLE_CTRL_TOKEN")
+        .hide_env_values(true)
+        .help("The token the coordinator's control service requires"),
     )
     .arg(
-      Arg::with_name("delete")
-        .short("d")
-        .long("delete")
-        .takes_value(true)
-        .help("Endorser to delete"),
+      Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .possible_values(&["json", "table"])
+        .default_value("table")
+        .help("Print the responses as JSON or as tables"),
     )
-    .arg(
-      Arg::with_name("get")
-        .short("g")
-        .long("get")
-        .takes_value(true)
-        .help("Endorser to read"),
+    .subcommand(
+      SubCommand::with_name("status").about("Show the health of the coordinator and its view"),
+    )
+    .subcommand(
+      SubCommand::with_name("endorsers")
+        .about("List the connected endorsers with their modes and how far they lag behind"),
+    )
+    .subcommand(
+      SubCommand::with_name("endorser")
+        .about("Show the public key of an endorser")
+        .arg(endorser_arg("The URI of the endorser")),
+    )
+    .subcommand(
+      SubCommand::with_name("ledgers")
+        .about("List ledgers and their heights")
+        .arg(
+          Arg::with_name("cursor")
+            .long("cursor")
+            .takes_value(true)
+            .help("Handle (base64url) after which to start listing ledgers"),
+        )
+        .arg(
+          Arg::with_name("limit")
+            .long("limit")
+            .takes_value(true)
+            .help("The maximum number of ledgers to list at a time"),
+        )
+        .arg(
+          Arg::with_name("all")
+            .long("all")
+            .help("Keep listing until every ledger is listed"),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("ledger")
+        .about("Show the height of a ledger, or one of its entries with its receipts")
+        .arg(
+          Arg::with_name("handle")
+            .required(true)
+            .help("The handle (base64url) of the ledger, as listed by `ledgers`"),
+        )
+        .arg(
+          Arg::with_name("index")
+            .long("index")
+            .takes_value(true)
+            .help("The index of the entry to show"),
+        )
+        .arg(
+          Arg::with_name("verify")
+            .long("verify")
+            .requires("index")
+            .help("Verify the receipts of the entry against the view ledger"),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("views")
+        .about("List the views with the reasons for their view changes")
+        .arg(
+          Arg::with_name("verify")
+            .long("verify")
+            .help("Verify the receipts of every view the way endpoints do"),
+        ),
+    )
+    .subcommand(SubCommand::with_name("stats").about("Count the ledgers"))
+    .subcommand(
+      SubCommand::with_name("replace")
+        .about("Move to a view of new endorsers, replacing every endorser of the view")
+        .arg(
+          Arg::with_name("uris")
+            .required(true)
+            .help("The URIs of the new endorsers, separated by `;`"),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("add-member")
+        .about("Add an endorser to the current view, keeping its endorsers")
+        .arg(endorser_arg("The URI of the endorser to add")),
+    )
+    .subcommand(
+      SubCommand::with_name("remove-member")
+        .about("Remove an endorser from the current view, keeping the others")
+        .arg(endorser_arg("The URI of the endorser to remove")),
+    )
+    .subcommand(
+      SubCommand::with_name("disconnect")
+        .about("Disconnect from an endorser without a view change")
+        .arg(endorser_arg("The URI of the endorser")),
+    )
+    .subcommand(
+      SubCommand::with_name("resync").about("Catch up the endorsers that lag behind, and wait"),
+    )
+    .subcommand(
+      SubCommand::with_name("drain").about("Stop serving once the requests in flight are done"),
+    )
+    .subcommand(SubCommand::with_name("resume").about("Serve again after a drain"))
+    .subcommand(
+      SubCommand::with_name("backup")
+        .about("Write a backup of the ledger store")
+        .arg(
+          Arg::with_name("name")
+            .required(true)
+            .help("Name of the file to write the backup to in the coordinator's backup directory"),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("repair")
+        .about("Back-fill the replicas of a replicated ledger store that missed writes"),
+    )
+    .subcommand(
+      SubCommand::with_name("prune")
+        .about("Prune the ledgers now, as set by the coordinator's retention policy"),
     );
   let cli_matches = config.get_matches();
-  let coordinator_addr = cli_matches.value_of("coordinator").unwrap();
-
-  let client = reqwest::Client::new();
-
-  if let Some(x) = cli_matches.value_of("add") {
-    let uri = base64_url::encode(&x);
-    let endorser_url =
-      reqwest::Url::parse(&format!("{}/endorsers/{}", coordinator_addr, uri)).unwrap();
 
-    let now = Instant::now();
-    let res = client.put(endorser_url).send().await;
-    println!("Reconfiguration time: {} ms", now.elapsed().as_millis());
-
-    match res {
-      Ok(resp) => {
-        assert!(resp.status() == reqwest::StatusCode::OK);
-        let endorser_op_resp: EndorserOpResponse = resp.json().await.unwrap();
-        let pk = base64_url::decode(&endorser_op_resp.pk).unwrap();
-        println!("add_endorser: {} {:?}", x, pk);
-      },
-      Err(error) => {
-        eprintln!("add_endorser failed: {:?}", error);
-      },
-    }
+  let mut headers = reqwest::header::HeaderMap::new();
+  if let Some(token) = cli_matches.value_of("token") {
+    let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+      .expect("the token must be printable ASCII");
+    value.set_sensitive(true);
+    headers.insert(reqwest::header::AUTHORIZATION, value);
   }
-  if let Some(x) = cli_matches.value_of("delete") {
-    let uri = base64_url::encode(&x);
-    let endorser_url =
-      reqwest::Url::parse(&format!("{}/endorsers/{}", coordinator_addr, uri)).unwrap();
-    let res = client.delete(endorser_url).send().await;
-    match res {
-      Ok(resp) => {
-        assert!(resp.status() == reqwest::StatusCode::OK);
-        let endorser_op_resp: EndorserOpResponse = resp.json().await.unwrap();
-        let pk = base64_url::decode(&endorser_op_resp.pk).unwrap();
-        println!("delete_endorser: {} {:?}", x, pk);
-      },
-      Err(error) => {
-        eprintln!("delete_endorser failed: {:?}", error);
-      },
-    }
-  }
-  if let Some(x) = cli_matches.value_of("get") {
-    let uri = base64_url::encode(&x);
-    let endorser_url =
-      reqwest::Url::parse(&format!("{}/endorsers/{}", coordinator_addr, uri)).unwrap();
-    let res = client.get(endorser_url).send().await;
-    match res {
-      Ok(resp) => {
-        assert!(resp.status() == reqwest::StatusCode::OK);
-        let endorser_op_resp: EndorserOpResponse = resp.json().await.unwrap();
-        let pk = base64_url::decode(&endorser_op_resp.pk).unwrap();
-        println!("get_endorser: {} {:?}", x, pk);
-      },
-      Err(error) => {
-        eprintln!("get_endorser failed: {:?}", error);
-      },
-    }
+  let ctrl = Ctrl {
+    client: reqwest::Client::builder()
+      .default_headers(headers)
+      .build()
+      .unwrap(),
+    coordinator: cli_matches
+      .value_of("coordinator")
+      .unwrap()
+      .trim_end_matches('/')
+      .to_string(),
+    output: match cli_matches.value_of("output") {
+      Some("json") => Output::Json,
+      _ => Output::Table,
+    },
+  };
+
+  if let Err(error) = run(&ctrl, &cli_matches).await {
+    eprintln!(
+      "{} failed: {}",
+      cli_matches.subcommand_name().unwrap(),
+      error
+    );
+    std::process::exit(1);
   }
 }
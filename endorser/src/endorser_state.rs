@@ -1,4 +1,4 @@
-use crate::errors::EndorserError;
+use crate::{errors::EndorserError, metrics};
 
 use itertools::Itertools;
 
@@ -6,9 +6,9 @@ use ledger::endorser_proto::{EndorserMode, LedgerChunkEntry, LedgerTailMap, Ledg
 
 use ledger::{
   produce_hash_of_state,
-  signature::{PrivateKey, PrivateKeyTrait, PublicKey},
-  Block, CustomSerde, Handle, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Nonces, Receipt,
-  Receipts,
+  signature::{PrivateKey, PrivateKeyTrait, PublicKey, Signature},
+  tls_certificate_hash, tombstone_block_hash, tombstone_hash, Block, CustomSerde, Handle, IdSig,
+  MetaBlock, NimbleDigest, NimbleHashTrait, Nonces, Receipt, Receipts,
 };
 use std::{
   collections::{hash_map, HashMap},
@@ -41,6 +41,9 @@ pub struct EndorserState {
   /// a map from fixed-sized labels to a tail hash and a counter
   ledger_tail_map: Arc<RwLock<HashMap<Handle, ProtectedMetaBlock>>>,
 
+  /// the tombstone metablocks of deleted ledgers, which keep their handles from being reused
+  tombstones: Arc<RwLock<HashMap<Handle, MetaBlock>>>,
+
   view_ledger_state: Arc<RwLock<ViewLedgerState>>,
 }
 
@@ -52,6 +55,7 @@ impl EndorserState {
       private_key,
       public_key,
       ledger_tail_map: Arc::new(RwLock::new(HashMap::new())),
+      tombstones: Arc::new(RwLock::new(HashMap::new())),
       view_ledger_state: Arc::new(RwLock::new(ViewLedgerState {
         view_ledger_tail_metablock: MetaBlock::default(),
         view_ledger_tail_hash: MetaBlock::default().hash(),
@@ -76,16 +80,25 @@ impl EndorserState {
       }
 
       if let Ok(mut ledger_tail_map_wr) = self.ledger_tail_map.write() {
-        for entry in ledger_tail_map {
-          ledger_tail_map_wr.insert(
-            NimbleDigest::from_bytes(&entry.handle).unwrap(),
-            Arc::new(RwLock::new((
-              MetaBlock::from_bytes(&entry.metablock).unwrap(),
-              Block::from_bytes(&entry.block).unwrap(),
-              Nonces::from_bytes(&entry.nonces).unwrap(),
-            ))),
-          );
+        if let Ok(mut tombstones_wr) = self.tombstones.write() {
+          for entry in ledger_tail_map {
+            let handle = NimbleDigest::from_bytes(&entry.handle).unwrap();
+            let metablock = MetaBlock::from_bytes(&entry.metablock).unwrap();
+            if metablock.is_tombstone() {
+              tombstones_wr.insert(handle, metablock);
+            } else {
+              ledger_tail_map_wr.insert(
+                handle,
+                Arc::new(RwLock::new((
+                  metablock,
+                  Block::from_bytes(&entry.block).unwrap(),
+                  Nonces::from_bytes(&entry.nonces).unwrap(),
+                ))),
+              );
+            }
+          }
         }
+        metrics::TAIL_MAP_SIZE.set(ledger_tail_map_wr.len() as i64);
       }
 
       view_ledger_state.view_ledger_prev_metablock =
@@ -123,6 +136,10 @@ impl EndorserState {
         _ => {},
       }
 
+      if *block_hash == tombstone_block_hash() {
+        return Err(EndorserError::ReservedBlockHash);
+      }
+
       // create a genesis metablock that embeds the current tail of the view/membership ledger
       let view = view_ledger_state.view_ledger_tail_hash;
       let metablock = MetaBlock::genesis(block_hash);
@@ -133,12 +150,23 @@ impl EndorserState {
 
       // check if the handle already exists, if so, return an error
       if let Ok(mut ledger_tail_map) = self.ledger_tail_map.write() {
+        if self.is_deleted(handle)? {
+          return Err(EndorserError::LedgerDeleted);
+        }
         if let hash_map::Entry::Vacant(e) = ledger_tail_map.entry(*handle) {
           e.insert(Arc::new(RwLock::new((
             metablock.clone(),
             block.clone(),
             Nonces::new(),
           ))));
+          metrics::TAIL_MAP_SIZE.set(ledger_tail_map.len() as i64);
+          Ok(Receipt::new(
+            view,
+            metablock,
+            IdSig::new(self.public_key.clone(), signature),
+          ))
+        } else if self.is_tail(&ledger_tail_map, handle, &metablock)? {
+          // the genesis sent again is signed again, so a retried request can collect receipts
           Ok(Receipt::new(
             view,
             metablock,
@@ -173,7 +201,26 @@ impl EndorserState {
 
       if let Ok(ledger_tail_map) = self.ledger_tail_map.read() {
         match ledger_tail_map.get(handle) {
-          None => Err(EndorserError::InvalidLedgerName),
+          None => match self.read_tombstone(handle)? {
+            Some(tombstone) => {
+              let view = view_ledger_state.view_ledger_tail_hash;
+              let message = view_ledger_state.group_identity.digest_with(
+                &view.digest_with(&tombstone_hash(handle, &tombstone).digest_with_bytes(nonce)),
+              );
+              let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+
+              Ok((
+                Receipt::new(
+                  view,
+                  tombstone,
+                  IdSig::new(self.public_key.clone(), signature),
+                ),
+                Block::tombstone(),
+                Nonces::new(),
+              ))
+            },
+            None => Err(EndorserError::InvalidLedgerName),
+          },
           Some(protected_metablock) => {
             if let Ok(e) = protected_metablock.read() {
               let view = view_ledger_state.view_ledger_tail_hash;
@@ -245,6 +292,20 @@ impl EndorserState {
     block: &Block,
     nonces: &Nonces,
   ) -> Result<Receipt, EndorserError> {
+    let entry = (*block_hash, block.clone(), nonces.clone());
+    let mut receipts = self.append_batch(handle, expected_height, &[entry])?;
+    Ok(receipts.remove(0))
+  }
+
+  /// Appends the blocks, given with their hashes and nonces, to the ledger at consecutive
+  /// heights starting at `expected_height`, and returns a receipt for each of them. The
+  /// appends are all made or none is.
+  pub fn append_batch(
+    &self,
+    handle: &NimbleDigest,
+    expected_height: usize,
+    entries: &[(NimbleDigest, Block, Nonces)],
+  ) -> Result<Vec<Receipt>, EndorserError> {
     if let Ok(view_ledger_state) = self.view_ledger_state.read() {
       match view_ledger_state.endorser_mode {
         EndorserMode::Uninitialized | EndorserMode::Initialized => {
@@ -256,12 +317,28 @@ impl EndorserState {
         _ => {},
       }
 
+      if entries.is_empty() {
+        return Err(EndorserError::InvalidTailHeight);
+      }
+      if entries
+        .iter()
+        .any(|(block_hash, _, _)| *block_hash == tombstone_block_hash())
+      {
+        return Err(EndorserError::ReservedBlockHash);
+      }
+
       if let Ok(ledger_tail_map) = self.ledger_tail_map.read() {
         match ledger_tail_map.get(handle) {
-          None => Err(EndorserError::InvalidLedgerName),
+          None => {
+            if self.is_deleted(handle)? {
+              Err(EndorserError::LedgerDeleted)
+            } else {
+              Err(EndorserError::InvalidLedgerName)
+            }
+          },
           Some(protected_metablock) => {
             if let Ok(mut e) = protected_metablock.write() {
-              let metablock = &e.0;
+              let mut metablock = e.0.clone();
               // increment height and returning an error in case of overflow
               let height_plus_one = {
                 let res = metablock.get_height().checked_add(1);
@@ -272,6 +349,17 @@ impl EndorserState {
               };
 
               if expected_height < height_plus_one {
+                // the tail sent again is signed again, so a retried request can collect receipts
+                if entries.len() == 1
+                  && expected_height == metablock.get_height()
+                  && metablock.get_block_hash() == &entries[0].0
+                {
+                  return Ok(vec![self.sign_metablock(
+                    &view_ledger_state,
+                    handle,
+                    &metablock,
+                  )]);
+                }
                 return Err(EndorserError::LedgerExists);
               }
 
@@ -279,21 +367,33 @@ impl EndorserState {
                 return Err(EndorserError::OutOfOrder);
               }
 
-              let new_metablock = MetaBlock::new(&metablock.hash(), block_hash, height_plus_one);
+              if height_plus_one.checked_add(entries.len() - 1).is_none() {
+                return Err(EndorserError::LedgerHeightOverflow);
+              }
 
               let view = view_ledger_state.view_ledger_tail_hash;
-              let message = view_ledger_state
-                .group_identity
-                .digest_with(&view.digest_with(&handle.digest_with(&new_metablock.hash())));
+              let mut receipts = Vec::with_capacity(entries.len());
+              for (i, (block_hash, _, _)) in entries.iter().enumerate() {
+                let new_metablock =
+                  MetaBlock::new(&metablock.hash(), block_hash, height_plus_one + i);
 
-              let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+                let message = view_ledger_state
+                  .group_identity
+                  .digest_with(&view.digest_with(&handle.digest_with(&new_metablock.hash())));
 
-              *e = (new_metablock.clone(), block.clone(), nonces.clone());
-              Ok(Receipt::new(
-                view,
-                new_metablock,
-                IdSig::new(self.public_key.clone(), signature),
-              ))
+                let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+
+                receipts.push(Receipt::new(
+                  view,
+                  new_metablock.clone(),
+                  IdSig::new(self.public_key.clone(), signature),
+                ));
+                metablock = new_metablock;
+              }
+
+              let (_, block, nonces) = &entries[entries.len() - 1];
+              *e = (metablock, block.clone(), nonces.clone());
+              Ok(receipts)
             } else {
               Err(EndorserError::FailedToAcquireLedgerEntryWriteLock)
             }
@@ -307,10 +407,157 @@ impl EndorserState {
     }
   }
 
+  fn is_tail(
+    &self,
+    ledger_tail_map: &HashMap<Handle, ProtectedMetaBlock>,
+    handle: &NimbleDigest,
+    metablock: &MetaBlock,
+  ) -> Result<bool, EndorserError> {
+    match ledger_tail_map.get(handle) {
+      Some(protected_metablock) => match protected_metablock.read() {
+        Ok(e) => Ok(e.0 == *metablock),
+        Err(_) => Err(EndorserError::FailedToAcquireLedgerEntryReadLock),
+      },
+      None => Ok(false),
+    }
+  }
+
+  fn sign_metablock(
+    &self,
+    view_ledger_state: &ViewLedgerState,
+    handle: &NimbleDigest,
+    metablock: &MetaBlock,
+  ) -> Receipt {
+    let view = view_ledger_state.view_ledger_tail_hash;
+    let message = view_ledger_state
+      .group_identity
+      .digest_with(&view.digest_with(&handle.digest_with(&metablock.hash())));
+    let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+
+    Receipt::new(
+      view,
+      metablock.clone(),
+      IdSig::new(self.public_key.clone(), signature),
+    )
+  }
+
+  fn read_tombstone(&self, handle: &NimbleDigest) -> Result<Option<MetaBlock>, EndorserError> {
+    if let Ok(tombstones) = self.tombstones.read() {
+      Ok(tombstones.get(handle).cloned())
+    } else {
+      Err(EndorserError::FailedToAcquireLedgerMapReadLock)
+    }
+  }
+
+  fn is_deleted(&self, handle: &NimbleDigest) -> Result<bool, EndorserError> {
+    Ok(self.read_tombstone(handle)?.is_some())
+  }
+
+  fn sign_tombstone(
+    &self,
+    view_ledger_state: &ViewLedgerState,
+    handle: &NimbleDigest,
+    tombstone: &MetaBlock,
+  ) -> Receipt {
+    let view = view_ledger_state.view_ledger_tail_hash;
+    let message = view_ledger_state
+      .group_identity
+      .digest_with(&view.digest_with(&tombstone_hash(handle, tombstone)));
+    let signature = self.private_key.sign(&message.to_bytes()).unwrap();
+
+    Receipt::new(
+      view,
+      tombstone.clone(),
+      IdSig::new(self.public_key.clone(), signature),
+    )
+  }
+
+  /// Ends a ledger with a tombstone at `expected_height` and drops its tail. Only the
+  /// tombstone is kept, so the ledger cannot be created again.
+  pub fn delete_ledger(
+    &self,
+    handle: &NimbleDigest,
+    expected_height: usize,
+  ) -> Result<Receipt, EndorserError> {
+    if let Ok(view_ledger_state) = self.view_ledger_state.read() {
+      match view_ledger_state.endorser_mode {
+        EndorserMode::Uninitialized | EndorserMode::Initialized => {
+          return Err(EndorserError::NotActive);
+        },
+        EndorserMode::Finalized => {
+          return Err(EndorserError::AlreadyFinalized);
+        },
+        _ => {},
+      }
+
+      if let Ok(mut ledger_tail_map) = self.ledger_tail_map.write() {
+        let tombstone = match ledger_tail_map.get(handle) {
+          None => {
+            // the deletion is signed again if it is retried
+            return match self.read_tombstone(handle)? {
+              Some(tombstone) if tombstone.get_height() == expected_height => {
+                Ok(self.sign_tombstone(view_ledger_state.deref(), handle, &tombstone))
+              },
+              Some(_) => Err(EndorserError::LedgerDeleted),
+              None => Err(EndorserError::InvalidLedgerName),
+            };
+          },
+          Some(protected_metablock) => {
+            if let Ok(e) = protected_metablock.read() {
+              let metablock = &e.0;
+              let height_plus_one = {
+                let res = metablock.get_height().checked_add(1);
+                if res.is_none() {
+                  return Err(EndorserError::LedgerHeightOverflow);
+                }
+                res.unwrap()
+              };
+
+              if expected_height < height_plus_one {
+                return Err(EndorserError::LedgerExists);
+              }
+
+              if expected_height > height_plus_one {
+                return Err(EndorserError::OutOfOrder);
+              }
+
+              MetaBlock::tombstone(&metablock.hash(), height_plus_one)
+            } else {
+              return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
+            }
+          },
+        };
+
+        if let Ok(mut tombstones) = self.tombstones.write() {
+          tombstones.insert(*handle, tombstone.clone());
+        } else {
+          return Err(EndorserError::FailedToAcquireLedgerMapWriteLock);
+        }
+        ledger_tail_map.remove(handle);
+        metrics::TAIL_MAP_SIZE.set(ledger_tail_map.len() as i64);
+
+        Ok(self.sign_tombstone(view_ledger_state.deref(), handle, &tombstone))
+      } else {
+        Err(EndorserError::FailedToAcquireLedgerMapWriteLock)
+      }
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerReadLock)
+    }
+  }
+
   pub fn get_public_key(&self) -> PublicKey {
     self.public_key.clone()
   }
 
+  /// Signs the TLS certificate the endorser is served with, given in DER, so that the
+  /// coordinator can tell the certificate belongs to the endorser with this public key
+  pub fn sign_tls_certificate(&self, cert: &[u8]) -> Signature {
+    self
+      .private_key
+      .sign(&tls_certificate_hash(cert).to_bytes())
+      .unwrap()
+  }
+
   fn append_view_ledger(
     &self,
     view_ledger_state: &mut ViewLedgerState,
@@ -370,10 +617,11 @@ impl EndorserState {
     )
   }
 
+  // deleted ledgers are listed with their tombstones, so they are carried over to new endorsers
   fn construct_ledger_tail_map(&self) -> Result<Vec<LedgerTailMapEntry>, EndorserError> {
     let mut ledger_tail_map = Vec::new();
     if let Ok(ledger_tail_map_rd) = self.ledger_tail_map.read() {
-      for (handle, value) in ledger_tail_map_rd.deref().iter().sorted_by_key(|x| x.0) {
+      for (handle, value) in ledger_tail_map_rd.deref().iter() {
         if let Ok(e) = value.read() {
           ledger_tail_map.push(LedgerTailMapEntry {
             handle: handle.to_bytes(),
@@ -386,11 +634,29 @@ impl EndorserState {
           return Err(EndorserError::FailedToAcquireLedgerEntryReadLock);
         }
       }
+      if let Ok(tombstones) = self.tombstones.read() {
+        for (handle, tombstone) in tombstones.iter() {
+          ledger_tail_map.push(LedgerTailMapEntry {
+            handle: handle.to_bytes(),
+            height: tombstone.get_height() as u64,
+            metablock: tombstone.to_bytes(),
+            block: Block::tombstone().to_bytes(),
+            nonces: Nonces::new().to_bytes(),
+          });
+        }
+      } else {
+        return Err(EndorserError::FailedToAcquireLedgerMapReadLock);
+      }
     } else {
       return Err(EndorserError::FailedToAcquireLedgerMapReadLock);
     }
 
-    Ok(ledger_tail_map)
+    Ok(
+      ledger_tail_map
+        .into_iter()
+        .sorted_by(|a, b| a.handle.cmp(&b.handle))
+        .collect(),
+    )
   }
 
   pub fn finalize_state(
@@ -426,6 +692,45 @@ impl EndorserState {
     }
   }
 
+  /// Appends the block of the next view to the view ledger of an active endorser that stays
+  /// in that view, so that it signs for the next view without being finalized
+  pub fn advance_view(
+    &self,
+    block_hash: &NimbleDigest,
+    expected_height: usize,
+  ) -> Result<(Receipt, Vec<LedgerTailMapEntry>), EndorserError> {
+    if let Ok(mut view_ledger_state) = self.view_ledger_state.write() {
+      match view_ledger_state.endorser_mode {
+        EndorserMode::Uninitialized | EndorserMode::Initialized => {
+          return Err(EndorserError::NotActive);
+        },
+        EndorserMode::Finalized => {
+          return Err(EndorserError::AlreadyFinalized);
+        },
+        _ => {},
+      }
+
+      let ledger_tail_map = self.construct_ledger_tail_map()?;
+
+      // a view change that is applied again finds the endorser in the new view already
+      let tail = &view_ledger_state.view_ledger_tail_metablock;
+      let receipt = if tail.get_height() == expected_height && tail.get_block_hash() == block_hash {
+        self.sign_view_ledger(view_ledger_state.deref(), &ledger_tail_map)
+      } else {
+        self.append_view_ledger(
+          view_ledger_state.deref_mut(),
+          &ledger_tail_map,
+          block_hash,
+          expected_height,
+        )?
+      };
+
+      Ok((receipt, ledger_tail_map))
+    } else {
+      Err(EndorserError::FailedToAcquireViewLedgerWriteLock)
+    }
+  }
+
   pub fn read_state(
     &self,
   ) -> Result<(Receipt, EndorserMode, Vec<LedgerTailMapEntry>), EndorserError> {
@@ -575,6 +880,13 @@ mod tests {
       )
       .is_ok());
 
+    // The genesis sent again is signed again, while a different genesis is refused
+    let res = endorser_state.new_ledger(&handle, &block_hash, &block);
+    assert_eq!(res.unwrap().get_metablock(), receipt.get_metablock());
+    let other_block = Block::new(&rand::thread_rng().gen::<[u8; 32]>());
+    let res = endorser_state.new_ledger(&handle, &other_block.hash(), &other_block);
+    assert_eq!(res.unwrap_err(), EndorserError::LedgerExists);
+
     // Fetch the value currently in the tail.
     let tail_result = endorser_state.read_latest(&handle, &[0]);
     assert!(tail_result.is_ok());
@@ -703,6 +1015,24 @@ mod tests {
     assert_eq!(*receipt.get_prev(), prev_tail);
     assert_eq!(new_ledger_height, height_plus_one);
 
+    // The tail sent again is signed again, while the entry before it is not
+    let res = endorser_state.append(
+      &handle,
+      &block_hash_to_append,
+      height_plus_one,
+      &block_hash_to_append_data,
+      &Nonces::new(),
+    );
+    assert_eq!(res.unwrap().get_metablock(), receipt.get_metablock());
+    let res = endorser_state.append(
+      &handle,
+      &block_hash,
+      height_plus_one - 1,
+      &block,
+      &Nonces::new(),
+    );
+    assert_eq!(res.unwrap_err(), EndorserError::LedgerExists);
+
     let metadata = MetaBlock::new(&prev_tail, &block_hash_to_append, new_ledger_height);
 
     let endorser_tail_expectation = metadata.hash();
@@ -729,4 +1059,227 @@ mod tests {
       panic!("Signature verification failed when it should not have failed");
     }
   }
+
+  #[test]
+  pub fn check_endorser_delete_ledger() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handle = NimbleDigest::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+    let block = Block::new(&rand::thread_rng().gen::<[u8; 32]>());
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+    assert!(endorser_state
+      .append(&handle, &block.hash(), 1, &block, &Nonces::new())
+      .is_ok());
+
+    // The block hash of a tombstone is never signed for an ordinary entry
+    assert_eq!(
+      endorser_state
+        .append(&handle, &tombstone_block_hash(), 2, &block, &Nonces::new())
+        .unwrap_err(),
+      EndorserError::ReservedBlockHash
+    );
+
+    assert_eq!(
+      endorser_state.delete_ledger(&handle, 3).unwrap_err(),
+      EndorserError::OutOfOrder
+    );
+    let prev = endorser_state.read_latest(&handle, &[0]).unwrap().0;
+    let receipt = endorser_state.delete_ledger(&handle, 2).unwrap();
+    let tombstone = MetaBlock::tombstone(&prev.get_metablock_hash(), 2);
+    assert_eq!(*receipt.get_metablock(), tombstone);
+    assert!(receipt
+      .get_id_sig()
+      .verify_with_id(
+        &endorser_state.public_key,
+        &view_block_hash
+          .digest_with(
+            &receipt
+              .get_view()
+              .digest_with(&tombstone_hash(&handle, &tombstone))
+          )
+          .to_bytes(),
+      )
+      .is_ok());
+    // which is not a receipt for an entry
+    assert!(receipt
+      .get_id_sig()
+      .verify_with_id(
+        &endorser_state.public_key,
+        &view_block_hash
+          .digest_with(
+            &receipt
+              .get_view()
+              .digest_with(&handle.digest_with(&tombstone.hash()))
+          )
+          .to_bytes(),
+      )
+      .is_err());
+
+    // Only the tombstone is kept, and the deletion can be retried
+    assert!(!endorser_state
+      .ledger_tail_map
+      .read()
+      .expect("failed")
+      .contains_key(&handle));
+    let retried = endorser_state.delete_ledger(&handle, 2).unwrap();
+    assert_eq!(*retried.get_metablock(), tombstone);
+    assert_eq!(
+      endorser_state.delete_ledger(&handle, 3).unwrap_err(),
+      EndorserError::LedgerDeleted
+    );
+
+    // Reads prove the deletion, and the ledger cannot be appended to or created again
+    let (receipt, block_read, nonces) = endorser_state.read_latest(&handle, &[1]).unwrap();
+    assert_eq!(*receipt.get_metablock(), tombstone);
+    assert_eq!(block_read.to_bytes(), Block::tombstone().to_bytes());
+    assert!(nonces.is_empty());
+    assert_eq!(
+      endorser_state
+        .append(&handle, &block.hash(), 3, &block, &Nonces::new())
+        .unwrap_err(),
+      EndorserError::LedgerDeleted
+    );
+    assert_eq!(
+      endorser_state
+        .new_ledger(&handle, &block.hash(), &block)
+        .unwrap_err(),
+      EndorserError::LedgerDeleted
+    );
+
+    // The tombstone is carried over to an endorser initialized from this one's state
+    let ledger_tail_map = endorser_state.construct_ledger_tail_map().unwrap();
+    assert_eq!(ledger_tail_map.len(), 1);
+    assert_eq!(ledger_tail_map[0].metablock, tombstone.to_bytes());
+    let new_endorser_state = EndorserState::new();
+    assert!(new_endorser_state
+      .initialize_state(
+        &view_block_hash,
+        &ledger_tail_map,
+        &MetaBlock::default(),
+        &view_block_hash,
+        1,
+      )
+      .is_ok());
+    assert_eq!(
+      new_endorser_state.tombstones.read().expect("failed")[&handle],
+      tombstone
+    );
+    assert!(new_endorser_state
+      .ledger_tail_map
+      .read()
+      .expect("failed")
+      .is_empty());
+  }
+
+  #[test]
+  pub fn check_endorser_append_batch() {
+    let endorser_state = EndorserState::new();
+    let view_block_hash = NimbleDigest::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+    let res = endorser_state.initialize_state(
+      &view_block_hash,
+      &Vec::new(),
+      &MetaBlock::default(),
+      &view_block_hash,
+      1,
+    );
+    assert!(res.is_ok());
+    endorser_state
+      .view_ledger_state
+      .write()
+      .expect("failed to acquire write lock")
+      .endorser_mode = ledger::endorser_proto::EndorserMode::Active;
+
+    let handle = NimbleDigest::from_bytes(&rand::thread_rng().gen::<[u8; 32]>()).unwrap();
+    let block = Block::new(&rand::thread_rng().gen::<[u8; 32]>());
+    assert!(endorser_state
+      .new_ledger(&handle, &block.hash(), &block)
+      .is_ok());
+    let genesis = endorser_state.read_latest(&handle, &[0]).unwrap().0;
+
+    let entries = (1..=3u8)
+      .map(|i| {
+        let block = Block::new(&[i; 32]);
+        (block.hash(), block, Nonces::new())
+      })
+      .collect::<Vec<_>>();
+
+    // each entry is signed on top of the one before it
+    let receipts = endorser_state.append_batch(&handle, 1, &entries).unwrap();
+    assert_eq!(receipts.len(), 3);
+    let mut prev = genesis.get_metablock_hash();
+    for (i, receipt) in receipts.iter().enumerate() {
+      assert_eq!(receipt.get_height(), i + 1);
+      assert_eq!(*receipt.get_prev(), prev);
+      assert_eq!(*receipt.get_block_hash(), entries[i].0);
+      assert!(receipt
+        .get_id_sig()
+        .verify_with_id(
+          &endorser_state.public_key,
+          &view_block_hash
+            .digest_with(
+              &receipt
+                .get_view()
+                .digest_with(&handle.digest_with(&receipt.get_metablock_hash()))
+            )
+            .to_bytes(),
+        )
+        .is_ok());
+      prev = receipt.get_metablock_hash();
+    }
+    assert_eq!(endorser_state.get_height(&handle).unwrap(), 3);
+
+    // a batch that cannot be appended in full leaves the ledger as it was
+    assert_eq!(
+      endorser_state
+        .append_batch(&handle, 3, &entries)
+        .unwrap_err(),
+      EndorserError::LedgerExists
+    );
+    let mut reserved = entries.clone();
+    reserved[1].0 = tombstone_block_hash();
+    assert_eq!(
+      endorser_state
+        .append_batch(&handle, 4, &reserved)
+        .unwrap_err(),
+      EndorserError::ReservedBlockHash
+    );
+    assert_eq!(endorser_state.get_height(&handle).unwrap(), 3);
+    let receipt = endorser_state
+      .append(&handle, &entries[0].0, 4, &entries[0].1, &Nonces::new())
+      .unwrap();
+    assert_eq!(*receipt.get_prev(), prev);
+  }
+
+  #[test]
+  pub fn check_endorser_sign_tls_certificate() {
+    use ledger::signature::SignatureTrait;
+
+    let endorser_state = EndorserState::new();
+    let cert = rand::thread_rng().gen::<[u8; 32]>();
+    let signature = endorser_state.sign_tls_certificate(&cert);
+    let pk = endorser_state.get_public_key();
+    assert!(signature
+      .verify(&pk, &tls_certificate_hash(&cert).to_bytes())
+      .is_ok());
+    // the signature covers this certificate alone
+    assert!(signature
+      .verify(&pk, &tls_certificate_hash(&cert[1..]).to_bytes())
+      .is_err());
+  }
 }
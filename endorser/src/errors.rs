@@ -34,4 +34,8 @@ pub enum EndorserError {
   NotActive,
   /// returned if the endorser is already activated
   AlreadyActivated,
+  /// returned if the ledger was deleted
+  LedgerDeleted,
+  /// returned if the block hash is reserved for the tombstones of deleted ledgers
+  ReservedBlockHash,
 }
@@ -0,0 +1,343 @@
+use ledger::endorser_proto::{
+  endorser_call_server::EndorserCall, ActivateReq, ActivateResp, AdvanceViewReq, AdvanceViewResp,
+  AppendBatchReq, AppendBatchResp, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp,
+  FinalizeStateReq, FinalizeStateResp, GetPublicKeyReq, GetPublicKeyResp, InitializeStateReq,
+  InitializeStateResp, NewLedgerReq, NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq,
+  ReadStateResp,
+};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use tonic::{Code, Request, Response, Status};
+use tracing::warn;
+
+const METHODS: [&str; 12] = [
+  "get_public_key",
+  "new_ledger",
+  "append",
+  "append_batch",
+  "delete_ledger",
+  "read_latest",
+  "finalize_state",
+  "advance_view",
+  "initialize_state",
+  "read_state",
+  "activate",
+  "*",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+  /// the call is delayed before it is served
+  Delay(Duration),
+  /// the call is never answered
+  Drop,
+  /// the call fails with the code
+  Fail(Code),
+  /// the endorser exits
+  Crash,
+}
+
+/// A fault injected into the calls of one method (or of all methods with `*`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultRule {
+  method: String,
+  fault: Fault,
+  // the number of matching calls served before the fault kicks in
+  after: usize,
+  // the number of calls the fault applies to, or all of them if `None`
+  times: Option<usize>,
+}
+
+impl FaultRule {
+  /// Parses `method:fault[:after[:times]]`, where `fault` is `delay=<ms>`, `drop`, `crash`,
+  /// or a gRPC code in snake case (e.g., `resource_exhausted`)
+  pub fn parse(spec: &str) -> Result<Self, String> {
+    let parts = spec.split(':').collect::<Vec<&str>>();
+    if parts.len() < 2 || parts.len() > 4 {
+      return Err(format!("Invalid fault {}", spec));
+    }
+
+    let method = parts[0].to_string();
+    if !METHODS.contains(&parts[0]) {
+      return Err(format!("Unknown method {}", parts[0]));
+    }
+
+    let fault = match parts[1] {
+      "drop" => Fault::Drop,
+      "crash" => Fault::Crash,
+      f if f.starts_with("delay=") => match f["delay=".len()..].parse::<u64>() {
+        Ok(ms) => Fault::Delay(Duration::from_millis(ms)),
+        Err(_) => return Err(format!("Invalid delay {}", f)),
+      },
+      f => match parse_code(f) {
+        Some(code) => Fault::Fail(code),
+        None => return Err(format!("Unknown fault {}", f)),
+      },
+    };
+
+    let after = match parts.get(2) {
+      Some(a) => match a.parse::<usize>() {
+        Ok(a) => a,
+        Err(_) => return Err(format!("Invalid number of calls {}", a)),
+      },
+      None => 0,
+    };
+    let times = match parts.get(3) {
+      Some(t) => match t.parse::<usize>() {
+        Ok(t) => Some(t),
+        Err(_) => return Err(format!("Invalid number of calls {}", t)),
+      },
+      None => None,
+    };
+
+    Ok(FaultRule {
+      method,
+      fault,
+      after,
+      times,
+    })
+  }
+
+  fn applies_to(&self, calls: usize) -> bool {
+    calls > self.after
+      && match self.times {
+        Some(t) => calls <= self.after + t,
+        None => true,
+      }
+  }
+}
+
+fn parse_code(code: &str) -> Option<Code> {
+  let code = match code {
+    "cancelled" => Code::Cancelled,
+    "unknown" => Code::Unknown,
+    "invalid_argument" => Code::InvalidArgument,
+    "deadline_exceeded" => Code::DeadlineExceeded,
+    "not_found" => Code::NotFound,
+    "already_exists" => Code::AlreadyExists,
+    "permission_denied" => Code::PermissionDenied,
+    "resource_exhausted" => Code::ResourceExhausted,
+    "failed_precondition" => Code::FailedPrecondition,
+    "aborted" => Code::Aborted,
+    "out_of_range" => Code::OutOfRange,
+    "unimplemented" => Code::Unimplemented,
+    "internal" => Code::Internal,
+    "unavailable" => Code::Unavailable,
+    "data_loss" => Code::DataLoss,
+    "unauthenticated" => Code::Unauthenticated,
+    _ => return None,
+  };
+  Some(code)
+}
+
+/// An endorser service that injects faults into the calls it forwards to another one, so
+/// that the coordinator's handling of misbehaving endorsers can be tested
+pub struct FaultyEndorserService<T: EndorserCall> {
+  service: T,
+  rules: Vec<FaultRule>,
+  calls: Mutex<HashMap<String, usize>>,
+}
+
+impl<T: EndorserCall> FaultyEndorserService<T> {
+  pub fn new(service: T, rules: Vec<FaultRule>) -> Self {
+    FaultyEndorserService {
+      service,
+      rules,
+      calls: Mutex::new(HashMap::new()),
+    }
+  }
+
+  fn next_fault(&self, method: &str) -> Option<Fault> {
+    let mut calls = self.calls.lock().unwrap();
+    let method_calls = {
+      let c = calls.entry(method.to_string()).or_insert(0);
+      *c += 1;
+      *c
+    };
+    let all_calls = {
+      let c = calls.entry("*".to_string()).or_insert(0);
+      *c += 1;
+      *c
+    };
+
+    self
+      .rules
+      .iter()
+      .find(|r| {
+        if r.method == "*" {
+          r.applies_to(all_calls)
+        } else {
+          r.method == method && r.applies_to(method_calls)
+        }
+      })
+      .map(|r| r.fault.clone())
+  }
+
+  async fn inject(&self, method: &str) -> Result<(), Status> {
+    match self.next_fault(method) {
+      None => Ok(()),
+      Some(Fault::Delay(d)) => {
+        tokio::time::sleep(d).await;
+        Ok(())
+      },
+      Some(Fault::Drop) => {
+        std::future::pending::<()>().await;
+        Ok(())
+      },
+      // the coordinator expects the height of the endorser's ledger with this code
+      Some(Fault::Fail(Code::FailedPrecondition)) => Err(Status::with_details(
+        Code::FailedPrecondition,
+        "Injected fault",
+        bytes::Bytes::copy_from_slice(&0u64.to_le_bytes()),
+      )),
+      Some(Fault::Fail(code)) => Err(Status::new(code, "Injected fault")),
+      Some(Fault::Crash) => {
+        warn!("Crashing the endorser on a call to {}", method);
+        std::process::exit(1);
+      },
+    }
+  }
+}
+
+#[tonic::async_trait]
+impl<T: EndorserCall> EndorserCall for FaultyEndorserService<T> {
+  async fn get_public_key(
+    &self,
+    req: Request<GetPublicKeyReq>,
+  ) -> Result<Response<GetPublicKeyResp>, Status> {
+    self.inject("get_public_key").await?;
+    self.service.get_public_key(req).await
+  }
+
+  async fn new_ledger(
+    &self,
+    req: Request<NewLedgerReq>,
+  ) -> Result<Response<NewLedgerResp>, Status> {
+    self.inject("new_ledger").await?;
+    self.service.new_ledger(req).await
+  }
+
+  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    self.inject("append").await?;
+    self.service.append(req).await
+  }
+
+  async fn append_batch(
+    &self,
+    req: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    self.inject("append_batch").await?;
+    self.service.append_batch(req).await
+  }
+
+  async fn delete_ledger(
+    &self,
+    req: Request<DeleteLedgerReq>,
+  ) -> Result<Response<DeleteLedgerResp>, Status> {
+    self.inject("delete_ledger").await?;
+    self.service.delete_ledger(req).await
+  }
+
+  async fn read_latest(
+    &self,
+    req: Request<ReadLatestReq>,
+  ) -> Result<Response<ReadLatestResp>, Status> {
+    self.inject("read_latest").await?;
+    self.service.read_latest(req).await
+  }
+
+  async fn finalize_state(
+    &self,
+    req: Request<FinalizeStateReq>,
+  ) -> Result<Response<FinalizeStateResp>, Status> {
+    self.inject("finalize_state").await?;
+    self.service.finalize_state(req).await
+  }
+
+  async fn advance_view(
+    &self,
+    req: Request<AdvanceViewReq>,
+  ) -> Result<Response<AdvanceViewResp>, Status> {
+    self.inject("advance_view").await?;
+    self.service.advance_view(req).await
+  }
+
+  async fn initialize_state(
+    &self,
+    req: Request<InitializeStateReq>,
+  ) -> Result<Response<InitializeStateResp>, Status> {
+    self.inject("initialize_state").await?;
+    self.service.initialize_state(req).await
+  }
+
+  async fn read_state(
+    &self,
+    req: Request<ReadStateReq>,
+  ) -> Result<Response<ReadStateResp>, Status> {
+    self.inject("read_state").await?;
+    self.service.read_state(req).await
+  }
+
+  async fn activate(&self, req: Request<ActivateReq>) -> Result<Response<ActivateResp>, Status> {
+    self.inject("activate").await?;
+    self.service.activate(req).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::EndorserServiceState;
+
+  #[test]
+  pub fn check_fault_rule_parsing() {
+    assert_eq!(
+      FaultRule::parse("append:resource_exhausted:1:2").unwrap(),
+      FaultRule {
+        method: "append".to_string(),
+        fault: Fault::Fail(Code::ResourceExhausted),
+        after: 1,
+        times: Some(2),
+      }
+    );
+    assert_eq!(
+      FaultRule::parse("*:delay=20").unwrap().fault,
+      Fault::Delay(Duration::from_millis(20))
+    );
+    assert_eq!(FaultRule::parse("read_latest:crash:5").unwrap().after, 5);
+    assert!(FaultRule::parse("append").is_err());
+    assert!(FaultRule::parse("appendx:drop").is_err());
+    assert!(FaultRule::parse("append:no_such_code").is_err());
+    assert!(FaultRule::parse("append:drop:x").is_err());
+  }
+
+  #[tokio::test]
+  pub async fn check_faulty_endorser_service() {
+    let rules = vec![
+      FaultRule::parse("get_public_key:internal:1:2").unwrap(),
+      FaultRule::parse("*:unavailable:4").unwrap(),
+    ];
+    let service = FaultyEndorserService::new(EndorserServiceState::new(), rules);
+
+    let mut codes = Vec::new();
+    for _ in 0..5 {
+      let res = service
+        .get_public_key(Request::new(GetPublicKeyReq {}))
+        .await;
+      codes.push(res.err().map(|s| s.code()));
+    }
+    assert_eq!(
+      codes,
+      vec![
+        None,
+        Some(Code::Internal),
+        Some(Code::Internal),
+        None,
+        Some(Code::Unavailable)
+      ]
+    );
+
+    // the fault on all methods keeps applying to other methods too
+    let res = service.read_state(Request::new(ReadStateReq {})).await;
+    assert_eq!(res.unwrap_err().code(), Code::Unavailable);
+  }
+}
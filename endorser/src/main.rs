@@ -1,31 +1,54 @@
-use crate::{endorser_state::EndorserState, errors::EndorserError};
+use crate::{
+  endorser_state::EndorserState,
+  errors::EndorserError,
+  faults::{FaultRule, FaultyEndorserService},
+  metrics::MeteredEndorserService,
+};
+use axum::{routing::get, Router};
 use clap::{App, Arg};
 use ledger::{
-  signature::PublicKeyTrait, Block, CustomSerde, MetaBlock, NimbleDigest, Nonces, Receipts,
+  signature::{PublicKeyTrait, SignatureTrait},
+  Block, CustomSerde, MetaBlock, NimbleDigest, Nonces, Receipts,
+};
+use tonic::{
+  transport::{server::TcpIncoming, Certificate, Identity, Server, ServerTlsConfig},
+  Code, Request, Response, Status,
 };
-use tonic::{transport::Server, Code, Request, Response, Status};
+use tracing::warn;
 
 mod endorser_state;
 mod errors;
+mod faults;
+mod metrics;
 
 use ledger::endorser_proto::{
   endorser_call_server::{EndorserCall, EndorserCallServer},
-  ActivateReq, ActivateResp, AppendReq, AppendResp, FinalizeStateReq, FinalizeStateResp,
-  GetPublicKeyReq, GetPublicKeyResp, InitializeStateReq, InitializeStateResp, NewLedgerReq,
-  NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq, ReadStateResp,
+  ActivateReq, ActivateResp, AdvanceViewReq, AdvanceViewResp, AppendBatchEntry, AppendBatchReq,
+  AppendBatchResp, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp, FinalizeStateReq,
+  FinalizeStateResp, GetPublicKeyReq, GetPublicKeyResp, InitializeStateReq, InitializeStateResp,
+  NewLedgerReq, NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq, ReadStateResp,
 };
 
 pub struct EndorserServiceState {
   state: EndorserState,
+  // the signature over the TLS certificate the endorser is served with, if any
+  tls_signature: Vec<u8>,
 }
 
 impl EndorserServiceState {
   pub fn new() -> Self {
     EndorserServiceState {
       state: EndorserState::new(),
+      tls_signature: Vec::new(),
     }
   }
 
+  /// Binds the TLS certificate the endorser is served with, in DER, to its public key
+  pub fn with_tls_certificate(mut self, cert: &[u8]) -> Self {
+    self.tls_signature = self.state.sign_tls_certificate(cert).to_bytes();
+    self
+  }
+
   fn process_error(
     &self,
     error: EndorserError,
@@ -54,6 +77,8 @@ impl EndorserServiceState {
       },
       EndorserError::NotInitialized => Status::unimplemented("Endorser is not initialized"),
       EndorserError::AlreadyFinalized => Status::unavailable("Endorser is already finalized"),
+      EndorserError::LedgerDeleted => Status::permission_denied("Ledger is deleted"),
+      EndorserError::ReservedBlockHash => Status::invalid_argument("Block hash is reserved"),
       _ => Status::internal(default_msg),
     }
   }
@@ -75,6 +100,7 @@ impl EndorserCall for EndorserServiceState {
 
     let reply = GetPublicKeyResp {
       pk: pk.to_bytes().to_vec(),
+      tls_signature: self.tls_signature.clone(),
     };
 
     Ok(Response::new(reply))
@@ -191,6 +217,100 @@ impl EndorserCall for EndorserServiceState {
     }
   }
 
+  async fn append_batch(
+    &self,
+    req: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    let AppendBatchReq {
+      handle,
+      expected_height,
+      entries,
+    } = req.into_inner();
+
+    let handle = NimbleDigest::from_bytes(&handle)
+      .map_err(|_| Status::invalid_argument("Invalid input sizes"))?;
+    let mut batch = Vec::with_capacity(entries.len());
+    for AppendBatchEntry {
+      block_hash,
+      block,
+      nonces,
+    } in entries
+    {
+      match (
+        NimbleDigest::from_bytes(&block_hash),
+        Block::from_bytes(&block),
+        Nonces::from_bytes(&nonces),
+      ) {
+        (Ok(block_hash), Ok(block), Ok(nonces)) => batch.push((block_hash, block, nonces)),
+        _ => return Err(Status::invalid_argument("Invalid input sizes")),
+      }
+    }
+
+    if expected_height == 0 || batch.is_empty() {
+      return Err(Status::invalid_argument("Invalid expected height"));
+    }
+
+    match self
+      .state
+      .append_batch(&handle, expected_height as usize, &batch)
+    {
+      Ok(receipts) => {
+        let reply = AppendBatchResp {
+          receipts: receipts
+            .iter()
+            .map(|receipt| receipt.to_bytes().to_vec())
+            .collect(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => Err(self.process_error(
+        error,
+        Some(&handle),
+        "Failed to append to a ledger due to an internal error",
+      )),
+    }
+  }
+
+  async fn delete_ledger(
+    &self,
+    req: Request<DeleteLedgerReq>,
+  ) -> Result<Response<DeleteLedgerResp>, Status> {
+    let DeleteLedgerReq {
+      handle,
+      expected_height,
+    } = req.into_inner();
+    let handle = {
+      let res = NimbleDigest::from_bytes(&handle);
+      if res.is_err() {
+        return Err(Status::invalid_argument("Invalid handle size"));
+      }
+      res.unwrap()
+    };
+
+    if expected_height == 0 {
+      return Err(Status::invalid_argument("Invalid expected height"));
+    }
+
+    let res = self.state.delete_ledger(&handle, expected_height as usize);
+
+    match res {
+      Ok(receipt) => {
+        let reply = DeleteLedgerResp {
+          receipt: receipt.to_bytes().to_vec(),
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          Some(&handle),
+          "Failed to delete a ledger due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
   async fn read_latest(
     &self,
     request: Request<ReadLatestReq>,
@@ -263,6 +383,44 @@ impl EndorserCall for EndorserServiceState {
     }
   }
 
+  async fn advance_view(
+    &self,
+    req: Request<AdvanceViewReq>,
+  ) -> Result<Response<AdvanceViewResp>, Status> {
+    let AdvanceViewReq {
+      block_hash,
+      expected_height,
+    } = req.into_inner();
+
+    let block_hash_instance = NimbleDigest::from_bytes(&block_hash);
+
+    if block_hash_instance.is_err() {
+      return Err(Status::invalid_argument("Invalid input sizes"));
+    }
+
+    let res = self
+      .state
+      .advance_view(&block_hash_instance.unwrap(), expected_height as usize);
+
+    match res {
+      Ok((receipt, ledger_tail_map)) => {
+        let reply = AdvanceViewResp {
+          receipt: receipt.to_bytes().to_vec(),
+          ledger_tail_map,
+        };
+        Ok(Response::new(reply))
+      },
+      Err(error) => {
+        let status = self.process_error(
+          error,
+          None,
+          "Failed to advance the view of the endorser due to an internal error",
+        );
+        Err(status)
+      },
+    }
+  }
+
   async fn initialize_state(
     &self,
     req: Request<InitializeStateReq>,
@@ -379,23 +537,131 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .long("port")
         .help("The port number to run the Service On. Default: 9096")
         .default_value("9090"),
+    )
+    .arg(
+      Arg::with_name("fault")
+        .long("fault")
+        .takes_value(true)
+        .multiple(true)
+        .help("Inject a fault for testing, given as method:fault[:after[:times]]"),
+    )
+    .arg(
+      Arg::with_name("metrics")
+        .long("metrics")
+        .takes_value(true)
+        .help("The port number to serve Prometheus metrics at /metrics on"),
+    )
+    .arg(
+      Arg::with_name("otlp_endpoint")
+        .long("otlp_endpoint")
+        .takes_value(true)
+        .help("Export spans to the OTLP collector at this URI (e.g., http://localhost:4317)"),
+    )
+    .arg(
+      Arg::with_name("tls_cert")
+        .long("tls_cert")
+        .takes_value(true)
+        .requires("tls_key")
+        .help("Serve over TLS with the certificate in this PEM file"),
+    )
+    .arg(
+      Arg::with_name("tls_key")
+        .long("tls_key")
+        .takes_value(true)
+        .requires("tls_cert")
+        .help("The private key, in a PEM file, of the TLS certificate"),
+    )
+    .arg(
+      Arg::with_name("tls_client_ca")
+        .long("tls_client_ca")
+        .takes_value(true)
+        .requires("tls_cert")
+        .help("Require coordinators to present a certificate signed by the CA in this PEM file"),
     );
+
   let cli_matches = config.get_matches();
+  if let Err(error) = telemetry::init("endorser", cli_matches.value_of("otlp_endpoint")) {
+    eprintln!("Failed to set up tracing ({:?})", error);
+  }
   let hostname = cli_matches.value_of("host").unwrap();
   let port_number = cli_matches.value_of("port").unwrap();
   let addr = format!("{}:{}", hostname, port_number).parse()?;
-  let server = EndorserServiceState::new();
+  let mut server = EndorserServiceState::new();
+  let tls_config = match (
+    cli_matches.value_of("tls_cert"),
+    cli_matches.value_of("tls_key"),
+  ) {
+    (Some(cert), Some(key)) => {
+      let cert = std::fs::read(cert)?;
+      // the certificate is signed with the endorser's key, which pins it to the public key
+      // that the coordinator records in the view ledger
+      let der = rustls_pemfile::certs(&mut cert.as_slice())?;
+      match der.first() {
+        Some(der) => server = server.with_tls_certificate(der),
+        None => return Err("The TLS certificate file has no certificate".into()),
+      }
+      let tls_config =
+        ServerTlsConfig::new().identity(Identity::from_pem(cert, std::fs::read(key)?));
+      match cli_matches.value_of("tls_client_ca") {
+        Some(ca) => Some(tls_config.client_ca_root(Certificate::from_pem(std::fs::read(ca)?))),
+        None => Some(tls_config),
+      }
+    },
+    _ => None,
+  };
+  let fault_rules = match cli_matches.values_of("fault") {
+    Some(faults) => faults
+      .map(FaultRule::parse)
+      .collect::<Result<Vec<FaultRule>, String>>()?,
+    None => Vec::new(),
+  };
+
+  if let Some(metrics_port) = cli_matches.value_of("metrics") {
+    let metrics_addr = format!("{}:{}", hostname, metrics_port).parse()?;
+    let app = Router::new().route("/metrics", get(|| async { metrics::render() }));
+    let _metrics = tokio::spawn(async move {
+      println!("Serving metrics at {:?}", metrics_addr);
+      let _ = axum::Server::bind(&metrics_addr)
+        .serve(app.into_make_service())
+        .await;
+    });
+  }
 
+  // The socket is bound before the endorser reports that it is listening, so that callers
+  // waiting for the report can connect right away
+  let incoming =
+    TcpIncoming::new(addr, true, None).map_err(|e| -> Box<dyn std::error::Error> { e })?;
   let job = tokio::spawn(async move {
     println!("Endorser host listening on {:?}", addr);
 
-    let _ = Server::builder()
-      .add_service(EndorserCallServer::new(server))
-      .serve(addr)
-      .await;
+    let mut builder = match tls_config {
+      Some(tls_config) => match Server::builder().tls_config(tls_config) {
+        Ok(builder) => builder,
+        Err(error) => {
+          eprintln!("Invalid TLS configuration: {:?}", error);
+          return;
+        },
+      },
+      None => Server::builder(),
+    };
+    let _ = if fault_rules.is_empty() {
+      builder
+        .add_service(EndorserCallServer::new(MeteredEndorserService::new(server)))
+        .serve_with_incoming(incoming)
+        .await
+    } else {
+      warn!("Injecting faults {:?}", fault_rules);
+      builder
+        .add_service(EndorserCallServer::new(MeteredEndorserService::new(
+          FaultyEndorserService::new(server, fault_rules),
+        )))
+        .serve_with_incoming(incoming)
+        .await
+    };
   });
 
   job.await?;
+  telemetry::shutdown();
 
   Ok(())
 }
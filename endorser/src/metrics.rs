@@ -0,0 +1,163 @@
+use lazy_static::lazy_static;
+use ledger::endorser_proto::{
+  endorser_call_server::EndorserCall, ActivateReq, ActivateResp, AdvanceViewReq, AdvanceViewResp,
+  AppendBatchReq, AppendBatchResp, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp,
+  FinalizeStateReq, FinalizeStateResp, GetPublicKeyReq, GetPublicKeyResp, InitializeStateReq,
+  InitializeStateResp, NewLedgerReq, NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadStateReq,
+  ReadStateResp,
+};
+use prometheus::{
+  register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+  IntCounterVec, IntGauge, TextEncoder,
+};
+use std::future::Future;
+use tonic::{Code, Request, Response, Status};
+use tracing::{debug, error, field, info_span, Instrument};
+
+lazy_static! {
+  static ref REQUESTS: IntCounterVec = register_int_counter_vec!(
+    "nimble_endorser_requests_total",
+    "The number of requests served by the endorser, by RPC and gRPC code",
+    &["rpc", "code"]
+  )
+  .unwrap();
+  static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+    "nimble_endorser_request_duration_seconds",
+    "The latency of requests served by the endorser",
+    &["rpc"]
+  )
+  .unwrap();
+  pub static ref TAIL_MAP_SIZE: IntGauge = register_int_gauge!(
+    "nimble_endorser_tail_map_size",
+    "The number of ledgers whose tails the endorser holds"
+  )
+  .unwrap();
+}
+
+/// Renders the metrics of the default registry in the Prometheus text format
+pub fn render() -> String {
+  let mut buffer = Vec::new();
+  if let Err(error) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+    error!("Failed to encode the metrics ({:?})", error);
+  }
+  String::from_utf8(buffer).unwrap_or_default()
+}
+
+// Serves a call in a span that carries the caller's request ID, and records its code and
+// latency
+async fn observe<R, T, F, Fut>(rpc: &str, req: Request<R>, call: F) -> Result<Response<T>, Status>
+where
+  F: FnOnce(Request<R>) -> Fut,
+  Fut: Future<Output = Result<Response<T>, Status>>,
+{
+  let span = info_span!("rpc", otel.name = rpc, rpc, request_id = field::Empty);
+  let request_id = telemetry::accept(req.metadata(), &span);
+
+  let timer = REQUEST_DURATION.with_label_values(&[rpc]).start_timer();
+  let res = telemetry::scope(request_id, call(req).instrument(span.clone())).await;
+  timer.observe_duration();
+  let code = match &res {
+    Ok(_) => Code::Ok,
+    Err(status) => status.code(),
+  };
+  REQUESTS
+    .with_label_values(&[rpc, &format!("{:?}", code)])
+    .inc();
+  debug!(parent: &span, ?code, "served {}", rpc);
+  res
+}
+
+/// An endorser service that records the number and latency of the calls it forwards to
+/// another one, each in a span that carries the request ID of the caller
+pub struct MeteredEndorserService<T: EndorserCall> {
+  service: T,
+}
+
+impl<T: EndorserCall> MeteredEndorserService<T> {
+  pub fn new(service: T) -> Self {
+    MeteredEndorserService { service }
+  }
+}
+
+#[tonic::async_trait]
+impl<T: EndorserCall> EndorserCall for MeteredEndorserService<T> {
+  async fn get_public_key(
+    &self,
+    req: Request<GetPublicKeyReq>,
+  ) -> Result<Response<GetPublicKeyResp>, Status> {
+    observe("get_public_key", req, |req| {
+      self.service.get_public_key(req)
+    })
+    .await
+  }
+
+  async fn new_ledger(
+    &self,
+    req: Request<NewLedgerReq>,
+  ) -> Result<Response<NewLedgerResp>, Status> {
+    observe("new_ledger", req, |req| self.service.new_ledger(req)).await
+  }
+
+  async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+    observe("append", req, |req| self.service.append(req)).await
+  }
+
+  async fn append_batch(
+    &self,
+    req: Request<AppendBatchReq>,
+  ) -> Result<Response<AppendBatchResp>, Status> {
+    observe("append_batch", req, |req| self.service.append_batch(req)).await
+  }
+
+  async fn delete_ledger(
+    &self,
+    req: Request<DeleteLedgerReq>,
+  ) -> Result<Response<DeleteLedgerResp>, Status> {
+    observe("delete_ledger", req, |req| self.service.delete_ledger(req)).await
+  }
+
+  async fn read_latest(
+    &self,
+    req: Request<ReadLatestReq>,
+  ) -> Result<Response<ReadLatestResp>, Status> {
+    observe("read_latest", req, |req| self.service.read_latest(req)).await
+  }
+
+  async fn finalize_state(
+    &self,
+    req: Request<FinalizeStateReq>,
+  ) -> Result<Response<FinalizeStateResp>, Status> {
+    observe("finalize_state", req, |req| {
+      self.service.finalize_state(req)
+    })
+    .await
+  }
+
+  async fn advance_view(
+    &self,
+    req: Request<AdvanceViewReq>,
+  ) -> Result<Response<AdvanceViewResp>, Status> {
+    observe("advance_view", req, |req| self.service.advance_view(req)).await
+  }
+
+  async fn initialize_state(
+    &self,
+    req: Request<InitializeStateReq>,
+  ) -> Result<Response<InitializeStateResp>, Status> {
+    observe("initialize_state", req, |req| {
+      self.service.initialize_state(req)
+    })
+    .await
+  }
+
+  async fn read_state(
+    &self,
+    req: Request<ReadStateReq>,
+  ) -> Result<Response<ReadStateResp>, Status> {
+    observe("read_state", req, |req| self.service.read_state(req)).await
+  }
+
+  async fn activate(&self, req: Request<ActivateReq>) -> Result<Response<ActivateResp>, Status> {
+    observe("activate", req, |req| self.service.activate(req)).await
+  }
+}
@@ -4,6 +4,14 @@ pub enum EndpointError {
   CoordinatorHostNameNotFound,
   /// returned if the endpoint fails to connect to the Coordinator while creating a channel
   UnableToConnectToCoordinator,
+  /// returned if no coordinator could be reached, or none of them is the leader
+  CoordinatorUnavailable,
+  /// returned if the TLS configuration for connecting to the coordinators is invalid
+  InvalidTlsConfig,
+  /// returned if the coordinator does not authorize the endpoint to make the call
+  NotAuthorized,
+  /// returned if the private key of the endpoint is invalid
+  InvalidPrivateKey,
   /// returned if the endpoint fails to create a new counter
   FailedToCreateNewCounter,
   /// returned if the endpoint fails to verify a new counter
@@ -18,6 +26,14 @@ pub enum EndpointError {
   FailedToReadCounter,
   /// returned if the endpoint fails to verify the read counter
   FaieldToVerifyReadCounter,
+  /// returned if the endpoint fails to watch a counter
+  FailedToWatchCounter,
+  /// returned if the endpoint fails to verify an update of a watched counter
+  FailedToVerifyCounterUpdate,
+  /// returned if an update of a watched counter skips or repeats a value of the counter
+  UnexpectedCounterUpdate,
+  /// returned if the endpoint fails to delete a ledger
+  FailedToDeleteLedger,
   /// returned if the endpoint fails to read the view ledger
   FailedToReadViewLedger,
   /// returned if the endpoint fails to acquire the read lock
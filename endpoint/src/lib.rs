@@ -1,30 +1,52 @@
 mod errors;
+pub mod metrics;
 
 use tonic::{
   transport::{Channel, Endpoint},
-  Request,
+  Code, Request, Response, Status, Streaming,
 };
 
+pub use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
 #[allow(clippy::derive_partial_eq_without_eq)]
 pub mod coordinator_proto {
   tonic::include_proto!("coordinator_proto");
 }
 
-use crate::errors::EndpointError;
+pub use crate::errors::EndpointError;
 use coordinator_proto::{
-  call_client::CallClient, AppendReq, AppendResp, NewLedgerReq, NewLedgerResp, ReadLatestReq,
-  ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp, ReadViewTailReq, ReadViewTailResp,
+  call_client::CallClient, AppendReq, AppendResp, DeleteLedgerReq, DeleteLedgerResp, NewLedgerReq,
+  NewLedgerResp, ReadLatestReq, ReadLatestResp, ReadViewByIndexReq, ReadViewByIndexResp,
+  ReadViewTailReq, ReadViewTailResp, WatchReq, WatchResp,
+};
+use futures::{
+  future::join_all,
+  stream::{BoxStream, StreamExt},
 };
 use ledger::{
   errors::VerificationError,
   signature::{PrivateKey, PrivateKeyTrait, PublicKey, PublicKeyTrait, Signature, SignatureTrait},
-  Block, CustomSerde, NimbleDigest, NimbleHashTrait, VerifierState,
+  Block, CustomSerde, NimbleDigest, NimbleHashTrait, Receipts, VerifierState,
 };
 use rand::random;
 use std::{
   convert::TryFrom,
-  sync::{Arc, RwLock},
+  future::Future,
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, RwLock,
+  },
+  time::Duration,
 };
+use tracing::{error, info, instrument};
+
+// Records that a response of the coordinator failed verification
+fn verification_failure(op: &str, error: EndpointError) -> EndpointError {
+  metrics::VERIFICATION_FAILURES
+    .with_label_values(&[op])
+    .inc();
+  error
+}
 
 #[allow(dead_code)]
 enum MessageType {
@@ -37,53 +59,258 @@ enum MessageType {
 }
 
 const DEFAULT_NUM_GRPC_CHANNELS: usize = 1;
+const COORDINATOR_CONNECT_TIMEOUT: u64 = 5; // seconds: the connect timeout to coordinators
+const HEALTH_CHECK_INTERVAL: u64 = 5; // seconds: the time between two health checks
+const HEALTH_CHECK_TIMEOUT: u64 = 2; // seconds: the time a coordinator has to answer a health check
+const WRITE_ATTEMPTS: u64 = 3; // the number of times a write is sent while its outcome is unknown
+const WRITE_RETRY_DELAY: u64 = 200; // milliseconds: the delay before a write is sent again, per attempt
+const REQUEST_ID_SIZE: usize = 16; // bytes: the size of the request IDs of writes
+
+#[derive(Debug)]
+struct Coordinator {
+  uri: String,
+  clients: Vec<CallClient<Channel>>,
+  // whether the coordinator served its latest health check or request
+  healthy: AtomicBool,
+}
+
+impl Coordinator {
+  fn client(&self) -> CallClient<Channel> {
+    self.clients[random::<usize>() % self.clients.len()].clone()
+  }
+}
+
+// Marks the coordinators that serve a cheap read as healthy; a coordinator that is down, or
+// is a standby, is not
+async fn check_health(coordinators: &[Coordinator]) -> usize {
+  let checks = coordinators.iter().map(|c| async move {
+    let res = tokio::time::timeout(
+      Duration::from_secs(HEALTH_CHECK_TIMEOUT),
+      c.client().read_view_tail(Request::new(ReadViewTailReq {})),
+    )
+    .await;
+    let healthy = matches!(res, Ok(Ok(_)));
+    if c.healthy.swap(healthy, Ordering::SeqCst) != healthy {
+      info!(
+        "Coordinator {} is {}",
+        c.uri,
+        if healthy { "healthy" } else { "unhealthy" }
+      );
+    }
+    healthy
+  });
+  join_all(checks).await.into_iter().filter(|h| *h).count()
+}
+
+// errors that another coordinator may not run into, so that a read is sent to it instead
+fn is_transient(code: Code) -> bool {
+  matches!(
+    code,
+    Code::Unavailable
+      | Code::DeadlineExceeded
+      | Code::Aborted
+      | Code::Unknown
+      | Code::Internal
+      | Code::Cancelled
+      | Code::ResourceExhausted
+  )
+}
+
+// errors after which a write may or may not have taken effect, so that it is sent again with
+// the same request ID
+fn is_uncertain(code: Code) -> bool {
+  matches!(
+    code,
+    Code::Unavailable | Code::DeadlineExceeded | Code::Unknown | Code::Cancelled
+  )
+}
+
+// Maps the status of a failed request to the error of the operation, unless no coordinator
+// could serve the request, in which case it may succeed later, or the coordinator refused it
+fn to_endpoint_error(status: &Status, error: EndpointError) -> EndpointError {
+  match status.code() {
+    Code::Unavailable => EndpointError::CoordinatorUnavailable,
+    Code::PermissionDenied | Code::Unauthenticated => EndpointError::NotAuthorized,
+    _ => error,
+  }
+}
 
 #[derive(Debug, Clone)]
 pub struct Connection {
-  clients: Vec<CallClient<Channel>>,
-  num_grpc_channels: usize,
+  // the coordinators in the order their URIs were given
+  coordinators: Arc<Vec<Coordinator>>,
+  // the coordinator that served the latest request, which requests are sent to first
+  leader: Arc<AtomicUsize>,
 }
 
 impl Connection {
+  /// Connects to the coordinators whose URIs are separated by commas in
+  /// `coordinator_endpoint_address`, over TLS if `tls` is given
   pub async fn new(
     coordinator_endpoint_address: String,
     num_grpc_channels_opt: Option<usize>,
+    tls: Option<ClientTlsConfig>,
+  ) -> Result<Self, EndpointError> {
+    let uris = coordinator_endpoint_address
+      .split(',')
+      .map(|u| u.trim().to_string())
+      .filter(|u| !u.is_empty())
+      .collect::<Vec<String>>();
+    Self::with_coordinators(&uris, num_grpc_channels_opt, tls).await
+  }
+
+  /// Connects to several coordinators, of which only the leader serves if they elect one.
+  /// Requests go to the coordinator that served last, then to the healthy ones, and then to
+  /// the rest. A read moves on to the next coordinator after any error that may be
+  /// transient, and a write only if the coordinator turned it away or could not be reached.
+  /// The coordinators are checked in the background for as long as the connection is kept.
+  pub async fn with_coordinators(
+    uris: &[String],
+    num_grpc_channels_opt: Option<usize>,
+    tls: Option<ClientTlsConfig>,
   ) -> Result<Self, EndpointError> {
     let num_grpc_channels = match num_grpc_channels_opt {
-      Some(n) => n,
-      None => DEFAULT_NUM_GRPC_CHANNELS,
-    };
-    let mut clients = Vec::new();
-    for _idx in 0..num_grpc_channels {
-      let connection_attempt = Endpoint::from_shared(coordinator_endpoint_address.clone());
-      let connection = match connection_attempt {
-        Ok(connection) => connection,
-        Err(_err) => return Err(EndpointError::CoordinatorHostNameNotFound),
-      };
-      let channel = connection.connect_lazy();
-      let client = CallClient::new(channel);
-      clients.push(client);
+      Some(n) if n > 0 => n,
+      _ => DEFAULT_NUM_GRPC_CHANNELS,
+    };
+    if uris.is_empty() {
+      return Err(EndpointError::CoordinatorHostNameNotFound);
+    }
+    let mut coordinators = Vec::new();
+    for uri in uris {
+      let mut clients = Vec::new();
+      for _idx in 0..num_grpc_channels {
+        let connection_attempt = Endpoint::from_shared(uri.clone());
+        let mut connection = match connection_attempt {
+          Ok(connection) => {
+            connection.connect_timeout(Duration::from_secs(COORDINATOR_CONNECT_TIMEOUT))
+          },
+          Err(_err) => return Err(EndpointError::CoordinatorHostNameNotFound),
+        };
+        if let Some(tls) = &tls {
+          connection = connection.tls_config(tls.clone()).map_err(|e| {
+            error!("Failed to set up TLS to the coordinator {} {:?}", uri, e);
+            EndpointError::InvalidTlsConfig
+          })?;
+        }
+        let channel = connection.connect_lazy();
+        let client = CallClient::new(channel);
+        clients.push(client);
+      }
+      coordinators.push(Coordinator {
+        uri: uri.clone(),
+        clients,
+        healthy: AtomicBool::new(true),
+      });
     }
+
+    let coordinators = Arc::new(coordinators);
+    check_health(&coordinators).await;
+    let checked = Arc::downgrade(&coordinators);
+    tokio::spawn(async move {
+      let mut interval = tokio::time::interval(Duration::from_secs(HEALTH_CHECK_INTERVAL));
+      interval.tick().await;
+      loop {
+        interval.tick().await;
+        // the checks stop once the connection is dropped
+        match checked.upgrade() {
+          Some(coordinators) => check_health(&coordinators).await,
+          None => break,
+        };
+      }
+    });
+
     Ok(Self {
-      clients,
-      num_grpc_channels,
+      coordinators,
+      leader: Arc::new(AtomicUsize::new(0)),
     })
   }
 
+  /// Returns the URIs of the coordinators that served their latest health check or request
+  pub fn get_healthy_coordinators(&self) -> Vec<String> {
+    self
+      .coordinators
+      .iter()
+      .filter(|c| c.healthy.load(Ordering::SeqCst))
+      .map(|c| c.uri.clone())
+      .collect()
+  }
+
+  // the order in which a request tries the coordinators
+  fn coordinator_order(&self) -> Vec<usize> {
+    let num = self.coordinators.len();
+    let first = self.leader.load(Ordering::SeqCst) % num;
+    let mut order = (0..num).map(|i| (first + i) % num).collect::<Vec<usize>>();
+    // the sort is stable, so the coordinator that served last stays ahead of the others
+    order
+      .sort_by_key(|idx| *idx != first && !self.coordinators[*idx].healthy.load(Ordering::SeqCst));
+    order
+  }
+
+  // Sends a request to the coordinators in turn until one serves it or fails it with an
+  // error that `retry` does not allow to be retried
+  async fn call<T, F, Fut>(&self, retry: fn(Code) -> bool, rpc: F) -> Result<T, Status>
+  where
+    F: Fn(CallClient<Channel>) -> Fut,
+    Fut: Future<Output = Result<Response<T>, Status>>,
+  {
+    let mut last_status = Status::unavailable("No coordinator is available");
+    for idx in self.coordinator_order() {
+      let coordinator = &self.coordinators[idx];
+      match rpc(coordinator.client()).await {
+        Ok(resp) => {
+          coordinator.healthy.store(true, Ordering::SeqCst);
+          self.leader.store(idx, Ordering::SeqCst);
+          return Ok(resp.into_inner());
+        },
+        Err(status) if retry(status.code()) => {
+          if status.code() == Code::Unavailable {
+            coordinator.healthy.store(false, Ordering::SeqCst);
+          }
+          last_status = status;
+        },
+        Err(status) => return Err(status),
+      }
+    }
+    Err(last_status)
+  }
+
+  // Sends a write that carries a request ID to the coordinators in turn, and sends it again
+  // after a short delay while its outcome is unknown. The coordinator answers a write that
+  // took effect already with the receipts of the one that did.
+  async fn call_with_retries<T, F, Fut>(&self, rpc: F) -> Result<T, Status>
+  where
+    F: Fn(CallClient<Channel>) -> Fut,
+    Fut: Future<Output = Result<Response<T>, Status>>,
+  {
+    let mut attempt = 1;
+    loop {
+      match self.call(is_uncertain, &rpc).await {
+        Err(status) if is_uncertain(status.code()) && attempt < WRITE_ATTEMPTS => {
+          tokio::time::sleep(Duration::from_millis(WRITE_RETRY_DELAY * attempt)).await;
+          attempt += 1;
+        },
+        res => return res,
+      }
+    }
+  }
+
   pub async fn new_ledger(&self, handle: &[u8], block: &[u8]) -> Result<Vec<u8>, EndpointError> {
-    let req = Request::new(NewLedgerReq {
+    let req = NewLedgerReq {
       handle: handle.to_vec(),
       block: block.to_vec(),
-    });
-    let NewLedgerResp { receipts } = self.clients[random::<usize>() % self.num_grpc_channels]
-      .clone()
-      .new_ledger(req)
+      request_id: random::<[u8; REQUEST_ID_SIZE]>().to_vec(),
+    };
+    let NewLedgerResp { receipts } = self
+      .call_with_retries(|mut client| {
+        let req = req.clone();
+        async move { client.new_ledger(telemetry::request(req)).await }
+      })
       .await
       .map_err(|e| {
-        eprintln!("Failed to create a new ledger {:?}", e);
-        EndpointError::FailedToCreateNewCounter
-      })?
-      .into_inner();
+        error!("Failed to create a new ledger {:?}", e);
+        to_endpoint_error(&e, EndpointError::FailedToCreateNewCounter)
+      })?;
     Ok(receipts)
   }
 
@@ -93,63 +320,116 @@ impl Connection {
     block: &[u8],
     expected_height: u64,
   ) -> Result<(Vec<u8>, Vec<u8>), EndpointError> {
-    let req = Request::new(AppendReq {
+    let req = AppendReq {
       handle: handle.to_vec(),
       block: block.to_vec(),
       expected_height,
-    });
+      request_id: random::<[u8; REQUEST_ID_SIZE]>().to_vec(),
+    };
     let AppendResp {
       hash_nonces,
       receipts,
-    } = self.clients[random::<usize>() % self.num_grpc_channels]
-      .clone()
-      .append(req)
+    } = self
+      .call_with_retries(|mut client| {
+        let req = req.clone();
+        async move { client.append(telemetry::request(req)).await }
+      })
       .await
       .map_err(|e| {
-        eprintln!("Failed to append to a ledger {:?}", e);
-        EndpointError::FailedToIncrementCounter
-      })?
-      .into_inner();
+        error!("Failed to append to a ledger {:?}", e);
+        to_endpoint_error(&e, EndpointError::FailedToIncrementCounter)
+      })?;
     Ok((hash_nonces, receipts))
   }
 
+  pub async fn delete_ledger(
+    &self,
+    handle: &[u8],
+    expected_height: u64,
+  ) -> Result<Vec<u8>, EndpointError> {
+    let req = DeleteLedgerReq {
+      handle: handle.to_vec(),
+      expected_height,
+    };
+    let DeleteLedgerResp { receipts } = self
+      .call(
+        |code| code == Code::Unavailable,
+        |mut client| {
+          let req = req.clone();
+          async move { client.delete_ledger(telemetry::request(req)).await }
+        },
+      )
+      .await
+      .map_err(|e| {
+        error!("Failed to delete a ledger {:?}", e);
+        to_endpoint_error(&e, EndpointError::FailedToDeleteLedger)
+      })?;
+    Ok(receipts)
+  }
+
   pub async fn read_latest(
     &self,
     handle: &[u8],
     nonce: &[u8],
   ) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), EndpointError> {
+    let req = ReadLatestReq {
+      handle: handle.to_vec(),
+      nonce: nonce.to_vec(),
+    };
     let ReadLatestResp {
       block,
       nonces,
       receipts,
-    } = self.clients[random::<usize>() % self.num_grpc_channels]
-      .clone()
-      .read_latest(ReadLatestReq {
-        handle: handle.to_vec(),
-        nonce: nonce.to_vec(),
+    } = self
+      .call(is_transient, |mut client| {
+        let req = req.clone();
+        async move { client.read_latest(telemetry::request(req)).await }
       })
       .await
       .map_err(|e| {
-        eprintln!("Failed to read a ledger {:?}", e);
-        EndpointError::FailedToReadCounter
-      })?
-      .into_inner();
+        error!("Failed to read a ledger {:?}", e);
+        to_endpoint_error(&e, EndpointError::FailedToReadCounter)
+      })?;
     Ok((block, nonces, receipts))
   }
 
+  /// Watches a ledger: the returned stream sends its entries from `from_height` on, each once
+  /// the endorsers signed it, and ends after its tombstone
+  pub async fn watch(
+    &self,
+    handle: &[u8],
+    from_height: u64,
+  ) -> Result<Streaming<WatchResp>, EndpointError> {
+    let req = WatchReq {
+      handle: handle.to_vec(),
+      from_height,
+    };
+    self
+      .call(is_transient, |mut client| {
+        let req = req.clone();
+        async move { client.watch(telemetry::request(req)).await }
+      })
+      .await
+      .map_err(|e| {
+        error!("Failed to watch a ledger {:?}", e);
+        to_endpoint_error(&e, EndpointError::FailedToWatchCounter)
+      })
+  }
+
   pub async fn read_view_by_index(
     &self,
     index: usize,
   ) -> Result<(Vec<u8>, Vec<u8>), EndpointError> {
-    let ReadViewByIndexResp { block, receipts } = self.clients
-      [random::<usize>() % self.num_grpc_channels]
-      .clone()
-      .read_view_by_index(ReadViewByIndexReq {
-        index: index as u64,
+    let req = ReadViewByIndexReq {
+      index: index as u64,
+    };
+    let ReadViewByIndexResp { block, receipts } = self
+      .call(is_transient, |mut client| {
+        let req = req.clone();
+        async move { client.read_view_by_index(telemetry::request(req)).await }
       })
       .await
-      .map_err(|_e| EndpointError::FailedToReadViewLedger)?
-      .into_inner();
+      .map_err(|e| to_endpoint_error(&e, EndpointError::FailedToReadViewLedger))?;
     Ok((block, receipts))
   }
 
@@ -159,16 +439,56 @@ impl Connection {
       receipts,
       height,
       attestations,
-    } = self.clients[random::<usize>() % self.num_grpc_channels]
-      .clone()
-      .read_view_tail(ReadViewTailReq {})
+    } = self
+      .call(is_transient, |mut client| async move {
+        client
+          .read_view_tail(telemetry::request(ReadViewTailReq {}))
+          .await
+      })
       .await
-      .map_err(|_e| EndpointError::FailedToReadViewLedger)?
-      .into_inner();
+      .map_err(|e| to_endpoint_error(&e, EndpointError::FailedToReadViewLedger))?;
     Ok((block, receipts, height as usize, attestations))
   }
 }
 
+/// The updates of a counter that the endpoint watches
+pub struct CounterWatch {
+  handle: Vec<u8>,
+  updates: BoxStream<'static, Result<WatchResp, Status>>,
+  // the value of the counter the next update must be for
+  next: u64,
+  // set once the counter is deleted, or the watch failed
+  done: bool,
+}
+
+impl CounterWatch {
+  // returns the next update from the coordinator, which must be for the next value of the
+  // counter, so that updates are neither skipped nor repeated
+  async fn next_update(&mut self) -> Result<Option<WatchResp>, EndpointError> {
+    match self.updates.next().await {
+      Some(Ok(update)) if update.height == self.next => {
+        self.next += 1;
+        Ok(Some(update))
+      },
+      Some(Ok(update)) => {
+        error!(
+          "Expected an update of a watched counter to {}, not {}",
+          self.next, update.height
+        );
+        Err(EndpointError::UnexpectedCounterUpdate)
+      },
+      Some(Err(status)) => {
+        error!("Failed to watch a counter {:?}", status);
+        Err(to_endpoint_error(
+          &status,
+          EndpointError::FailedToWatchCounter,
+        ))
+      },
+      None => Ok(None),
+    }
+  }
+}
+
 pub struct EndpointState {
   conn: Connection,
   id: NimbleDigest,
@@ -195,37 +515,43 @@ impl EndpointState {
     hostname: String,
     pem_opt: Option<String>,
     num_grpc_channels_opt: Option<usize>,
+    coordinator_tls: Option<ClientTlsConfig>,
   ) -> Result<Self, EndpointError> {
-    // make a connection to the coordinator
-    let conn = {
-      let res = Connection::new(hostname, num_grpc_channels_opt).await;
-
-      match res {
-        Ok(conn) => conn,
-        Err(e) => {
-          panic!("Endpoint Error: {:?}", e);
-        },
-      }
-    };
+    // make a connection to the coordinators
+    let conn = Connection::new(hostname, num_grpc_channels_opt, coordinator_tls).await?;
 
     // initialize id and vs
     let (id, vs) = {
       let mut vs = VerifierState::default();
 
-      let (block, _r) = conn.read_view_by_index(1usize).await.unwrap();
+      let (block, _r) = conn.read_view_by_index(1usize).await?;
 
       // the hash of the genesis block of the view ledger uniquely identifies a particular instance of NimbleLedger
-      let id = Block::from_bytes(&block).unwrap().hash();
+      let id = match Block::from_bytes(&block) {
+        Ok(b) => b.hash(),
+        Err(_) => return Err(EndpointError::FailedToReadViewLedger),
+      };
       vs.set_group_identity(id);
 
-      let (block, receipts, height, attestations) = conn.read_view_tail().await.unwrap();
-      let res = vs.apply_view_change(&block, &receipts, Some(&attestations));
-      assert!(res.is_ok());
+      let (block, receipts, height, attestations) = conn.read_view_tail().await?;
+      if vs
+        .apply_view_change(&block, &receipts, Some(&attestations))
+        .is_err()
+      {
+        return Err(verification_failure(
+          "view_change",
+          EndpointError::FailedToApplyViewChange,
+        ));
+      }
 
       for index in (1..height).rev() {
-        let (block, receipts) = conn.read_view_by_index(index).await.unwrap();
-        let res = vs.apply_view_change(&block, &receipts, None);
-        assert!(res.is_ok());
+        let (block, receipts) = conn.read_view_by_index(index).await?;
+        if vs.apply_view_change(&block, &receipts, None).is_err() {
+          return Err(verification_failure(
+            "view_change",
+            EndpointError::FailedToApplyViewChange,
+          ));
+        }
       }
 
       (id, vs)
@@ -233,11 +559,13 @@ impl EndpointState {
 
     // produce a private key pair to sign responses
     let sk = if let Some(pem) = pem_opt {
-      let res = PrivateKey::from_pem(pem.as_bytes());
-      if let Err(error) = res {
-        panic!("Endpoint Error: {:?}", error);
+      match PrivateKey::from_pem(pem.as_bytes()) {
+        Ok(sk) => sk,
+        Err(error) => {
+          error!("Failed to read the private key {:?}", error);
+          return Err(EndpointError::InvalidPrivateKey);
+        },
       }
-      res.unwrap()
     } else {
       PrivateKey::new()
     };
@@ -268,6 +596,7 @@ impl EndpointState {
     ))
   }
 
+  #[instrument(skip_all)]
   async fn update_view(&self) -> Result<(), EndpointError> {
     let start_height = {
       if let Ok(vs_rd) = self.vs.read() {
@@ -277,22 +606,28 @@ impl EndpointState {
       }
     };
 
-    let (block, receipts, height, attestations) = self.conn.read_view_tail().await.unwrap();
+    let (block, receipts, height, attestations) = self.conn.read_view_tail().await?;
     if let Ok(mut vs_wr) = self.vs.write() {
       let res = vs_wr.apply_view_change(&block, &receipts, Some(&attestations));
       if res.is_err() {
-        return Err(EndpointError::FailedToApplyViewChange);
+        return Err(verification_failure(
+          "view_change",
+          EndpointError::FailedToApplyViewChange,
+        ));
       }
     } else {
       return Err(EndpointError::FailedToAcquireWriteLock);
     }
 
     for index in (start_height..height).rev() {
-      let (block, receipts) = self.conn.read_view_by_index(index).await.unwrap();
+      let (block, receipts) = self.conn.read_view_by_index(index).await?;
       if let Ok(mut vs_wr) = self.vs.write() {
         let res = vs_wr.apply_view_change(&block, &receipts, None);
         if res.is_err() {
-          return Err(EndpointError::FailedToApplyViewChange);
+          return Err(verification_failure(
+            "view_change",
+            EndpointError::FailedToApplyViewChange,
+          ));
         }
       } else {
         return Err(EndpointError::FailedToAcquireWriteLock);
@@ -302,6 +637,7 @@ impl EndpointState {
     Ok(())
   }
 
+  #[instrument(skip_all)]
   pub async fn new_counter(
     &self,
     handle: &[u8],
@@ -329,13 +665,7 @@ impl EndpointState {
     };
 
     // issue a request to the coordinator and receive a response
-    let receipts = {
-      let res = self.conn.new_ledger(handle, &block).await;
-      if res.is_err() {
-        return Err(EndpointError::FailedToCreateNewCounter);
-      }
-      res.unwrap()
-    };
+    let receipts = self.conn.new_ledger(handle, &block).await?;
 
     // verify the response received from the coordinator;
     let res = {
@@ -348,11 +678,16 @@ impl EndpointState {
 
     if res.is_err() {
       if res.unwrap_err() != VerificationError::ViewNotFound {
-        return Err(EndpointError::FailedToVerifyNewCounter);
+        return Err(verification_failure(
+          "new_counter",
+          EndpointError::FailedToVerifyNewCounter,
+        ));
       } else {
-        let res = self.update_view().await;
-        if res.is_err() {
-          return Err(EndpointError::FailedToVerifyNewCounter);
+        if let Err(error) = self.update_view().await {
+          return Err(match error {
+            EndpointError::CoordinatorUnavailable => error,
+            _ => EndpointError::FailedToVerifyNewCounter,
+          });
         }
         let res = {
           if let Ok(vs_rd) = self.vs.read() {
@@ -362,8 +697,11 @@ impl EndpointState {
           }
         };
         if res.is_err() {
-          eprintln!("failed to create a new counter {:?}", res);
-          return Err(EndpointError::FailedToVerifyNewCounter);
+          error!("failed to create a new counter {:?}", res);
+          return Err(verification_failure(
+            "new_counter",
+            EndpointError::FailedToVerifyNewCounter,
+          ));
         }
       }
     }
@@ -389,6 +727,7 @@ impl EndpointState {
     Ok(signature)
   }
 
+  #[instrument(skip_all)]
   pub async fn increment_counter(
     &self,
     handle: &[u8],
@@ -425,14 +764,7 @@ impl EndpointState {
     };
 
     // issue a request to the coordinator and receive a response
-    let (hash_nonces, receipts) = {
-      let res = self.conn.append(handle, &block, expected_counter).await;
-
-      if res.is_err() {
-        return Err(EndpointError::FailedToIncrementCounter);
-      }
-      res.unwrap()
-    };
+    let (hash_nonces, receipts) = self.conn.append(handle, &block, expected_counter).await?;
 
     // verify the response received from the coordinator; TODO: handle the case where vs does not have the returned view hash
     let res = {
@@ -444,11 +776,16 @@ impl EndpointState {
     };
     if res.is_err() {
       if res.unwrap_err() != VerificationError::ViewNotFound {
-        return Err(EndpointError::FailedToVerifyIncrementedCounter);
+        return Err(verification_failure(
+          "increment_counter",
+          EndpointError::FailedToVerifyIncrementedCounter,
+        ));
       } else {
-        let res = self.update_view().await;
-        if res.is_err() {
-          return Err(EndpointError::FailedToVerifyIncrementedCounter);
+        if let Err(error) = self.update_view().await {
+          return Err(match error {
+            EndpointError::CoordinatorUnavailable => error,
+            _ => EndpointError::FailedToVerifyIncrementedCounter,
+          });
         }
         let res = {
           if let Ok(vs_rd) = self.vs.read() {
@@ -458,8 +795,11 @@ impl EndpointState {
           }
         };
         if res.is_err() {
-          eprintln!("failed to increment a counter {:?}", res);
-          return Err(EndpointError::FailedToVerifyIncrementedCounter);
+          error!("failed to increment a counter {:?}", res);
+          return Err(verification_failure(
+            "increment_counter",
+            EndpointError::FailedToVerifyIncrementedCounter,
+          ));
         }
       }
     }
@@ -485,6 +825,7 @@ impl EndpointState {
     Ok(signature)
   }
 
+  #[instrument(skip_all)]
   pub async fn read_counter(
     &self,
     handle: &[u8],
@@ -492,14 +833,7 @@ impl EndpointState {
     sigformat: SignatureFormat,
   ) -> Result<(Vec<u8>, u64, Vec<u8>), EndpointError> {
     // issue a request to the coordinator and receive a response
-    let (block, nonces, receipts) = {
-      let res = self.conn.read_latest(handle, nonce).await;
-
-      if res.is_err() {
-        return Err(EndpointError::FailedToReadCounter);
-      }
-      res.unwrap()
-    };
+    let (block, nonces, receipts) = self.conn.read_latest(handle, nonce).await?;
 
     // verify the response received from the coordinator
     let res = {
@@ -512,11 +846,16 @@ impl EndpointState {
     let counter = {
       if res.is_err() {
         if res.unwrap_err() != VerificationError::ViewNotFound {
-          return Err(EndpointError::FaieldToVerifyReadCounter);
+          return Err(verification_failure(
+            "read_counter",
+            EndpointError::FaieldToVerifyReadCounter,
+          ));
         } else {
-          let res = self.update_view().await;
-          if res.is_err() {
-            return Err(EndpointError::FaieldToVerifyReadCounter);
+          if let Err(error) = self.update_view().await {
+            return Err(match error {
+              EndpointError::CoordinatorUnavailable => error,
+              _ => EndpointError::FaieldToVerifyReadCounter,
+            });
           }
           let res = {
             if let Ok(vs_rd) = self.vs.read() {
@@ -526,7 +865,10 @@ impl EndpointState {
             }
           };
           if res.is_err() {
-            return Err(EndpointError::FaieldToVerifyReadCounter);
+            return Err(verification_failure(
+              "read_counter",
+              EndpointError::FaieldToVerifyReadCounter,
+            ));
           } else {
             res.unwrap()
           }
@@ -538,7 +880,10 @@ impl EndpointState {
 
     // verify the integrity of the coordinator's response by checking the signature
     if block.len() < Signature::num_bytes() {
-      return Err(EndpointError::FaieldToVerifyReadCounter);
+      return Err(verification_failure(
+        "read_counter",
+        EndpointError::FaieldToVerifyReadCounter,
+      ));
     }
     let (tag, sig) = {
       let (t, s) = block.split_at(block.len() - Signature::num_bytes());
@@ -564,7 +909,10 @@ impl EndpointState {
     };
 
     if sig.verify(&self.pk, &msg.to_bytes()).is_err() {
-      return Err(EndpointError::FaieldToVerifyReadCounter);
+      return Err(verification_failure(
+        "read_counter",
+        EndpointError::FaieldToVerifyReadCounter,
+      ));
     }
 
     // sign a message to the client that unequivocally identifies the counter and tag
@@ -589,4 +937,611 @@ impl EndpointState {
     // respond to the light client
     Ok((tag.to_vec(), counter as u64, signature))
   }
+
+  /// Watches the counter with the handle, from the value `from_counter` on
+  #[instrument(skip_all)]
+  pub async fn watch_counter(
+    &self,
+    handle: &[u8],
+    from_counter: u64,
+  ) -> Result<CounterWatch, EndpointError> {
+    let updates = self.conn.watch(handle, from_counter).await?;
+    Ok(CounterWatch {
+      handle: handle.to_vec(),
+      updates: updates.boxed(),
+      next: from_counter,
+      done: false,
+    })
+  }
+
+  /// Returns the next update of a watched counter once it is verified, as its tag, its value,
+  /// and the signature the endpoint returned to the call that made the update. Nothing is
+  /// returned once the counter is deleted, or after an error.
+  pub async fn next_counter_update(
+    &self,
+    watch: &mut CounterWatch,
+    sigformat: SignatureFormat,
+  ) -> Option<Result<(Vec<u8>, u64, Vec<u8>), EndpointError>> {
+    if watch.done {
+      return None;
+    }
+    let res = match watch.next_update().await {
+      Ok(Some(update)) => {
+        self
+          .verify_counter_update(&watch.handle, update, sigformat)
+          .await
+      },
+      Ok(None) => Ok(None),
+      Err(error) => Err(error),
+    };
+    match res {
+      Ok(Some(update)) => Some(Ok(update)),
+      Ok(None) => {
+        watch.done = true;
+        None
+      },
+      Err(error) => {
+        watch.done = true;
+        Some(Err(error))
+      },
+    }
+  }
+
+  // Verifies an entry of a watched counter's ledger, and signs it unless it is the tombstone
+  async fn verify_counter_update(
+    &self,
+    handle: &[u8],
+    update: WatchResp,
+    sigformat: SignatureFormat,
+  ) -> Result<Option<(Vec<u8>, u64, Vec<u8>)>, EndpointError> {
+    let WatchResp {
+      height: counter,
+      block,
+      hash_nonces,
+      receipts,
+    } = update;
+    let height = usize::try_from(counter).map_err(|_| EndpointError::FailedToConvertCounter)?;
+    // a tombstone is told by its receipts, which sign it rather than an entry
+    let deleted = Receipts::from_bytes(&receipts)
+      .map(|receipts| receipts.is_tombstone())
+      .unwrap_or(false);
+
+    // verify the entry received from the coordinator
+    let verify = |vs: &VerifierState| {
+      if deleted {
+        vs.verify_delete_ledger(handle, height, &receipts)
+      } else if height == 0 {
+        vs.verify_new_ledger(handle, &block, &receipts)
+      } else {
+        vs.verify_append(handle, &block, &hash_nonces, height, &receipts)
+      }
+    };
+    let res = match self.vs.read() {
+      Ok(vs_rd) => verify(&vs_rd),
+      Err(_) => return Err(EndpointError::FailedToAcquireReadLock),
+    };
+    if let Err(error) = res {
+      if error != VerificationError::ViewNotFound {
+        return Err(verification_failure(
+          "watch_counter",
+          EndpointError::FailedToVerifyCounterUpdate,
+        ));
+      }
+      if let Err(error) = self.update_view().await {
+        return Err(match error {
+          EndpointError::CoordinatorUnavailable => error,
+          _ => EndpointError::FailedToVerifyCounterUpdate,
+        });
+      }
+      let res = match self.vs.read() {
+        Ok(vs_rd) => verify(&vs_rd),
+        Err(_) => return Err(EndpointError::FailedToAcquireReadLock),
+      };
+      if res.is_err() {
+        error!("failed to verify a counter update {:?}", res);
+        return Err(verification_failure(
+          "watch_counter",
+          EndpointError::FailedToVerifyCounterUpdate,
+        ));
+      }
+    }
+    if deleted {
+      return Ok(None);
+    }
+
+    // verify that the endpoint made the update, by checking the signature in the block
+    if block.len() < Signature::num_bytes() {
+      return Err(verification_failure(
+        "watch_counter",
+        EndpointError::FailedToVerifyCounterUpdate,
+      ));
+    }
+    let (tag, sig) = block.split_at(block.len() - Signature::num_bytes());
+    let sig = Signature::from_bytes(sig).unwrap();
+    let (req_type, resp_type) = if counter == 0 {
+      (MessageType::NewCounterReq, MessageType::NewCounterResp)
+    } else {
+      (
+        MessageType::IncrementCounterReq,
+        MessageType::IncrementCounterResp,
+      )
+    };
+
+    let msg = {
+      let s = format!(
+        "{}.{}.{}.{}.{}",
+        base64_url::encode(&(req_type as u64).to_le_bytes()),
+        base64_url::encode(&self.id.to_bytes()),
+        base64_url::encode(handle),
+        base64_url::encode(&counter.to_le_bytes()),
+        base64_url::encode(&tag),
+      );
+      NimbleDigest::digest(s.as_bytes())
+    };
+    if sig.verify(&self.pk, &msg.to_bytes()).is_err() {
+      return Err(verification_failure(
+        "watch_counter",
+        EndpointError::FailedToVerifyCounterUpdate,
+      ));
+    }
+
+    // sign the same message as the response to the call that made the update
+    let msg = {
+      let s = format!(
+        "{}.{}.{}.{}.{}",
+        base64_url::encode(&(resp_type as u64).to_le_bytes()),
+        base64_url::encode(&self.id.to_bytes()),
+        base64_url::encode(handle),
+        base64_url::encode(&counter.to_le_bytes()),
+        base64_url::encode(&tag),
+      );
+      NimbleDigest::digest(s.as_bytes())
+    };
+    let sig = self.sk.sign(&msg.to_bytes()).unwrap();
+    let signature = match sigformat {
+      SignatureFormat::DER => sig.to_der(),
+      _ => sig.to_bytes(),
+    };
+
+    Ok(Some((tag.to_vec(), counter, signature)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use coordinator_proto::{
+    call_server::{Call, CallServer},
+    ReadByIndexReq, ReadByIndexResp,
+  };
+  use futures::channel::oneshot;
+  use std::sync::Mutex;
+  use tonic::transport::{server::TcpIncoming, Server};
+
+  // the requests a coordinator was sent, by method, along with their request IDs
+  type Requests = Arc<Mutex<Vec<(&'static str, Vec<u8>)>>>;
+
+  // A coordinator that answers every request with its name, or fails it with the status code
+  // it is told to, and records the request IDs of the requests it is sent
+  #[derive(Clone)]
+  struct FakeCoordinator {
+    name: &'static str,
+    failure: Arc<Mutex<Option<Code>>>,
+    requests: Requests,
+  }
+
+  impl FakeCoordinator {
+    fn new(name: &'static str) -> Self {
+      FakeCoordinator {
+        name,
+        failure: Arc::new(Mutex::new(None)),
+        requests: Arc::new(Mutex::new(Vec::new())),
+      }
+    }
+
+    fn fail_with(&self, code: Option<Code>) {
+      *self.failure.lock().unwrap() = code;
+    }
+
+    // the request IDs of the requests of `method` the coordinator was sent
+    fn requests(&self, method: &str) -> Vec<Vec<u8>> {
+      let requests = self.requests.lock().unwrap();
+      requests
+        .iter()
+        .filter(|(m, _id)| *m == method)
+        .map(|(_m, id)| id.clone())
+        .collect()
+    }
+
+    fn serve(&self, method: &'static str, request_id: &[u8]) -> Result<Vec<u8>, Code> {
+      self
+        .requests
+        .lock()
+        .unwrap()
+        .push((method, request_id.to_vec()));
+      match *self.failure.lock().unwrap() {
+        Some(code) => Err(code),
+        None => Ok(self.name.as_bytes().to_vec()),
+      }
+    }
+  }
+
+  fn injected(code: Code) -> Status {
+    Status::new(code, "injected")
+  }
+
+  #[tonic::async_trait]
+  impl Call for FakeCoordinator {
+    async fn new_ledger(
+      &self,
+      req: Request<NewLedgerReq>,
+    ) -> Result<Response<NewLedgerResp>, Status> {
+      let receipts = self
+        .serve("new_ledger", &req.get_ref().request_id)
+        .map_err(injected)?;
+      Ok(Response::new(NewLedgerResp { receipts }))
+    }
+
+    async fn append(&self, req: Request<AppendReq>) -> Result<Response<AppendResp>, Status> {
+      let receipts = self
+        .serve("append", &req.get_ref().request_id)
+        .map_err(injected)?;
+      Ok(Response::new(AppendResp {
+        hash_nonces: Vec::new(),
+        receipts,
+      }))
+    }
+
+    async fn delete_ledger(
+      &self,
+      _req: Request<DeleteLedgerReq>,
+    ) -> Result<Response<DeleteLedgerResp>, Status> {
+      let receipts = self.serve("delete_ledger", &[]).map_err(injected)?;
+      Ok(Response::new(DeleteLedgerResp { receipts }))
+    }
+
+    async fn read_latest(
+      &self,
+      _req: Request<ReadLatestReq>,
+    ) -> Result<Response<ReadLatestResp>, Status> {
+      let block = self.serve("read_latest", &[]).map_err(injected)?;
+      Ok(Response::new(ReadLatestResp {
+        block,
+        nonces: Vec::new(),
+        receipts: Vec::new(),
+      }))
+    }
+
+    async fn read_by_index(
+      &self,
+      _req: Request<ReadByIndexReq>,
+    ) -> Result<Response<ReadByIndexResp>, Status> {
+      let block = self.serve("read_by_index", &[]).map_err(injected)?;
+      Ok(Response::new(ReadByIndexResp {
+        block,
+        nonces: Vec::new(),
+        receipts: Vec::new(),
+      }))
+    }
+
+    async fn read_view_by_index(
+      &self,
+      _req: Request<ReadViewByIndexReq>,
+    ) -> Result<Response<ReadViewByIndexResp>, Status> {
+      let block = self.serve("read_view_by_index", &[]).map_err(injected)?;
+      Ok(Response::new(ReadViewByIndexResp {
+        block,
+        receipts: Vec::new(),
+      }))
+    }
+
+    async fn read_view_tail(
+      &self,
+      _req: Request<ReadViewTailReq>,
+    ) -> Result<Response<ReadViewTailResp>, Status> {
+      let block = self.serve("read_view_tail", &[]).map_err(injected)?;
+      Ok(Response::new(ReadViewTailResp {
+        block,
+        receipts: Vec::new(),
+        height: 1,
+        attestations: Vec::new(),
+      }))
+    }
+
+    type WatchStream = BoxStream<'static, Result<WatchResp, Status>>;
+
+    async fn watch(&self, _req: Request<WatchReq>) -> Result<Response<Self::WatchStream>, Status> {
+      self.serve("watch", &[]).map_err(injected)?;
+      Ok(Response::new(futures::stream::empty().boxed()))
+    }
+  }
+
+  // A fake coordinator served on a port of its own until it is stopped
+  struct Served {
+    uri: String,
+    shutdown: oneshot::Sender<()>,
+    server: tokio::task::JoinHandle<()>,
+  }
+
+  impl Served {
+    fn start(coordinator: &FakeCoordinator) -> Self {
+      let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+      // bound before the task starts, so that the coordinator is up once this returns
+      let incoming = TcpIncoming::new(addr, true, None).unwrap();
+      let service = CallServer::new(coordinator.clone());
+      let (shutdown, stopped) = oneshot::channel::<()>();
+      let server = tokio::spawn(async move {
+        Server::builder()
+          .add_service(service)
+          .serve_with_incoming_shutdown(incoming, async {
+            let _ = stopped.await;
+          })
+          .await
+          .unwrap();
+      });
+      Served {
+        uri: format!("http://{}", addr),
+        shutdown,
+        server,
+      }
+    }
+
+    async fn stop(self) {
+      self.shutdown.send(()).unwrap();
+      self.server.await.unwrap();
+    }
+  }
+
+  // the URI of a port nothing listens on
+  fn unused_uri() -> String {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+      .unwrap()
+      .local_addr()
+      .unwrap();
+    format!("http://{}", addr)
+  }
+
+  async fn connect(served: &[&Served]) -> Connection {
+    let uris = served.iter().map(|s| s.uri.clone()).collect::<Vec<_>>();
+    Connection::with_coordinators(&uris, None, None)
+      .await
+      .unwrap()
+  }
+
+  // the name of the coordinator that served a read
+  async fn read(conn: &Connection) -> Result<String, EndpointError> {
+    let (block, _nonces, _receipts) = conn.read_latest(&[1], &[2; 16]).await?;
+    Ok(String::from_utf8(block).unwrap())
+  }
+
+  #[tokio::test]
+  async fn test_connection_failover() {
+    let (a, b) = (FakeCoordinator::new("a"), FakeCoordinator::new("b"));
+    let (served_a, served_b) = (Served::start(&a), Served::start(&b));
+    let conn = connect(&[&served_a, &served_b]).await;
+    assert_eq!(
+      conn.get_healthy_coordinators(),
+      vec![served_a.uri.clone(), served_b.uri.clone()]
+    );
+
+    // reads go to the first coordinator while it serves them
+    assert_eq!(read(&conn).await, Ok("a".to_string()));
+    assert_eq!(read(&conn).await, Ok("a".to_string()));
+
+    // and to the other one once it is down, which they keep going to
+    served_a.stop().await;
+    assert_eq!(read(&conn).await, Ok("b".to_string()));
+    assert_eq!(conn.get_healthy_coordinators(), vec![served_b.uri.clone()]);
+    assert_eq!(read(&conn).await, Ok("b".to_string()));
+    assert_eq!(a.requests("read_latest").len(), 2);
+    assert_eq!(b.requests("read_latest").len(), 2);
+
+    // the coordinator that serves is the one a read goes to first
+    b.fail_with(Some(Code::Unavailable));
+    assert_eq!(
+      read(&conn).await,
+      Err(EndpointError::CoordinatorUnavailable)
+    );
+    b.fail_with(None);
+    assert_eq!(read(&conn).await, Ok("b".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_connection_retries_transient_reads() {
+    let (a, b) = (FakeCoordinator::new("a"), FakeCoordinator::new("b"));
+    let (served_a, served_b) = (Served::start(&a), Served::start(&b));
+
+    // a read that fails with an error another coordinator may not run into is sent to it
+    for code in [
+      Code::Unavailable,
+      Code::DeadlineExceeded,
+      Code::Aborted,
+      Code::Internal,
+      Code::ResourceExhausted,
+    ] {
+      let conn = connect(&[&served_a, &served_b]).await;
+      a.fail_with(Some(code));
+      assert_eq!(read(&conn).await, Ok("b".to_string()), "{:?}", code);
+      a.fail_with(None);
+    }
+
+    // but not after an error that every coordinator would answer with
+    let reads = b.requests("read_latest").len();
+    for (code, error) in [
+      (Code::NotFound, EndpointError::FailedToReadCounter),
+      (Code::InvalidArgument, EndpointError::FailedToReadCounter),
+      (Code::PermissionDenied, EndpointError::NotAuthorized),
+    ] {
+      let conn = connect(&[&served_a, &served_b]).await;
+      a.fail_with(Some(code));
+      assert_eq!(read(&conn).await, Err(error), "{:?}", code);
+      a.fail_with(None);
+    }
+    assert_eq!(b.requests("read_latest").len(), reads);
+  }
+
+  #[tokio::test]
+  async fn test_connection_retries_writes() {
+    let (a, b) = (FakeCoordinator::new("a"), FakeCoordinator::new("b"));
+    let (served_a, served_b) = (Served::start(&a), Served::start(&b));
+
+    // a write the coordinator failed is not sent again, as it may have been applied
+    let conn = connect(&[&served_a, &served_b]).await;
+    a.fail_with(Some(Code::Internal));
+    assert_eq!(
+      conn.append(&[1], &[2], 1).await,
+      Err(EndpointError::FailedToIncrementCounter)
+    );
+    assert_eq!(
+      conn.new_ledger(&[1], &[2]).await,
+      Err(EndpointError::FailedToCreateNewCounter)
+    );
+    assert!(b.requests("append").is_empty() && b.requests("new_ledger").is_empty());
+
+    // a write turned away is sent to the next coordinator with the same request ID
+    a.fail_with(Some(Code::Unavailable));
+    let (_hash_nonces, receipts) = conn.append(&[1], &[2], 1).await.unwrap();
+    assert_eq!(receipts, b"b".to_vec());
+    let ids = b.requests("append");
+    assert_eq!(ids.len(), 1);
+    assert_eq!(a.requests("append").last(), ids.last());
+
+    // as is one whose outcome is unknown, which is sent again while it stays so
+    let conn = connect(&[&served_a, &served_b]).await;
+    let sent = a.requests("new_ledger").len();
+    a.fail_with(Some(Code::DeadlineExceeded));
+    b.fail_with(Some(Code::DeadlineExceeded));
+    assert_eq!(
+      conn.new_ledger(&[1], &[2]).await,
+      Err(EndpointError::FailedToCreateNewCounter)
+    );
+    let ids = [&a.requests("new_ledger")[sent..], &b.requests("new_ledger")].concat();
+    assert_eq!(ids.len(), 2 * WRITE_ATTEMPTS as usize);
+    assert!(ids
+      .iter()
+      .all(|id| *id == ids[0] && id.len() == REQUEST_ID_SIZE));
+
+    // a delete carries no request ID, so it is only sent again if it was turned away
+    a.fail_with(Some(Code::DeadlineExceeded));
+    b.fail_with(None);
+    assert_eq!(
+      conn.delete_ledger(&[1], 1).await,
+      Err(EndpointError::FailedToDeleteLedger)
+    );
+    assert!(b.requests("delete_ledger").is_empty());
+    a.fail_with(Some(Code::Unavailable));
+    assert_eq!(conn.delete_ledger(&[1], 1).await, Ok(b"b".to_vec()));
+  }
+
+  #[tokio::test]
+  async fn test_connection_errors() {
+    // coordinators that cannot be named or reached fail requests rather than panic
+    assert_eq!(
+      Connection::with_coordinators(&[], None, None).await.err(),
+      Some(EndpointError::CoordinatorHostNameNotFound)
+    );
+    assert_eq!(
+      Connection::new("not a uri".to_string(), None, None)
+        .await
+        .err(),
+      Some(EndpointError::CoordinatorHostNameNotFound)
+    );
+
+    let uris = vec![unused_uri(), unused_uri()];
+    let conn = Connection::with_coordinators(&uris, None, None)
+      .await
+      .unwrap();
+    assert!(conn.get_healthy_coordinators().is_empty());
+    assert_eq!(
+      read(&conn).await,
+      Err(EndpointError::CoordinatorUnavailable)
+    );
+    assert_eq!(
+      conn.append(&[1], &[2], 1).await,
+      Err(EndpointError::CoordinatorUnavailable)
+    );
+    assert_eq!(
+      conn.watch(&[1], 0).await.err(),
+      Some(EndpointError::CoordinatorUnavailable)
+    );
+    assert_eq!(
+      EndpointState::new(uris.join(","), None, None, None)
+        .await
+        .err(),
+      Some(EndpointError::CoordinatorUnavailable)
+    );
+
+    // as does a coordinator whose view ledger cannot be verified
+    let a = FakeCoordinator::new("a");
+    let served_a = Served::start(&a);
+    assert_eq!(
+      EndpointState::new(served_a.uri.clone(), None, None, None)
+        .await
+        .err(),
+      Some(EndpointError::FailedToApplyViewChange)
+    );
+  }
+
+  fn counter_watch(from: u64, heights: &[u64]) -> CounterWatch {
+    let updates = heights
+      .iter()
+      .map(|height| WatchResp {
+        height: *height,
+        block: Vec::new(),
+        hash_nonces: Vec::new(),
+        receipts: Vec::new(),
+      })
+      .collect::<Vec<_>>();
+    CounterWatch {
+      handle: Vec::new(),
+      updates: futures::stream::iter(updates).map(Ok).boxed(),
+      next: from,
+      done: false,
+    }
+  }
+
+  async fn next_height(watch: &mut CounterWatch) -> Result<Option<u64>, EndpointError> {
+    watch
+      .next_update()
+      .await
+      .map(|update| update.map(|u| u.height))
+  }
+
+  #[tokio::test]
+  async fn test_counter_watch_order() {
+    // a gap after the first updates
+    let mut watch = counter_watch(3, &[3, 4, 6]);
+    assert_eq!(next_height(&mut watch).await, Ok(Some(3)));
+    assert_eq!(next_height(&mut watch).await, Ok(Some(4)));
+    assert_eq!(
+      next_height(&mut watch).await,
+      Err(EndpointError::UnexpectedCounterUpdate)
+    );
+
+    // a repeated update
+    let mut watch = counter_watch(0, &[0, 0]);
+    assert_eq!(next_height(&mut watch).await, Ok(Some(0)));
+    assert_eq!(
+      next_height(&mut watch).await,
+      Err(EndpointError::UnexpectedCounterUpdate)
+    );
+
+    // updates that start before or after the value the watch starts from
+    for first in [1, 3] {
+      let mut watch = counter_watch(2, &[first]);
+      assert_eq!(
+        next_height(&mut watch).await,
+        Err(EndpointError::UnexpectedCounterUpdate)
+      );
+    }
+
+    // the watch ends with the stream
+    let mut watch = counter_watch(0, &[0, 1]);
+    assert_eq!(next_height(&mut watch).await, Ok(Some(0)));
+    assert_eq!(next_height(&mut watch).await, Ok(Some(1)));
+    assert_eq!(next_height(&mut watch).await, Ok(None));
+  }
 }
@@ -0,0 +1,21 @@
+use lazy_static::lazy_static;
+use prometheus::{register_int_counter_vec, Encoder, IntCounterVec, TextEncoder};
+use tracing::error;
+
+lazy_static! {
+  pub(crate) static ref VERIFICATION_FAILURES: IntCounterVec = register_int_counter_vec!(
+    "nimble_endpoint_verification_failures_total",
+    "The number of responses of the coordinator that failed verification, by operation",
+    &["op"]
+  )
+  .unwrap();
+}
+
+/// Renders the metrics of the default registry in the Prometheus text format
+pub fn render() -> String {
+  let mut buffer = Vec::new();
+  if let Err(error) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+    error!("Failed to encode the metrics ({:?})", error);
+  }
+  String::from_utf8(buffer).unwrap_or_default()
+}
@@ -1,21 +1,46 @@
-use endpoint::{EndpointState, PublicKeyFormat, SignatureFormat};
+use endpoint::{
+  Certificate, ClientTlsConfig, EndpointError, EndpointState, Identity, PublicKeyFormat,
+  SignatureFormat,
+};
 
 use axum::{
-  extract::{Extension, Path, Query},
-  http::StatusCode,
-  response::IntoResponse,
+  extract::{Extension, MatchedPath, Path, Query},
+  http::{HeaderValue, Request, StatusCode},
+  middleware::{self, Next},
+  response::{
+    sse::{Event, KeepAlive, Sse},
+    IntoResponse, Response,
+  },
   routing::get,
   Json, Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
+use lazy_static::lazy_static;
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
 use serde_json::json;
 use std::{collections::HashMap, sync::Arc};
 use tower::ServiceBuilder;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 use clap::{App, Arg};
 
 use serde::{Deserialize, Serialize};
 
+lazy_static! {
+  static ref REQUESTS: IntCounterVec = register_int_counter_vec!(
+    "nimble_endpoint_requests_total",
+    "The number of requests served by the endpoint, by route, method and HTTP status",
+    &["route", "method", "status"]
+  )
+  .unwrap();
+  static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+    "nimble_endpoint_request_duration_seconds",
+    "The latency of requests served by the endpoint",
+    &["route", "method"]
+  )
+  .unwrap();
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
   let config = App::new("endpoint")
@@ -23,7 +48,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
       Arg::with_name("coordinator")
         .short("c")
         .long("coordinator")
-        .help("The hostname of the coordinator")
+        .help("The hostname of the coordinator, or of several coordinators separated by commas")
         .default_value("http://[::1]:8080"),
     )
     .arg(
@@ -67,8 +92,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .long("channels")
         .takes_value(true)
         .help("The number of grpc channels"),
+    )
+    .arg(
+      Arg::with_name("coordinator_ca")
+        .long("coordinator_ca")
+        .takes_value(true)
+        .help("The CA certificate (PEM) to check the coordinator's certificate against over TLS"),
+    )
+    .arg(
+      Arg::with_name("coordinator_cert")
+        .long("coordinator_cert")
+        .takes_value(true)
+        .requires("coordinator_key")
+        .help("The certificate (PEM) the endpoint presents to the coordinator over TLS"),
+    )
+    .arg(
+      Arg::with_name("coordinator_key")
+        .long("coordinator_key")
+        .takes_value(true)
+        .requires("coordinator_cert")
+        .help("The private key (PEM) of the certificate presented to the coordinator"),
+    )
+    .arg(
+      Arg::with_name("otlp_endpoint")
+        .long("otlp_endpoint")
+        .takes_value(true)
+        .help("Export spans to the OTLP collector at this URI (e.g., http://localhost:4317)"),
     );
+
   let cli_matches = config.get_matches();
+  if let Err(error) = telemetry::init("endpoint", cli_matches.value_of("otlp_endpoint")) {
+    eprintln!("Failed to set up tracing ({:?})", error);
+  }
   let hostname = cli_matches.value_of("host").unwrap();
   let port_num = cli_matches.value_of("port").unwrap();
   let addr = format!("{}:{}", hostname, port_num).parse()?;
@@ -79,6 +134,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     .value_of("pem")
     .map(|p| std::fs::read_to_string(p).expect("Failed to read the private key pem file"));
 
+  // connect to the coordinators over TLS if they are checked against a CA or the endpoint
+  // has a certificate to present to them
+  let read = |arg: &str| {
+    cli_matches
+      .value_of(arg)
+      .map(|p| std::fs::read(p).unwrap_or_else(|e| panic!("Failed to read {} ({})", p, e)))
+  };
+  let coordinator_tls = match (read("coordinator_ca"), read("coordinator_cert")) {
+    (None, None) => None,
+    (ca, cert) => {
+      let mut tls = ClientTlsConfig::new();
+      if let Some(ca) = ca {
+        tls = tls.ca_certificate(Certificate::from_pem(ca));
+      }
+      if let Some(cert) = cert {
+        tls = tls.identity(Identity::from_pem(cert, read("coordinator_key").unwrap()));
+      }
+      Some(tls)
+    },
+  };
+
   let num_grpc_channels: Option<usize> = if let Some(x) = cli_matches.value_of("channels") {
     match x.to_string().parse() {
       Ok(v) => Some(v),
@@ -88,16 +164,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     None
   };
 
-  let endpoint_state = Arc::new(
-    EndpointState::new(coordinator_hostname, pem, num_grpc_channels)
-      .await
-      .unwrap(),
-  );
+  // wait for a coordinator to become available, since the endpoint may start before them
+  let endpoint_state = loop {
+    match EndpointState::new(
+      coordinator_hostname.clone(),
+      pem.clone(),
+      num_grpc_channels,
+      coordinator_tls.clone(),
+    )
+    .await
+    {
+      Ok(state) => break Arc::new(state),
+      Err(EndpointError::CoordinatorUnavailable) => {
+        info!("No coordinator is available, retrying");
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+      },
+      Err(error) => {
+        error!("Failed to start the endpoint {:?}", error);
+        std::process::exit(1);
+      },
+    }
+  };
 
   // Build our application by composing routes
   let app = Router::new()
       .route("/serviceid", get(get_identity))
       .route("/counters/:handle", get(read_counter).put(new_counter).post(increment_counter))
+      .route("/counters/:handle/updates", get(watch_counter))
+      .route("/metrics", get(get_metrics))
+      .route_layer(middleware::from_fn(record_request))
+      .route_layer(middleware::from_fn(trace_request))
       // Add middleware to all routes
       .layer(
           ServiceBuilder::new()
@@ -129,10 +225,70 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
   };
 
   job.await?;
+  telemetry::shutdown();
 
   Ok(())
 }
 
+async fn get_metrics() -> impl IntoResponse {
+  endpoint::metrics::render()
+}
+
+// Records the number and latency of requests by route, which keeps the handles in the paths
+// out of the labels
+async fn record_request<B>(req: Request<B>, next: Next<B>) -> impl IntoResponse {
+  let route = match req.extensions().get::<MatchedPath>() {
+    Some(path) => path.as_str().to_string(),
+    None => req.uri().path().to_string(),
+  };
+  let method = req.method().to_string();
+
+  let timer = REQUEST_DURATION
+    .with_label_values(&[&route, &method])
+    .start_timer();
+  let resp = next.run(req).await;
+  timer.observe_duration();
+  REQUESTS
+    .with_label_values(&[&route, &method, resp.status().as_str()])
+    .inc();
+  resp
+}
+
+// Serves a request in a span, on behalf of the request ID in its `x-request-id` header or of
+// a new one, which is passed on to the coordinator and returned in the same header
+async fn trace_request<B>(req: Request<B>, next: Next<B>) -> impl IntoResponse {
+  let route = match req.extensions().get::<MatchedPath>() {
+    Some(path) => path.as_str().to_string(),
+    None => req.uri().path().to_string(),
+  };
+  let span = info_span!(
+    "http",
+    otel.name = %route,
+    method = %req.method(),
+    path = %req.uri().path(),
+    request_id = tracing::field::Empty,
+  );
+  let request_id = telemetry::accept_http(req.headers(), &span);
+
+  let mut resp = telemetry::scope(request_id.clone(), next.run(req).instrument(span.clone())).await;
+  debug!(parent: &span, status = %resp.status(), "served request");
+  if let Ok(value) = HeaderValue::from_str(&request_id) {
+    resp
+      .headers_mut()
+      .insert(telemetry::REQUEST_ID_HEADER, value);
+  }
+  resp
+}
+
+// a request that no coordinator could serve may be retried later
+fn error_status(error: &EndpointError) -> StatusCode {
+  match error {
+    EndpointError::CoordinatorUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+    EndpointError::NotAuthorized => StatusCode::FORBIDDEN,
+    _ => StatusCode::CONFLICT,
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GetIdentityResponse {
   #[serde(rename = "Identity")]
@@ -189,7 +345,7 @@ async fn get_identity(
       "der" => PublicKeyFormat::DER,
       "uncompressed" => PublicKeyFormat::UNCOMPRESSED,
       _ => {
-        eprintln!("unsupported format");
+        warn!("unsupported format");
         return (StatusCode::BAD_REQUEST, Json(json!({})));
       },
     }
@@ -211,14 +367,14 @@ async fn new_counter(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&handle);
   if res.is_err() {
-    eprintln!("received a bad handle {:?}", res);
+    warn!("received a bad handle {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let handle = res.unwrap();
 
   let res = base64_url::decode(&req.tag);
   if res.is_err() {
-    eprintln!("received a bad tag {:?}", res);
+    warn!("received a bad tag {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let tag = res.unwrap();
@@ -233,9 +389,9 @@ async fn new_counter(
   };
 
   let res = state.new_counter(&handle, &tag, sigformat).await;
-  if res.is_err() {
-    eprintln!("failed to create a new counter {:?}", res);
-    return (StatusCode::CONFLICT, Json(json!({})));
+  if let Err(error) = &res {
+    error!("failed to create a new counter {:?}", error);
+    return (error_status(error), Json(json!({})));
   }
   let signature = res.unwrap();
 
@@ -253,18 +409,18 @@ async fn read_counter(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&handle);
   if res.is_err() {
-    eprintln!("received a bad handle {:?}", res);
+    warn!("received a bad handle {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let handle = res.unwrap();
 
   if !params.contains_key("nonce") {
-    eprintln!("missing a nonce");
+    warn!("missing a nonce");
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let res = base64_url::decode(&params["nonce"]);
   if res.is_err() {
-    eprintln!("received a bad nonce {:?}", res);
+    warn!("received a bad nonce {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let nonce = res.unwrap();
@@ -279,9 +435,9 @@ async fn read_counter(
   };
 
   let res = state.read_counter(&handle, &nonce, sigformat).await;
-  if res.is_err() {
-    eprintln!("failed to read a counter {:?}", res);
-    return (StatusCode::CONFLICT, Json(json!({})));
+  if let Err(error) = &res {
+    error!("failed to read a counter {:?}", error);
+    return (error_status(error), Json(json!({})));
   }
   let (tag, counter, signature) = res.unwrap();
 
@@ -302,14 +458,14 @@ async fn increment_counter(
 ) -> impl IntoResponse {
   let res = base64_url::decode(&handle);
   if res.is_err() {
-    eprintln!("received a bad handle {:?}", res);
+    warn!("received a bad handle {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let handle = res.unwrap();
 
   let res = base64_url::decode(&req.tag);
   if res.is_err() {
-    eprintln!("received a bad tag {:?}", res);
+    warn!("received a bad tag {:?}", res);
     return (StatusCode::BAD_REQUEST, Json(json!({})));
   }
   let tag = res.unwrap();
@@ -326,9 +482,9 @@ async fn increment_counter(
   let res = state
     .increment_counter(&handle, &tag, req.expected_counter, sigformat)
     .await;
-  if res.is_err() {
-    eprintln!("failed to increment a counter {:?}", res);
-    return (StatusCode::CONFLICT, Json(json!({})));
+  if let Err(error) = &res {
+    error!("failed to increment a counter {:?}", error);
+    return (error_status(error), Json(json!({})));
   }
   let signature = res.unwrap();
 
@@ -338,3 +494,68 @@ async fn increment_counter(
 
   (StatusCode::OK, Json(json!(resp)))
 }
+
+// Streams the updates of a counter as server-sent events, from the value in the `from`
+// parameter on. Each `update` event carries the tag, the value, and the signature of an
+// update once the endpoint verified it. The stream ends when the counter is deleted, or after
+// an `error` event with the HTTP status the update would have been answered with.
+async fn watch_counter(
+  Path(handle): Path<String>,
+  Query(params): Query<HashMap<String, String>>,
+  Extension(state): Extension<Arc<EndpointState>>,
+) -> Response {
+  let res = base64_url::decode(&handle);
+  if res.is_err() {
+    warn!("received a bad handle {:?}", res);
+    return (StatusCode::BAD_REQUEST, Json(json!({}))).into_response();
+  }
+  let handle = res.unwrap();
+
+  let from_counter = match params.get("from").map(|from| from.parse::<u64>()) {
+    None => 0,
+    Some(Ok(from_counter)) => from_counter,
+    Some(Err(_)) => {
+      warn!("received a bad counter to watch from {:?}", params["from"]);
+      return (StatusCode::BAD_REQUEST, Json(json!({}))).into_response();
+    },
+  };
+
+  let der = params.get("sigformat").is_some_and(|f| f == "der");
+
+  let watch = match state.watch_counter(&handle, from_counter).await {
+    Ok(watch) => watch,
+    Err(error) => {
+      error!("failed to watch a counter {:?}", error);
+      return (error_status(&error), Json(json!({}))).into_response();
+    },
+  };
+
+  let updates = futures::stream::unfold((state, watch), move |(state, mut watch)| async move {
+    let sigformat = if der {
+      SignatureFormat::DER
+    } else {
+      SignatureFormat::RAW
+    };
+    let event = match state.next_counter_update(&mut watch, sigformat).await? {
+      Ok((tag, counter, signature)) => {
+        let resp = ReadCounterResponse {
+          tag: base64_url::encode(&tag),
+          counter,
+          signature: base64_url::encode(&signature),
+        };
+        Event::default().event("update").json_data(resp)
+      },
+      Err(error) => {
+        error!("failed to watch a counter {:?}", error);
+        Event::default()
+          .event("error")
+          .json_data(json!({ "Status": error_status(&error).as_u16() }))
+      },
+    };
+    Some((event, (state, watch)))
+  });
+
+  Sse::new(updates)
+    .keep_alive(KeepAlive::default())
+    .into_response()
+}
@@ -20,7 +20,7 @@ def reconfigure(out_folder, tcpdump_folder, num):
     # perform reconfiguration
     cmd = "\'" + NIMBLE_BIN_PATH + "/coordinator_ctrl"
     cmd += " -c \"http://" + LISTEN_IP_COORDINATOR + ":" + PORT_COORDINATOR_CTRL + "\""
-    cmd += " -a \"http://" + LISTEN_IP_ENDORSER_4 + ":" + PORT_ENDORSER_4
+    cmd += " replace \"http://" + LISTEN_IP_ENDORSER_4 + ":" + PORT_ENDORSER_4
     cmd += ";http://" + LISTEN_IP_ENDORSER_5 + ":" + PORT_ENDORSER_5
     cmd += ";http://" + LISTEN_IP_ENDORSER_6 + ":" + PORT_ENDORSER_6
     cmd += "\" >> " + out_folder + "/reconf-time-" + str(num)  + "ledgers.log\'"
@@ -173,6 +173,15 @@ impl Nonces {
   }
 }
 
+/// the contents of the block that ends a deleted ledger
+const TOMBSTONE_BLOCK: &[u8] = b"NIMBLE_TOMBSTONE";
+
+/// the prefix of what an endorser signs to delete a ledger
+const TOMBSTONE_PREFIX: &[u8] = b"NIMBLE_ENDORSER_TOMBSTONE";
+
+/// the prefix of what an endorser signs to bind its TLS certificate to its public key
+const TLS_CERTIFICATE_PREFIX: &[u8] = b"NIMBLE_ENDORSER_TLS_CERTIFICATE";
+
 /// A block in a ledger is a byte array
 #[derive(Clone, Debug, Default)]
 pub struct Block {
@@ -186,6 +195,12 @@ impl Block {
     }
   }
 
+  /// the block that stands for the tombstone of a deleted ledger when it is read. Its
+  /// receipts, not its contents, tell it from an application block with the same bytes.
+  pub fn tombstone() -> Self {
+    Block::new(TOMBSTONE_BLOCK)
+  }
+
   pub fn len(&self) -> usize {
     self.block.len()
   }
@@ -226,6 +241,19 @@ impl MetaBlock {
     }
   }
 
+  /// the metablock that ends a deleted ledger at `height`
+  pub fn tombstone(prev: &NimbleDigest, height: usize) -> Self {
+    MetaBlock {
+      prev: *prev,
+      block_hash: tombstone_block_hash(),
+      height,
+    }
+  }
+
+  pub fn is_tombstone(&self) -> bool {
+    self.block_hash == tombstone_block_hash()
+  }
+
   pub fn get_height(&self) -> usize {
     self.height
   }
@@ -357,6 +385,25 @@ impl Receipt {
 
 const MIN_NUM_ENDORSERS: usize = 1;
 
+/// the block hash in the metablock of a tombstone. It is not the hash of a block and
+/// its nonces, so endorsers refuse to sign it for anything but the deletion of a ledger.
+pub fn tombstone_block_hash() -> NimbleDigest {
+  NimbleDigest::digest(TOMBSTONE_BLOCK)
+}
+
+/// the hash an endorser signs, in place of the handle and the hash of the metablock, to end
+/// the ledger with the given handle with `tombstone`. The prefix keeps the signature from
+/// being taken for a receipt.
+pub fn tombstone_hash(handle: &NimbleDigest, tombstone: &MetaBlock) -> NimbleDigest {
+  NimbleDigest::digest(TOMBSTONE_PREFIX).digest_with(&handle.digest_with(&tombstone.hash()))
+}
+
+/// the hash an endorser signs to show that the TLS certificate, in DER, is its own. The
+/// prefix keeps the signature from being taken for a receipt.
+pub fn tls_certificate_hash(cert: &[u8]) -> NimbleDigest {
+  NimbleDigest::digest(TLS_CERTIFICATE_PREFIX).digest_with_bytes(cert)
+}
+
 pub fn compute_aggregated_block_hash(
   hash_block_bytes: &[u8],
   hash_nonces_bytes: &[u8],
@@ -521,10 +568,28 @@ impl Receipts {
     expected_height: Option<usize>,
     nonce_bytes: Option<&[u8]>,
   ) -> Result<usize, VerificationError> {
-    let block_hash = compute_aggregated_block_hash(
-      &NimbleDigest::digest(block_bytes).to_bytes(),
+    self.verify_digests(
+      verifier_state,
+      &NimbleDigest::digest(handle_bytes),
+      &NimbleDigest::digest(block_bytes),
       hash_nonces_bytes,
-    );
+      expected_height,
+      nonce_bytes,
+    )
+  }
+
+  /// same as `verify`, but takes the hash of the handle and the hash of the block
+  /// (as kept by the ledger store) instead of their bytes
+  pub fn verify_digests(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &NimbleDigest,
+    hash_block: &NimbleDigest,
+    hash_nonces_bytes: &[u8],
+    expected_height: Option<usize>,
+    nonce_bytes: Option<&[u8]>,
+  ) -> Result<usize, VerificationError> {
+    let block_hash = compute_aggregated_block_hash(&hash_block.to_bytes(), hash_nonces_bytes);
 
     for (ex_meta_block, id_sigs) in &self.receipts {
       let pks = verifier_state.get_pks_for_view(ex_meta_block.get_view())?;
@@ -551,7 +616,7 @@ impl Receipts {
       let message = verifier_state.get_group_identity().digest_with(
         &ex_meta_block
           .get_view()
-          .digest_with(&NimbleDigest::digest(handle_bytes).digest_with(&tail_hash)),
+          .digest_with(&handle.digest_with(&tail_hash)),
       );
 
       let mut num_receipts = 0;
@@ -572,6 +637,68 @@ impl Receipts {
     Err(VerificationError::InvalidReceipt)
   }
 
+  /// whether the receipts are the endorsers' signatures on the tombstone of a deleted ledger
+  /// rather than receipts for an entry
+  pub fn is_tombstone(&self) -> bool {
+    !self.receipts.is_empty()
+      && self
+        .receipts
+        .keys()
+        .all(|ex_meta_block| ex_meta_block.get_metablock().is_tombstone())
+  }
+
+  /// verifies that a quorum of endorsers signed the tombstone that ends the ledger with the
+  /// given handle, and returns the height of the tombstone. With a nonce, the endorsers
+  /// signed it in response to reading the tail of the deleted ledger.
+  pub fn verify_tombstone(
+    &self,
+    verifier_state: &VerifierState,
+    handle: &NimbleDigest,
+    expected_height: Option<usize>,
+    nonce_bytes: Option<&[u8]>,
+  ) -> Result<usize, VerificationError> {
+    for (ex_meta_block, id_sigs) in &self.receipts {
+      let pks = verifier_state.get_pks_for_view(ex_meta_block.get_view())?;
+      if id_sigs.len() < pks.len() / 2 + 1 {
+        continue;
+      }
+
+      let tombstone = ex_meta_block.get_metablock();
+      if !tombstone.is_tombstone() {
+        return Err(VerificationError::InvalidBlockHash);
+      }
+      if let Some(h) = expected_height {
+        if h != tombstone.get_height() {
+          return Err(VerificationError::InvalidHeight);
+        }
+      }
+      let hash = match nonce_bytes {
+        Some(n) => tombstone_hash(handle, tombstone).digest_with_bytes(n),
+        None => tombstone_hash(handle, tombstone),
+      };
+
+      let message = verifier_state
+        .get_group_identity()
+        .digest_with(&ex_meta_block.get_view().digest_with(&hash));
+
+      let mut num_receipts = 0;
+      for id_sig in id_sigs {
+        id_sig
+          .verify(&message.to_bytes())
+          .map_err(|_e| VerificationError::InvalidSignature)?;
+        if pks.contains(id_sig.get_id()) {
+          num_receipts += 1;
+        }
+      }
+
+      if num_receipts > pks.len() / 2 {
+        return Ok(tombstone.get_height());
+      }
+    }
+
+    Err(VerificationError::InvalidReceipt)
+  }
+
   #[allow(clippy::too_many_arguments)]
   pub fn verify_view_change(
     &self,
@@ -661,6 +788,7 @@ impl Receipts {
     let mut j: usize = 0;
     while i < cut_diffs.len() && j < ledger_chunks.len() {
       if cut_diffs[i].low == cut_diffs[i].high {
+        i += 1;
         continue;
       }
       if cut_diffs[i].handle.cmp(&ledger_chunks[j].handle) != Ordering::Equal
@@ -696,6 +824,11 @@ impl Receipts {
       j += 1;
     }
 
+    // the ledgers that every endorser has at the same height have no chunks
+    while i < cut_diffs.len() && cut_diffs[i].low == cut_diffs[i].high {
+      i += 1;
+    }
+
     if i != cut_diffs.len() || j != ledger_chunks.len() {
       eprintln!("incorrect information for comparing cuts");
       return Err(VerificationError::InconsistentLedgerTailMaps);
@@ -962,6 +1095,82 @@ impl VerifierState {
     receipts.verify_read_latest(self, handle_bytes, block_bytes, nonces_bytes, nonce_bytes)
   }
 
+  /// verifies the receipts of the entry at height `idx` of the ledger with the given handle,
+  /// where the handle and the block are given by their hashes as kept in the ledger store
+  pub fn verify_ledger_entry(
+    &self,
+    handle: &NimbleDigest,
+    hash_block: &NimbleDigest,
+    hash_nonces: &NimbleDigest,
+    idx: usize,
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    let res = receipts.verify_digests(
+      self,
+      handle,
+      hash_block,
+      &hash_nonces.to_bytes(),
+      Some(idx),
+      None,
+    );
+    match res {
+      Ok(_h) => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// verifies that the ledger with the given handle was deleted, and that its tombstone
+  /// is at height `expected_height`
+  pub fn verify_delete_ledger(
+    &self,
+    handle_bytes: &[u8],
+    expected_height: usize,
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerificationError> {
+    self.verify_ledger_tombstone(
+      &NimbleDigest::digest(handle_bytes),
+      expected_height,
+      receipts_bytes,
+    )
+  }
+
+  /// same as `verify_delete_ledger`, but takes the hash of the handle as kept in the ledger
+  /// store instead of its bytes
+  pub fn verify_ledger_tombstone(
+    &self,
+    handle: &NimbleDigest,
+    expected_height: usize,
+    receipts_bytes: &[u8],
+  ) -> Result<(), VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    let res = receipts.verify_tombstone(self, handle, Some(expected_height), None);
+    match res {
+      Ok(_h) => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// verifies a response to reading the tail of a deleted ledger with the given nonce, and
+  /// returns the height at which the ledger was deleted
+  pub fn verify_read_deleted(
+    &self,
+    handle_bytes: &[u8],
+    nonce_bytes: &[u8],
+    receipts_bytes: &[u8],
+  ) -> Result<usize, VerificationError> {
+    let receipts =
+      Receipts::from_bytes(receipts_bytes).map_err(|_e| VerificationError::InvalidReceipt)?;
+    receipts.verify_tombstone(
+      self,
+      &NimbleDigest::digest(handle_bytes),
+      None,
+      Some(nonce_bytes),
+    )
+  }
+
   pub fn verify_read_by_index(
     &self,
     handle_bytes: &[u8],
@@ -1061,6 +1270,8 @@ pub fn compute_cut_diffs(ledger_tail_maps: &Vec<LedgerTailMap>) -> Vec<CutDiff>
             } else if (ledger_tail_map.entries[j].height as usize) > cut_diffs[i].high {
               cut_diffs[i].high = ledger_tail_map.entries[j].height as usize;
             }
+            i += 1;
+            j += 1;
           },
           Ordering::Greater => {
             cut_diffs.insert(
@@ -1407,4 +1618,37 @@ mod tests {
     let hash = produce_hash_of_state(&map);
     assert_ne!(hash, NimbleDigest::default());
   }
+
+  #[test]
+  pub fn test_cut_diffs_of_shared_ledgers() {
+    let entry = |handle: u8, height: u64| LedgerTailMapEntry {
+      handle: vec![handle; 32],
+      metablock: vec![handle; 32],
+      height,
+      block: vec![],
+      nonces: vec![],
+    };
+    let ledger_tail_maps = vec![
+      LedgerTailMap {
+        entries: vec![entry(1, 2), entry(2, 5)],
+      },
+      LedgerTailMap {
+        entries: vec![entry(1, 4), entry(2, 3), entry(3, 1)],
+      },
+    ];
+
+    let cut_diffs = compute_cut_diffs(&ledger_tail_maps);
+    let ranges = cut_diffs
+      .iter()
+      .map(|cut_diff| (cut_diff.handle[0], cut_diff.low, cut_diff.high))
+      .collect::<Vec<_>>();
+    assert_eq!(ranges, vec![(1, 2, 4), (2, 3, 5), (3, 1, 1)]);
+
+    let max_cut = compute_max_cut(&ledger_tail_maps);
+    let heights = max_cut
+      .iter()
+      .map(|entry| (entry.handle[0], entry.height))
+      .collect::<Vec<_>>();
+    assert_eq!(heights, vec![(1, 4), (2, 5), (3, 1)]);
+  }
 }
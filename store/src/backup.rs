@@ -0,0 +1,524 @@
+use crate::{
+  errors::StorageError,
+  ledger::{LedgerEntry, LedgerStore},
+};
+use chrono::Utc;
+use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonces, Receipts};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{ErrorKind, Read, Write};
+
+const BACKUP_MAGIC: &[u8] = b"nimble-backup-v1\n";
+
+// a corrupted length must not make the reader allocate without bound
+const MAX_RECORD_SIZE: u64 = 1 << 30;
+
+/// The height and hash of the records of one ledger in a backup
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LedgerManifest {
+  pub handle: Vec<u8>,
+  pub height: u64,
+  pub digest: Vec<u8>,
+}
+
+/// The last record of a backup, which describes the cut it captured
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BackupManifest {
+  pub created_at: String,
+  pub view_height: u64,
+  /// hash of the records of the view ledger
+  pub view_digest: Vec<u8>,
+  pub ledgers: Vec<LedgerManifest>,
+  /// hash of every record before the manifest
+  pub digest: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize)]
+enum Record {
+  ViewEntry {
+    idx: u64,
+    block: Vec<u8>,
+    receipts: Vec<u8>,
+  },
+  LedgerEntry {
+    handle: Vec<u8>,
+    idx: u64,
+    block: Vec<u8>,
+    nonces: Vec<u8>,
+    receipts: Vec<u8>,
+  },
+  Manifest(BackupManifest),
+}
+
+/// An entry read back from a backup
+pub enum BackupEntry {
+  View(usize, LedgerEntry),
+  Ledger(Handle, usize, LedgerEntry),
+}
+
+// Tracks the order of the records and their hashes on both ends of a backup: the view
+// ledger comes first from index 1, then each ledger in turn from its oldest entry, which is
+// at index 0 unless the ledger was pruned
+struct Digests {
+  all: Sha256,
+  view_height: u64,
+  view: Sha256,
+  ledgers: Vec<LedgerManifest>,
+  current: Option<(Vec<u8>, u64, Sha256)>,
+}
+
+impl Digests {
+  fn new() -> Self {
+    Digests {
+      all: Sha256::new(),
+      view_height: 0,
+      view: Sha256::new(),
+      ledgers: Vec::new(),
+      current: None,
+    }
+  }
+
+  fn close_ledger(&mut self) {
+    if let Some((handle, height, digest)) = self.current.take() {
+      self.ledgers.push(LedgerManifest {
+        handle,
+        height,
+        digest: digest.finalize().to_vec(),
+      });
+    }
+  }
+
+  fn add(&mut self, record: &Record, bytes: &[u8]) -> Result<(), StorageError> {
+    match record {
+      Record::ViewEntry { idx, .. } => {
+        if !self.ledgers.is_empty() || self.current.is_some() || *idx != self.view_height + 1 {
+          eprintln!("The view ledger entry at index {} is out of order", idx);
+          return Err(StorageError::InvalidBackup);
+        }
+        self.view_height = *idx;
+        self.view.update(bytes);
+      },
+      Record::LedgerEntry { handle, idx, .. } => {
+        let next = match &self.current {
+          Some((h, height, _)) if h == handle => Some(*height + 1),
+          _ => None,
+        };
+        match next {
+          Some(n) if n == *idx => {},
+          None if !self.ledgers.iter().any(|l| &l.handle == handle) => {
+            self.close_ledger();
+            self.current = Some((handle.clone(), *idx, Sha256::new()));
+          },
+          _ => {
+            eprintln!("The ledger entry at index {} is out of order", idx);
+            return Err(StorageError::InvalidBackup);
+          },
+        }
+        let (_handle, height, digest) = self.current.as_mut().unwrap();
+        *height = *idx;
+        digest.update(bytes);
+      },
+      // the manifest is not part of the hash it carries
+      Record::Manifest(_) => return Ok(()),
+    }
+    self.all.update(bytes);
+    Ok(())
+  }
+
+  fn manifest(mut self) -> BackupManifest {
+    self.close_ledger();
+    BackupManifest {
+      created_at: Utc::now().to_rfc3339(),
+      view_height: self.view_height,
+      view_digest: self.view.finalize().to_vec(),
+      ledgers: self.ledgers,
+      digest: self.all.finalize().to_vec(),
+    }
+  }
+}
+
+/// Writes a portable backup of ledger entries: the view ledger first, then every ledger
+/// from its genesis or checkpoint, followed by a manifest with the height and hash of each of them
+pub struct BackupWriter<W: Write> {
+  writer: W,
+  digests: Digests,
+}
+
+impl<W: Write> BackupWriter<W> {
+  pub fn new(mut writer: W) -> Result<Self, StorageError> {
+    if let Err(e) = writer.write_all(BACKUP_MAGIC) {
+      eprintln!("Failed to write the backup header {:?}", e);
+      return Err(StorageError::UnhandledError);
+    }
+    Ok(BackupWriter {
+      writer,
+      digests: Digests::new(),
+    })
+  }
+
+  fn write_record(&mut self, record: &Record) -> Result<(), StorageError> {
+    let bytes = bincode::serialize(record).map_err(|_| StorageError::SerializationError)?;
+    self.digests.add(record, &bytes)?;
+    let res = self
+      .writer
+      .write_all(&(bytes.len() as u64).to_le_bytes())
+      .and_then(|()| self.writer.write_all(&bytes));
+    if let Err(e) = res {
+      eprintln!("Failed to write a backup record {:?}", e);
+      return Err(StorageError::UnhandledError);
+    }
+    Ok(())
+  }
+
+  pub fn write_view_entry(&mut self, idx: usize, entry: &LedgerEntry) -> Result<(), StorageError> {
+    self.write_record(&Record::ViewEntry {
+      idx: idx as u64,
+      block: entry.get_block().to_bytes(),
+      receipts: entry.get_receipts().to_bytes(),
+    })
+  }
+
+  pub fn write_ledger_entry(
+    &mut self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), StorageError> {
+    self.write_record(&Record::LedgerEntry {
+      handle: handle.to_bytes(),
+      idx: idx as u64,
+      block: entry.get_block().to_bytes(),
+      nonces: entry.get_nonces().to_bytes(),
+      receipts: entry.get_receipts().to_bytes(),
+    })
+  }
+
+  /// Writes the manifest and returns it along with the underlying writer
+  pub fn finish(mut self) -> Result<(BackupManifest, W), StorageError> {
+    let digests = std::mem::replace(&mut self.digests, Digests::new());
+    let manifest = digests.manifest();
+    self.write_record(&Record::Manifest(manifest.clone()))?;
+    if let Err(e) = self.writer.flush() {
+      eprintln!("Failed to flush the backup {:?}", e);
+      return Err(StorageError::UnhandledError);
+    }
+    Ok((manifest, self.writer))
+  }
+}
+
+/// Reads back the entries of a backup, checking their order and hashes against the
+/// manifest. The manifest is only available once every entry has been read.
+pub struct BackupReader<R: Read> {
+  reader: R,
+  digests: Option<Digests>,
+  manifest: Option<BackupManifest>,
+}
+
+impl<R: Read> BackupReader<R> {
+  pub fn new(mut reader: R) -> Result<Self, StorageError> {
+    let mut magic = vec![0u8; BACKUP_MAGIC.len()];
+    if reader.read_exact(&mut magic).is_err() || magic != BACKUP_MAGIC {
+      eprintln!("The file is not a backup");
+      return Err(StorageError::InvalidBackup);
+    }
+    Ok(BackupReader {
+      reader,
+      digests: Some(Digests::new()),
+      manifest: None,
+    })
+  }
+
+  pub fn manifest(&self) -> Option<&BackupManifest> {
+    self.manifest.as_ref()
+  }
+
+  fn read_record(&mut self) -> Result<(Record, Vec<u8>), StorageError> {
+    let mut len = [0u8; 8];
+    self.read_bytes(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > MAX_RECORD_SIZE {
+      eprintln!("The backup has a record of {} bytes", len);
+      return Err(StorageError::InvalidBackup);
+    }
+    let mut bytes = vec![0u8; len as usize];
+    self.read_bytes(&mut bytes)?;
+    match bincode::deserialize(&bytes) {
+      Ok(record) => Ok((record, bytes)),
+      Err(_) => Err(StorageError::InvalidBackup),
+    }
+  }
+
+  fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), StorageError> {
+    match self.reader.read_exact(buf) {
+      Ok(()) => Ok(()),
+      Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+        eprintln!("The backup is truncated");
+        Err(StorageError::InvalidBackup)
+      },
+      Err(e) => {
+        eprintln!("Failed to read the backup {:?}", e);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+
+  /// Returns the next entry, or `None` once the manifest has been read and checked
+  pub fn next_entry(&mut self) -> Result<Option<BackupEntry>, StorageError> {
+    if self.manifest.is_some() {
+      return Ok(None);
+    }
+
+    let (record, bytes) = self.read_record()?;
+    let digests = self.digests.as_mut().unwrap();
+    digests.add(&record, &bytes)?;
+
+    let parse_err = |_| StorageError::InvalidBackup;
+    match record {
+      Record::ViewEntry {
+        idx,
+        block,
+        receipts,
+      } => {
+        let entry = LedgerEntry::new(
+          Block::from_bytes(&block).map_err(parse_err)?,
+          Receipts::from_bytes(&receipts).map_err(parse_err)?,
+          None,
+        );
+        Ok(Some(BackupEntry::View(idx as usize, entry)))
+      },
+      Record::LedgerEntry {
+        handle,
+        idx,
+        block,
+        nonces,
+        receipts,
+      } => {
+        let receipts = Receipts::from_bytes(&receipts).map_err(parse_err)?;
+        // a tombstone is told by its receipts, which sign it rather than an entry
+        let tombstone = receipts.is_tombstone();
+        let entry = LedgerEntry::new(
+          Block::from_bytes(&block).map_err(parse_err)?,
+          receipts,
+          Some(Nonces::from_bytes(&nonces).map_err(parse_err)?),
+        )
+        .with_tombstone(tombstone);
+        let handle = NimbleDigest::from_bytes(&handle).map_err(parse_err)?;
+        Ok(Some(BackupEntry::Ledger(handle, idx as usize, entry)))
+      },
+      Record::Manifest(manifest) => {
+        let expected = self.digests.take().unwrap().manifest();
+        if manifest.view_height != expected.view_height
+          || manifest.view_digest != expected.view_digest
+          || manifest.ledgers != expected.ledgers
+          || manifest.digest != expected.digest
+        {
+          eprintln!("The backup does not match its manifest");
+          return Err(StorageError::InvalidBackup);
+        }
+        let mut trailing = [0u8; 1];
+        if !matches!(self.reader.read(&mut trailing), Ok(0)) {
+          eprintln!("The backup has data after its manifest");
+          return Err(StorageError::InvalidBackup);
+        }
+        self.manifest = Some(manifest);
+        Ok(None)
+      },
+    }
+  }
+}
+
+/// Writes every entry of a backup into an empty ledger store and returns the manifest.
+/// Nonces are attached before each append so that the store hands back the same ones, and
+/// ledgers that were pruned are created from their checkpoint.
+pub async fn load_backup<R: Read>(
+  reader: &mut BackupReader<R>,
+  ledger_store: &(dyn LedgerStore + Send + Sync),
+) -> Result<BackupManifest, StorageError> {
+  let write_err = |e| {
+    eprintln!("Failed to write a backup entry to the store {:?}", e);
+    StorageError::UnhandledError
+  };
+
+  let mut current: Option<Handle> = None;
+  while let Some(entry) = reader.next_entry()? {
+    match entry {
+      BackupEntry::View(idx, entry) => {
+        ledger_store
+          .append_view_ledger(entry.get_block(), idx)
+          .await
+          .map_err(write_err)?;
+        ledger_store
+          .attach_view_ledger_receipts(idx, entry.get_receipts())
+          .await
+          .map_err(write_err)?;
+      },
+      BackupEntry::Ledger(handle, idx, entry) => {
+        // the reader checks that the entries of each ledger are in order and contiguous
+        let first = current != Some(handle);
+        current = Some(handle);
+        if idx == 0 {
+          ledger_store
+            .create_ledger(&handle, entry.get_block().clone())
+            .await
+            .map_err(write_err)?;
+        } else if first {
+          ledger_store
+            .create_pruned_ledger(&handle, idx, &entry)
+            .await
+            .map_err(write_err)?;
+        } else {
+          for nonce in entry.get_nonces().get() {
+            ledger_store
+              .attach_ledger_nonce(&handle, nonce)
+              .await
+              .map_err(write_err)?;
+          }
+          if entry.is_tombstone() {
+            ledger_store
+              .append_tombstone(&handle, idx)
+              .await
+              .map_err(write_err)?;
+          } else {
+            ledger_store
+              .append_ledger(&handle, entry.get_block(), idx)
+              .await
+              .map_err(write_err)?;
+          }
+        }
+        ledger_store
+          .attach_ledger_receipts(&handle, idx, entry.get_receipts())
+          .await
+          .map_err(write_err)?;
+      },
+    }
+  }
+
+  // the reader only stops once it has checked the manifest
+  Ok(reader.manifest().unwrap().clone())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ledger::in_memory::InMemoryLedgerStore;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    IdSig, MetaBlock, NimbleHashTrait, Nonce, Receipt,
+  };
+
+  async fn write_backup(store: &InMemoryLedgerStore, handles: &[Handle]) -> Vec<u8> {
+    let mut writer = BackupWriter::new(Vec::new()).unwrap();
+    let (_entry, view_height) = store.read_view_ledger_tail().await.unwrap();
+    for idx in 1..=view_height {
+      let entry = store.read_view_ledger_by_index(idx).await.unwrap();
+      writer.write_view_entry(idx, &entry).unwrap();
+    }
+    for handle in handles {
+      let height = store.ledger_height(handle).await.unwrap();
+      let checkpoint = store.ledger_checkpoint(handle).await.unwrap();
+      for idx in checkpoint..=height {
+        let entry = store.read_ledger_by_index(handle, idx).await.unwrap();
+        writer.write_ledger_entry(handle, idx, &entry).unwrap();
+      }
+    }
+    let (manifest, bytes) = writer.finish().unwrap();
+    assert_eq!(manifest.view_height, view_height as u64);
+    assert_eq!(manifest.ledgers.len(), handles.len());
+    bytes
+  }
+
+  async fn load(bytes: &[u8]) -> Result<BackupManifest, StorageError> {
+    let store = InMemoryLedgerStore::new();
+    let mut reader = BackupReader::new(bytes)?;
+    load_backup(&mut reader, &store).await
+  }
+
+  #[tokio::test]
+  pub async fn check_backup_round_trip() {
+    let store = InMemoryLedgerStore::new();
+    store
+      .append_view_ledger(&Block::new(b"view"), 1)
+      .await
+      .unwrap();
+    let handles = vec![NimbleDigest::digest(b"a"), NimbleDigest::digest(b"b")];
+    for (i, handle) in handles.iter().enumerate() {
+      store
+        .create_ledger(handle, Block::new(&[i as u8]))
+        .await
+        .unwrap();
+      for idx in 1..=i + 1 {
+        let nonce = Nonce::new(&[idx as u8; 16]).unwrap();
+        store.attach_ledger_nonce(handle, &nonce).await.unwrap();
+        store
+          .append_ledger(handle, &Block::new(&[i as u8, idx as u8]), idx)
+          .await
+          .unwrap();
+      }
+    }
+    // the second ledger is pruned, so it is backed up from its checkpoint
+    let sk = PrivateKey::new();
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(
+      NimbleDigest::default(),
+      MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::default(), 1),
+      IdSig::new(sk.get_public_key().unwrap(), sk.sign(&[0u8]).unwrap()),
+    ));
+    store
+      .attach_ledger_receipts(&handles[1], 1, &receipts)
+      .await
+      .unwrap();
+    assert_eq!(store.prune_ledger(&handles[1], 1).await.unwrap(), 1);
+    let bytes = write_backup(&store, &handles).await;
+
+    let restored = InMemoryLedgerStore::new();
+    let mut reader = BackupReader::new(&bytes[..]).unwrap();
+    let manifest = load_backup(&mut reader, &restored).await.unwrap();
+    assert_eq!(manifest.ledgers[1].height, 2);
+    for handle in &handles {
+      let height = store.ledger_height(handle).await.unwrap();
+      let checkpoint = store.ledger_checkpoint(handle).await.unwrap();
+      assert_eq!(restored.ledger_height(handle).await.unwrap(), height);
+      assert_eq!(
+        restored.ledger_checkpoint(handle).await.unwrap(),
+        checkpoint
+      );
+      for idx in checkpoint..=height {
+        let a = store.read_ledger_by_index(handle, idx).await.unwrap();
+        let b = restored.read_ledger_by_index(handle, idx).await.unwrap();
+        assert_eq!(a.get_block().hash(), b.get_block().hash());
+        assert_eq!(a.get_nonces().to_bytes(), b.get_nonces().to_bytes());
+      }
+    }
+
+    // tampered, truncated, and extended backups are rejected
+    let mut tampered = bytes.clone();
+    let pos = BACKUP_MAGIC.len() + 20;
+    tampered[pos] ^= 1;
+    assert!(load(&tampered).await.is_err());
+    assert_eq!(
+      load(&bytes[..bytes.len() - 1]).await.unwrap_err(),
+      StorageError::InvalidBackup
+    );
+    let mut extended = bytes.clone();
+    extended.push(0);
+    assert_eq!(
+      load(&extended).await.unwrap_err(),
+      StorageError::InvalidBackup
+    );
+    assert_eq!(
+      load(b"not a backup").await.unwrap_err(),
+      StorageError::InvalidBackup
+    );
+
+    // entries must be written in order and without gaps
+    let mut writer = BackupWriter::new(Vec::new()).unwrap();
+    let entry = store.read_ledger_by_index(&handles[0], 1).await.unwrap();
+    writer.write_ledger_entry(&handles[0], 1, &entry).unwrap();
+    assert_eq!(
+      writer
+        .write_ledger_entry(&handles[0], 3, &entry)
+        .unwrap_err(),
+      StorageError::InvalidBackup
+    );
+  }
+}
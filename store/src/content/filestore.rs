@@ -0,0 +1,100 @@
+use super::Handle;
+use crate::{content::ContentStore, errors::StorageError};
+use async_trait::async_trait;
+use hex;
+use std::{
+  collections::HashMap,
+  fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub struct FileContentStore {
+  dir_path: PathBuf,
+}
+
+impl FileContentStore {
+  pub async fn new(args: &HashMap<String, String>) -> Result<Self, StorageError> {
+    if !args.contains_key("NIMBLE_CONTENT_DIR") {
+      return Err(StorageError::MissingArguments);
+    }
+    let dir_path = Path::new(&args["NIMBLE_CONTENT_DIR"]).to_path_buf();
+
+    // Try to create directory. If it exists that's fine.
+    match fs::create_dir_all(&dir_path) {
+      Ok(()) => (),
+      Err(e) => {
+        eprintln!("Unable to create path {:?}, error: {:?}", &dir_path, e);
+        return Err(StorageError::InvalidDBName);
+      },
+    };
+
+    Ok(FileContentStore { dir_path })
+  }
+
+  fn content_path(&self, handle: &Handle) -> PathBuf {
+    self.dir_path.join(hex::encode(handle.to_bytes()))
+  }
+}
+
+#[async_trait]
+impl ContentStore for FileContentStore {
+  async fn put(&self, data: &[u8]) -> Result<Handle, StorageError> {
+    let handle = Handle::digest(data);
+    let path = self.content_path(&handle);
+
+    // Content is addressed by its hash, so an existing file already holds this data
+    if path.exists() {
+      return Ok(handle);
+    }
+
+    // Write to a temporary file first so that a crash never leaves a partial file
+    // under the content's name
+    let tmp_path = path.with_extension(format!("tmp{}", rand::random::<u64>()));
+    if let Err(e) = fs::write(&tmp_path, data) {
+      eprintln!("Failed to write content to {:?}, error: {:?}", &tmp_path, e);
+      return Err(StorageError::UnhandledError);
+    }
+    if let Err(e) = fs::rename(&tmp_path, &path) {
+      eprintln!(
+        "Failed to rename {:?} to {:?}, error: {:?}",
+        &tmp_path, &path, e
+      );
+      let _ = fs::remove_file(&tmp_path);
+      return Err(StorageError::UnhandledError);
+    }
+
+    Ok(handle)
+  }
+
+  async fn get(&self, handle: &Handle) -> Result<Vec<u8>, StorageError> {
+    match fs::read(self.content_path(handle)) {
+      Ok(data) => Ok(data),
+      Err(e) if e.kind() == ErrorKind::NotFound => Err(StorageError::KeyDoesNotExist),
+      Err(e) => {
+        eprintln!("Failed to read content {:?}, error: {:?}", handle, e);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+
+  async fn reset_store(&self) -> Result<(), StorageError> {
+    match fs::remove_dir_all(&self.dir_path) {
+      Ok(()) => (),
+      Err(e) if e.kind() == ErrorKind::NotFound => (),
+      Err(e) => {
+        eprintln!("Failed to remove {:?}, error: {:?}", &self.dir_path, e);
+        return Err(StorageError::UnhandledError);
+      },
+    };
+
+    match fs::create_dir_all(&self.dir_path) {
+      Ok(()) => Ok(()),
+      Err(e) => {
+        eprintln!("Unable to create path {:?}, error: {:?}", &self.dir_path, e);
+        Err(StorageError::InvalidDBName)
+      },
+    }
+  }
+}
@@ -1,8 +1,13 @@
 use crate::errors::StorageError;
 use async_trait::async_trait;
-use ledger::Handle;
+use ledger::{Block, CustomSerde, Handle, NimbleHashTrait};
 
+pub mod filestore;
 pub mod in_memory;
+pub mod s3;
+
+/// Prefix of a block that stands in for content held in a `ContentStore`
+const CONTENT_REF_PREFIX: &[u8] = b"nimble-content-ref:";
 
 #[async_trait]
 pub trait ContentStore {
@@ -10,3 +15,119 @@ pub trait ContentStore {
   async fn get(&self, handle: &Handle) -> Result<Vec<u8>, StorageError>;
   async fn reset_store(&self) -> Result<(), StorageError>; // only used for testing
 }
+
+/// Produces the block that is kept in the ledger store and the endorsers in place of
+/// content that was put in a `ContentStore` under `handle`
+pub fn encode_content_ref(handle: &Handle) -> Block {
+  Block::new(&[CONTENT_REF_PREFIX, &handle.to_bytes()].concat())
+}
+
+/// Returns the handle of the out-of-line content if `block` is a reference block
+pub fn decode_content_ref(block: &Block) -> Option<Handle> {
+  if block.len() != CONTENT_REF_PREFIX.len() + Handle::num_bytes() {
+    return None;
+  }
+  let bytes = block.to_bytes();
+  if !bytes.starts_with(CONTENT_REF_PREFIX) {
+    return None;
+  }
+  Handle::from_bytes(&bytes[CONTENT_REF_PREFIX.len()..]).ok()
+}
+
+/// Returns the hash of the content that `block` represents. For a reference block this
+/// is the handle it embeds, which lets the coordinator compute block hashes without
+/// fetching the content.
+pub fn content_hash(block: &Block) -> Handle {
+  match decode_content_ref(block) {
+    Some(handle) => handle,
+    None => block.hash(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::content::{
+    content_hash, decode_content_ref, encode_content_ref, filestore::FileContentStore,
+    in_memory::InMemoryContentStore, s3::S3ContentStore, ContentStore,
+  };
+  use crate::errors::StorageError;
+  use ledger::{Block, Handle, NimbleHashTrait};
+  use std::collections::HashMap;
+
+  pub async fn check_content_store_operations(state: &dyn ContentStore) {
+    let data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+
+    let handle = state.put(&data).await.expect("failed to put content");
+    assert_eq!(handle, Handle::digest(&data));
+
+    // putting the same content again is idempotent
+    let handle2 = state.put(&data).await.expect("failed to put content");
+    assert_eq!(handle, handle2);
+
+    let res = state.get(&handle).await;
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), data);
+
+    let res = state.get(&Handle::digest(&[1, 2, 3])).await;
+    assert_eq!(res.unwrap_err(), StorageError::KeyDoesNotExist);
+  }
+
+  #[test]
+  pub fn check_content_ref() {
+    let content = Block::new(&[7u8; 2048]);
+    let handle = content.hash();
+
+    let block_ref = encode_content_ref(&handle);
+    assert_eq!(decode_content_ref(&block_ref), Some(handle));
+    assert_eq!(content_hash(&block_ref), handle);
+
+    assert_eq!(decode_content_ref(&content), None);
+    assert_eq!(content_hash(&content), handle);
+  }
+
+  #[tokio::test]
+  pub async fn check_in_memory_content_store() {
+    let state = InMemoryContentStore::new();
+    check_content_store_operations(&state).await;
+  }
+
+  #[tokio::test]
+  pub async fn check_file_content_store() {
+    if std::env::var_os("NIMBLE_CONTENT_DIR").is_none() {
+      // The right env variables are not available so let's skip tests
+      return;
+    }
+
+    let mut args = HashMap::<String, String>::new();
+    args.insert(
+      String::from("NIMBLE_CONTENT_DIR"),
+      std::env::var_os("NIMBLE_CONTENT_DIR")
+        .unwrap()
+        .into_string()
+        .unwrap(),
+    );
+
+    let state = FileContentStore::new(&args).await.unwrap();
+    let _ = state.reset_store().await;
+    check_content_store_operations(&state).await;
+  }
+
+  #[tokio::test]
+  pub async fn check_s3_content_store() {
+    let keys = ["S3_ENDPOINT", "S3_BUCKET", "S3_ACCESS_KEY", "S3_SECRET_KEY"];
+    if keys.iter().any(|k| std::env::var_os(k).is_none()) {
+      // The right env variables are not available so let's skip tests
+      return;
+    }
+
+    let mut args = HashMap::<String, String>::new();
+    for k in keys.iter().chain(["S3_REGION"].iter()) {
+      if let Some(v) = std::env::var_os(k) {
+        args.insert(k.to_string(), v.into_string().unwrap());
+      }
+    }
+
+    let state = S3ContentStore::new(&args).await.unwrap();
+    check_content_store_operations(&state).await;
+  }
+}
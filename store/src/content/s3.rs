@@ -0,0 +1,189 @@
+use super::Handle;
+use crate::{content::ContentStore, errors::StorageError};
+use async_trait::async_trait;
+use chrono::Utc;
+use hex;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const DEFAULT_S3_REGION: &str = "us-east-1";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A content store backed by any service that speaks the S3 object API (AWS S3, MinIO,
+/// Ceph RGW, ...). Objects are addressed path-style as `<endpoint>/<bucket>/<hex(handle)>`
+/// and requests are authenticated with AWS Signature Version 4.
+#[derive(Debug)]
+pub struct S3ContentStore {
+  client: Client,
+  endpoint: Url,
+  bucket: String,
+  region: String,
+  access_key: String,
+  secret_key: String,
+}
+
+impl S3ContentStore {
+  pub async fn new(args: &HashMap<String, String>) -> Result<Self, StorageError> {
+    if !args.contains_key("S3_ENDPOINT")
+      || !args.contains_key("S3_BUCKET")
+      || !args.contains_key("S3_ACCESS_KEY")
+      || !args.contains_key("S3_SECRET_KEY")
+    {
+      return Err(StorageError::MissingArguments);
+    }
+
+    let endpoint = match Url::parse(&args["S3_ENDPOINT"]) {
+      Ok(url) if url.host_str().is_some() => url,
+      _ => {
+        eprintln!("Invalid S3 endpoint {}", args["S3_ENDPOINT"]);
+        return Err(StorageError::InvalidDBUri);
+      },
+    };
+
+    let region = match args.get("S3_REGION") {
+      Some(r) => r.clone(),
+      None => DEFAULT_S3_REGION.to_string(),
+    };
+
+    Ok(S3ContentStore {
+      client: Client::new(),
+      endpoint,
+      bucket: args["S3_BUCKET"].clone(),
+      region,
+      access_key: args["S3_ACCESS_KEY"].clone(),
+      secret_key: args["S3_SECRET_KEY"].clone(),
+    })
+  }
+
+  fn host(&self) -> String {
+    let host = self.endpoint.host_str().unwrap_or_default();
+    match self.endpoint.port() {
+      Some(port) => format!("{}:{}", host, port),
+      None => host.to_string(),
+    }
+  }
+
+  async fn send(
+    &self,
+    method: Method,
+    handle: &Handle,
+    body: Vec<u8>,
+  ) -> Result<reqwest::Response, StorageError> {
+    let path = format!(
+      "{}/{}/{}",
+      self.endpoint.path().trim_end_matches('/'),
+      self.bucket,
+      hex::encode(handle.to_bytes())
+    );
+    let mut url = self.endpoint.clone();
+    url.set_path(&path);
+
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(Sha256::digest(&body));
+    let host = self.host();
+
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+      "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+      method.as_str(),
+      path,
+      host,
+      payload_hash,
+      amz_date,
+      signed_headers,
+      payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date_stamp, self.region);
+    let string_to_sign = format!(
+      "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+      amz_date,
+      scope,
+      hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = [self.region.as_bytes(), b"s3", b"aws4_request"]
+      .iter()
+      .fold(
+        hmac_sha256(
+          format!("AWS4{}", self.secret_key).as_bytes(),
+          date_stamp.as_bytes(),
+        ),
+        |key, data| hmac_sha256(&key, data),
+      );
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    let authorization = format!(
+      "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+      self.access_key, scope, signed_headers, signature
+    );
+
+    let res = self
+      .client
+      .request(method, url)
+      .header("host", host)
+      .header("x-amz-content-sha256", payload_hash)
+      .header("x-amz-date", amz_date)
+      .header("authorization", authorization)
+      .body(body)
+      .send()
+      .await;
+    match res {
+      Ok(resp) => Ok(resp),
+      Err(e) => {
+        eprintln!("Failed to reach the S3 endpoint {:?}", e);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+  // HMAC accepts keys of any length, so this never fails
+  let mut mac = HmacSha256::new_from_slice(key).unwrap();
+  mac.update(data);
+  mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait]
+impl ContentStore for S3ContentStore {
+  async fn put(&self, data: &[u8]) -> Result<Handle, StorageError> {
+    let handle = Handle::digest(data);
+
+    // Objects are addressed by their hash, so overwriting an existing object is harmless
+    let resp = self.send(Method::PUT, &handle, data.to_vec()).await?;
+    if resp.status().is_success() {
+      Ok(handle)
+    } else {
+      eprintln!("Failed to put content {:?} ({})", handle, resp.status());
+      Err(StorageError::UnhandledError)
+    }
+  }
+
+  async fn get(&self, handle: &Handle) -> Result<Vec<u8>, StorageError> {
+    let resp = self.send(Method::GET, handle, Vec::new()).await?;
+    match resp.status() {
+      StatusCode::OK => match resp.bytes().await {
+        Ok(bytes) => Ok(bytes.to_vec()),
+        Err(e) => {
+          eprintln!("Failed to read content {:?} ({:?})", handle, e);
+          Err(StorageError::UnhandledError)
+        },
+      },
+      StatusCode::NOT_FOUND => Err(StorageError::KeyDoesNotExist),
+      status => {
+        eprintln!("Failed to get content {:?} ({})", handle, status);
+        Err(StorageError::UnhandledError)
+      },
+    }
+  }
+
+  async fn reset_store(&self) -> Result<(), StorageError> {
+    // Deleting the objects requires listing the bucket, which this store does not need
+    // otherwise. Since objects are content-addressed, leftovers do not affect tests.
+    Ok(())
+  }
+}
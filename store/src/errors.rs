@@ -50,6 +50,16 @@ pub enum StorageError {
   UnhandledError,
   /// return if the name for the nimble database is not acceptable for the store
   InvalidDBName,
+  /// return if an encrypted block cannot be decrypted (e.g., wrong key or tampered data)
+  DecryptionFailed,
+  /// return if a backup is malformed or does not match its manifest
+  InvalidBackup,
+  /// return if the requested entry was pruned from the ledger
+  PrunedIndex,
+  /// return if a ledger is pruned at, or created from, an entry without receipts
+  InvalidCheckpoint,
+  /// return if the store does not support the operation
+  UnsupportedOperation,
 }
 
 use std::fmt::Display;
@@ -1,6 +1,6 @@
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{Lease, LedgerEntry, LedgerStore},
 };
 use async_trait::async_trait;
 use azure_data_tables::{clients::TableClient, prelude::*};
@@ -21,6 +21,7 @@ use std::{
 use http::{self, StatusCode};
 
 const TAIL: &str = "TAIL";
+const MAX_QUERY_RESULTS: usize = 1000; // the maximum number of entities returned by a query
 
 enum AzureOp {
   Append,
@@ -128,6 +129,12 @@ struct DBEntry {
   pub block: String,
   pub receipts: String,
   pub nonces: String,
+  // the ID of the request that added the entry, if the client gave one
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub request_id: String,
+  // whether the entry is the tombstone of a deleted ledger
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub tombstone: bool,
 }
 
 // This is a projection so you only modify the receipt, not the rest
@@ -150,6 +157,47 @@ struct DBEntryNonceProjection {
   pub nonces: String,
 }
 
+// This is a projection so you only read the height of the tail, not the rest
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBEntryHeightProjection {
+  #[serde(rename = "PartitionKey")]
+  pub handle: String,
+  #[serde(rename = "RowKey")]
+  pub row: String,
+  pub height: i64,
+}
+
+// The lease is kept in a row of its own partition; the partition is not the encoding of any
+// handle, and the row is not a TAIL row, so the lease is never listed as a ledger
+const LEASE: &str = "LEASE";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLease {
+  #[serde(rename = "PartitionKey")]
+  pub partition: String,
+  #[serde(rename = "RowKey")]
+  pub row: String,
+  pub holder: String,
+  pub epoch: i64,
+  pub expires_at: i64,
+}
+
+// Every ledger but the view ledger has a row in the index partition, keyed by the hex encoding
+// of its handle, so that ledgers can be listed in the order of their handles: queries return
+// rows in the order of their keys, and the order of hex encodings is the order of handles,
+// while the order of the base64url encodings used as partition keys is not. The marker row of
+// its own partition records that the ledgers created before the index existed were added to it.
+const LEDGERS: &str = "LEDGERS";
+const INDEXED: &str = "INDEXED";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLedger {
+  #[serde(rename = "PartitionKey")]
+  pub partition: String,
+  #[serde(rename = "RowKey")]
+  pub row: String,
+}
+
 #[derive(Debug)]
 pub struct TableLedgerStore {
   client: Arc<TableClient>,
@@ -237,6 +285,8 @@ impl TableLedgerStore {
               block: base64_url::encode(&Block::new(&[0; 0]).to_bytes()),
               receipts: base64_url::encode(&Receipts::new().to_bytes()),
               nonces: base64_url::encode(&Nonces::new().to_bytes()),
+              request_id: String::new(),
+              tombstone: false,
             };
 
             azure_op(
@@ -270,8 +320,140 @@ impl TableLedgerStore {
       },
     };
 
+    ledger_store.index_existing_ledgers().await?;
+
     Ok(ledger_store)
   }
+
+  // writes the row `row` of partition `partition`, replacing it if it exists
+  async fn upsert_row(&self, partition: &str, row: &str) -> Result<(), LedgerStoreError> {
+    let partition_client = self.client.as_partition_key_client(partition);
+    let row_client = match partition_client.as_entity_client(row) {
+      Ok(v) => v,
+      Err(e) => {
+        eprintln!("Unable to get row client in upsert_row: {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    let entity = DBLedger {
+      partition: partition.to_owned(),
+      row: row.to_owned(),
+    };
+    if let Err(err) = row_client.insert_or_replace().execute(&entity).await {
+      return Err(parse_error_status(get_error_status!(err)));
+    }
+    Ok(())
+  }
+
+  // adds `handle` to the index, doing nothing if it is there already
+  async fn index_ledger(&self, handle: &Handle) -> Result<(), LedgerStoreError> {
+    self
+      .upsert_row(LEDGERS, &hex::encode(handle.to_bytes()))
+      .await
+  }
+
+  // adds the ledgers created before the index existed to it, once
+  async fn index_existing_ledgers(&self) -> Result<(), LedgerStoreError> {
+    let partition_client = self.client.as_partition_key_client(INDEXED);
+    let row_client = match partition_client.as_entity_client(INDEXED) {
+      Ok(v) => v,
+      Err(e) => {
+        eprintln!(
+          "Unable to get row client in index_existing_ledgers: {:?}",
+          e
+        );
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+    match row_client.get().execute::<DBLedger>().await {
+      Ok(_) => return Ok(()),
+      Err(err) => match parse_error_status(get_error_status!(err)) {
+        LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist) => (),
+        e => return Err(e),
+      },
+    }
+
+    // Every ledger has exactly one TAIL row
+    let filter = format!("RowKey eq '{}'", TAIL);
+    let mut continuation = None;
+    loop {
+      let query = self
+        .client
+        .query()
+        .filter(Filter::new(filter.as_str()))
+        .select(Select::new("PartitionKey,RowKey,height"))
+        .top(Top::new(checked_conversion!(MAX_QUERY_RESULTS, u32)));
+      let query = match continuation {
+        Some(c) => query.continuation_next_partition_and_row_key(c),
+        None => query,
+      };
+
+      let res = match query.execute::<DBEntryHeightProjection>().await {
+        Ok(res) => res,
+        Err(err) => return Err(parse_error_status(get_error_status!(err))),
+      };
+
+      for entity in res.entities {
+        let handle = match NimbleDigest::from_bytes(&string_decode(&entity.handle)?) {
+          Ok(h) => h,
+          Err(e) => {
+            eprintln!("Unable to decode handle {:?}", e);
+            return Err(LedgerStoreError::LedgerError(
+              StorageError::DeserializationError,
+            ));
+          },
+        };
+        if handle != self.view_handle {
+          self.index_ledger(&handle).await?;
+        }
+      }
+
+      continuation = res.continuation_next_partition_and_row_key;
+      if continuation.is_none() {
+        break;
+      }
+    }
+
+    self.upsert_row(INDEXED, INDEXED).await
+  }
+
+  // appends `entry`, retrying when another append got in the way of updating the tail
+  async fn append_entry(
+    &self,
+    handle: &Handle,
+    entry: &LedgerEntry,
+    expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let ledger = self.client.clone();
+    let handle_string = base64_url::encode(&handle.to_bytes());
+
+    loop {
+      let res = append_ledger_internal(
+        &handle_string,
+        entry,
+        expected_height,
+        ledger.clone(),
+        &self.cache,
+      )
+      .await;
+
+      match res {
+        Ok(v) => return Ok(v),
+        Err(e) => match e {
+          LedgerStoreError::LedgerError(StorageError::ConcurrentOperation) => {
+            fix_cached_entry(&handle_string, &self.cache, ledger.clone()).await?;
+          },
+          LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData) => {
+            return Err(LedgerStoreError::LedgerError(
+              StorageError::IncorrectConditionalData,
+            ))
+          },
+          _ => return Err(e),
+        },
+      }
+    }
+  }
 }
 
 fn decode_nonces_string(nonces: &str) -> Result<Nonces, LedgerStoreError> {
@@ -400,6 +582,28 @@ async fn azure_op(
   Ok(())
 }
 
+async fn find_lease(ledger: Arc<TableClient>) -> Result<Option<(DBLease, Etag)>, LedgerStoreError> {
+  let partition_client = ledger.as_partition_key_client(LEASE);
+  let row_client = match partition_client.as_entity_client(LEASE) {
+    Ok(v) => v,
+    Err(e) => {
+      eprintln!("Error in find_lease: {:?}", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    },
+  };
+
+  let res = row_client.get().execute().await;
+  if let Err(err) = res {
+    return match parse_error_status(get_error_status!(err)) {
+      LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist) => Ok(None),
+      e => Err(e),
+    };
+  }
+
+  let res = res.unwrap();
+  Ok(Some((res.entity, res.etag)))
+}
+
 async fn attach_ledger_receipts_internal(
   ledger: Arc<TableClient>,
   handle_string: &str,
@@ -468,9 +672,10 @@ async fn find_db_entry(
   Ok((res.entity, res.etag))
 }
 
+// appends the block, request ID and tombstone flag of `entry`
 async fn append_ledger_internal(
   handle: &str,
-  block: &Block,
+  entry: &LedgerEntry,
   expected_height: usize,
   ledger: Arc<TableClient>,
   cache: &CacheMap,
@@ -522,18 +727,22 @@ async fn append_ledger_internal(
     handle: handle.to_owned(),
     row: height_plus_one.to_string(),
     height: height_plus_one,
-    block: base64_url::encode(&block.to_bytes()),
+    block: base64_url::encode(&entry.get_block().to_bytes()),
     receipts: base64_url::encode(&Receipts::new().to_bytes()),
     nonces: base64_url::encode(&Nonces::new().to_bytes()), // clear out the nonces in tail
+    request_id: base64_url::encode(entry.get_request_id()),
+    tombstone: entry.is_tombstone(),
   };
 
   let indexed_entry = DBEntry {
     handle: handle.to_owned(),
     row: height_plus_one.to_string(),
     height: height_plus_one,
-    block: base64_url::encode(&block.to_bytes()),
+    block: base64_url::encode(&entry.get_block().to_bytes()),
     receipts: base64_url::encode(&Receipts::new().to_bytes()),
     nonces: base64_url::encode(&cache_entry.get_nonces().to_bytes()),
+    request_id: base64_url::encode(entry.get_request_id()),
+    tombstone: entry.is_tombstone(),
   };
 
   // 4. Try to insert the new entry into the ledger and set the tail
@@ -696,9 +905,12 @@ async fn read_ledger_internal(
   };
 
   let nonce_list = decode_nonces_string(&entry.nonces)?;
+  let request_id = string_decode(&entry.request_id)?;
 
   Ok((
-    LedgerEntry::new(ret_block, ret_receipts, Some(nonce_list)),
+    LedgerEntry::new(ret_block, ret_receipts, Some(nonce_list))
+      .with_request_id(&request_id)
+      .with_tombstone(entry.tombstone),
     checked_conversion!(entry.height, usize),
   ))
 }
@@ -798,10 +1010,11 @@ fn update_cache_entry(
 
 #[async_trait]
 impl LedgerStore for TableLedgerStore {
-  async fn create_ledger(
+  async fn create_ledger_with_request(
     &self,
     handle: &Handle,
     genesis_block: Block,
+    request_id: &[u8],
   ) -> Result<(), LedgerStoreError> {
     let ledger = self.client.clone();
     let handle_string = base64_url::encode(&handle.to_bytes());
@@ -814,9 +1027,11 @@ impl LedgerStore for TableLedgerStore {
       block: base64_url::encode(&genesis_block.to_bytes()),
       receipts: base64_url::encode(&Receipts::new().to_bytes()),
       nonces,
+      request_id: base64_url::encode(request_id),
+      tombstone: false,
     };
 
-    azure_op(
+    let res = azure_op(
       ledger,
       &handle_string,
       entry.clone(),
@@ -825,43 +1040,40 @@ impl LedgerStore for TableLedgerStore {
       AzureOp::Create,
       None,
     )
-    .await
+    .await;
+
+    // A ledger that already exists may have been created by an earlier call that failed before
+    // indexing it, so it is indexed either way
+    match res {
+      Ok(()) | Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey)) => {
+        self.index_ledger(handle).await?;
+        res
+      },
+      Err(_) => res,
+    }
   }
 
-  async fn append_ledger(
+  async fn append_ledger_with_request(
     &self,
     handle: &Handle,
     block: &Block,
     expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(usize, Nonces), LedgerStoreError> {
-    let ledger = self.client.clone();
-    let handle_string = base64_url::encode(&handle.to_bytes());
-
-    loop {
-      let res = append_ledger_internal(
-        &handle_string,
-        block,
-        expected_height,
-        ledger.clone(),
-        &self.cache,
-      )
-      .await;
+    let entry = LedgerEntry::new(block.clone(), Receipts::new(), None).with_request_id(request_id);
+    self.append_entry(handle, &entry, expected_height).await
+  }
 
-      match res {
-        Ok(v) => return Ok(v),
-        Err(e) => match e {
-          LedgerStoreError::LedgerError(StorageError::ConcurrentOperation) => {
-            fix_cached_entry(&handle_string, &self.cache, ledger.clone()).await?;
-          },
-          LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData) => {
-            return Err(LedgerStoreError::LedgerError(
-              StorageError::IncorrectConditionalData,
-            ))
-          },
-          _ => return Err(e),
-        },
-      }
-    }
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let entry = LedgerEntry::new(Block::tombstone(), Receipts::new(), None).with_tombstone(true);
+    self
+      .append_entry(handle, &entry, expected_height)
+      .await
+      .map(|_| ())
   }
 
   async fn attach_ledger_receipts(
@@ -959,6 +1171,145 @@ impl LedgerStore for TableLedgerStore {
     Ok(height)
   }
 
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let ledger = self.client.clone();
+    let handle_string = base64_url::encode(&handle.to_bytes());
+    let (entry, _etag) = find_db_entry(ledger, &handle_string, TAIL).await?;
+    Ok(checked_conversion!(entry.height, usize))
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    if limit == 0 {
+      return Ok(Vec::new());
+    }
+
+    // The rows of the index partition are returned in the order of their keys, which is the
+    // order of the handles they encode
+    let filter = match cursor {
+      Some(c) => format!(
+        "PartitionKey eq '{}' and RowKey gt '{}'",
+        LEDGERS,
+        hex::encode(c.to_bytes())
+      ),
+      None => format!("PartitionKey eq '{}'", LEDGERS),
+    };
+    let top = checked_conversion!(std::cmp::min(limit, MAX_QUERY_RESULTS), u32);
+
+    let mut handles = Vec::new();
+    let mut continuation = None;
+    loop {
+      let query = self
+        .client
+        .query()
+        .filter(Filter::new(filter.as_str()))
+        .top(Top::new(top));
+      let query = match continuation {
+        Some(c) => query.continuation_next_partition_and_row_key(c),
+        None => query,
+      };
+
+      let res = query.execute::<DBLedger>().await;
+      if let Err(err) = res {
+        return Err(parse_error_status(get_error_status!(err)));
+      }
+      let res = res.unwrap();
+
+      for entity in res.entities {
+        let bytes = match hex::decode(&entity.row) {
+          Ok(b) => b,
+          Err(e) => {
+            eprintln!("Unable to decode handle {:?}", e);
+            return Err(LedgerStoreError::LedgerError(
+              StorageError::DeserializationError,
+            ));
+          },
+        };
+        match NimbleDigest::from_bytes(&bytes) {
+          Ok(h) => handles.push(h),
+          Err(e) => {
+            eprintln!("Unable to decode handle {:?}", e);
+            return Err(LedgerStoreError::LedgerError(
+              StorageError::DeserializationError,
+            ));
+          },
+        }
+      }
+
+      continuation = res.continuation_next_partition_and_row_key;
+      if handles.len() >= limit || continuation.is_none() {
+        break;
+      }
+    }
+
+    handles.truncate(limit);
+    Ok(handles)
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    match find_lease(self.client.clone()).await? {
+      None => Ok(None),
+      Some((l, _etag)) => Ok(Some(Lease {
+        holder: l.holder,
+        epoch: checked_conversion!(l.epoch, u64),
+        expires_at: checked_conversion!(l.expires_at, u64),
+      })),
+    }
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    let new_lease = DBLease {
+      partition: LEASE.to_owned(),
+      row: LEASE.to_owned(),
+      holder: lease.holder.clone(),
+      epoch: checked_conversion!(lease.epoch, i64),
+      expires_at: checked_conversion!(lease.expires_at, i64),
+    };
+    let conflict = || LedgerStoreError::LedgerError(StorageError::IncorrectConditionalData);
+
+    let current = find_lease(self.client.clone()).await?;
+    let res = match (expected, current) {
+      // the insert fails with CONFLICT if another coordinator wrote a lease first
+      (None, None) => self.client.insert().execute(&new_lease).await.map(|_| ()),
+      // the update fails with PRECONDITION_FAILED if the lease changed since it was read
+      (Some(e), Some((l, etag)))
+        if l.holder == e.holder
+          && l.epoch == checked_conversion!(e.epoch, i64)
+          && l.expires_at == checked_conversion!(e.expires_at, i64) =>
+      {
+        let partition_client = self.client.as_partition_key_client(LEASE);
+        let row_client = match partition_client.as_entity_client(LEASE) {
+          Ok(v) => v,
+          Err(e) => {
+            eprintln!("Unable to get row client in write lease: {:?}", e);
+            return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+          },
+        };
+        row_client
+          .update()
+          .execute(&new_lease, &IfMatchCondition::Etag(etag))
+          .await
+          .map(|_| ())
+      },
+      _ => return Err(conflict()),
+    };
+
+    if let Err(err) = res {
+      return match get_error_status!(err) {
+        StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => Err(conflict()),
+        status => Err(parse_error_status(status)),
+      };
+    }
+    Ok(())
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     let ledger = self.client.clone();
     ledger
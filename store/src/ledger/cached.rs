@@ -0,0 +1,420 @@
+use super::{Lease, LedgerEntry, LedgerStore};
+use crate::errors::{LedgerStoreError, StorageError};
+use async_trait::async_trait;
+use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+use lru_cache::LruCache;
+use std::sync::{
+  atomic::{AtomicU64, Ordering},
+  Mutex,
+};
+
+/// Hit and miss counts of a `CachedLedgerStore`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+  pub hits: u64,
+  pub misses: u64,
+}
+
+impl CacheStats {
+  pub fn hit_rate(&self) -> f64 {
+    let total = self.hits + self.misses;
+    if total == 0 {
+      0.0
+    } else {
+      self.hits as f64 / total as f64
+    }
+  }
+}
+
+struct Cache {
+  // the height of the tail of each ledger
+  tails: LruCache<Handle, usize>,
+  // recently read or written entries of each ledger
+  entries: LruCache<(Handle, usize), LedgerEntry>,
+  // bumped on every invalidation so that a read that started before it does not put
+  // back what was just invalidated
+  epoch: u64,
+  // the epoch of the last lease written through the cache, if any
+  lease_epoch: Option<u64>,
+}
+
+/// A ledger store that keeps the tails and recently used entries of ledgers in memory in
+/// front of another ledger store.
+///
+/// The cache is kept coherent with the writes that go through it, so it must be the only
+/// writer of the underlying store while it holds anything. Coordinators that share the store
+/// take turns through a lease, which is written through the cache, so the cache is dropped
+/// whenever a lease changes hands: what the other leaders wrote is read back from the store.
+/// The view ledger is not cached.
+pub struct CachedLedgerStore<S: LedgerStore> {
+  store: S,
+  cache: Mutex<Cache>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+}
+
+impl<S: LedgerStore> CachedLedgerStore<S> {
+  pub fn new(store: S, capacity: usize) -> Self {
+    CachedLedgerStore {
+      store,
+      cache: Mutex::new(Cache {
+        tails: LruCache::new(capacity),
+        entries: LruCache::new(capacity),
+        epoch: 0,
+        lease_epoch: None,
+      }),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+    }
+  }
+
+  pub fn get_store(&self) -> &S {
+    &self.store
+  }
+
+  pub fn stats(&self) -> CacheStats {
+    CacheStats {
+      hits: self.hits.load(Ordering::SeqCst),
+      misses: self.misses.load(Ordering::SeqCst),
+    }
+  }
+
+  fn lock(&self) -> Result<std::sync::MutexGuard<'_, Cache>, LedgerStoreError> {
+    self
+      .cache
+      .lock()
+      .map_err(|_| LedgerStoreError::LedgerError(StorageError::LedgerMapWriteLockFailed))
+  }
+
+  fn hit(&self) {
+    self.hits.fetch_add(1, Ordering::SeqCst);
+  }
+
+  fn miss(&self) {
+    self.misses.fetch_add(1, Ordering::SeqCst);
+  }
+
+  fn cached_tail(&self, handle: &Handle) -> Result<(Option<usize>, u64), LedgerStoreError> {
+    let mut cache = self.lock()?;
+    let height = cache.tails.get_mut(handle).copied();
+    Ok((height, cache.epoch))
+  }
+
+  fn cached_entry(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<(Option<LedgerEntry>, u64), LedgerStoreError> {
+    let mut cache = self.lock()?;
+    let entry = cache.entries.get_mut(&(*handle, idx)).cloned();
+    Ok((entry, cache.epoch))
+  }
+
+  // Heights only grow, so a tail read concurrently with an append never replaces a newer one
+  fn update_tail(&self, handle: &Handle, height: usize, epoch: Option<u64>) {
+    if let Ok(mut cache) = self.cache.lock() {
+      if let Some(e) = epoch {
+        if e != cache.epoch {
+          return;
+        }
+      }
+      let newer = match cache.tails.get_mut(handle) {
+        Some(h) => *h < height,
+        None => true,
+      };
+      if newer {
+        cache.tails.insert(*handle, height);
+      }
+    }
+  }
+
+  fn update_entry(&self, handle: &Handle, idx: usize, entry: LedgerEntry, epoch: Option<u64>) {
+    if let Ok(mut cache) = self.cache.lock() {
+      if let Some(e) = epoch {
+        if e != cache.epoch {
+          return;
+        }
+      }
+      cache.entries.insert((*handle, idx), entry);
+    }
+  }
+
+  fn clear(&self) {
+    if let Ok(mut cache) = self.cache.lock() {
+      cache.epoch += 1;
+      cache.tails.clear();
+      cache.entries.clear();
+    }
+  }
+
+  fn invalidate(&self, handle: &Handle, idx: Option<usize>) {
+    if let Ok(mut cache) = self.cache.lock() {
+      cache.epoch += 1;
+      match idx {
+        Some(i) => {
+          cache.entries.remove(&(*handle, i));
+        },
+        None => {
+          cache.tails.remove(handle);
+        },
+      }
+    }
+  }
+}
+
+#[async_trait]
+impl<S: LedgerStore + Send + Sync> LedgerStore for CachedLedgerStore<S> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .store
+      .create_ledger_with_request(handle, genesis_block.clone(), request_id)
+      .await?;
+    self.update_tail(handle, 0, None);
+    self.update_entry(
+      handle,
+      0,
+      LedgerEntry::new(genesis_block, Receipts::new(), None).with_request_id(request_id),
+      None,
+    );
+    Ok(())
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    match self
+      .store
+      .append_ledger_with_request(handle, block, expected_height, request_id)
+      .await
+    {
+      Ok((height, nonces)) => {
+        self.update_tail(handle, height, None);
+        self.update_entry(
+          handle,
+          height,
+          LedgerEntry::new(block.clone(), Receipts::new(), Some(nonces.clone()))
+            .with_request_id(request_id),
+          None,
+        );
+        Ok((height, nonces))
+      },
+      Err(e) => {
+        // the ledger may have moved on since the tail was cached
+        self.invalidate(handle, None);
+        Err(e)
+      },
+    }
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let res = self.store.append_tombstone(handle, expected_height).await;
+    // the tail is read back from the store, with the tombstone, if it was appended
+    self.invalidate(handle, None);
+    res
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    // the store merges receipts, so drop the entry rather than guess the result
+    let res = self
+      .store
+      .attach_ledger_receipts(handle, idx, receipts)
+      .await;
+    self.invalidate(handle, Some(idx));
+    res
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    let res = self
+      .store
+      .attach_ledger_receipts_batch(handle, idx, receipts)
+      .await;
+    for i in idx..idx + receipts.len() {
+      self.invalidate(handle, Some(i));
+    }
+    res
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    let res = self.store.attach_ledger_nonce(handle, nonce).await;
+    match res {
+      Ok(height) => self.invalidate(handle, Some(height)),
+      Err(_) => self.invalidate(handle, None),
+    }
+    res
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    if let (Some(height), _) = self.cached_tail(handle)? {
+      if let (Some(entry), _) = self.cached_entry(handle, height)? {
+        self.hit();
+        return Ok((entry, height));
+      }
+    }
+
+    self.miss();
+    let epoch = self.lock()?.epoch;
+    let (entry, height) = self.store.read_ledger_tail(handle).await?;
+    self.update_tail(handle, height, Some(epoch));
+    self.update_entry(handle, height, entry.clone(), Some(epoch));
+    Ok((entry, height))
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    let (cached, epoch) = self.cached_entry(handle, idx)?;
+    if let Some(entry) = cached {
+      self.hit();
+      return Ok(entry);
+    }
+
+    self.miss();
+    let entry = self.store.read_ledger_by_index(handle, idx).await?;
+    self.update_entry(handle, idx, entry.clone(), Some(epoch));
+    Ok(entry)
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    self.store.append_view_ledger(block, expected_height).await
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.attach_view_ledger_receipts(idx, receipts).await
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    self.store.read_view_ledger_tail().await
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    self.store.read_view_ledger_by_index(idx).await
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let (cached, epoch) = self.cached_tail(handle)?;
+    if let Some(height) = cached {
+      self.hit();
+      return Ok(height);
+    }
+
+    self.miss();
+    let height = self.store.ledger_height(handle).await?;
+    self.update_tail(handle, height, Some(epoch));
+    Ok(height)
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    self.store.list_ledgers(cursor, limit).await
+  }
+
+  fn cache_stats(&self) -> Option<CacheStats> {
+    Some(self.stats())
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    self.store.repair().await
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    let res = self.store.prune_ledger(handle, height).await;
+    if let Ok(mut cache) = self.cache.lock() {
+      cache.epoch += 1;
+      let pruned = cache
+        .entries
+        .iter()
+        .filter(|((h, i), _e)| h == handle && *i < height)
+        .map(|(key, _e)| *key)
+        .collect::<Vec<(Handle, usize)>>();
+      for key in pruned {
+        cache.entries.remove(&key);
+      }
+    }
+    res
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.store.ledger_checkpoint(handle).await
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.create_pruned_ledger(handle, idx, entry).await?;
+    self.update_tail(handle, idx, None);
+    Ok(())
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    self.store.read_lease().await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.write_lease(lease, expected).await?;
+    // a lease in another epoch changed hands since the last one written here, and the
+    // leaders in between may have appended to any ledger
+    let changed_hands = match self.cache.lock() {
+      Ok(mut cache) => cache.lease_epoch.replace(lease.epoch) != Some(lease.epoch),
+      Err(_) => true,
+    };
+    if changed_hands {
+      self.clear();
+    }
+    Ok(())
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    self.clear();
+    self.store.reset_store().await
+  }
+}
@@ -0,0 +1,327 @@
+use super::{Lease, LedgerEntry, LedgerStore};
+use crate::errors::{LedgerStoreError, StorageError};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use sha2::Sha256;
+
+pub const KEY_SIZE: usize = 32; // bytes in the master key and in the derived keys
+
+const ENCRYPTED_BLOCK_PREFIX: &[u8] = b"nimble-enc-v1:";
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+const LEDGER_KEY_LABEL: &[u8] = b"nimble-ledger-key";
+const VIEW_LEDGER_KEY_LABEL: &[u8] = b"nimble-view-ledger-key";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A ledger store that encrypts blocks with AES-256-GCM before handing them to another
+/// ledger store, and decrypts them when they are read back.
+///
+/// Each ledger is encrypted under its own key derived from the master key and the handle,
+/// and the handle and index of an entry are bound to its ciphertext, so blocks cannot be
+/// moved between ledgers or positions. Receipts and nonces are stored as is: receipts are
+/// over the hash of the plaintext block, which the coordinator computes before the block
+/// reaches this store. Tombstones and the genesis entry of the view ledger, which the
+/// underlying store creates on its own, are stored as is; any other block that is not
+/// encrypted is refused when read.
+pub struct EncryptedLedgerStore<S: LedgerStore> {
+  store: S,
+  master_key: Vec<u8>,
+}
+
+impl<S: LedgerStore> EncryptedLedgerStore<S> {
+  pub fn new(store: S, master_key: &[u8]) -> Result<Self, StorageError> {
+    if master_key.len() != KEY_SIZE {
+      eprintln!(
+        "The ledger encryption key must be {} bytes, not {}",
+        KEY_SIZE,
+        master_key.len()
+      );
+      return Err(StorageError::BadRequest);
+    }
+
+    Ok(EncryptedLedgerStore {
+      store,
+      master_key: master_key.to_vec(),
+    })
+  }
+
+  pub fn get_store(&self) -> &S {
+    &self.store
+  }
+
+  pub fn into_store(self) -> S {
+    self.store
+  }
+
+  // the view ledger is identified by `None`
+  fn derive_key(&self, handle: Option<&Handle>) -> Vec<u8> {
+    // HMAC accepts keys of any length, so this never fails
+    let mut mac = HmacSha256::new_from_slice(&self.master_key).unwrap();
+    match handle {
+      Some(h) => {
+        mac.update(LEDGER_KEY_LABEL);
+        mac.update(&h.to_bytes());
+      },
+      None => mac.update(VIEW_LEDGER_KEY_LABEL),
+    }
+    mac.finalize().into_bytes().to_vec()
+  }
+
+  fn associated_data(handle: Option<&Handle>, idx: usize) -> Vec<u8> {
+    let mut aad = match handle {
+      Some(h) => h.to_bytes(),
+      None => VIEW_LEDGER_KEY_LABEL.to_vec(),
+    };
+    aad.extend_from_slice(&(idx as u64).to_le_bytes());
+    aad
+  }
+
+  fn encrypt(
+    &self,
+    handle: Option<&Handle>,
+    idx: usize,
+    block: &Block,
+  ) -> Result<Block, LedgerStoreError> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    if let Err(e) = openssl::rand::rand_bytes(&mut nonce) {
+      eprintln!("Failed to generate a nonce {:?}", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+
+    let mut tag = [0u8; TAG_SIZE];
+    let ciphertext = encrypt_aead(
+      Cipher::aes_256_gcm(),
+      &self.derive_key(handle),
+      Some(&nonce),
+      &Self::associated_data(handle, idx),
+      &block.to_bytes(),
+      &mut tag,
+    )
+    .map_err(|e| {
+      eprintln!("Failed to encrypt a block {:?}", e);
+      LedgerStoreError::LedgerError(StorageError::UnhandledError)
+    })?;
+
+    Ok(Block::new(
+      &[ENCRYPTED_BLOCK_PREFIX, &nonce, &ciphertext, &tag].concat(),
+    ))
+  }
+
+  fn decrypt(
+    &self,
+    handle: Option<&Handle>,
+    idx: usize,
+    mut entry: LedgerEntry,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    let bytes = entry.get_block().to_bytes();
+    if !bytes.starts_with(ENCRYPTED_BLOCK_PREFIX) {
+      if entry.is_tombstone() || (handle.is_none() && idx == 0) {
+        return Ok(entry);
+      }
+      eprintln!("The block at index {} is not encrypted", idx);
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::DecryptionFailed,
+      ));
+    }
+
+    let sealed = &bytes[ENCRYPTED_BLOCK_PREFIX.len()..];
+    if sealed.len() < NONCE_SIZE + TAG_SIZE {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::DecryptionFailed,
+      ));
+    }
+    let (nonce, rest) = sealed.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+
+    let plaintext = decrypt_aead(
+      Cipher::aes_256_gcm(),
+      &self.derive_key(handle),
+      Some(nonce),
+      &Self::associated_data(handle, idx),
+      ciphertext,
+      tag,
+    )
+    .map_err(|_| {
+      eprintln!("Failed to decrypt the block at index {}", idx);
+      LedgerStoreError::LedgerError(StorageError::DecryptionFailed)
+    })?;
+
+    entry.set_block(Block::new(&plaintext));
+    Ok(entry)
+  }
+}
+
+#[async_trait]
+impl<S: LedgerStore + Send + Sync> LedgerStore for EncryptedLedgerStore<S> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    let block = self.encrypt(Some(handle), 0, &genesis_block)?;
+    self
+      .store
+      .create_ledger_with_request(handle, block, request_id)
+      .await
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let block = self.encrypt(Some(handle), expected_height, block)?;
+    self
+      .store
+      .append_ledger_with_request(handle, &block, expected_height, request_id)
+      .await
+  }
+
+  // the tombstone holds nothing to hide, and is stored as is
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.append_tombstone(handle, expected_height).await
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .store
+      .attach_ledger_receipts(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .store
+      .attach_ledger_receipts_batch(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    self.store.attach_ledger_nonce(handle, nonce).await
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    let (entry, height) = self.store.read_ledger_tail(handle).await?;
+    Ok((self.decrypt(Some(handle), height, entry)?, height))
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    let entry = self.store.read_ledger_by_index(handle, idx).await?;
+    self.decrypt(Some(handle), idx, entry)
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    let block = self.encrypt(None, expected_height, block)?;
+    self.store.append_view_ledger(&block, expected_height).await
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.attach_view_ledger_receipts(idx, receipts).await
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    let (entry, height) = self.store.read_view_ledger_tail().await?;
+    Ok((self.decrypt(None, height, entry)?, height))
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    let entry = self.store.read_view_ledger_by_index(idx).await?;
+    self.decrypt(None, idx, entry)
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.store.ledger_height(handle).await
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    self.store.list_ledgers(cursor, limit).await
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    self.store.repair().await
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    self.store.prune_ledger(handle, height).await
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.store.ledger_checkpoint(handle).await
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    let mut encrypted = entry.clone();
+    if !entry.is_tombstone() {
+      encrypted.set_block(self.encrypt(Some(handle), idx, entry.get_block())?);
+    }
+    self
+      .store
+      .create_pruned_ledger(handle, idx, &encrypted)
+      .await
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    self.store.read_lease().await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    self.store.write_lease(lease, expected).await
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    self.store.reset_store().await
+  }
+}
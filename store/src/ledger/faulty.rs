@@ -0,0 +1,350 @@
+use super::{Lease, LedgerEntry, LedgerStore};
+use crate::errors::{LedgerStoreError, StorageError};
+use async_trait::async_trait;
+use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+/// The operations of a ledger store that faults can be injected into
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LedgerOp {
+  CreateLedger,
+  AppendLedger,
+  AttachLedgerReceipts,
+  AttachLedgerNonce,
+  ReadLedgerTail,
+  ReadLedgerByIndex,
+  AppendViewLedger,
+  AttachViewLedgerReceipts,
+  ReadViewLedgerTail,
+  ReadViewLedgerByIndex,
+  LedgerHeight,
+  ListLedgers,
+  PruneLedger,
+  LedgerCheckpoint,
+  CreatePrunedLedger,
+  ReadLease,
+  WriteLease,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+  /// the operation is delayed before it is forwarded
+  Delay(Duration),
+  /// the operation never completes
+  Drop,
+  /// the operation fails with the error without being forwarded
+  Fail(StorageError),
+}
+
+/// A fault injected into one operation, or into all of them if `op` is `None`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultRule {
+  op: Option<LedgerOp>,
+  fault: Fault,
+  // the number of matching calls forwarded before the fault kicks in
+  after: usize,
+  // the number of calls the fault applies to, or all of them if `None`
+  times: Option<usize>,
+}
+
+impl FaultRule {
+  pub fn new(op: Option<LedgerOp>, fault: Fault) -> Self {
+    FaultRule {
+      op,
+      fault,
+      after: 0,
+      times: None,
+    }
+  }
+
+  pub fn after(mut self, calls: usize) -> Self {
+    self.after = calls;
+    self
+  }
+
+  pub fn times(mut self, calls: usize) -> Self {
+    self.times = Some(calls);
+    self
+  }
+
+  fn applies_to(&self, calls: usize) -> bool {
+    calls > self.after
+      && match self.times {
+        Some(t) => calls <= self.after + t,
+        None => true,
+      }
+  }
+}
+
+struct Faults {
+  rules: Vec<FaultRule>,
+  calls: HashMap<LedgerOp, usize>,
+  all_calls: usize,
+}
+
+/// A ledger store that injects faults into the operations it forwards to another ledger
+/// store, so that the coordinator's handling of a misbehaving store can be tested
+pub struct FaultyLedgerStore<S: LedgerStore> {
+  store: S,
+  faults: Mutex<Faults>,
+}
+
+impl<S: LedgerStore> FaultyLedgerStore<S> {
+  pub fn new(store: S) -> Self {
+    FaultyLedgerStore {
+      store,
+      faults: Mutex::new(Faults {
+        rules: Vec::new(),
+        calls: HashMap::new(),
+        all_calls: 0,
+      }),
+    }
+  }
+
+  pub fn get_store(&self) -> &S {
+    &self.store
+  }
+
+  /// Calls are counted from when the rule is injected
+  pub fn inject(&self, rule: FaultRule) {
+    let mut faults = self.faults.lock().unwrap();
+    let calls = match rule.op {
+      Some(op) => faults.calls.get(&op).copied().unwrap_or(0),
+      None => faults.all_calls,
+    };
+    faults.rules.push(FaultRule {
+      after: rule.after + calls,
+      ..rule
+    });
+  }
+
+  /// Every operation fails after `calls` more operations, as if the store went away
+  pub fn crash_after(&self, calls: usize) {
+    self.inject(FaultRule::new(None, Fault::Fail(StorageError::UnhandledError)).after(calls));
+  }
+
+  pub fn clear_faults(&self) {
+    self.faults.lock().unwrap().rules.clear();
+  }
+
+  pub fn calls(&self, op: LedgerOp) -> usize {
+    let faults = self.faults.lock().unwrap();
+    faults.calls.get(&op).copied().unwrap_or(0)
+  }
+
+  fn next_fault(&self, op: LedgerOp) -> Option<Fault> {
+    let mut faults = self.faults.lock().unwrap();
+    let op_calls = {
+      let c = faults.calls.entry(op).or_insert(0);
+      *c += 1;
+      *c
+    };
+    faults.all_calls += 1;
+    let all_calls = faults.all_calls;
+
+    faults
+      .rules
+      .iter()
+      .find(|r| match r.op {
+        Some(o) => o == op && r.applies_to(op_calls),
+        None => r.applies_to(all_calls),
+      })
+      .map(|r| r.fault.clone())
+  }
+
+  async fn inject_fault(&self, op: LedgerOp) -> Result<(), LedgerStoreError> {
+    match self.next_fault(op) {
+      None => Ok(()),
+      Some(Fault::Delay(d)) => {
+        tokio::time::sleep(d).await;
+        Ok(())
+      },
+      Some(Fault::Drop) => {
+        std::future::pending::<()>().await;
+        Ok(())
+      },
+      Some(Fault::Fail(e)) => Err(LedgerStoreError::LedgerError(e)),
+    }
+  }
+}
+
+#[async_trait]
+impl<S: LedgerStore + Send + Sync> LedgerStore for FaultyLedgerStore<S> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::CreateLedger).await?;
+    self
+      .store
+      .create_ledger_with_request(handle, genesis_block, request_id)
+      .await
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    self.inject_fault(LedgerOp::AppendLedger).await?;
+    self
+      .store
+      .append_ledger_with_request(handle, block, expected_height, request_id)
+      .await
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::AppendLedger).await?;
+    self.store.append_tombstone(handle, expected_height).await
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::AttachLedgerReceipts).await?;
+    self
+      .store
+      .attach_ledger_receipts(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::AttachLedgerReceipts).await?;
+    self
+      .store
+      .attach_ledger_receipts_batch(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    self.inject_fault(LedgerOp::AttachLedgerNonce).await?;
+    self.store.attach_ledger_nonce(handle, nonce).await
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    self.inject_fault(LedgerOp::ReadLedgerTail).await?;
+    self.store.read_ledger_tail(handle).await
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    self.inject_fault(LedgerOp::ReadLedgerByIndex).await?;
+    self.store.read_ledger_by_index(handle, idx).await
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    self.inject_fault(LedgerOp::AppendViewLedger).await?;
+    self.store.append_view_ledger(block, expected_height).await
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .inject_fault(LedgerOp::AttachViewLedgerReceipts)
+      .await?;
+    self.store.attach_view_ledger_receipts(idx, receipts).await
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    self.inject_fault(LedgerOp::ReadViewLedgerTail).await?;
+    self.store.read_view_ledger_tail().await
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    self.inject_fault(LedgerOp::ReadViewLedgerByIndex).await?;
+    self.store.read_view_ledger_by_index(idx).await
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.inject_fault(LedgerOp::LedgerHeight).await?;
+    self.store.ledger_height(handle).await
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    self.inject_fault(LedgerOp::ListLedgers).await?;
+    self.store.list_ledgers(cursor, limit).await
+  }
+
+  fn cache_stats(&self) -> Option<super::cached::CacheStats> {
+    self.store.cache_stats()
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    self.store.repair().await
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    self.inject_fault(LedgerOp::PruneLedger).await?;
+    self.store.prune_ledger(handle, height).await
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.inject_fault(LedgerOp::LedgerCheckpoint).await?;
+    self.store.ledger_checkpoint(handle).await
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::CreatePrunedLedger).await?;
+    self.store.create_pruned_ledger(handle, idx, entry).await
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    self.inject_fault(LedgerOp::ReadLease).await?;
+    self.store.read_lease().await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    self.inject_fault(LedgerOp::WriteLease).await?;
+    self.store.write_lease(lease, expected).await
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    self.store.reset_store().await
+  }
+}
@@ -34,17 +34,35 @@ macro_rules! checked_conversion {
 
 type FileLock = Arc<RwLock<File>>;
 type FileMap = Arc<RwLock<HashMap<Handle, FileLock>>>;
+type CheckpointMap = Arc<RwLock<HashMap<Handle, usize>>>;
+
+const CHECKPOINT_SUFFIX: &str = "checkpoint"; // the file with the checkpoint of a pruned ledger
+const COMPACT_SUFFIX: &str = "compact"; // the file a pruned ledger is compacted into
+const NONCES_SUFFIX: &str = "nonces"; // the file with the nonces attached since the latest append
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct StoreEntry {
   pub block: Vec<u8>,
   pub receipts: Vec<u8>,
+  // the nonces attached before the entry was appended; entries are padded with zeros, so that
+  // the entries written before nonces were kept read as having none
+  pub nonces: Vec<u8>,
+  // the ID of the request that added the entry, which reads as empty in older entries likewise
+  pub request_id: Vec<u8>,
+  // whether the entry is the tombstone of a deleted ledger, which older entries are not
+  pub tombstone: bool,
+  // whether the entry did not fit in its slot and was written to a file of its own, which is
+  // all the slot holds then; older entries fit in their slots
+  pub overflow: bool,
 }
 
 #[derive(Debug)]
 pub struct FileStore {
   dir_path: PathBuf,
   open_files: FileMap,
+  // the index of the oldest entry of each open ledger; entries keep their offsets when a
+  // ledger is pruned, and the pruned ones are left as a hole in a sparse file
+  checkpoints: CheckpointMap,
   view_handle: Handle,
 }
 
@@ -103,6 +121,10 @@ impl FileStore {
       let entry = StoreEntry {
         block: Block::new(&[0; 0]).to_bytes(),
         receipts: Receipts::new().to_bytes(),
+        nonces: Vec::new(),
+        request_id: Vec::new(),
+        tombstone: false,
+        overflow: false,
       };
 
       // Guaranteed to be the size of 1 file entry
@@ -114,11 +136,312 @@ impl FileStore {
     let file_store = FileStore {
       dir_path,
       open_files,
+      checkpoints: Arc::new(RwLock::new(HashMap::new())),
       view_handle,
     };
 
     Ok(file_store)
   }
+
+  fn ledger_path(&self, handle: &Handle, suffix: Option<&str>) -> PathBuf {
+    let name = hex::encode(handle.to_bytes());
+    match suffix {
+      Some(s) => self.dir_path.join(format!("{}.{}", name, s)),
+      None => self.dir_path.join(name),
+    }
+  }
+
+  // Returns the index of the oldest entry of a ledger, which is 0 unless it was pruned. The
+  // ledger must have been opened, so that only existing ledgers are remembered.
+  fn checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    if let Ok(map) = self.checkpoints.read() {
+      if let Some(c) = map.get(handle) {
+        return Ok(*c);
+      }
+    } else {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ));
+    }
+
+    let checkpoint = match fs::read(self.ledger_path(handle, Some(CHECKPOINT_SUFFIX))) {
+      Ok(bytes) => match <[u8; 8]>::try_from(bytes.as_slice()) {
+        Ok(b) => checked_conversion!(u64::from_le_bytes(b), usize),
+        Err(_) => {
+          eprintln!("The checkpoint file of a ledger is corrupted");
+          return Err(LedgerStoreError::LedgerError(
+            StorageError::DeserializationError,
+          ));
+        },
+      },
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+      Err(e) => {
+        eprintln!("Failed to read the checkpoint of a ledger {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+    self.set_checkpoint(handle, checkpoint)?;
+    Ok(checkpoint)
+  }
+
+  fn set_checkpoint(&self, handle: &Handle, checkpoint: usize) -> Result<(), LedgerStoreError> {
+    match self.checkpoints.write() {
+      Ok(mut map) => {
+        map.insert(*handle, checkpoint);
+        Ok(())
+      },
+      Err(_) => Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      )),
+    }
+  }
+
+  // Records the checkpoint of a ledger; the file is replaced in one step, so it is never
+  // seen half-written
+  fn write_checkpoint(&self, handle: &Handle, checkpoint: usize) -> Result<(), LedgerStoreError> {
+    let path = self.ledger_path(handle, Some(CHECKPOINT_SUFFIX));
+    let tmp_path = self.ledger_path(handle, Some(format!("{}.tmp", CHECKPOINT_SUFFIX).as_str()));
+    let bytes = (checked_conversion!(checkpoint, u64)).to_le_bytes();
+    let res = File::create(&tmp_path)
+      .and_then(|mut f| f.write_all(&bytes).and_then(|_| f.sync_all()))
+      .and_then(|_| fs::rename(&tmp_path, &path));
+    if let Err(e) = res {
+      eprintln!("Failed to write the checkpoint of a ledger {:?}", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+    self.set_checkpoint(handle, checkpoint)
+  }
+
+  // the file an entry that does not fit in its slot is written to
+  fn overflow_path(&self, handle: &Handle, idx: usize) -> PathBuf {
+    self.ledger_path(handle, Some(idx.to_string().as_str()))
+  }
+
+  fn read_entry(
+    &self,
+    handle: &Handle,
+    ledger: &mut File,
+    idx: usize,
+  ) -> Result<StoreEntry, LedgerStoreError> {
+    let offset = match idx.checked_mul(ENTRY_SIZE) {
+      Some(v) => checked_conversion!(v, u64),
+      None => {
+        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
+      },
+    };
+    let mut serialized_entry = [0; ENTRY_SIZE];
+    read_at(SeekFrom::Start(offset), ledger, &mut serialized_entry)?;
+    let entry: StoreEntry = match bincode::deserialize(&serialized_entry) {
+      Ok(e) => e,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ));
+      },
+    };
+    if !entry.overflow {
+      return Ok(entry);
+    }
+
+    let serialized_entry = match fs::read(self.overflow_path(handle, idx)) {
+      Ok(bytes) => bytes,
+      Err(e) => {
+        eprintln!("Failed to read an entry of a ledger {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+    match bincode::deserialize(&serialized_entry) {
+      Ok(e) => Ok(e),
+      Err(_) => Err(LedgerStoreError::LedgerError(
+        StorageError::DeserializationError,
+      )),
+    }
+  }
+
+  // Writes the entry at `idx` of a ledger. An entry too large for its slot, say with many
+  // nonces or receipts, is written to a file of its own first, and the slot only marks it, so
+  // that the slot is never seen pointing to a file that is not there.
+  fn write_entry(
+    &self,
+    handle: &Handle,
+    ledger: &mut File,
+    idx: usize,
+    entry: &StoreEntry,
+  ) -> Result<(), LedgerStoreError> {
+    let offset = match idx.checked_mul(ENTRY_SIZE) {
+      Some(v) => SeekFrom::Start(checked_conversion!(v, u64)),
+      None => {
+        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
+      },
+    };
+    match serialize_entry(entry) {
+      Ok(ser_entry) => return write_at(offset, ledger, &ser_entry),
+      Err(LedgerStoreError::LedgerError(StorageError::DataTooLarge)) => {},
+      Err(e) => return Err(e),
+    }
+
+    let ser_entry = match bincode::serialize(entry) {
+      Ok(e) => e,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::SerializationError,
+        ));
+      },
+    };
+    let path = self.overflow_path(handle, idx);
+    let tmp_path = self.ledger_path(handle, Some(format!("{}.tmp", idx).as_str()));
+    let res = File::create(&tmp_path)
+      .and_then(|mut f| f.write_all(&ser_entry).and_then(|_| f.sync_all()))
+      .and_then(|_| fs::rename(&tmp_path, &path));
+    if let Err(e) = res {
+      eprintln!("Failed to write an entry of a ledger {:?}", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+
+    let marker = StoreEntry {
+      block: Vec::new(),
+      receipts: Vec::new(),
+      nonces: Vec::new(),
+      request_id: Vec::new(),
+      tombstone: false,
+      overflow: true,
+    };
+    write_at(offset, ledger, &serialize_entry(&marker)?)
+  }
+
+  async fn read_ledger_op(
+    &self,
+    handle: &Handle,
+    req_idx: Option<usize>,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+
+    let mut ledger = match ledger_lock.write() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerWriteLockFailed,
+        ));
+      },
+    };
+
+    // Find where to seek
+    let index = match req_idx {
+      Some(idx) => {
+        if idx < self.checkpoint(handle)? {
+          return Err(LedgerStoreError::LedgerError(StorageError::PrunedIndex));
+        }
+        idx
+      },
+      None => match ledger.metadata() {
+        Ok(m) => {
+          if checked_conversion!(m.len(), usize) < ENTRY_SIZE {
+            eprintln!("Trying to read an empty file");
+            return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+          }
+
+          (checked_conversion!(m.len(), usize) / ENTRY_SIZE) - 1
+        },
+        Err(e) => {
+          eprintln!("Failed to access file metadata {:?}", e);
+          return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+        },
+      },
+    };
+
+    let entry = self.read_entry(handle, &mut ledger, index)?;
+
+    let nonces = match Nonces::from_bytes(&entry.nonces) {
+      Ok(n) => n,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ));
+      },
+    };
+
+    // 3. Return ledger entry by deserializing its contents
+    Ok((
+      LedgerEntry::new(
+        Block::from_bytes(&entry.block).unwrap(),
+        Receipts::from_bytes(&entry.receipts).unwrap(),
+        Some(nonces),
+      )
+      .with_request_id(&entry.request_id)
+      .with_tombstone(entry.tombstone),
+      index,
+    ))
+  }
+
+  // appends the entry at `expected_height`, along with the nonces attached since the tail
+  fn append_entry(
+    &self,
+    handle: &Handle,
+    mut new_entry: StoreEntry,
+    expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+
+    let mut ledger = match ledger_lock.write() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerWriteLockFailed,
+        ));
+      },
+    };
+
+    let next_index = match ledger.metadata() {
+      Ok(m) => checked_conversion!(m.len(), usize) / ENTRY_SIZE,
+      Err(e) => {
+        eprintln!("Failed to access file metadata {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    // 1. check if condition holds
+    if expected_height != next_index {
+      eprintln!(
+        "Expected height {};  Height-plus-one: {}",
+        expected_height, next_index
+      );
+
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::IncorrectConditionalData,
+      ));
+    }
+
+    // 2. Take the nonces attached since the previous append
+    let nonces_path = self.ledger_path(handle, Some(NONCES_SUFFIX));
+    let nonces = match fs::read(&nonces_path) {
+      Ok(bytes) => match Nonces::from_bytes(&bytes) {
+        Ok(n) => n,
+        Err(_) => {
+          eprintln!("The nonces file of a ledger is corrupted");
+          return Err(LedgerStoreError::LedgerError(
+            StorageError::DeserializationError,
+          ));
+        },
+      },
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Nonces::new(),
+      Err(e) => {
+        eprintln!("Failed to read the nonces of a ledger {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    // 3. Add the nonces to the new entry we are going to append to the ledger
+    new_entry.nonces = nonces.to_bytes();
+
+    self.write_entry(handle, &mut ledger, next_index, &new_entry)?;
+    if !nonces.is_empty() {
+      if let Err(e) = fs::remove_file(&nonces_path) {
+        eprintln!("Failed to clear the nonces of a ledger {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      }
+    }
+    Ok((next_index, nonces))
+  }
 }
 
 fn serialize_entry(entry: &StoreEntry) -> Result<Vec<u8>, LedgerStoreError> {
@@ -248,78 +571,13 @@ fn open_and_lock(
   }
 }
 
-async fn read_ledger_op(
-  handle: &Handle,
-  req_idx: Option<usize>,
-  dir_path: &Path,
-  file_map: &FileMap,
-) -> Result<(LedgerEntry, usize), LedgerStoreError> {
-  let ledger_lock = open_and_lock(handle, dir_path, file_map, false)?;
-
-  let mut ledger = match ledger_lock.write() {
-    Ok(v) => v,
-    Err(_) => {
-      return Err(LedgerStoreError::LedgerError(
-        StorageError::LedgerWriteLockFailed,
-      ));
-    },
-  };
-
-  // Find where to seek
-  let index = match req_idx {
-    Some(idx) => idx,
-    None => match ledger.metadata() {
-      Ok(m) => {
-        if checked_conversion!(m.len(), usize) < ENTRY_SIZE {
-          eprintln!("Trying to read an empty file");
-          return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
-        }
-
-        (checked_conversion!(m.len(), usize) / ENTRY_SIZE) - 1
-      },
-      Err(e) => {
-        eprintln!("Failed to access file metadata {:?}", e);
-        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
-      },
-    },
-  };
-
-  let offset = match index.checked_mul(ENTRY_SIZE) {
-    Some(v) => checked_conversion!(v, u64),
-    None => {
-      return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
-    },
-  };
-
-  let mut serialized_entry = [0; ENTRY_SIZE];
-  read_at(SeekFrom::Start(offset), &mut ledger, &mut serialized_entry)?;
-
-  let entry: StoreEntry = match bincode::deserialize(&serialized_entry) {
-    Ok(e) => e,
-    Err(_) => {
-      return Err(LedgerStoreError::LedgerError(
-        StorageError::DeserializationError,
-      ));
-    },
-  };
-
-  // 3. Return ledger entry by deserializing its contents
-  Ok((
-    LedgerEntry::new(
-      Block::from_bytes(&entry.block).unwrap(),
-      Receipts::from_bytes(&entry.receipts).unwrap(),
-      None, //TODO
-    ),
-    index,
-  ))
-}
-
 #[async_trait]
 impl LedgerStore for FileStore {
-  async fn create_ledger(
+  async fn create_ledger_with_request(
     &self,
     handle: &Handle,
     genesis_block: Block,
+    request_id: &[u8],
   ) -> Result<(), LedgerStoreError> {
     // 1. Create and lock file
     let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, true)?;
@@ -350,24 +608,62 @@ impl LedgerStore for FileStore {
     let init_entry = StoreEntry {
       block: genesis_block.to_bytes(),
       receipts: Receipts::new().to_bytes(),
+      nonces: Vec::new(),
+      request_id: request_id.to_vec(),
+      tombstone: false,
+      overflow: false,
     };
 
-    // Serialize the entry
-    let ser_entry = serialize_entry(&init_entry)?;
-    write_at(SeekFrom::Start(0), &mut ledger, &ser_entry)?;
+    self.write_entry(handle, &mut ledger, 0, &init_entry)?;
 
     Ok(())
   }
 
-  async fn append_ledger(
+  async fn append_ledger_with_request(
     &self,
     handle: &Handle,
     block: &Block,
     expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let new_entry = StoreEntry {
+      block: block.to_bytes(),
+      receipts: Receipts::new().to_bytes(),
+      nonces: Vec::new(),
+      request_id: request_id.to_vec(),
+      tombstone: false,
+      overflow: false,
+    };
+    self.append_entry(handle, new_entry, expected_height)
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let new_entry = StoreEntry {
+      block: Block::tombstone().to_bytes(),
+      receipts: Receipts::new().to_bytes(),
+      nonces: Vec::new(),
+      request_id: Vec::new(),
+      tombstone: true,
+      overflow: false,
+    };
+    self
+      .append_entry(handle, new_entry, expected_height)
+      .map(|_| ())
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
     let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
 
-    let mut ledger = match ledger_lock.write() {
+    // held so that no entry is appended before the nonce is recorded
+    let ledger = match ledger_lock.write() {
       Ok(v) => v,
       Err(_) => {
         return Err(LedgerStoreError::LedgerError(
@@ -383,38 +679,21 @@ impl LedgerStore for FileStore {
         return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
       },
     };
-
-    // 1. check if condition holds
-    if expected_height != next_index {
-      eprintln!(
-        "Expected height {};  Height-plus-one: {}",
-        expected_height, next_index
-      );
-
-      return Err(LedgerStoreError::LedgerError(
-        StorageError::IncorrectConditionalData,
-      ));
+    if next_index == 0 {
+      return Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist));
     }
 
-    // 2. Construct the new entry we are going to append to the ledger
-    let new_entry = StoreEntry {
-      block: block.to_bytes(),
-      receipts: Receipts::new().to_bytes(),
-    };
-
-    let ser_entry = serialize_entry(&new_entry)?;
-
-    write_at(SeekFrom::End(0), &mut ledger, &ser_entry)?;
-    Ok((next_index, Nonces::new()))
-  }
-
-  #[allow(unused_variables)]
-  async fn attach_ledger_nonce(
-    &self,
-    handle: &Handle,
-    receipt: &Nonce,
-  ) -> Result<usize, LedgerStoreError> {
-    unimplemented!()
+    // the nonce goes into the next entry appended to the ledger
+    let res = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(self.ledger_path(handle, Some(NONCES_SUFFIX)))
+      .and_then(|mut f| f.write_all(&nonce.to_bytes()));
+    if let Err(e) = res {
+      eprintln!("Failed to attach a nonce to a ledger {:?}", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+    Ok(next_index)
   }
 
   async fn attach_ledger_receipts(
@@ -423,14 +702,6 @@ impl LedgerStore for FileStore {
     idx: usize,
     receipts: &Receipts,
   ) -> Result<(), LedgerStoreError> {
-    // 1. Get the desired offset
-    let offset = match idx.checked_mul(ENTRY_SIZE) {
-      Some(v) => checked_conversion!(v, u64),
-      None => {
-        return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
-      },
-    };
-
     let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
 
     let mut ledger = match ledger_lock.write() {
@@ -442,34 +713,22 @@ impl LedgerStore for FileStore {
       },
     };
 
-    let seek_from = SeekFrom::Start(offset);
-
-    // 2. Find the appropriate entry in the ledger
-    let mut serialized_entry = [0; ENTRY_SIZE];
-    read_at(seek_from, &mut ledger, &mut serialized_entry)?;
-
-    let mut ledger_entry: StoreEntry = match bincode::deserialize(&serialized_entry) {
-      Ok(e) => e,
-      Err(_) => {
-        return Err(LedgerStoreError::LedgerError(
-          StorageError::DeserializationError,
-        ));
-      },
-    };
+    if idx < self.checkpoint(handle)? {
+      return Err(LedgerStoreError::LedgerError(StorageError::PrunedIndex));
+    }
+    // 1. Find the appropriate entry in the ledger
+    let mut ledger_entry = self.read_entry(handle, &mut ledger, idx)?;
 
-    // 3. Recover the contents of the ledger entry
+    // 2. Recover the contents of the ledger entry
     let mut ledger_entry_receipts =
       Receipts::from_bytes(&ledger_entry.receipts).expect("failed to deserialize receipt");
 
-    // 4. Update receipt
+    // 3. Update receipt
     ledger_entry_receipts.merge_receipts(receipts);
     ledger_entry.receipts = ledger_entry_receipts.to_bytes();
 
-    // 5. Re-serialize
-    let ser_entry = serialize_entry(&ledger_entry)?;
-
-    // 6. Update entry
-    write_at(seek_from, &mut ledger, &ser_entry)?;
+    // 4. Update entry
+    self.write_entry(handle, &mut ledger, idx, &ledger_entry)?;
 
     Ok(())
   }
@@ -478,8 +737,7 @@ impl LedgerStore for FileStore {
     &self,
     handle: &Handle,
   ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
-    let (ledger_entry, height) =
-      read_ledger_op(handle, None, &self.dir_path, &self.open_files).await?;
+    let (ledger_entry, height) = self.read_ledger_op(handle, None).await?;
     Ok((ledger_entry, height))
   }
 
@@ -488,8 +746,7 @@ impl LedgerStore for FileStore {
     handle: &Handle,
     index: usize,
   ) -> Result<LedgerEntry, LedgerStoreError> {
-    let (ledger_entry, _height) =
-      read_ledger_op(handle, Some(index), &self.dir_path, &self.open_files).await?;
+    let (ledger_entry, _height) = self.read_ledger_op(handle, Some(index)).await?;
     Ok(ledger_entry)
   }
 
@@ -522,6 +779,226 @@ impl LedgerStore for FileStore {
     Ok(res.0)
   }
 
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+
+    let ledger = match ledger_lock.read() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerReadLockFailed,
+        ));
+      },
+    };
+
+    match ledger.metadata() {
+      Ok(m) => {
+        let num_entries = checked_conversion!(m.len(), usize) / ENTRY_SIZE;
+        if num_entries == 0 {
+          eprintln!("Trying to find the height of an empty file");
+          return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+        }
+        Ok(num_entries - 1)
+      },
+      Err(e) => {
+        eprintln!("Failed to access file metadata {:?}", e);
+        Err(LedgerStoreError::LedgerError(StorageError::UnhandledError))
+      },
+    }
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    let dir_entries = match fs::read_dir(&self.dir_path) {
+      Ok(d) => d,
+      Err(e) => {
+        eprintln!("Unable to list {:?}, error: {:?}", &self.dir_path, e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    let mut handles = Vec::new();
+    for dir_entry in dir_entries.flatten() {
+      // Ledger files are named after the hex encoding of their handles
+      let handle = match dir_entry
+        .file_name()
+        .to_str()
+        .and_then(|name| hex::decode(name).ok())
+        .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok())
+      {
+        Some(h) => h,
+        None => continue,
+      };
+
+      if handle == self.view_handle {
+        continue;
+      }
+      if let Some(c) = cursor {
+        if handle <= *c {
+          continue;
+        }
+      }
+
+      // Skip files of ledgers whose genesis entry was never written
+      match dir_entry.metadata() {
+        Ok(m) if checked_conversion!(m.len(), usize) >= ENTRY_SIZE => handles.push(handle),
+        _ => continue,
+      }
+    }
+
+    handles.sort();
+    handles.truncate(limit);
+    Ok(handles)
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+
+    let mut ledger = match ledger_lock.write() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerWriteLockFailed,
+        ));
+      },
+    };
+
+    let num_entries = match ledger.metadata() {
+      Ok(m) => checked_conversion!(m.len(), usize) / ENTRY_SIZE,
+      Err(e) => {
+        eprintln!("Failed to access file metadata {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+    if height >= num_entries {
+      return Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex));
+    }
+    let checkpoint = self.checkpoint(handle)?;
+    if height <= checkpoint {
+      return Ok(0);
+    }
+
+    // 1. Check that the new checkpoint carries receipts
+    let entry = self.read_entry(handle, &mut ledger, height)?;
+    match Receipts::from_bytes(&entry.receipts) {
+      Ok(r) if !r.is_empty() => {},
+      _ => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::InvalidCheckpoint,
+        ));
+      },
+    }
+
+    // 2. Record the checkpoint first, so that the pruned entries are never read again even
+    // if the compaction below is interrupted
+    self.write_checkpoint(handle, height)?;
+
+    // 3. Copy the remaining entries into a sparse file at the same offsets, and replace the
+    // ledger with it
+    let compact_path = self.ledger_path(handle, Some(COMPACT_SUFFIX));
+    let mut compacted = match OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(&compact_path)
+    {
+      Ok(f) => f,
+      Err(e) => {
+        eprintln!("Failed to create {:?} ({:?})", compact_path, e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+    let mut serialized_entry = [0; ENTRY_SIZE];
+    for idx in height..num_entries {
+      let offset = SeekFrom::Start(checked_conversion!(idx * ENTRY_SIZE, u64));
+      read_at(offset, &mut ledger, &mut serialized_entry)?;
+      write_at(offset, &mut compacted, &serialized_entry)?;
+    }
+    if let Err(e) = compacted.sync_all() {
+      eprintln!("Failed to sync {:?} ({:?})", compact_path, e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+    if compacted.try_lock_exclusive().is_err() {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerWriteLockFailed,
+      ));
+    }
+    if let Err(e) = fs::rename(&compact_path, self.ledger_path(handle, None)) {
+      eprintln!("Failed to replace a pruned ledger ({:?})", e);
+      return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+    }
+    *ledger = compacted;
+
+    // 4. Remove the files of the pruned entries that did not fit in their slots
+    for idx in checkpoint..height {
+      match fs::remove_file(self.overflow_path(handle, idx)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+          eprintln!("Failed to remove a pruned entry of a ledger {:?}", e);
+        },
+        _ => {},
+      }
+    }
+
+    Ok(height - checkpoint)
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    open_and_lock(handle, &self.dir_path, &self.open_files, false)?;
+    self.checkpoint(handle)
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    if entry.get_receipts().is_empty() {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::InvalidCheckpoint,
+      ));
+    }
+    let ledger_lock = open_and_lock(handle, &self.dir_path, &self.open_files, true)?;
+
+    let mut ledger = match ledger_lock.write() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerWriteLockFailed,
+        ));
+      },
+    };
+
+    match ledger.metadata() {
+      Ok(m) => {
+        if m.len() > 0 {
+          return Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey));
+        }
+      },
+      Err(e) => {
+        eprintln!("Failed to access file metadata {:?}", e);
+        return Err(LedgerStoreError::LedgerError(StorageError::UnhandledError));
+      },
+    };
+
+    let checkpoint_entry = StoreEntry {
+      block: entry.get_block().to_bytes(),
+      receipts: entry.get_receipts().to_bytes(),
+      nonces: entry.get_nonces().to_bytes(),
+      request_id: entry.get_request_id().to_vec(),
+      tombstone: entry.is_tombstone(),
+      overflow: false,
+    };
+    self.write_checkpoint(handle, idx)?;
+    self.write_entry(handle, &mut ledger, idx, &checkpoint_entry)?;
+
+    Ok(())
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     match fs::remove_dir_all(&self.dir_path) {
       Ok(_) => Ok(()),
@@ -1,7 +1,7 @@
 use super::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{Lease, LedgerEntry, LedgerStore},
 };
 use async_trait::async_trait;
 use std::{
@@ -9,7 +9,30 @@ use std::{
   sync::{Arc, RwLock},
 };
 
-type LedgerArray = Arc<RwLock<Vec<LedgerEntry>>>;
+// the entries of a ledger that have not been pruned, the first of which is at index `base`
+#[derive(Debug, Default)]
+struct Ledger {
+  base: usize,
+  entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+  fn len(&self) -> usize {
+    self.base + self.entries.len()
+  }
+
+  fn offset(&self, idx: usize) -> Result<usize, LedgerStoreError> {
+    if idx < self.base {
+      Err(LedgerStoreError::LedgerError(StorageError::PrunedIndex))
+    } else if idx < self.len() {
+      Ok(idx - self.base)
+    } else {
+      Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
+    }
+  }
+}
+
+type LedgerArray = Arc<RwLock<Ledger>>;
 type NonceArray = Arc<RwLock<Vec<Nonce>>>;
 
 #[derive(Debug, Default)]
@@ -17,6 +40,7 @@ pub struct InMemoryLedgerStore {
   ledgers: Arc<RwLock<HashMap<Handle, LedgerArray>>>,
   nonces: Arc<RwLock<HashMap<Handle, NonceArray>>>,
   view_ledger: Arc<RwLock<Vec<LedgerEntry>>>,
+  lease: Arc<RwLock<Option<Lease>>>,
 }
 
 impl InMemoryLedgerStore {
@@ -31,6 +55,7 @@ impl InMemoryLedgerStore {
       ledgers: Arc::new(RwLock::new(ledgers)),
       nonces: Arc::new(RwLock::new(HashMap::new())),
       view_ledger: Arc::new(RwLock::new(view_ledger)),
+      lease: Arc::new(RwLock::new(None)),
     }
   }
 
@@ -54,20 +79,63 @@ impl InMemoryLedgerStore {
       ))
     }
   }
+
+  // appends the entry at `expected_height`, along with the nonces attached since the tail
+  fn append_entry(
+    &self,
+    handle: &Handle,
+    mut ledger_entry: LedgerEntry,
+    expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    if let Ok(ledgers_map) = self.ledgers.read() {
+      if ledgers_map.contains_key(handle) {
+        if let Ok(mut ledgers) = ledgers_map[handle].write() {
+          if expected_height == ledgers.len() {
+            let nonces = self.drain_nonces(handle)?;
+
+            ledger_entry.nonces = nonces.clone();
+            ledgers.entries.push(ledger_entry);
+
+            Ok(((ledgers.len() - 1), nonces))
+          } else {
+            Err(LedgerStoreError::LedgerError(
+              StorageError::IncorrectConditionalData,
+            ))
+          }
+        } else {
+          Err(LedgerStoreError::LedgerError(
+            StorageError::LedgerWriteLockFailed,
+          ))
+        }
+      } else {
+        eprintln!("Key does not exist in the ledger map");
+        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
 }
 
 #[async_trait]
 impl LedgerStore for InMemoryLedgerStore {
-  async fn create_ledger(
+  async fn create_ledger_with_request(
     &self,
     handle: &NimbleDigest,
     genesis_block: Block,
+    request_id: &[u8],
   ) -> Result<(), LedgerStoreError> {
-    let genesis_ledger_entry = LedgerEntry::new(genesis_block, Receipts::new(), None);
+    let genesis_ledger_entry =
+      LedgerEntry::new(genesis_block, Receipts::new(), None).with_request_id(request_id);
     if let Ok(mut ledgers_map) = self.ledgers.write() {
       if let Ok(mut nonce_map) = self.nonces.write() {
         if let hash_map::Entry::Vacant(e) = ledgers_map.entry(*handle) {
-          e.insert(Arc::new(RwLock::new(vec![genesis_ledger_entry])));
+          e.insert(Arc::new(RwLock::new(Ledger {
+            base: 0,
+            entries: vec![genesis_ledger_entry],
+          })));
 
           if let hash_map::Entry::Vacant(n) = nonce_map.entry(*handle) {
             n.insert(Arc::new(RwLock::new(Vec::new())));
@@ -90,38 +158,48 @@ impl LedgerStore for InMemoryLedgerStore {
     }
   }
 
-  async fn append_ledger(
+  async fn append_ledger_with_request(
     &self,
     handle: &Handle,
     block: &Block,
     expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let ledger_entry =
+      LedgerEntry::new(block.clone(), Receipts::new(), None).with_request_id(request_id);
+    self.append_entry(handle, ledger_entry, expected_height)
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let ledger_entry =
+      LedgerEntry::new(Block::tombstone(), Receipts::new(), None).with_tombstone(true);
+    self
+      .append_entry(handle, ledger_entry, expected_height)
+      .map(|_| ())
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
     if let Ok(ledgers_map) = self.ledgers.read() {
       if ledgers_map.contains_key(handle) {
         if let Ok(mut ledgers) = ledgers_map[handle].write() {
-          if expected_height == ledgers.len() {
-            let nonces = self.drain_nonces(handle)?;
-
-            let ledger_entry = LedgerEntry {
-              block: block.clone(),
-              receipts: Receipts::new(),
-              nonces: nonces.clone(),
-            };
-            ledgers.push(ledger_entry);
-
-            Ok(((ledgers.len() - 1), nonces))
-          } else {
-            Err(LedgerStoreError::LedgerError(
-              StorageError::IncorrectConditionalData,
-            ))
-          }
+          let offset = ledgers.offset(idx)?;
+          ledgers.entries[offset].receipts.merge_receipts(receipts);
+          Ok(())
         } else {
           Err(LedgerStoreError::LedgerError(
             StorageError::LedgerWriteLockFailed,
           ))
         }
       } else {
-        eprintln!("Key does not exist in the ledger map");
         Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
       }
     } else {
@@ -131,22 +209,25 @@ impl LedgerStore for InMemoryLedgerStore {
     }
   }
 
-  async fn attach_ledger_receipts(
+  async fn attach_ledger_receipts_batch(
     &self,
     handle: &Handle,
     idx: usize,
-    receipts: &Receipts,
+    receipts: &[Receipts],
   ) -> Result<(), LedgerStoreError> {
+    if receipts.is_empty() {
+      return Ok(());
+    }
     if let Ok(ledgers_map) = self.ledgers.read() {
       if ledgers_map.contains_key(handle) {
         if let Ok(mut ledgers) = ledgers_map[handle].write() {
-          let height = idx;
-          if height < ledgers.len() {
-            ledgers[height].receipts.merge_receipts(receipts);
-            Ok(())
-          } else {
-            Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
+          // check every index before attaching any receipt
+          let offset = ledgers.offset(idx)?;
+          ledgers.offset(idx + receipts.len() - 1)?;
+          for (i, r) in receipts.iter().enumerate() {
+            ledgers.entries[offset + i].receipts.merge_receipts(r);
           }
+          Ok(())
         } else {
           Err(LedgerStoreError::LedgerError(
             StorageError::LedgerWriteLockFailed,
@@ -214,7 +295,7 @@ impl LedgerStore for InMemoryLedgerStore {
     if let Ok(ledgers_map) = self.ledgers.read() {
       if ledgers_map.contains_key(handle) {
         if let Ok(ledgers) = ledgers_map[handle].read() {
-          let ledgers_entry = ledgers[ledgers.len() - 1].clone();
+          let ledgers_entry = ledgers.entries[ledgers.entries.len() - 1].clone();
           Ok((ledgers_entry, ledgers.len() - 1))
         } else {
           Err(LedgerStoreError::LedgerError(
@@ -239,11 +320,8 @@ impl LedgerStore for InMemoryLedgerStore {
     if let Ok(ledgers_map) = self.ledgers.read() {
       if ledgers_map.contains_key(handle) {
         if let Ok(ledgers) = ledgers_map[handle].read() {
-          if idx < ledgers.len() {
-            Ok(ledgers[idx].clone())
-          } else {
-            Err(LedgerStoreError::LedgerError(StorageError::InvalidIndex))
-          }
+          let offset = ledgers.offset(idx)?;
+          Ok(ledgers.entries[offset].clone())
         } else {
           Err(LedgerStoreError::LedgerError(
             StorageError::LedgerReadLockFailed,
@@ -326,6 +404,169 @@ impl LedgerStore for InMemoryLedgerStore {
     }
   }
 
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    if let Ok(ledgers_map) = self.ledgers.read() {
+      if ledgers_map.contains_key(handle) {
+        if let Ok(ledgers) = ledgers_map[handle].read() {
+          Ok(ledgers.len() - 1)
+        } else {
+          Err(LedgerStoreError::LedgerError(
+            StorageError::LedgerReadLockFailed,
+          ))
+        }
+      } else {
+        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    if let Ok(ledgers_map) = self.ledgers.read() {
+      let mut handles = ledgers_map
+        .keys()
+        .filter(|handle| match cursor {
+          Some(c) => *handle > c,
+          None => true,
+        })
+        .cloned()
+        .collect::<Vec<Handle>>();
+      handles.sort();
+      handles.truncate(limit);
+      Ok(handles)
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    if let Ok(ledgers_map) = self.ledgers.read() {
+      if ledgers_map.contains_key(handle) {
+        if let Ok(mut ledgers) = ledgers_map[handle].write() {
+          if height <= ledgers.base {
+            return Ok(0);
+          }
+          let offset = ledgers.offset(height)?;
+          if ledgers.entries[offset].receipts.is_empty() {
+            return Err(LedgerStoreError::LedgerError(
+              StorageError::InvalidCheckpoint,
+            ));
+          }
+          ledgers.entries.drain(..offset);
+          ledgers.entries.shrink_to_fit();
+          ledgers.base = height;
+          Ok(offset)
+        } else {
+          Err(LedgerStoreError::LedgerError(
+            StorageError::LedgerWriteLockFailed,
+          ))
+        }
+      } else {
+        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    if let Ok(ledgers_map) = self.ledgers.read() {
+      if ledgers_map.contains_key(handle) {
+        if let Ok(ledgers) = ledgers_map[handle].read() {
+          Ok(ledgers.base)
+        } else {
+          Err(LedgerStoreError::LedgerError(
+            StorageError::LedgerReadLockFailed,
+          ))
+        }
+      } else {
+        Err(LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist))
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapReadLockFailed,
+      ))
+    }
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    if entry.receipts.is_empty() {
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::InvalidCheckpoint,
+      ));
+    }
+    if let Ok(mut ledgers_map) = self.ledgers.write() {
+      if let Ok(mut nonce_map) = self.nonces.write() {
+        if ledgers_map.contains_key(handle) || nonce_map.contains_key(handle) {
+          return Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey));
+        }
+        ledgers_map.insert(
+          *handle,
+          Arc::new(RwLock::new(Ledger {
+            base: idx,
+            entries: vec![entry.clone()],
+          })),
+        );
+        nonce_map.insert(*handle, Arc::new(RwLock::new(Vec::new())));
+        Ok(())
+      } else {
+        Err(LedgerStoreError::LedgerError(
+          StorageError::LedgerMapWriteLockFailed,
+        ))
+      }
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerMapWriteLockFailed,
+      ))
+    }
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    if let Ok(lease) = self.lease.read() {
+      Ok(lease.clone())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerReadLockFailed,
+      ))
+    }
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    if let Ok(mut current) = self.lease.write() {
+      if current.as_ref() != expected {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::IncorrectConditionalData,
+        ));
+      }
+      *current = Some(lease.clone());
+      Ok(())
+    } else {
+      Err(LedgerStoreError::LedgerError(
+        StorageError::LedgerWriteLockFailed,
+      ))
+    }
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     // not really needed for in-memory since state is already volatile.
     // this API is only for testing persistent storage services.
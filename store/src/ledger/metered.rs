@@ -0,0 +1,260 @@
+use super::{cached::CacheStats, Lease, LedgerEntry, LedgerStore};
+use crate::errors::LedgerStoreError;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use std::future::Future;
+
+lazy_static! {
+  static ref OPERATION_DURATION: HistogramVec = register_histogram_vec!(
+    "nimble_store_operation_duration_seconds",
+    "The latency of ledger store operations",
+    &["op"]
+  )
+  .unwrap();
+  static ref OPERATION_ERRORS: IntCounterVec = register_int_counter_vec!(
+    "nimble_store_operation_errors_total",
+    "The number of ledger store operations that failed",
+    &["op"]
+  )
+  .unwrap();
+}
+
+/// A ledger store that records the latency and failures of the operations it forwards to
+/// another ledger store in the default Prometheus registry, labeled by operation
+pub struct MeteredLedgerStore<S: LedgerStore> {
+  store: S,
+}
+
+impl<S: LedgerStore> MeteredLedgerStore<S> {
+  pub fn new(store: S) -> Self {
+    MeteredLedgerStore { store }
+  }
+
+  pub fn get_store(&self) -> &S {
+    &self.store
+  }
+}
+
+/// Returns the number of calls to an operation that were recorded, and of those that failed,
+/// across all metered stores
+pub fn operation_counts(op: &str) -> (u64, u64) {
+  (
+    OPERATION_DURATION
+      .with_label_values(&[op])
+      .get_sample_count(),
+    OPERATION_ERRORS.with_label_values(&[op]).get(),
+  )
+}
+
+async fn observe<T>(
+  op: &str,
+  call: impl Future<Output = Result<T, LedgerStoreError>>,
+) -> Result<T, LedgerStoreError> {
+  let timer = OPERATION_DURATION.with_label_values(&[op]).start_timer();
+  let res = call.await;
+  timer.observe_duration();
+  if res.is_err() {
+    OPERATION_ERRORS.with_label_values(&[op]).inc();
+  }
+  res
+}
+
+#[async_trait]
+impl<S: LedgerStore + Send + Sync> LedgerStore for MeteredLedgerStore<S> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "create_ledger",
+      self
+        .store
+        .create_ledger_with_request(handle, genesis_block, request_id),
+    )
+    .await
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    observe(
+      "append_ledger",
+      self
+        .store
+        .append_ledger_with_request(handle, block, expected_height, request_id),
+    )
+    .await
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "append_tombstone",
+      self.store.append_tombstone(handle, expected_height),
+    )
+    .await
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "attach_ledger_receipts",
+      self.store.attach_ledger_receipts(handle, idx, receipts),
+    )
+    .await
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "attach_ledger_receipts_batch",
+      self
+        .store
+        .attach_ledger_receipts_batch(handle, idx, receipts),
+    )
+    .await
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    observe(
+      "attach_ledger_nonce",
+      self.store.attach_ledger_nonce(handle, nonce),
+    )
+    .await
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    observe("read_ledger_tail", self.store.read_ledger_tail(handle)).await
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    observe(
+      "read_ledger_by_index",
+      self.store.read_ledger_by_index(handle, idx),
+    )
+    .await
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    observe(
+      "append_view_ledger",
+      self.store.append_view_ledger(block, expected_height),
+    )
+    .await
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "attach_view_ledger_receipts",
+      self.store.attach_view_ledger_receipts(idx, receipts),
+    )
+    .await
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    observe("read_view_ledger_tail", self.store.read_view_ledger_tail()).await
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    observe(
+      "read_view_ledger_by_index",
+      self.store.read_view_ledger_by_index(idx),
+    )
+    .await
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    observe("ledger_height", self.store.ledger_height(handle)).await
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    observe("list_ledgers", self.store.list_ledgers(cursor, limit)).await
+  }
+
+  fn cache_stats(&self) -> Option<CacheStats> {
+    self.store.cache_stats()
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    observe("repair", self.store.repair()).await
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    observe("prune_ledger", self.store.prune_ledger(handle, height)).await
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    observe("ledger_checkpoint", self.store.ledger_checkpoint(handle)).await
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    observe(
+      "create_pruned_ledger",
+      self.store.create_pruned_ledger(handle, idx, entry),
+    )
+    .await
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    observe("read_lease", self.store.read_lease()).await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    observe("write_lease", self.store.write_lease(lease, expected)).await
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    self.store.reset_store().await
+  }
+}
@@ -2,17 +2,27 @@ use async_trait::async_trait;
 use ledger::{Block, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 
 pub mod azure_table;
+pub mod cached;
+pub mod encrypted;
+pub mod faulty;
 pub mod filestore;
 pub mod in_memory;
+pub mod metered;
 pub mod mongodb_cosmos;
+pub mod replicated;
 
-use crate::errors::LedgerStoreError;
+use crate::errors::{LedgerStoreError, StorageError};
+use cached::CacheStats;
 
 #[derive(Debug, Default, Clone)]
 pub struct LedgerEntry {
   block: Block,
   receipts: Receipts,
   nonces: Nonces,
+  // the ID the client gave the request that added the entry, or empty if it gave none
+  request_id: Vec<u8>,
+  // whether the entry is the tombstone of a deleted ledger rather than an application block
+  tombstone: bool,
 }
 
 impl LedgerEntry {
@@ -25,13 +35,29 @@ impl LedgerEntry {
       } else {
         Nonces::new()
       },
+      request_id: Vec::new(),
+      tombstone: false,
     }
   }
 
+  pub fn with_request_id(mut self, request_id: &[u8]) -> Self {
+    self.request_id = request_id.to_vec();
+    self
+  }
+
+  pub fn with_tombstone(mut self, tombstone: bool) -> Self {
+    self.tombstone = tombstone;
+    self
+  }
+
   pub fn get_block(&self) -> &Block {
     &self.block
   }
 
+  pub fn set_block(&mut self, new_block: Block) {
+    self.block = new_block;
+  }
+
   pub fn get_receipts(&self) -> &Receipts {
     &self.receipts
   }
@@ -43,6 +69,24 @@ impl LedgerEntry {
   pub fn get_nonces(&self) -> &Nonces {
     &self.nonces
   }
+
+  pub fn get_request_id(&self) -> &[u8] {
+    &self.request_id
+  }
+
+  pub fn is_tombstone(&self) -> bool {
+    self.tombstone
+  }
+}
+
+/// A lease on the leadership of the coordinators that share a ledger store. `holder` leads
+/// until `expires_at` (in milliseconds since the Unix epoch), and `epoch` grows by one every
+/// time the lease changes hands, so that a former leader can tell it was replaced.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Lease {
+  pub holder: String,
+  pub epoch: u64,
+  pub expires_at: u64,
 }
 
 #[async_trait]
@@ -51,19 +95,65 @@ pub trait LedgerStore {
     &self,
     handle: &NimbleDigest,
     genesis_block: Block,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .create_ledger_with_request(handle, genesis_block, &[])
+      .await
+  }
+  // creates the ledger, and keeps `request_id`, the ID the client gave the request if any, with
+  // its genesis entry, so that a retry of the request can be told from other requests
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
   ) -> Result<(), LedgerStoreError>;
   async fn append_ledger(
     &self,
     handle: &Handle,
     block: &Block,
     expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    self
+      .append_ledger_with_request(handle, block, expected_height, &[])
+      .await
+  }
+  // appends the block, and keeps `request_id` with the new entry as `create_ledger_with_request`
+  // does
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(usize, Nonces), LedgerStoreError>;
+  // appends the tombstone that ends a deleted ledger at `expected_height`. It reads back as
+  // `Block::tombstone()`, and is told from an application block with the same bytes by
+  // `LedgerEntry::is_tombstone`.
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError>;
   async fn attach_ledger_receipts(
     &self,
     handle: &Handle,
     idx: usize,
     receipt: &Receipts,
   ) -> Result<(), LedgerStoreError>;
+  // attaches `receipts[i]` to the entry at index `idx + i`, for entries appended as a batch;
+  // stores that can attach them in one write override this
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    for (i, r) in receipts.iter().enumerate() {
+      self.attach_ledger_receipts(handle, idx + i, r).await?;
+    }
+    Ok(())
+  }
   async fn attach_ledger_nonce(
     &self,
     handle: &Handle,
@@ -90,20 +180,271 @@ pub trait LedgerStore {
   ) -> Result<(), LedgerStoreError>;
   async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError>;
   async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError>;
+  // returns the height of the tail of the ledger
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError>;
+  // returns up to `limit` handles of ledgers (other than the view ledger) that come after
+  // `cursor`, in the order of the handles' bytes; the last handle of a page is the cursor of
+  // the next page
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError>;
+  // returns the hit and miss counts of stores that cache entries
+  fn cache_stats(&self) -> Option<CacheStats> {
+    None
+  }
+  // brings lagging replicas up to date in stores that replicate ledgers, and returns the
+  // number of entries written
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    Ok(0)
+  }
+  // deletes the entries of a ledger below `height` and returns the number deleted; the entry
+  // at `height` is kept as the checkpoint that later entries are read and verified from, so
+  // it must carry receipts
+  async fn prune_ledger(
+    &self,
+    _handle: &Handle,
+    _height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::UnsupportedOperation,
+    ))
+  }
+  // returns the index of the oldest entry of a ledger that has not been pruned
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.ledger_height(handle).await.map(|_height| 0)
+  }
+  // creates a ledger whose oldest entry is the checkpoint `entry` at index `idx`, e.g., when
+  // a pruned ledger is copied to another store
+  async fn create_pruned_ledger(
+    &self,
+    _handle: &Handle,
+    _idx: usize,
+    _entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::UnsupportedOperation,
+    ))
+  }
+
+  // returns the lease that coordinators sharing the store elect a leader with, or `None` if
+  // no lease was ever written
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::UnsupportedOperation,
+    ))
+  }
+  // replaces the lease with `lease` only if it is still `expected` (`None` if no lease was
+  // ever written), and fails with `IncorrectConditionalData` otherwise
+  async fn write_lease(
+    &self,
+    _lease: &Lease,
+    _expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    Err(LedgerStoreError::LedgerError(
+      StorageError::UnsupportedOperation,
+    ))
+  }
 
   async fn reset_store(&self) -> Result<(), LedgerStoreError>; // only used for testing
 }
 
+// lets replicas of different types be kept behind one `ReplicatedLedgerStore`
+#[async_trait]
+impl LedgerStore for Box<dyn LedgerStore + Send + Sync> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .as_ref()
+      .create_ledger_with_request(handle, genesis_block, request_id)
+      .await
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    self
+      .as_ref()
+      .append_ledger_with_request(handle, block, expected_height, request_id)
+      .await
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .as_ref()
+      .append_tombstone(handle, expected_height)
+      .await
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .as_ref()
+      .attach_ledger_receipts(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .as_ref()
+      .attach_ledger_receipts_batch(handle, idx, receipts)
+      .await
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    self.as_ref().attach_ledger_nonce(handle, nonce).await
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    self.as_ref().read_ledger_tail(handle).await
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    self.as_ref().read_ledger_by_index(handle, idx).await
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    self
+      .as_ref()
+      .append_view_ledger(block, expected_height)
+      .await
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    self
+      .as_ref()
+      .attach_view_ledger_receipts(idx, receipts)
+      .await
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    self.as_ref().read_view_ledger_tail().await
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    self.as_ref().read_view_ledger_by_index(idx).await
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.as_ref().ledger_height(handle).await
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    self.as_ref().list_ledgers(cursor, limit).await
+  }
+
+  fn cache_stats(&self) -> Option<CacheStats> {
+    self.as_ref().cache_stats()
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    self.as_ref().repair().await
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    self.as_ref().prune_ledger(handle, height).await
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    self.as_ref().ledger_checkpoint(handle).await
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    self.as_ref().create_pruned_ledger(handle, idx, entry).await
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    self.as_ref().read_lease().await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    self.as_ref().write_lease(lease, expected).await
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    self.as_ref().reset_store().await
+  }
+}
+
 #[cfg(test)]
 mod tests {
+  use crate::errors::{LedgerStoreError, StorageError};
   use crate::ledger::{
-    azure_table::TableLedgerStore, filestore::FileStore, in_memory::InMemoryLedgerStore,
-    mongodb_cosmos::MongoCosmosLedgerStore, LedgerStore,
+    azure_table::TableLedgerStore,
+    cached::{CacheStats, CachedLedgerStore},
+    encrypted::EncryptedLedgerStore,
+    faulty::{Fault, FaultRule, FaultyLedgerStore, LedgerOp},
+    filestore::FileStore,
+    in_memory::InMemoryLedgerStore,
+    metered::{operation_counts, MeteredLedgerStore},
+    mongodb_cosmos::MongoCosmosLedgerStore,
+    replicated::ReplicatedLedgerStore,
+    Lease, LedgerStore,
+  };
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    Block, CustomSerde, IdSig, MetaBlock, NimbleDigest, NimbleHashTrait, Nonce, Receipt, Receipts,
+  };
+  use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
   };
-  use ledger::{Block, CustomSerde, NimbleHashTrait};
-  use std::collections::HashMap;
 
-  pub async fn check_store_creation_and_operations(state: &dyn LedgerStore) {
+  pub async fn check_store_creation_and_operations(state: &(dyn LedgerStore + Send + Sync)) {
     let initial_value: Vec<u8> = vec![
       1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
       1, 2,
@@ -113,7 +454,7 @@ mod tests {
     let handle = genesis_block.hash();
 
     state
-      .create_ledger(&handle, genesis_block)
+      .create_ledger_with_request(&handle, genesis_block, b"create")
       .await
       .expect("failed create ledger");
 
@@ -122,6 +463,7 @@ mod tests {
 
     let (current_entry, height) = res.unwrap();
     assert_eq!(current_entry.get_block().to_bytes(), initial_value);
+    assert_eq!(current_entry.get_request_id(), b"create");
 
     let new_value_appended: Vec<u8> = vec![
       2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1,
@@ -130,7 +472,9 @@ mod tests {
 
     let new_block = Block::new(&new_value_appended);
 
-    let res = state.append_ledger(&handle, &new_block, height + 1).await;
+    let res = state
+      .append_ledger_with_request(&handle, &new_block, height + 1, b"append")
+      .await;
     assert!(res.is_ok());
 
     let res = state.read_ledger_tail(&handle).await;
@@ -138,6 +482,16 @@ mod tests {
 
     let (current_entry, _height) = res.unwrap();
     assert_eq!(current_entry.get_block().to_bytes(), new_value_appended);
+    // the entry keeps the ID of the request that appended it once its receipts are attached
+    state
+      .attach_ledger_receipts(&handle, height + 1, &Receipts::new())
+      .await
+      .unwrap();
+    let entry = state
+      .read_ledger_by_index(&handle, height + 1)
+      .await
+      .unwrap();
+    assert_eq!(entry.get_request_id(), b"append");
 
     let res = state.read_ledger_by_index(&handle, 0).await;
     assert!(res.is_ok());
@@ -145,16 +499,707 @@ mod tests {
     let data_at_index = res.unwrap();
     assert_eq!(data_at_index.block.to_bytes(), initial_value);
 
+    let res = state.ledger_height(&handle).await;
+    assert_eq!(res.unwrap(), height + 1);
+
+    // Create a few more ledgers and page through all of them
+    let mut handles = vec![handle];
+    for i in 0..4u8 {
+      let block = Block::new(&[initial_value.clone(), vec![i]].concat());
+      let h = block.hash();
+      state
+        .create_ledger(&h, block)
+        .await
+        .expect("failed create ledger");
+      assert_eq!(state.ledger_height(&h).await.unwrap(), 0);
+      let entry = state.read_ledger_by_index(&h, 0).await.unwrap();
+      assert!(entry.get_request_id().is_empty());
+      handles.push(h);
+    }
+
+    // a tombstone is told from an application block with the same bytes, also once its
+    // receipts are attached
+    state
+      .append_ledger(&handles[1], &Block::tombstone(), 1)
+      .await
+      .unwrap();
+    state.append_tombstone(&handles[1], 2).await.unwrap();
+    state
+      .attach_ledger_receipts(&handles[1], 2, &Receipts::new())
+      .await
+      .unwrap();
+    let entry = state.read_ledger_by_index(&handles[1], 1).await.unwrap();
+    assert_eq!(entry.get_block().to_bytes(), Block::tombstone().to_bytes());
+    assert!(!entry.is_tombstone());
+    let (entry, height) = state.read_ledger_tail(&handles[1]).await.unwrap();
+    assert_eq!(height, 2);
+    assert_eq!(entry.get_block().to_bytes(), Block::tombstone().to_bytes());
+    assert!(entry.is_tombstone());
+
+    let mut listed = Vec::new();
+    let mut cursor = None;
+    loop {
+      let page = state
+        .list_ledgers(cursor.as_ref(), 2)
+        .await
+        .expect("failed to list ledgers");
+      assert!(page.len() <= 2);
+      if page.is_empty() {
+        break;
+      }
+      cursor = page.last().cloned();
+      listed.extend(page);
+    }
+    for h in &handles {
+      assert_eq!(listed.iter().filter(|l| *l == h).count(), 1);
+    }
+
     let res = state.reset_store().await;
     assert!(res.is_ok());
   }
 
+  // stores only check that entries carry receipts, so any signature will do
+  fn fake_receipts(idx: usize) -> Receipts {
+    let sk = PrivateKey::new();
+    let metablock = MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::default(), idx);
+    let id_sig = IdSig::new(sk.get_public_key().unwrap(), sk.sign(&[0u8]).unwrap());
+    let mut receipts = Receipts::new();
+    receipts.add(&Receipt::new(NimbleDigest::default(), metablock, id_sig));
+    receipts
+  }
+
+  pub async fn check_ledger_pruning(state: &(dyn LedgerStore + Send + Sync)) {
+    let err = |e: LedgerStoreError| match e {
+      LedgerStoreError::LedgerError(s) => s,
+      _ => StorageError::UnhandledError,
+    };
+
+    let genesis_block = Block::new(b"genesis of a pruned ledger");
+    let handle = genesis_block.hash();
+    state.create_ledger(&handle, genesis_block).await.unwrap();
+    for idx in 1..=3u8 {
+      state
+        .append_ledger(&handle, &Block::new(&[idx]), idx as usize)
+        .await
+        .unwrap();
+    }
+    assert_eq!(state.ledger_checkpoint(&handle).await.unwrap(), 0);
+
+    // the checkpoint must carry receipts and be in the ledger
+    assert_eq!(
+      err(state.prune_ledger(&handle, 2).await.unwrap_err()),
+      StorageError::InvalidCheckpoint
+    );
+    state
+      .attach_ledger_receipts(&handle, 2, &fake_receipts(2))
+      .await
+      .unwrap();
+    assert_eq!(
+      err(state.prune_ledger(&handle, 4).await.unwrap_err()),
+      StorageError::InvalidIndex
+    );
+
+    assert_eq!(state.prune_ledger(&handle, 2).await.unwrap(), 2);
+    assert_eq!(state.prune_ledger(&handle, 1).await.unwrap(), 0);
+    assert_eq!(state.ledger_checkpoint(&handle).await.unwrap(), 2);
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 3);
+    for idx in 0..2 {
+      assert_eq!(
+        err(state.read_ledger_by_index(&handle, idx).await.unwrap_err()),
+        StorageError::PrunedIndex
+      );
+    }
+    assert_eq!(
+      err(
+        state
+          .attach_ledger_receipts(&handle, 1, &fake_receipts(1))
+          .await
+          .unwrap_err()
+      ),
+      StorageError::PrunedIndex
+    );
+    let batch = [fake_receipts(1), fake_receipts(2)];
+    assert_eq!(
+      err(
+        state
+          .attach_ledger_receipts_batch(&handle, 1, &batch)
+          .await
+          .unwrap_err()
+      ),
+      StorageError::PrunedIndex
+    );
+    let batch = [fake_receipts(2), fake_receipts(3)];
+    state
+      .attach_ledger_receipts_batch(&handle, 2, &batch)
+      .await
+      .unwrap();
+    let entry = state.read_ledger_by_index(&handle, 3).await.unwrap();
+    assert!(!entry.get_receipts().is_empty());
+    let checkpoint = state.read_ledger_by_index(&handle, 2).await.unwrap();
+    assert_eq!(checkpoint.get_block().to_bytes(), vec![2]);
+    assert!(!checkpoint.get_receipts().is_empty());
+    let (tail, height) = state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!((tail.get_block().to_bytes(), height), (vec![3], 3));
+    state
+      .append_ledger(&handle, &Block::new(&[4]), 4)
+      .await
+      .unwrap();
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 4);
+
+    // a pruned ledger can be recreated from its checkpoint elsewhere
+    let other = NimbleDigest::digest(b"copy of a pruned ledger");
+    let mut no_receipts = checkpoint.clone();
+    no_receipts.set_receipts(Receipts::new());
+    assert_eq!(
+      err(
+        state
+          .create_pruned_ledger(&other, 2, &no_receipts)
+          .await
+          .unwrap_err()
+      ),
+      StorageError::InvalidCheckpoint
+    );
+    state
+      .create_pruned_ledger(&other, 2, &checkpoint)
+      .await
+      .unwrap();
+    assert!(state
+      .create_pruned_ledger(&other, 2, &checkpoint)
+      .await
+      .is_err());
+    assert_eq!(state.ledger_checkpoint(&other).await.unwrap(), 2);
+    assert_eq!(state.ledger_height(&other).await.unwrap(), 2);
+    let entry = state.read_ledger_by_index(&other, 2).await.unwrap();
+    assert_eq!(entry.get_block().to_bytes(), vec![2]);
+    assert_eq!(
+      err(state.read_ledger_by_index(&other, 1).await.unwrap_err()),
+      StorageError::PrunedIndex
+    );
+    state
+      .append_ledger(&other, &Block::new(&[3]), 3)
+      .await
+      .unwrap();
+    assert_eq!(state.read_ledger_tail(&other).await.unwrap().1, 3);
+  }
+
+  #[tokio::test]
+  pub async fn check_pruned_stores() {
+    check_ledger_pruning(&InMemoryLedgerStore::new()).await;
+    check_ledger_pruning(&CachedLedgerStore::new(InMemoryLedgerStore::new(), 4)).await;
+    check_ledger_pruning(
+      &EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &[7u8; 32]).unwrap(),
+    )
+    .await;
+    let replicas = (0..3).map(|_| InMemoryLedgerStore::new()).collect();
+    check_ledger_pruning(&ReplicatedLedgerStore::new(replicas).unwrap()).await;
+
+    let dir = std::env::temp_dir().join(format!("nimble_pruned_fstore_{}", std::process::id()));
+    let mut args = HashMap::<String, String>::new();
+    args.insert(
+      String::from("NIMBLE_FSTORE_DIR"),
+      dir.to_str().unwrap().to_string(),
+    );
+    let state = FileStore::new(&args).await.unwrap();
+    check_ledger_pruning(&state).await;
+    // the checkpoint survives a restart
+    let handle = Block::new(b"genesis of a pruned ledger").hash();
+    drop(state);
+    let state = FileStore::new(&args).await.unwrap();
+    assert_eq!(state.ledger_checkpoint(&handle).await.unwrap(), 2);
+    assert!(state.read_ledger_by_index(&handle, 1).await.is_err());
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 4);
+    state.reset_store().await.unwrap();
+  }
+
+  // the store may hold ledgers besides the ones created here, so the check only relies on the
+  // order of the listing and on the ledgers it created
+  pub async fn check_ledger_listing(state: &(dyn LedgerStore + Send + Sync)) {
+    let mut handles = Vec::new();
+    for i in 0..12u8 {
+      let block = Block::new(&[b"listed ledger ".to_vec(), vec![i]].concat());
+      let handle = block.hash();
+      state.create_ledger(&handle, block).await.unwrap();
+      for height in 1..=(i as usize % 3) {
+        state
+          .append_ledger(&handle, &Block::new(&[i, height as u8]), height)
+          .await
+          .unwrap();
+      }
+      handles.push(handle);
+    }
+    // creating a ledger again fails and does not list it twice
+    let block = Block::new(&[b"listed ledger ".to_vec(), vec![0]].concat());
+    assert!(matches!(
+      state.create_ledger(&handles[0], block).await,
+      Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey))
+    ));
+
+    for (i, handle) in handles.iter().enumerate() {
+      assert_eq!(state.ledger_height(handle).await.unwrap(), i % 3);
+    }
+    let missing = Block::new(b"a ledger that was never created").hash();
+    assert!(state.ledger_height(&missing).await.is_err());
+
+    // pages of three are in the order of the handles' bytes and pick up after their cursors
+    let mut listed = Vec::new();
+    let mut cursor = None;
+    loop {
+      let page = state.list_ledgers(cursor.as_ref(), 3).await.unwrap();
+      assert!(page.len() <= 3);
+      if page.is_empty() {
+        break;
+      }
+      cursor = page.last().cloned();
+      listed.extend(page);
+    }
+    assert!(listed.windows(2).all(|w| w[0] < w[1]));
+    for handle in &handles {
+      assert_eq!(listed.iter().filter(|l| *l == handle).count(), 1);
+    }
+    let view_handle = NimbleDigest::from_bytes(&[0u8; 32]).unwrap();
+    assert!(!listed.contains(&view_handle));
+
+    // a cursor need not be the handle of a ledger
+    let page = state.list_ledgers(Some(&missing), 5).await.unwrap();
+    let expected = listed
+      .iter()
+      .filter(|h| **h > missing)
+      .take(5)
+      .cloned()
+      .collect::<Vec<_>>();
+    assert_eq!(page, expected);
+    assert!(state.list_ledgers(None, 0).await.unwrap().is_empty());
+    let last = listed.last().unwrap();
+    assert!(state.list_ledgers(Some(last), 5).await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  pub async fn check_listed_stores() {
+    check_ledger_listing(&InMemoryLedgerStore::new()).await;
+    check_ledger_listing(&CachedLedgerStore::new(InMemoryLedgerStore::new(), 4)).await;
+    check_ledger_listing(
+      &EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &[7u8; 32]).unwrap(),
+    )
+    .await;
+    check_ledger_listing(&MeteredLedgerStore::new(InMemoryLedgerStore::new())).await;
+    let replicas = (0..3).map(|_| InMemoryLedgerStore::new()).collect();
+    check_ledger_listing(&ReplicatedLedgerStore::new(replicas).unwrap()).await;
+
+    let dir = std::env::temp_dir().join(format!("nimble_listed_fstore_{}", std::process::id()));
+    let mut args = HashMap::<String, String>::new();
+    args.insert(
+      String::from("NIMBLE_FSTORE_DIR"),
+      dir.to_str().unwrap().to_string(),
+    );
+    let state = FileStore::new(&args).await.unwrap();
+    check_ledger_listing(&state).await;
+    state.reset_store().await.unwrap();
+  }
+
+  pub async fn check_lease(state: &(dyn LedgerStore + Send + Sync)) {
+    let err = |e: LedgerStoreError| match e {
+      LedgerStoreError::LedgerError(s) => s,
+      _ => StorageError::UnhandledError,
+    };
+    let lease = |holder: &str, epoch: u64, expires_at: u64| Lease {
+      holder: holder.to_string(),
+      epoch,
+      expires_at,
+    };
+
+    assert_eq!(state.read_lease().await.unwrap(), None);
+    state.write_lease(&lease("a", 1, 100), None).await.unwrap();
+    assert_eq!(
+      err(
+        state
+          .write_lease(&lease("b", 1, 100), None)
+          .await
+          .unwrap_err()
+      ),
+      StorageError::IncorrectConditionalData
+    );
+
+    // a renewal must name the lease it replaces
+    state
+      .write_lease(&lease("a", 1, 200), Some(&lease("a", 1, 100)))
+      .await
+      .unwrap();
+    assert_eq!(
+      err(
+        state
+          .write_lease(&lease("b", 2, 300), Some(&lease("a", 1, 100)))
+          .await
+          .unwrap_err()
+      ),
+      StorageError::IncorrectConditionalData
+    );
+    assert_eq!(state.read_lease().await.unwrap(), Some(lease("a", 1, 200)));
+
+    state
+      .write_lease(&lease("b", 2, 300), Some(&lease("a", 1, 200)))
+      .await
+      .unwrap();
+    assert_eq!(state.read_lease().await.unwrap(), Some(lease("b", 2, 300)));
+  }
+
+  #[tokio::test]
+  pub async fn check_leased_stores() {
+    check_lease(&InMemoryLedgerStore::new()).await;
+    check_lease(&CachedLedgerStore::new(InMemoryLedgerStore::new(), 4)).await;
+    check_lease(&EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &[7u8; 32]).unwrap()).await;
+    let replicas = (0..3).map(|_| InMemoryLedgerStore::new()).collect();
+    check_lease(&ReplicatedLedgerStore::new(replicas).unwrap()).await;
+
+    // a store that cannot be reached cannot hand out the lease
+    let state = FaultyLedgerStore::new(InMemoryLedgerStore::new());
+    state.inject(
+      FaultRule::new(
+        Some(LedgerOp::WriteLease),
+        Fault::Fail(StorageError::UnhandledError),
+      )
+      .times(1),
+    );
+    let lease = Lease {
+      holder: "a".to_string(),
+      epoch: 1,
+      expires_at: 100,
+    };
+    assert!(state.write_lease(&lease, None).await.is_err());
+    assert_eq!(state.read_lease().await.unwrap(), None);
+    check_lease(&state).await;
+  }
+
   #[tokio::test]
   pub async fn check_in_memory_store() {
     let state = InMemoryLedgerStore::new();
     check_store_creation_and_operations(&state).await;
   }
 
+  #[tokio::test]
+  pub async fn check_cached_store() {
+    let state = CachedLedgerStore::new(InMemoryLedgerStore::new(), 2);
+    check_store_creation_and_operations(&state).await;
+
+    let state = CachedLedgerStore::new(InMemoryLedgerStore::new(), 2);
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    state.create_ledger(&handle, genesis_block).await.unwrap();
+
+    // the tail is cached when the ledger is created and when it is appended to
+    assert_eq!(state.read_ledger_tail(&handle).await.unwrap().1, 0);
+    let nonce = Nonce::new(&[7u8; 16]).unwrap();
+    assert_eq!(state.attach_ledger_nonce(&handle, &nonce).await.unwrap(), 1);
+    let (height, nonces) = state
+      .append_ledger(&handle, &Block::new(&[4, 5, 6]), 1)
+      .await
+      .unwrap();
+    assert_eq!(height, 1);
+    let (entry, height) = state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(height, 1);
+    assert_eq!(entry.get_nonces().to_bytes(), nonces.to_bytes());
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 1);
+    assert_eq!(state.stats(), CacheStats { hits: 3, misses: 0 });
+
+    // attaching receipts drops the entry so the merged receipts are read back
+    let receipts = state
+      .get_store()
+      .read_ledger_by_index(&handle, 1)
+      .await
+      .unwrap()
+      .get_receipts()
+      .clone();
+    state
+      .attach_ledger_receipts(&handle, 1, &receipts)
+      .await
+      .unwrap();
+    state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(state.stats(), CacheStats { hits: 3, misses: 1 });
+
+    // and so does attaching them in a batch
+    state
+      .attach_ledger_receipts_batch(&handle, 1, &[receipts])
+      .await
+      .unwrap();
+    state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(state.stats(), CacheStats { hits: 3, misses: 2 });
+
+    // a stale expected height invalidates the cached tail
+    assert!(state
+      .append_ledger(&handle, &Block::new(&[7]), 1)
+      .await
+      .is_err());
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 1);
+    assert_eq!(state.stats(), CacheStats { hits: 3, misses: 3 });
+
+    // the least recently used entry is evicted once the capacity is reached
+    state.read_ledger_by_index(&handle, 0).await.unwrap();
+    assert_eq!(state.stats(), CacheStats { hits: 4, misses: 3 });
+    let other = Block::new(&[8]);
+    state.create_ledger(&other.hash(), other).await.unwrap();
+    state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(state.stats(), CacheStats { hits: 4, misses: 4 });
+    assert_eq!(state.cache_stats().unwrap().hit_rate(), 4.0 / 8.0);
+
+    // renewing the lease keeps the cache, but taking it in a new epoch drops it, since
+    // another leader may have appended in between
+    let lease = |epoch: u64, expires_at: u64| Lease {
+      holder: "a".to_string(),
+      epoch,
+      expires_at,
+    };
+    state.write_lease(&lease(1, 100), None).await.unwrap();
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 1);
+    state
+      .write_lease(&lease(1, 200), Some(&lease(1, 100)))
+      .await
+      .unwrap();
+    state
+      .get_store()
+      .append_ledger(&handle, &Block::new(&[9]), 2)
+      .await
+      .unwrap();
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 1);
+    state
+      .write_lease(&lease(2, 300), Some(&lease(1, 200)))
+      .await
+      .unwrap();
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 2);
+    assert_eq!(state.read_ledger_tail(&handle).await.unwrap().1, 2);
+  }
+
+  #[tokio::test]
+  pub async fn check_metered_store() {
+    let state = MeteredLedgerStore::new(InMemoryLedgerStore::new());
+    let (calls, errors) = operation_counts("create_ledger");
+    check_store_creation_and_operations(&state).await;
+
+    // the counts are shared by all metered stores, which other tests may create concurrently
+    let genesis_block = Block::new(&[9, 9, 9]);
+    let handle = genesis_block.hash();
+    state
+      .create_ledger(&handle, genesis_block.clone())
+      .await
+      .unwrap();
+    assert!(state.create_ledger(&handle, genesis_block).await.is_err());
+    let (new_calls, new_errors) = operation_counts("create_ledger");
+    assert!(new_calls >= calls + 3);
+    assert!(new_errors > errors);
+  }
+
+  #[tokio::test]
+  pub async fn check_faulty_store() {
+    let state = FaultyLedgerStore::new(InMemoryLedgerStore::new());
+    check_store_creation_and_operations(&state).await;
+
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    state.create_ledger(&handle, genesis_block).await.unwrap();
+
+    // the second and third appends fail, and the fourth goes through
+    state.inject(
+      FaultRule::new(
+        Some(LedgerOp::AppendLedger),
+        Fault::Fail(StorageError::ConcurrentOperation),
+      )
+      .after(1)
+      .times(2),
+    );
+    assert!(state
+      .append_ledger(&handle, &Block::new(&[1]), 1)
+      .await
+      .is_ok());
+    for _ in 0..2 {
+      assert_eq!(
+        state
+          .append_ledger(&handle, &Block::new(&[2]), 2)
+          .await
+          .unwrap_err()
+          .to_string(),
+        "ConcurrentOperation"
+      );
+    }
+    assert!(state
+      .append_ledger(&handle, &Block::new(&[2]), 2)
+      .await
+      .is_ok());
+    // three more appends, one of them a tombstone, went through in
+    // check_store_creation_and_operations
+    assert_eq!(state.calls(LedgerOp::AppendLedger), 7);
+
+    // delays only slow the operation down
+    state.inject(FaultRule::new(
+      Some(LedgerOp::ReadLedgerTail),
+      Fault::Delay(Duration::from_millis(10)),
+    ));
+    let start = Instant::now();
+    assert_eq!(state.read_ledger_tail(&handle).await.unwrap().1, 2);
+    assert!(start.elapsed() >= Duration::from_millis(10));
+
+    // dropped operations never complete
+    state.clear_faults();
+    state.inject(FaultRule::new(Some(LedgerOp::LedgerHeight), Fault::Drop));
+    assert!(
+      tokio::time::timeout(Duration::from_millis(10), state.ledger_height(&handle))
+        .await
+        .is_err()
+    );
+
+    // once crashed, every operation fails
+    state.clear_faults();
+    state.crash_after(1);
+    assert!(state.read_ledger_by_index(&handle, 0).await.is_ok());
+    assert!(state.read_ledger_by_index(&handle, 0).await.is_err());
+    assert!(state.read_view_ledger_tail().await.is_err());
+    state.clear_faults();
+    assert!(state.read_view_ledger_tail().await.is_ok());
+  }
+
+  #[tokio::test]
+  pub async fn check_encrypted_store() {
+    let key = [7u8; 32];
+    assert!(EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &key[1..]).is_err());
+
+    let state = EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &key).unwrap();
+    check_store_creation_and_operations(&state).await;
+
+    let genesis_block = Block::new(b"genesis block of the first ledger");
+    let handle = genesis_block.hash();
+    state.create_ledger(&handle, genesis_block).await.unwrap();
+    let block = Block::new(b"application data");
+    state.append_ledger(&handle, &block, 1).await.unwrap();
+    let view_block = Block::new(b"view ledger block");
+    state.append_view_ledger(&view_block, 1).await.unwrap();
+
+    // only ciphertext reaches the underlying store
+    let (stored, _height) = state.get_store().read_ledger_tail(&handle).await.unwrap();
+    let stored_bytes = stored.get_block().to_bytes();
+    assert!(!stored_bytes
+      .windows(block.to_bytes().len())
+      .any(|w| w == block.to_bytes()));
+    let (entry, height) = state.read_ledger_tail(&handle).await.unwrap();
+    assert_eq!(height, 1);
+    assert_eq!(entry.get_block().to_bytes(), block.to_bytes());
+    let (entry, _height) = state.read_view_ledger_tail().await.unwrap();
+    assert_eq!(entry.get_block().to_bytes(), view_block.to_bytes());
+
+    // a block moved to another ledger or index does not decrypt
+    let other_block = Block::new(b"genesis block of the second ledger");
+    let other = other_block.hash();
+    state.create_ledger(&other, other_block).await.unwrap();
+    state
+      .get_store()
+      .append_ledger(&other, stored.get_block(), 1)
+      .await
+      .unwrap();
+    assert!(state.read_ledger_by_index(&other, 1).await.is_err());
+    assert!(state.read_ledger_by_index(&other, 0).await.is_ok());
+    state
+      .get_store()
+      .append_ledger(&handle, stored.get_block(), 2)
+      .await
+      .unwrap();
+    assert!(state.read_ledger_tail(&handle).await.is_err());
+
+    // nor does it with another key
+    let state = EncryptedLedgerStore::new(state.into_store(), &[8u8; 32]).unwrap();
+    assert!(state.read_ledger_by_index(&handle, 1).await.is_err());
+
+    // a block that is not encrypted is refused, except for the genesis entry of the view
+    // ledger, which the underlying store writes on its own
+    state
+      .get_store()
+      .append_ledger(&other, &Block::new(b"plaintext"), 2)
+      .await
+      .unwrap();
+    assert!(matches!(
+      state.read_ledger_by_index(&other, 2).await,
+      Err(LedgerStoreError::LedgerError(
+        StorageError::DecryptionFailed
+      ))
+    ));
+    state
+      .get_store()
+      .append_view_ledger(&Block::new(b"plaintext"), 2)
+      .await
+      .unwrap();
+    assert!(state.read_view_ledger_by_index(2).await.is_err());
+    assert!(state.read_view_ledger_by_index(0).await.is_ok());
+  }
+
+  #[tokio::test]
+  pub async fn check_replicated_store() {
+    let replicas = (0..3)
+      .map(|_| FaultyLedgerStore::new(InMemoryLedgerStore::new()))
+      .collect();
+    let state = ReplicatedLedgerStore::new(replicas).unwrap();
+    check_store_creation_and_operations(&state).await;
+
+    // repairs only look at whether entries carry receipts, so any signature will do
+    let receipts = fake_receipts;
+
+    let genesis_block = Block::new(&[1, 2, 3]);
+    let handle = genesis_block.hash();
+    state.create_ledger(&handle, genesis_block).await.unwrap();
+    state
+      .attach_ledger_receipts(&handle, 0, &receipts(0))
+      .await
+      .unwrap();
+
+    // one replica down does not stop appends or reads
+    let down = &state.get_replicas()[2];
+    down.crash_after(0);
+    let nonce = Nonce::new(&[9u8; 16]).unwrap();
+    assert_eq!(state.attach_ledger_nonce(&handle, &nonce).await.unwrap(), 1);
+    let (height, nonces) = state
+      .append_ledger(&handle, &Block::new(&[4]), 1)
+      .await
+      .unwrap();
+    assert_eq!(height, 1);
+    assert_eq!(nonces.get().len(), 1);
+    state
+      .attach_ledger_receipts(&handle, 1, &receipts(1))
+      .await
+      .unwrap();
+    state
+      .append_ledger(&handle, &Block::new(&[5]), 2)
+      .await
+      .unwrap();
+    assert_eq!(state.read_ledger_tail(&handle).await.unwrap().1, 2);
+
+    // two replicas down stop them
+    state.get_replicas()[1].crash_after(0);
+    assert!(state
+      .append_ledger(&handle, &Block::new(&[6]), 3)
+      .await
+      .is_err());
+    assert!(state.read_ledger_tail(&handle).await.is_err());
+    state.get_replicas()[1].clear_faults();
+
+    // a replica that is back is stale, but is outvoted until it is repaired
+    down.clear_faults();
+    assert_eq!(down.ledger_height(&handle).await.unwrap(), 0);
+    // as with a single store, the failed append is left behind on the replica that took it
+    assert_eq!(state.ledger_height(&handle).await.unwrap(), 3);
+    let entry = state.read_ledger_by_index(&handle, 1).await.unwrap();
+    assert_eq!(entry.get_block().to_bytes(), vec![4]);
+
+    // entries are back-filled up to the first one without receipts
+    assert_eq!(state.repair().await.unwrap(), 1);
+    assert_eq!(down.ledger_height(&handle).await.unwrap(), 1);
+    let entry = down.read_ledger_by_index(&handle, 1).await.unwrap();
+    assert_eq!(entry.get_block().to_bytes(), vec![4]);
+    assert_eq!(entry.get_nonces().to_bytes(), nonces.to_bytes());
+    assert!(!entry.get_receipts().is_empty());
+
+    state
+      .attach_ledger_receipts(&handle, 2, &receipts(2))
+      .await
+      .unwrap();
+    assert_eq!(state.repair_ledger(&handle).await.unwrap(), 1);
+    assert_eq!(down.ledger_height(&handle).await.unwrap(), 2);
+    assert_eq!(state.repair().await.unwrap(), 0);
+  }
+
   #[tokio::test]
   pub async fn check_mongo_cosmos_store() {
     if std::env::var_os("COSMOS_URL").is_none() {
@@ -171,6 +1216,8 @@ mod tests {
     );
 
     let state = MongoCosmosLedgerStore::new(&args).await.unwrap();
+    check_lease(&state).await;
+    check_ledger_listing(&state).await;
     check_store_creation_and_operations(&state).await;
   }
 
@@ -207,6 +1254,8 @@ mod tests {
     );
 
     let state = TableLedgerStore::new(&args).await.unwrap();
+    check_lease(&state).await;
+    check_ledger_listing(&state).await;
     check_store_creation_and_operations(&state).await;
   }
 
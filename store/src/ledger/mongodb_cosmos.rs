@@ -1,6 +1,6 @@
 use crate::{
   errors::{LedgerStoreError, StorageError},
-  ledger::{LedgerEntry, LedgerStore},
+  ledger::{Lease, LedgerEntry, LedgerStore},
 };
 use async_trait::async_trait;
 use bincode;
@@ -9,6 +9,7 @@ use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonce, Nonces, Receipts};
 use mongodb::{
   bson::{doc, spec::BinarySubtype, Binary},
   error::WriteFailure::WriteError,
+  options::{FindOptions, ReplaceOptions, UpdateOptions},
   Client, Collection,
 };
 use serde::{Deserialize, Serialize};
@@ -117,6 +118,52 @@ struct DBEntry {
   #[serde(rename = "_id")]
   index: i64,
   value: Binary, // SerializedLedgerEntry
+  // the nonces attached before the entry was appended; entries written without them have none
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  nonces: Option<Binary>,
+  // the ID of the request that added the entry, if the client gave one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  request_id: Option<Binary>,
+  // whether the entry is the tombstone of a deleted ledger
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  tombstone: bool,
+}
+
+// The nonces attached to ledgers since their latest appends are kept in a collection of their
+// own, with a document per ledger, since every document of a ledger's collection is an entry
+const NONCES_COLLECTION: &str = "nonces";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBNonces {
+  #[serde(rename = "_id")]
+  handle: String,
+  nonces: Vec<Binary>,
+}
+
+// The lease is the only document of its own collection, whose name is not the hex encoding
+// of any handle
+const LEASE_COLLECTION: &str = "lease";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLease {
+  #[serde(rename = "_id")]
+  index: i64,
+  holder: String,
+  epoch: i64,
+  expires_at: i64,
+}
+
+// Every ledger but the view ledger has a document in the index collection, keyed by the hex
+// encoding of its handle, so that ledgers can be listed in the order of their handles a page at
+// a time. The document with the empty key marks that the ledgers created before the index
+// existed have been added to it; it sorts before every handle.
+const LEDGERS_COLLECTION: &str = "ledgers";
+const INDEX_MARKER: &str = "";
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct DBLedger {
+  #[serde(rename = "_id")]
+  handle: String,
 }
 
 #[derive(Debug)]
@@ -191,6 +238,9 @@ impl MongoCosmosLedgerStore {
           let tail_entry = DBEntry {
             index: 0_i64,
             value: bson_entry.clone(),
+            nonces: None,
+            request_id: None,
+            tombstone: false,
           };
 
           ledger_store
@@ -215,8 +265,95 @@ impl MongoCosmosLedgerStore {
       fix_cached_height(&ledger_store.view_handle, &ledger_store.cache, &ledger).await?;
     }
 
+    ledger_store.index_existing_ledgers().await?;
+
     Ok(ledger_store)
   }
+
+  fn ledger_index(&self) -> Collection<DBLedger> {
+    self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLedger>(LEDGERS_COLLECTION)
+  }
+
+  // adds `handle` to the index, doing nothing if it is there already
+  async fn index_ledger(&self, handle: &str) -> Result<(), LedgerStoreError> {
+    let options = ReplaceOptions::builder().upsert(true).build();
+    self
+      .ledger_index()
+      .replace_one(
+        doc! { "_id": handle },
+        DBLedger {
+          handle: handle.to_string(),
+        },
+        options,
+      )
+      .await?;
+    Ok(())
+  }
+
+  // adds the ledgers created before the index existed to it, once
+  async fn index_existing_ledgers(&self) -> Result<(), LedgerStoreError> {
+    let index = self.ledger_index();
+    if index
+      .find_one(doc! { "_id": INDEX_MARKER }, None)
+      .await?
+      .is_some()
+    {
+      return Ok(());
+    }
+
+    let names = self
+      .client
+      .database(&self.dbname)
+      .list_collection_names(None)
+      .await?;
+    for name in names {
+      let is_ledger = hex::decode(&name)
+        .ok()
+        .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok())
+        .is_some_and(|handle| handle != self.view_handle);
+      if is_ledger {
+        self.index_ledger(&name).await?;
+      }
+    }
+
+    self.index_ledger(INDEX_MARKER).await
+  }
+
+  // appends `entry` with the nonces attached since the tail, retrying when the store is busy
+  async fn append_entry(
+    &self,
+    handle: &Handle,
+    entry: &LedgerEntry,
+    expected_height: usize,
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let client = self.client.clone();
+    let ledger = client
+      .database(&self.dbname)
+      .collection::<DBEntry>(&hex::encode(handle.to_bytes()));
+    let pending = client
+      .database(&self.dbname)
+      .collection::<DBNonces>(NONCES_COLLECTION);
+
+    loop {
+      with_retry!(
+        append_ledger_op(
+          handle,
+          entry,
+          expected_height,
+          &ledger,
+          &pending,
+          &self.cache
+        )
+        .await,
+        handle,
+        &self.cache,
+        &ledger
+      );
+    }
+  }
 }
 
 async fn find_db_entry(
@@ -243,11 +380,14 @@ async fn find_db_entry(
   Ok(db_entry)
 }
 
+// appends the block, request ID and tombstone flag of `entry`, along with the nonces attached
+// since the tail
 async fn append_ledger_op(
   handle: &Handle,
-  block: &Block,
+  entry: &LedgerEntry,
   expected_height: usize,
   ledger: &Collection<DBEntry>,
+  pending: &Collection<DBNonces>,
   cache: &CacheMap,
 ) -> Result<(usize, Nonces), LedgerStoreError> {
   let height = get_cached_height(handle, cache, ledger).await?;
@@ -267,7 +407,7 @@ async fn append_ledger_op(
 
   // 3. Construct the new entry we are going to append to the ledger
   let new_ledger_entry = SerializedLedgerEntry {
-    block: block.to_bytes(),
+    block: entry.get_block().to_bytes(),
     receipts: Receipts::new().to_bytes(),
   };
 
@@ -275,18 +415,61 @@ async fn append_ledger_op(
     .expect("failed to serialized new ledger entry")
     .to_bson_binary();
 
+  // 4. Take the nonces attached since the previous append, which go into the new entry
+  let handle_string = hex::encode(handle.to_bytes());
+  let taken = pending
+    .find_one_and_delete(doc! { "_id": handle_string.clone() }, None)
+    .await?
+    .map(|p| p.nonces)
+    .unwrap_or_default();
+  let mut nonces = Nonces::new();
+  for nonce in &taken {
+    match Nonce::from_bytes(&nonce.bytes) {
+      Ok(n) => nonces.add(n),
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ))
+      },
+    }
+  }
+
   let new_entry = DBEntry {
     index: height_plus_one,
     value: bson_new_ledger_entry,
+    nonces: Some(nonces.to_bytes().to_bson_binary()),
+    request_id: request_binary(entry.get_request_id()),
+    tombstone: entry.is_tombstone(),
   };
 
-  // 4. Try to insert the new entry into the ledger.
+  // 5. Try to insert the new entry into the ledger.
   // If it fails, caller must retry.
-  ledger.insert_one(new_entry, None).await?;
+  if let Err(error) = ledger.insert_one(new_entry, None).await {
+    // the nonces wait for the next append
+    if !taken.is_empty() {
+      push_nonces(pending, &handle_string, taken).await?;
+    }
+    return Err(LedgerStoreError::MongoDBError(error));
+  }
 
   // Update the cached height for this ledger
   update_cache_entry(handle, cache, height_plus_one)?;
-  Ok((height_plus_one as usize, Nonces::new()))
+  Ok((height_plus_one as usize, nonces))
+}
+
+async fn push_nonces(
+  pending: &Collection<DBNonces>,
+  handle_string: &str,
+  nonces: Vec<Binary>,
+) -> Result<(), LedgerStoreError> {
+  pending
+    .update_one(
+      doc! { "_id": handle_string },
+      doc! { "$push": { "nonces": { "$each": nonces } } },
+      UpdateOptions::builder().upsert(true).build(),
+    )
+    .await?;
+  Ok(())
 }
 
 async fn attach_ledger_receipts_op(
@@ -332,9 +515,18 @@ async fn attach_ledger_receipts_op(
   Ok(())
 }
 
+fn request_binary(request_id: &[u8]) -> Option<Binary> {
+  if request_id.is_empty() {
+    None
+  } else {
+    Some(request_id.to_vec().to_bson_binary())
+  }
+}
+
 async fn create_ledger_op(
   handle: &Handle,
   genesis_block: &Block,
+  request_id: &[u8],
   ledger: &Collection<DBEntry>,
   cache: &CacheMap,
 ) -> Result<(), LedgerStoreError> {
@@ -352,6 +544,9 @@ async fn create_ledger_op(
   let genesis_entry = DBEntry {
     index: 0,
     value: bson_init_data_ledger_entry,
+    nonces: None,
+    request_id: request_binary(request_id),
+    tombstone: false,
   };
 
   ledger.insert_one(&genesis_entry, None).await?;
@@ -398,11 +593,27 @@ async fn read_ledger_op(
   let entry: SerializedLedgerEntry =
     bincode::deserialize(&bson_entry.bytes).expect("failed to deserialize entry");
 
-  let res = LedgerEntry::new(
+  let nonces = match &ledger_entry.nonces {
+    Some(nonces) => match Nonces::from_bytes(&nonces.bytes) {
+      Ok(n) => n,
+      Err(_) => {
+        return Err(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ))
+      },
+    },
+    None => Nonces::new(),
+  };
+
+  let mut res = LedgerEntry::new(
     Block::from_bytes(&entry.block).unwrap(),
     Receipts::from_bytes(&entry.receipts).unwrap(),
-    None, //TODO
+    Some(nonces),
   );
+  if let Some(request_id) = &ledger_entry.request_id {
+    res = res.with_request_id(&request_id.bytes);
+  }
+  res = res.with_tombstone(ledger_entry.tombstone);
 
   Ok((res, checked_conversion!(index, usize)))
 }
@@ -523,45 +734,61 @@ const REQUEST_RATE_TOO_HIGH_CODE: i32 = 16500;
 
 #[async_trait]
 impl LedgerStore for MongoCosmosLedgerStore {
-  async fn create_ledger(
+  async fn create_ledger_with_request(
     &self,
     handle: &Handle,
     genesis_block: Block,
+    request_id: &[u8],
   ) -> Result<(), LedgerStoreError> {
     let client = self.client.clone();
     let ledger = client
       .database(&self.dbname)
       .collection::<DBEntry>(&hex::encode(&handle.to_bytes()));
 
-    loop {
-      with_retry!(
-        create_ledger_op(handle, &genesis_block, &ledger, &self.cache).await,
-        handle,
-        &self.cache,
-        &ledger
-      );
+    let res: Result<(), LedgerStoreError> = async {
+      loop {
+        with_retry!(
+          create_ledger_op(handle, &genesis_block, request_id, &ledger, &self.cache).await,
+          handle,
+          &self.cache,
+          &ledger
+        );
+      }
+    }
+    .await;
+
+    // A ledger that already exists may have been created by an earlier call that failed before
+    // indexing it, so it is indexed either way
+    match res {
+      Ok(()) | Err(LedgerStoreError::LedgerError(StorageError::DuplicateKey)) => {
+        self.index_ledger(&hex::encode(handle.to_bytes())).await?;
+        res
+      },
+      Err(_) => res,
     }
   }
 
-  async fn append_ledger(
+  async fn append_ledger_with_request(
     &self,
     handle: &Handle,
     block: &Block,
     expected_height: usize,
+    request_id: &[u8],
   ) -> Result<(usize, Nonces), LedgerStoreError> {
-    let client = self.client.clone();
-    let ledger = client
-      .database(&self.dbname)
-      .collection::<DBEntry>(&hex::encode(handle.to_bytes()));
+    let entry = LedgerEntry::new(block.clone(), Receipts::new(), None).with_request_id(request_id);
+    self.append_entry(handle, &entry, expected_height).await
+  }
 
-    loop {
-      with_retry!(
-        append_ledger_op(handle, block, expected_height, &ledger, &self.cache).await,
-        handle,
-        &self.cache,
-        &ledger
-      );
-    }
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let entry = LedgerEntry::new(Block::tombstone(), Receipts::new(), None).with_tombstone(true);
+    self
+      .append_entry(handle, &entry, expected_height)
+      .await
+      .map(|_| ())
   }
 
   async fn attach_ledger_receipts(
@@ -585,13 +812,28 @@ impl LedgerStore for MongoCosmosLedgerStore {
     }
   }
 
-  #[allow(unused_variables)]
   async fn attach_ledger_nonce(
     &self,
     handle: &Handle,
     nonce: &Nonce,
   ) -> Result<usize, LedgerStoreError> {
-    unimplemented!()
+    let client = self.client.clone();
+    let ledger = client
+      .database(&self.dbname)
+      .collection::<DBEntry>(&hex::encode(handle.to_bytes()));
+    let pending = client
+      .database(&self.dbname)
+      .collection::<DBNonces>(NONCES_COLLECTION);
+
+    // the nonce goes into the next entry appended to the ledger
+    let height = get_cached_height(handle, &self.cache, &ledger).await?;
+    push_nonces(
+      &pending,
+      &hex::encode(handle.to_bytes()),
+      vec![nonce.to_bytes().to_bson_binary()],
+    )
+    .await?;
+    Ok(checked_conversion!(checked_increment!(height), usize))
   }
 
   async fn read_ledger_tail(
@@ -649,6 +891,123 @@ impl LedgerStore for MongoCosmosLedgerStore {
     Ok(res.0)
   }
 
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let client = self.client.clone();
+    let ledger = client
+      .database(&self.dbname)
+      .collection::<DBEntry>(&hex::encode(handle.to_bytes()));
+
+    let height = find_ledger_height(&ledger).await?;
+    Ok(checked_conversion!(height, usize))
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    // a limit of zero means no limit to the query
+    if limit == 0 {
+      return Ok(Vec::new());
+    }
+
+    // The index is keyed by the hex encoding of handles, whose order is the order of handles,
+    // and its marker sorts before all of them
+    let after = cursor.map_or(INDEX_MARKER.to_string(), |c| hex::encode(c.to_bytes()));
+    let options = FindOptions::builder()
+      .sort(doc! { "_id": 1 })
+      .limit(checked_conversion!(limit, i64))
+      .build();
+    let mut entries = self
+      .ledger_index()
+      .find(doc! { "_id": { "$gt": after } }, options)
+      .await?;
+
+    let mut handles = Vec::new();
+    while entries.advance().await? {
+      let entry = entries.deserialize_current()?;
+      let handle = hex::decode(&entry.handle)
+        .ok()
+        .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok())
+        .ok_or(LedgerStoreError::LedgerError(
+          StorageError::DeserializationError,
+        ))?;
+      handles.push(handle);
+    }
+    Ok(handles)
+  }
+
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    let leases = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLease>(LEASE_COLLECTION);
+
+    match leases.find_one(doc! { "_id": 0_i64 }, None).await? {
+      None => Ok(None),
+      Some(l) => Ok(Some(Lease {
+        holder: l.holder,
+        epoch: checked_conversion!(l.epoch, u64),
+        expires_at: checked_conversion!(l.expires_at, u64),
+      })),
+    }
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    let leases = self
+      .client
+      .database(&self.dbname)
+      .collection::<DBLease>(LEASE_COLLECTION);
+    let new_lease = DBLease {
+      index: 0,
+      holder: lease.holder.clone(),
+      epoch: checked_conversion!(lease.epoch, i64),
+      expires_at: checked_conversion!(lease.expires_at, i64),
+    };
+
+    match expected {
+      // the insert fails with a duplicate key if another coordinator wrote a lease first
+      None => match leases.insert_one(&new_lease, None).await {
+        Ok(_) => Ok(()),
+        Err(error) => match error.kind.as_ref() {
+          mongodb::error::ErrorKind::Write(WriteError(write_error))
+            if write_error.code == DUPLICATE_KEY_CODE =>
+          {
+            Err(LedgerStoreError::LedgerError(
+              StorageError::IncorrectConditionalData,
+            ))
+          },
+          _ => Err(LedgerStoreError::MongoDBError(error)),
+        },
+      },
+      // the lease is replaced only if it still matches every field of the expected one
+      Some(e) => {
+        let res = leases
+          .replace_one(
+            doc! {
+                "_id": 0_i64,
+                "holder": e.holder.clone(),
+                "epoch": checked_conversion!(e.epoch, i64),
+                "expires_at": checked_conversion!(e.expires_at, i64),
+            },
+            &new_lease,
+            None,
+          )
+          .await?;
+        if res.matched_count == 0 {
+          return Err(LedgerStoreError::LedgerError(
+            StorageError::IncorrectConditionalData,
+          ));
+        }
+        Ok(())
+      },
+    }
+  }
+
   async fn reset_store(&self) -> Result<(), LedgerStoreError> {
     let client = self.client.clone();
     client
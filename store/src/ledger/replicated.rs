@@ -0,0 +1,601 @@
+use super::{Lease, LedgerEntry, LedgerStore};
+use crate::errors::{LedgerStoreError, StorageError};
+use async_trait::async_trait;
+use futures::future::join_all;
+use ledger::{Block, CustomSerde, Handle, NimbleDigest, Nonce, Nonces, Receipts};
+
+const REPAIR_LIST_PAGE_SIZE: usize = 100; // the number of ledgers listed at a time by a repair
+
+// errors that mean the replica answered but does not have what was asked for
+fn is_absent(error: &LedgerStoreError) -> bool {
+  matches!(
+    error,
+    LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)
+      | LedgerStoreError::LedgerError(StorageError::InvalidKey)
+      | LedgerStoreError::LedgerError(StorageError::InvalidIndex)
+      | LedgerStoreError::LedgerError(StorageError::PrunedIndex)
+  )
+}
+
+// Returns the error returned by the most replicas, so that callers see the same error as
+// they would from a single store
+fn most_common_error(errors: Vec<LedgerStoreError>) -> LedgerStoreError {
+  let count = |e: &LedgerStoreError| match e {
+    LedgerStoreError::LedgerError(s) => errors
+      .iter()
+      .filter(|o| matches!(o, LedgerStoreError::LedgerError(t) if t == s))
+      .count(),
+    LedgerStoreError::MongoDBError(_) => 1,
+  };
+  let mut best: Option<(usize, &LedgerStoreError)> = None;
+  for e in &errors {
+    let c = count(e);
+    let better = match best {
+      Some((b, _)) => c > b,
+      None => true,
+    };
+    if better {
+      best = Some((c, e));
+    }
+  }
+  match best {
+    Some((_, e)) => e.clone(),
+    None => LedgerStoreError::LedgerError(StorageError::UnhandledError),
+  }
+}
+
+// Picks the entry held by the most replicas and merges the receipts attached to it on each
+// of them, since receipts may have reached only some of them
+fn pick_entry(entries: Vec<LedgerEntry>) -> LedgerEntry {
+  let key = |e: &LedgerEntry| (e.get_block().to_bytes(), e.get_nonces().to_bytes());
+  let mut best: Option<(usize, &LedgerEntry)> = None;
+  for e in &entries {
+    let c = entries.iter().filter(|o| key(o) == key(e)).count();
+    let better = match best {
+      Some((b, _)) => c > b,
+      None => true,
+    };
+    if better {
+      best = Some((c, e));
+    }
+  }
+
+  // the caller never passes an empty list
+  let mut picked = best.unwrap().1.clone();
+  let mut receipts = picked.get_receipts().clone();
+  for e in entries.iter().filter(|o| key(o) == key(&picked)) {
+    receipts.merge_receipts(e.get_receipts());
+  }
+  picked.set_receipts(receipts);
+  picked
+}
+
+/// A ledger store that keeps every ledger on several underlying stores, so that it stays
+/// available as long as a majority of them are.
+///
+/// A write succeeds once a majority of the replicas accept it, and a read consults at least
+/// a majority and returns the latest entry any of them holds. Replicas that missed writes
+/// (e.g., while they were down) are back-filled by `repair`, which copies only entries
+/// that carry receipts, i.e., that were endorsed. A replica that holds a different entry
+/// at some index is reported and left alone; reads outvote it.
+pub struct ReplicatedLedgerStore<S: LedgerStore> {
+  replicas: Vec<S>,
+}
+
+impl<S: LedgerStore + Send + Sync> ReplicatedLedgerStore<S> {
+  pub fn new(replicas: Vec<S>) -> Result<Self, StorageError> {
+    if replicas.is_empty() {
+      eprintln!("A replicated ledger store needs at least one replica");
+      return Err(StorageError::BadRequest);
+    }
+    Ok(ReplicatedLedgerStore { replicas })
+  }
+
+  pub fn get_replicas(&self) -> &[S] {
+    &self.replicas
+  }
+
+  fn quorum(&self) -> usize {
+    self.replicas.len() / 2 + 1
+  }
+
+  // Returns the results of the replicas that succeeded, if they are a majority
+  fn require_quorum<T>(
+    &self,
+    results: Vec<Result<T, LedgerStoreError>>,
+  ) -> Result<Vec<T>, LedgerStoreError> {
+    let mut oks = Vec::new();
+    let mut errors = Vec::new();
+    for res in results {
+      match res {
+        Ok(v) => oks.push(v),
+        Err(e) => errors.push(e),
+      }
+    }
+    if oks.len() >= self.quorum() {
+      Ok(oks)
+    } else {
+      Err(most_common_error(errors))
+    }
+  }
+
+  // Returns the results of the replicas that answered a read, if a majority answered
+  // (including with the absence of what was asked for)
+  fn read_quorum<T>(
+    &self,
+    results: Vec<Result<T, LedgerStoreError>>,
+  ) -> Result<Vec<T>, LedgerStoreError> {
+    let mut oks = Vec::new();
+    let mut errors = Vec::new();
+    let mut num_absent = 0;
+    for res in results {
+      match res {
+        Ok(v) => oks.push(v),
+        Err(e) => {
+          if is_absent(&e) {
+            num_absent += 1;
+          }
+          errors.push(e);
+        },
+      }
+    }
+    if oks.len() + num_absent < self.quorum() || oks.is_empty() {
+      return Err(most_common_error(errors));
+    }
+    Ok(oks)
+  }
+
+  fn pick_tail(entries: Vec<(LedgerEntry, usize)>) -> (LedgerEntry, usize) {
+    // the caller never passes an empty list
+    let height = entries.iter().map(|(_e, h)| *h).max().unwrap();
+    let latest = entries
+      .into_iter()
+      .filter(|(_e, h)| *h == height)
+      .map(|(e, _h)| e)
+      .collect();
+    (pick_entry(latest), height)
+  }
+
+  /// Copies the entries of a ledger that carry receipts to the replicas that are missing
+  /// them, and returns the number of entries written. A pruned ledger is copied from its
+  /// checkpoint to replicas that do not have it, but a replica that has the ledger and is
+  /// behind the checkpoint cannot be repaired.
+  pub async fn repair_ledger(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let height = self.ledger_height(handle).await?;
+    let checkpoint = self.ledger_checkpoint(handle).await?;
+    // `None` for replicas without the ledger, and replicas that cannot be read are skipped
+    let mut heights = join_all(self.replicas.iter().map(|r| r.ledger_height(handle)))
+      .await
+      .into_iter()
+      .map(|res| match res {
+        Ok(h) => Ok(Some(h)),
+        Err(e) if is_absent(&e) => Ok(None),
+        Err(e) => Err(e),
+      })
+      .collect::<Vec<Result<Option<usize>, LedgerStoreError>>>();
+
+    let mut num_written = 0;
+    for idx in checkpoint..=height {
+      let lagging = heights
+        .iter()
+        .enumerate()
+        .filter(|(_i, h)| match h {
+          Ok(Some(h)) => *h < idx,
+          Ok(None) => true,
+          Err(_) => false,
+        })
+        .map(|(i, _h)| i)
+        .collect::<Vec<usize>>();
+      if lagging.is_empty() {
+        continue;
+      }
+
+      let entry = self.read_ledger_by_index(handle, idx).await?;
+      if entry.get_receipts().is_empty() {
+        break;
+      }
+
+      for i in lagging {
+        let replica = &self.replicas[i];
+        let res = if idx == 0 {
+          replica
+            .create_ledger(handle, entry.get_block().clone())
+            .await
+        } else if idx == checkpoint && matches!(heights[i], Ok(None)) {
+          replica.create_pruned_ledger(handle, idx, &entry).await
+        } else {
+          self.backfill(replica, handle, idx, &entry).await
+        };
+        let res = match res {
+          Ok(()) => {
+            replica
+              .attach_ledger_receipts(handle, idx, entry.get_receipts())
+              .await
+          },
+          Err(e) => Err(e),
+        };
+        match res {
+          Ok(()) => {
+            heights[i] = Ok(Some(idx));
+            num_written += 1;
+          },
+          Err(e) => {
+            eprintln!(
+              "Failed to repair replica {} at index {} of a ledger ({:?})",
+              i, idx, e
+            );
+            heights[i] = Err(e);
+          },
+        }
+      }
+    }
+
+    Ok(num_written)
+  }
+
+  async fn backfill(
+    &self,
+    replica: &S,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    // the replica hands back the nonces attached since the previous append, which must be
+    // the ones that were endorsed
+    for nonce in entry.get_nonces().get() {
+      replica.attach_ledger_nonce(handle, nonce).await?;
+    }
+    let (_height, nonces) = replica
+      .append_ledger(handle, entry.get_block(), idx)
+      .await?;
+    if nonces.to_bytes() != entry.get_nonces().to_bytes() {
+      eprintln!("The replica holds other nonces at index {}", idx);
+      return Err(LedgerStoreError::LedgerError(
+        StorageError::MismatchedReceipts,
+      ));
+    }
+    Ok(())
+  }
+
+  /// Copies the entries of the view ledger that carry receipts to the replicas that are
+  /// missing them, and returns the number of entries written
+  pub async fn repair_view_ledger(&self) -> Result<usize, LedgerStoreError> {
+    let (_tail, height) = self.read_view_ledger_tail().await?;
+    let mut heights = join_all(self.replicas.iter().map(|r| r.read_view_ledger_tail()))
+      .await
+      .into_iter()
+      .map(|res| res.map(|(_e, h)| h))
+      .collect::<Vec<Result<usize, LedgerStoreError>>>();
+
+    let mut num_written = 0;
+    // index 0 of the view ledger is created by every store when it is initialized
+    for idx in 1..=height {
+      let lagging = heights
+        .iter()
+        .enumerate()
+        .filter(|(_i, h)| matches!(h, Ok(h) if *h < idx))
+        .map(|(i, _h)| i)
+        .collect::<Vec<usize>>();
+      if lagging.is_empty() {
+        continue;
+      }
+
+      let entry = self.read_view_ledger_by_index(idx).await?;
+      if entry.get_receipts().is_empty() {
+        break;
+      }
+
+      for i in lagging {
+        let replica = &self.replicas[i];
+        let res = match replica.append_view_ledger(entry.get_block(), idx).await {
+          Ok(_height) => {
+            replica
+              .attach_view_ledger_receipts(idx, entry.get_receipts())
+              .await
+          },
+          Err(e) => Err(e),
+        };
+        match res {
+          Ok(()) => {
+            heights[i] = Ok(idx);
+            num_written += 1;
+          },
+          Err(e) => {
+            eprintln!(
+              "Failed to repair replica {} at index {} of the view ledger ({:?})",
+              i, idx, e
+            );
+            heights[i] = Err(e);
+          },
+        }
+      }
+    }
+
+    Ok(num_written)
+  }
+}
+
+#[async_trait]
+impl<S: LedgerStore + Send + Sync> LedgerStore for ReplicatedLedgerStore<S> {
+  async fn create_ledger_with_request(
+    &self,
+    handle: &NimbleDigest,
+    genesis_block: Block,
+    request_id: &[u8],
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.create_ledger_with_request(handle, genesis_block.clone(), request_id)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn append_ledger_with_request(
+    &self,
+    handle: &Handle,
+    block: &Block,
+    expected_height: usize,
+    request_id: &[u8],
+  ) -> Result<(usize, Nonces), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.append_ledger_with_request(handle, block, expected_height, request_id)),
+    )
+    .await;
+    let appended = self.require_quorum(results)?;
+
+    // the nonces are endorsed along with the block, so a majority must agree on them
+    let key = |(_h, n): &(usize, Nonces)| n.to_bytes();
+    for a in &appended {
+      if appended.iter().filter(|o| key(o) == key(a)).count() >= self.quorum() {
+        return Ok(a.clone());
+      }
+    }
+    eprintln!(
+      "The replicas disagree on the nonces at index {}",
+      expected_height
+    );
+    Err(LedgerStoreError::LedgerError(
+      StorageError::ConcurrentOperation,
+    ))
+  }
+
+  async fn append_tombstone(
+    &self,
+    handle: &Handle,
+    expected_height: usize,
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.append_tombstone(handle, expected_height)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn attach_ledger_receipts(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.attach_ledger_receipts(handle, idx, receipts)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn attach_ledger_receipts_batch(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    receipts: &[Receipts],
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.attach_ledger_receipts_batch(handle, idx, receipts)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn attach_ledger_nonce(
+    &self,
+    handle: &Handle,
+    nonce: &Nonce,
+  ) -> Result<usize, LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.attach_ledger_nonce(handle, nonce)),
+    )
+    .await;
+    let heights = self.require_quorum(results)?;
+
+    // a lagging replica attaches the nonce to an earlier index than the others
+    let mut best = (0, 0);
+    for h in &heights {
+      let c = heights.iter().filter(|o| *o == h).count();
+      if c > best.0 || (c == best.0 && *h > best.1) {
+        best = (c, *h);
+      }
+    }
+    Ok(best.1)
+  }
+
+  async fn read_ledger_tail(
+    &self,
+    handle: &Handle,
+  ) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.read_ledger_tail(handle))).await;
+    Ok(Self::pick_tail(self.read_quorum(results)?))
+  }
+
+  async fn read_ledger_by_index(
+    &self,
+    handle: &Handle,
+    idx: usize,
+  ) -> Result<LedgerEntry, LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.read_ledger_by_index(handle, idx)),
+    )
+    .await;
+    Ok(pick_entry(self.read_quorum(results)?))
+  }
+
+  async fn append_view_ledger(
+    &self,
+    block: &Block,
+    expected_height: usize,
+  ) -> Result<usize, LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.append_view_ledger(block, expected_height)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| expected_height)
+  }
+
+  async fn attach_view_ledger_receipts(
+    &self,
+    idx: usize,
+    receipts: &Receipts,
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.attach_view_ledger_receipts(idx, receipts)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn read_view_ledger_tail(&self) -> Result<(LedgerEntry, usize), LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.read_view_ledger_tail())).await;
+    Ok(Self::pick_tail(self.read_quorum(results)?))
+  }
+
+  async fn read_view_ledger_by_index(&self, idx: usize) -> Result<LedgerEntry, LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.read_view_ledger_by_index(idx)),
+    )
+    .await;
+    Ok(pick_entry(self.read_quorum(results)?))
+  }
+
+  async fn ledger_height(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.ledger_height(handle))).await;
+    // read_quorum never returns an empty list
+    Ok(self.read_quorum(results)?.into_iter().max().unwrap())
+  }
+
+  async fn list_ledgers(
+    &self,
+    cursor: Option<&Handle>,
+    limit: usize,
+  ) -> Result<Vec<Handle>, LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.list_ledgers(cursor, limit))).await;
+    // each replica returns its first `limit` handles, so the first `limit` of the union
+    // are the first `limit` overall
+    let mut handles = self
+      .require_quorum(results)?
+      .into_iter()
+      .flatten()
+      .collect::<Vec<Handle>>();
+    handles.sort();
+    handles.dedup();
+    handles.truncate(limit);
+    Ok(handles)
+  }
+
+  async fn prune_ledger(&self, handle: &Handle, height: usize) -> Result<usize, LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.prune_ledger(handle, height))).await;
+    // require_quorum never returns an empty list
+    Ok(self.require_quorum(results)?.into_iter().max().unwrap())
+  }
+
+  async fn ledger_checkpoint(&self, handle: &Handle) -> Result<usize, LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.ledger_checkpoint(handle))).await;
+    // entries below the latest checkpoint may be gone from the replicas that took it
+    Ok(self.read_quorum(results)?.into_iter().max().unwrap())
+  }
+
+  async fn create_pruned_ledger(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), LedgerStoreError> {
+    let results = join_all(
+      self
+        .replicas
+        .iter()
+        .map(|r| r.create_pruned_ledger(handle, idx, entry)),
+    )
+    .await;
+    self.require_quorum(results).map(|_| ())
+  }
+
+  async fn repair(&self) -> Result<usize, LedgerStoreError> {
+    let mut num_written = self.repair_view_ledger().await?;
+    let mut cursor = None;
+    loop {
+      let handles = self
+        .list_ledgers(cursor.as_ref(), REPAIR_LIST_PAGE_SIZE)
+        .await?;
+      if handles.is_empty() {
+        break;
+      }
+      for handle in &handles {
+        num_written += self.repair_ledger(handle).await?;
+      }
+      cursor = handles.last().cloned();
+    }
+    Ok(num_written)
+  }
+
+  // A conditional write is not atomic across replicas, so the lease is kept on the first
+  // replica only
+  async fn read_lease(&self) -> Result<Option<Lease>, LedgerStoreError> {
+    self.replicas[0].read_lease().await
+  }
+
+  async fn write_lease(
+    &self,
+    lease: &Lease,
+    expected: Option<&Lease>,
+  ) -> Result<(), LedgerStoreError> {
+    self.replicas[0].write_lease(lease, expected).await
+  }
+
+  async fn reset_store(&self) -> Result<(), LedgerStoreError> {
+    let results = join_all(self.replicas.iter().map(|r| r.reset_store())).await;
+    for res in results {
+      res?;
+    }
+    Ok(())
+  }
+}
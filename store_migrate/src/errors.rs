@@ -0,0 +1,29 @@
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MigrationError {
+  /// returned if the store type is unknown
+  InvalidStoreType,
+  /// returned if a store cannot be opened with the provided arguments
+  FailedToOpenStore,
+  /// returned if the key file of an encrypted store cannot be read
+  InvalidKeyFile,
+  /// returned if a call to the source store fails
+  FailedToReadSource,
+  /// returned if a call to the destination store fails
+  FailedToWriteDestination,
+  /// returned if the view ledger of the source cannot be verified
+  InvalidViewLedger,
+  /// returned if an entry already in the destination differs from the source
+  MismatchedEntry,
+  /// returned if the receipts of an entry do not verify against the view ledger
+  UnverifiedReceipts,
+  /// returned if the destination does not keep the nonces of an entry
+  NoncesNotPreserved,
+  /// returned if a backup cannot be read or does not match its manifest
+  InvalidBackup,
+  /// returned if the state of a majority of the endorsers of a backup cannot be read
+  FailedToReadEndorsers,
+  /// returned if restoring a backup would roll back a ledger relative to the endorsers
+  RollbackDetected,
+  /// returned if the source was pruned past the tail of a ledger in the destination
+  PrunedSource,
+}
@@ -0,0 +1,1162 @@
+mod errors;
+mod restore;
+
+use crate::errors::MigrationError;
+use clap::{App, Arg, ArgMatches};
+use ledger::{CustomSerde, Handle, NimbleDigest, NimbleHashTrait, VerifierState};
+use std::{collections::HashMap, sync::Arc};
+use store::{
+  content::content_hash,
+  errors::{LedgerStoreError, StorageError},
+  ledger::{
+    azure_table::TableLedgerStore,
+    encrypted::{EncryptedLedgerStore, KEY_SIZE as ENCRYPTION_KEY_SIZE},
+    filestore::FileStore,
+    in_memory::InMemoryLedgerStore,
+    mongodb_cosmos::MongoCosmosLedgerStore,
+    LedgerEntry, LedgerStore,
+  },
+};
+
+const DEFAULT_PAGE_SIZE: usize = 100; // the default number of ledgers listed at a time
+
+const ATTESTATION_STR: &str = "THIS IS A PLACE HOLDER FOR ATTESTATION";
+
+// (argument name, ledger store argument, help)
+const SOURCE_STORE_ARGS: [(&str, &str, &str); 6] = [
+  (
+    "source_cosmosurl",
+    "COSMOS_URL",
+    "The COSMOS URL of the source",
+  ),
+  (
+    "source_nimbledb",
+    "NIMBLE_DB",
+    "The database name of the source",
+  ),
+  (
+    "source_storage_account",
+    "STORAGE_ACCOUNT",
+    "The storage account name of the source",
+  ),
+  (
+    "source_storage_master_key",
+    "STORAGE_MASTER_KEY",
+    "The storage master key of the source",
+  ),
+  (
+    "source_fstore_dir",
+    "NIMBLE_FSTORE_DIR",
+    "The filestore directory of the source",
+  ),
+  (
+    "source_key_file",
+    "LEDGER_KEY_FILE",
+    "The file with the key the blocks of the source are encrypted with",
+  ),
+];
+
+const DEST_STORE_ARGS: [(&str, &str, &str); 6] = [
+  (
+    "dest_cosmosurl",
+    "COSMOS_URL",
+    "The COSMOS URL of the destination",
+  ),
+  (
+    "dest_nimbledb",
+    "NIMBLE_DB",
+    "The database name of the destination",
+  ),
+  (
+    "dest_storage_account",
+    "STORAGE_ACCOUNT",
+    "The storage account name of the destination",
+  ),
+  (
+    "dest_storage_master_key",
+    "STORAGE_MASTER_KEY",
+    "The storage master key of the destination",
+  ),
+  (
+    "dest_fstore_dir",
+    "NIMBLE_FSTORE_DIR",
+    "The filestore directory of the destination",
+  ),
+  (
+    "dest_key_file",
+    "LEDGER_KEY_FILE",
+    "The file with the key to encrypt the blocks of the destination with",
+  ),
+];
+
+type LedgerStoreRef = Arc<Box<dyn LedgerStore + Send + Sync>>;
+
+fn with_encryption<S: LedgerStore + Send + Sync + 'static>(
+  ledger_store: S,
+  key: Option<&[u8]>,
+) -> Box<dyn LedgerStore + Send + Sync> {
+  match key {
+    // the key length is checked when it is read
+    Some(k) => Box::new(EncryptedLedgerStore::new(ledger_store, k).unwrap()),
+    None => Box::new(ledger_store),
+  }
+}
+
+async fn open_store(
+  store_type: &str,
+  args: &HashMap<String, String>,
+) -> Result<LedgerStoreRef, MigrationError> {
+  // the key file holds a base64url-encoded key, as for the coordinator
+  let key = match args.get("LEDGER_KEY_FILE") {
+    None => None,
+    Some(path) => {
+      let key = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|k| base64_url::decode(k.trim()).ok());
+      match key {
+        Some(k) if k.len() == ENCRYPTION_KEY_SIZE => Some(k),
+        _ => {
+          eprintln!(
+            "Failed to read a {}-byte key from {}",
+            ENCRYPTION_KEY_SIZE, path
+          );
+          return Err(MigrationError::InvalidKeyFile);
+        },
+      }
+    },
+  };
+  let key = key.as_deref();
+
+  let res: Result<Box<dyn LedgerStore + Send + Sync>, LedgerStoreError> = match store_type {
+    "mongodb_cosmos" => match MongoCosmosLedgerStore::new(args).await {
+      Ok(s) => Ok(with_encryption(s, key)),
+      Err(e) => Err(e),
+    },
+    "table" => match TableLedgerStore::new(args).await {
+      Ok(s) => Ok(with_encryption(s, key)),
+      Err(e) => Err(e),
+    },
+    "filestore" => match FileStore::new(args).await {
+      Ok(s) => Ok(with_encryption(s, key)),
+      Err(e) => Err(e),
+    },
+    "memory" => Ok(with_encryption(InMemoryLedgerStore::new(), key)),
+    _ => {
+      eprintln!("Unknown store type {}", store_type);
+      return Err(MigrationError::InvalidStoreType);
+    },
+  };
+
+  match res {
+    Ok(s) => Ok(Arc::new(s)),
+    Err(e) => {
+      eprintln!("Failed to open the {} store ({:?})", store_type, e);
+      Err(MigrationError::FailedToOpenStore)
+    },
+  }
+}
+
+fn is_missing_ledger(error: &LedgerStoreError) -> bool {
+  matches!(
+    error,
+    LedgerStoreError::LedgerError(StorageError::KeyDoesNotExist)
+      | LedgerStoreError::LedgerError(StorageError::InvalidKey)
+  )
+}
+
+/// Rebuilds the verifier state from the view ledger in the same way the coordinator does
+/// on startup: the tail is checked against the attestation and each earlier view is
+/// authorized by the one that follows it.
+async fn rebuild_verifier_state(
+  ledger_store: &LedgerStoreRef,
+) -> Result<VerifierState, MigrationError> {
+  let mut vs = VerifierState::new();
+
+  let res = ledger_store.read_view_ledger_tail().await;
+  if res.is_err() {
+    eprintln!("Failed to read the view ledger tail {:?}", res);
+    return Err(MigrationError::FailedToReadSource);
+  }
+  let (view_ledger_tail, tail_height) = res.unwrap();
+
+  if tail_height == 0 {
+    return Ok(vs);
+  }
+
+  let view_ledger_head = if tail_height == 1 {
+    view_ledger_tail.clone()
+  } else {
+    match ledger_store.read_view_ledger_by_index(1).await {
+      Ok(e) => e,
+      Err(error) => {
+        eprintln!("Failed to read the view ledger head {:?}", error);
+        return Err(MigrationError::FailedToReadSource);
+      },
+    }
+  };
+  vs.set_group_identity(view_ledger_head.get_block().hash());
+
+  if let Err(error) = vs.apply_view_change(
+    &view_ledger_tail.get_block().to_bytes(),
+    &view_ledger_tail.get_receipts().to_bytes(),
+    Some(ATTESTATION_STR.as_bytes()),
+  ) {
+    eprintln!(
+      "Failed to verify the view ledger tail {} ({:?})",
+      tail_height, error
+    );
+    return Err(MigrationError::InvalidViewLedger);
+  }
+
+  for idx in (1..tail_height).rev() {
+    let view_ledger_entry = match ledger_store.read_view_ledger_by_index(idx).await {
+      Ok(e) => e,
+      Err(error) => {
+        eprintln!(
+          "Failed to read the view ledger entry at index {} ({:?})",
+          idx, error
+        );
+        return Err(MigrationError::FailedToReadSource);
+      },
+    };
+    if let Err(error) = vs.apply_view_change(
+      &view_ledger_entry.get_block().to_bytes(),
+      &view_ledger_entry.get_receipts().to_bytes(),
+      None,
+    ) {
+      eprintln!(
+        "Failed to verify the view ledger entry at index {} ({:?})",
+        idx, error
+      );
+      return Err(MigrationError::InvalidViewLedger);
+    }
+  }
+
+  Ok(vs)
+}
+
+/// Copies the view ledger and every ledger from one ledger store to another.
+///
+/// Entries that are already in the destination are compared with the source rather than
+/// copied again, so an interrupted migration can simply be run again. Receipts are merged
+/// into the destination, which makes re-attaching them idempotent.
+pub struct Migration {
+  source: LedgerStoreRef,
+  dest: LedgerStoreRef,
+  verifier_state: VerifierState,
+  allow_unverified: bool,
+}
+
+impl Migration {
+  pub async fn new(
+    source: LedgerStoreRef,
+    dest: LedgerStoreRef,
+    allow_unverified: bool,
+  ) -> Result<Self, MigrationError> {
+    let verifier_state = rebuild_verifier_state(&source).await?;
+    Ok(Migration {
+      source,
+      dest,
+      verifier_state,
+      allow_unverified,
+    })
+  }
+
+  pub async fn copy_view_ledger(&self) -> Result<usize, MigrationError> {
+    let src_height = match self.source.read_view_ledger_tail().await {
+      Ok((_entry, height)) => height,
+      Err(error) => {
+        eprintln!("Failed to read the source view ledger tail {:?}", error);
+        return Err(MigrationError::FailedToReadSource);
+      },
+    };
+    let dest_height = match self.dest.read_view_ledger_tail().await {
+      Ok((_entry, height)) => height,
+      Err(error) => {
+        eprintln!(
+          "Failed to read the destination view ledger tail {:?}",
+          error
+        );
+        return Err(MigrationError::FailedToWriteDestination);
+      },
+    };
+    if dest_height > src_height {
+      eprintln!(
+        "The destination view ledger is ahead of the source ({} > {})",
+        dest_height, src_height
+      );
+      return Err(MigrationError::MismatchedEntry);
+    }
+
+    // index 0 of the view ledger is created by every store when it is initialized
+    for idx in 1..=src_height {
+      let entry = match self.source.read_view_ledger_by_index(idx).await {
+        Ok(e) => e,
+        Err(error) => {
+          eprintln!(
+            "Failed to read the source view ledger at index {} ({:?})",
+            idx, error
+          );
+          return Err(MigrationError::FailedToReadSource);
+        },
+      };
+
+      if idx <= dest_height {
+        match self.dest.read_view_ledger_by_index(idx).await {
+          Ok(dest_entry) => {
+            if dest_entry.get_block().to_bytes() != entry.get_block().to_bytes() {
+              eprintln!("The view ledger entry at index {} differs", idx);
+              return Err(MigrationError::MismatchedEntry);
+            }
+          },
+          Err(error) => {
+            eprintln!(
+              "Failed to read the destination view ledger at index {} ({:?})",
+              idx, error
+            );
+            return Err(MigrationError::FailedToWriteDestination);
+          },
+        }
+      } else if let Err(error) = self.dest.append_view_ledger(entry.get_block(), idx).await {
+        eprintln!(
+          "Failed to append the view ledger at index {} ({:?})",
+          idx, error
+        );
+        return Err(MigrationError::FailedToWriteDestination);
+      }
+
+      if let Err(error) = self
+        .dest
+        .attach_view_ledger_receipts(idx, entry.get_receipts())
+        .await
+      {
+        eprintln!(
+          "Failed to attach the view ledger receipts at index {} ({:?})",
+          idx, error
+        );
+        return Err(MigrationError::FailedToWriteDestination);
+      }
+    }
+
+    Ok(src_height)
+  }
+
+  fn verify_entry(
+    &self,
+    handle: &Handle,
+    idx: usize,
+    entry: &LedgerEntry,
+  ) -> Result<(), MigrationError> {
+    let res = if entry.is_tombstone() {
+      self
+        .verifier_state
+        .verify_ledger_tombstone(handle, idx, &entry.get_receipts().to_bytes())
+    } else {
+      self.verifier_state.verify_ledger_entry(
+        handle,
+        &content_hash(entry.get_block()),
+        &entry.get_nonces().hash(),
+        idx,
+        &entry.get_receipts().to_bytes(),
+      )
+    };
+    if let Err(error) = res {
+      eprintln!(
+        "Failed to verify the receipts of ledger {} at index {} ({:?})",
+        base64_url::encode(&handle.to_bytes()),
+        idx,
+        error
+      );
+      if !self.allow_unverified {
+        return Err(MigrationError::UnverifiedReceipts);
+      }
+    }
+    Ok(())
+  }
+
+  pub async fn copy_ledger(&self, handle: &Handle) -> Result<usize, MigrationError> {
+    let src_height = match self.source.ledger_height(handle).await {
+      Ok(h) => h,
+      Err(error) => {
+        eprintln!("Failed to read the source ledger height {:?}", error);
+        return Err(MigrationError::FailedToReadSource);
+      },
+    };
+    let dest_height = match self.dest.ledger_height(handle).await {
+      Ok(h) => Some(h),
+      Err(error) if is_missing_ledger(&error) => None,
+      Err(error) => {
+        eprintln!("Failed to read the destination ledger height {:?}", error);
+        return Err(MigrationError::FailedToWriteDestination);
+      },
+    };
+
+    let src_checkpoint = match self.source.ledger_checkpoint(handle).await {
+      Ok(c) => c,
+      Err(error) => {
+        eprintln!("Failed to read the source ledger checkpoint {:?}", error);
+        return Err(MigrationError::FailedToReadSource);
+      },
+    };
+
+    // Resume from the tail of the destination, whose receipts may not have been attached,
+    // and copy a pruned ledger from its checkpoint
+    let start = match dest_height {
+      None => src_checkpoint,
+      Some(h) if h > src_height => {
+        eprintln!(
+          "The destination ledger is ahead of the source ({} > {})",
+          h, src_height
+        );
+        return Err(MigrationError::MismatchedEntry);
+      },
+      Some(h) if h < src_checkpoint => {
+        eprintln!(
+          "The source ledger was pruned up to {}, past the destination at {}",
+          src_checkpoint, h
+        );
+        return Err(MigrationError::PrunedSource);
+      },
+      Some(h) => h,
+    };
+
+    for idx in start..=src_height {
+      let entry = match self.source.read_ledger_by_index(handle, idx).await {
+        Ok(e) => e,
+        Err(error) => {
+          eprintln!(
+            "Failed to read the source ledger at index {} ({:?})",
+            idx, error
+          );
+          return Err(MigrationError::FailedToReadSource);
+        },
+      };
+      self.verify_entry(handle, idx, &entry)?;
+
+      if dest_height == Some(idx) {
+        match self.dest.read_ledger_by_index(handle, idx).await {
+          Ok(dest_entry) => {
+            if dest_entry.get_block().to_bytes() != entry.get_block().to_bytes() {
+              eprintln!("The ledger entry at index {} differs", idx);
+              return Err(MigrationError::MismatchedEntry);
+            }
+          },
+          Err(error) => {
+            eprintln!(
+              "Failed to read the destination ledger at index {} ({:?})",
+              idx, error
+            );
+            return Err(MigrationError::FailedToWriteDestination);
+          },
+        }
+      } else if idx == 0 {
+        if let Err(error) = self
+          .dest
+          .create_ledger_with_request(handle, entry.get_block().clone(), entry.get_request_id())
+          .await
+        {
+          eprintln!("Failed to create the ledger {:?}", error);
+          return Err(MigrationError::FailedToWriteDestination);
+        }
+      } else if dest_height.is_none() && idx == src_checkpoint {
+        if let Err(error) = self.dest.create_pruned_ledger(handle, idx, &entry).await {
+          eprintln!("Failed to create the pruned ledger {:?}", error);
+          return Err(MigrationError::FailedToWriteDestination);
+        }
+      } else {
+        // the store hands back the nonces attached since the previous append
+        for nonce in entry.get_nonces().get() {
+          if let Err(error) = self.dest.attach_ledger_nonce(handle, nonce).await {
+            eprintln!("Failed to attach a nonce at index {} ({:?})", idx, error);
+            return Err(MigrationError::FailedToWriteDestination);
+          }
+        }
+        let res = if entry.is_tombstone() {
+          self.dest.append_tombstone(handle, idx).await.map(|()| None)
+        } else {
+          self
+            .dest
+            .append_ledger_with_request(handle, entry.get_block(), idx, entry.get_request_id())
+            .await
+            .map(|(_height, nonces)| Some(nonces))
+        };
+        match res {
+          Ok(Some(nonces)) if nonces.to_bytes() != entry.get_nonces().to_bytes() => {
+            eprintln!("The destination did not keep the nonces at index {}", idx);
+            return Err(MigrationError::NoncesNotPreserved);
+          },
+          Ok(_) => {},
+          Err(error) => {
+            eprintln!("Failed to append the ledger at index {} ({:?})", idx, error);
+            return Err(MigrationError::FailedToWriteDestination);
+          },
+        }
+      }
+
+      if let Err(error) = self
+        .dest
+        .attach_ledger_receipts(handle, idx, entry.get_receipts())
+        .await
+      {
+        eprintln!(
+          "Failed to attach the ledger receipts at index {} ({:?})",
+          idx, error
+        );
+        return Err(MigrationError::FailedToWriteDestination);
+      }
+    }
+
+    Ok(src_height)
+  }
+
+  /// Verifies the receipts of every entry in the source without copying anything
+  pub async fn verify_ledgers(&self, page_size: usize) -> Result<usize, MigrationError> {
+    let mut cursor = None;
+    let mut num_ledgers = 0;
+    loop {
+      let handles = match self.source.list_ledgers(cursor.as_ref(), page_size).await {
+        Ok(h) => h,
+        Err(error) => {
+          eprintln!("Failed to list the source ledgers {:?}", error);
+          return Err(MigrationError::FailedToReadSource);
+        },
+      };
+      if handles.is_empty() {
+        break;
+      }
+
+      for handle in &handles {
+        let height = match self.source.ledger_height(handle).await {
+          Ok(h) => h,
+          Err(error) => {
+            eprintln!("Failed to read the source ledger height {:?}", error);
+            return Err(MigrationError::FailedToReadSource);
+          },
+        };
+        let checkpoint = match self.source.ledger_checkpoint(handle).await {
+          Ok(c) => c,
+          Err(error) => {
+            eprintln!("Failed to read the source ledger checkpoint {:?}", error);
+            return Err(MigrationError::FailedToReadSource);
+          },
+        };
+        for idx in checkpoint..=height {
+          match self.source.read_ledger_by_index(handle, idx).await {
+            Ok(entry) => self.verify_entry(handle, idx, &entry)?,
+            Err(error) => {
+              eprintln!(
+                "Failed to read the source ledger at index {} ({:?})",
+                idx, error
+              );
+              return Err(MigrationError::FailedToReadSource);
+            },
+          }
+        }
+        num_ledgers += 1;
+      }
+      cursor = handles.last().cloned();
+    }
+
+    Ok(num_ledgers)
+  }
+
+  pub async fn copy_ledgers(
+    &self,
+    cursor: Option<Handle>,
+    page_size: usize,
+  ) -> Result<usize, MigrationError> {
+    let mut cursor = cursor;
+    let mut num_ledgers = 0;
+    loop {
+      let handles = match self.source.list_ledgers(cursor.as_ref(), page_size).await {
+        Ok(h) => h,
+        Err(error) => {
+          eprintln!("Failed to list the source ledgers {:?}", error);
+          return Err(MigrationError::FailedToReadSource);
+        },
+      };
+      if handles.is_empty() {
+        break;
+      }
+
+      for handle in &handles {
+        let height = self.copy_ledger(handle).await?;
+        // the handle doubles as the cursor to resume from
+        println!(
+          "Copied ledger {} (height {})",
+          base64_url::encode(&handle.to_bytes()),
+          height
+        );
+        num_ledgers += 1;
+      }
+      cursor = handles.last().cloned();
+    }
+
+    Ok(num_ledgers)
+  }
+}
+
+fn store_args(
+  cli_matches: &ArgMatches,
+  store_args: &[(&str, &str, &str)],
+) -> HashMap<String, String> {
+  let mut args = HashMap::<String, String>::new();
+  for (name, key, _help) in store_args {
+    if let Some(x) = cli_matches.value_of(name) {
+      args.insert(key.to_string(), x.to_string());
+    }
+  }
+  args
+}
+
+#[tokio::main]
+async fn main() {
+  let mut config = App::new("nimble_store_migrate")
+    .arg(
+      Arg::with_name("source")
+        .short("s")
+        .long("source")
+        .required_unless("restore")
+        .conflicts_with("restore")
+        .takes_value(true)
+        .possible_values(&["memory", "filestore", "mongodb_cosmos", "table"])
+        .help("The type of the store to copy from"),
+    )
+    .arg(
+      Arg::with_name("dest")
+        .short("d")
+        .long("dest")
+        .required(true)
+        .takes_value(true)
+        .possible_values(&["memory", "filestore", "mongodb_cosmos", "table"])
+        .help("The type of the store to copy to"),
+    )
+    .arg(
+      Arg::with_name("cursor")
+        .long("cursor")
+        .takes_value(true)
+        .help("Handle (base64url) of the last copied ledger, to skip the ledgers up to it"),
+    )
+    .arg(
+      Arg::with_name("page_size")
+        .long("page_size")
+        .takes_value(true)
+        .help("The number of ledgers listed at a time"),
+    )
+    .arg(
+      Arg::with_name("allow_unverified")
+        .long("allow_unverified")
+        .help("Copy entries whose receipts do not verify instead of stopping"),
+    )
+    .arg(
+      Arg::with_name("restore")
+        .long("restore")
+        .takes_value(true)
+        .help("Backup written by the coordinator to restore instead of copying a store"),
+    )
+    .arg(
+      Arg::with_name("endorsers")
+        .long("endorsers")
+        .takes_value(true)
+        .requires("restore")
+        .help("Endorsers to check the backup against, instead of those in its view ledger"),
+    );
+  for (name, _key, help) in SOURCE_STORE_ARGS.iter().chain(DEST_STORE_ARGS.iter()) {
+    config = config.arg(Arg::with_name(name).long(name).takes_value(true).help(help));
+  }
+
+  let cli_matches = config.get_matches();
+
+  let cursor = cli_matches.value_of("cursor").map(|c| {
+    match base64_url::decode(c)
+      .ok()
+      .and_then(|bytes| NimbleDigest::from_bytes(&bytes).ok())
+    {
+      Some(h) => h,
+      None => panic!("Failed to parse the cursor {}", c),
+    }
+  });
+  let page_size = match cli_matches.value_of("page_size") {
+    Some(x) => match x.parse() {
+      Ok(v) => v,
+      Err(_) => panic!("Failed to parse the page size"),
+    },
+    None => DEFAULT_PAGE_SIZE,
+  };
+
+  if let Some(path) = cli_matches.value_of("restore") {
+    let endorsers = cli_matches
+      .value_of("endorsers")
+      .map(|e| e.split(',').map(|uri| uri.to_string()).collect());
+    let res = async {
+      let dest = open_store(
+        cli_matches.value_of("dest").unwrap(),
+        &store_args(&cli_matches, &DEST_STORE_ARGS),
+      )
+      .await?;
+      restore::restore_backup(
+        path,
+        dest,
+        endorsers,
+        cli_matches.is_present("allow_unverified"),
+        page_size,
+      )
+      .await
+    }
+    .await;
+
+    if let Err(error) = res {
+      eprintln!("Restore failed ({:?})", error);
+      std::process::exit(1);
+    }
+    return;
+  }
+
+  let res = async {
+    let source = open_store(
+      cli_matches.value_of("source").unwrap(),
+      &store_args(&cli_matches, &SOURCE_STORE_ARGS),
+    )
+    .await?;
+    let dest = open_store(
+      cli_matches.value_of("dest").unwrap(),
+      &store_args(&cli_matches, &DEST_STORE_ARGS),
+    )
+    .await?;
+
+    let migration =
+      Migration::new(source, dest, cli_matches.is_present("allow_unverified")).await?;
+    let view_height = migration.copy_view_ledger().await?;
+    println!("Copied the view ledger (height {})", view_height);
+    let num_ledgers = migration.copy_ledgers(cursor, page_size).await?;
+    println!("Copied {} ledgers", num_ledgers);
+    Ok::<(), MigrationError>(())
+  }
+  .await;
+
+  if let Err(error) = res {
+    eprintln!("Migration failed ({:?})", error);
+    std::process::exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{errors::MigrationError, restore, LedgerStoreRef, Migration};
+  use ledger::{
+    compute_aggregated_block_hash,
+    signature::{PrivateKey, PrivateKeyTrait, PublicKeyTrait},
+    tombstone_hash, Block, CustomSerde, EndorserHostnames, Handle, IdSig, MetaBlock, NimbleDigest,
+    NimbleHashTrait, Nonce, Receipt, Receipts,
+  };
+  use std::sync::Arc;
+  use store::{
+    backup::BackupWriter,
+    ledger::{
+      encrypted::EncryptedLedgerStore, filestore::FileStore, in_memory::InMemoryLedgerStore,
+    },
+  };
+
+  fn sign(
+    sks: &[PrivateKey],
+    view: &NimbleDigest,
+    metablock: &MetaBlock,
+    message: &NimbleDigest,
+  ) -> Receipts {
+    let mut receipts = Receipts::new();
+    for sk in sks {
+      let id_sig = IdSig::new(
+        sk.get_public_key().unwrap(),
+        sk.sign(&message.to_bytes()).unwrap(),
+      );
+      receipts.add(&Receipt::new(*view, metablock.clone(), id_sig));
+    }
+    receipts
+  }
+
+  // Populates the store with a view ledger of one endorser and `num_ledgers` ledgers whose
+  // entries carry receipts signed by `ledger_sk`
+  async fn populate(
+    store: &LedgerStoreRef,
+    sk: &PrivateKey,
+    ledger_sk: &PrivateKey,
+    num_ledgers: u8,
+  ) -> Vec<Handle> {
+    populate_with(
+      store,
+      std::slice::from_ref(sk),
+      std::slice::from_ref(ledger_sk),
+      num_ledgers,
+      1,
+    )
+    .await
+  }
+
+  // Like `populate`, but with a view ledger of the endorsers of `sks`, whose receipts every
+  // entry carries from `ledger_sks`, and with `num_nonces` nonces attached before each append
+  async fn populate_with(
+    store: &LedgerStoreRef,
+    sks: &[PrivateKey],
+    ledger_sks: &[PrivateKey],
+    num_ledgers: u8,
+    num_nonces: usize,
+  ) -> Vec<Handle> {
+    let config: EndorserHostnames = sks
+      .iter()
+      .enumerate()
+      .map(|(i, sk)| {
+        (
+          sk.get_public_key().unwrap().to_bytes(),
+          format!("http://[::1]:{}", 9090 + i),
+        )
+      })
+      .collect();
+    let view_block = Block::new(&bincode::serialize(&config).unwrap());
+    let group_identity = view_block.hash();
+    let view_metablock = MetaBlock::new(&MetaBlock::default().hash(), &view_block.hash(), 1);
+    let view = NimbleDigest::default();
+    let view_receipts = sign(
+      sks,
+      &view,
+      &view_metablock,
+      &group_identity.digest_with(&view.digest_with(&view_metablock.hash())),
+    );
+    store.append_view_ledger(&view_block, 1).await.unwrap();
+    store
+      .attach_view_ledger_receipts(1, &view_receipts)
+      .await
+      .unwrap();
+
+    let ledger_view = view_metablock.hash();
+    let mut handles = Vec::new();
+    for i in 0..num_ledgers {
+      let handle = NimbleDigest::digest(&[i]);
+      let mut prev = MetaBlock::default();
+      for idx in 0..=(i as usize + 1) {
+        let block = Block::new(&[i, idx as u8]);
+        let nonces = if idx == 0 {
+          store
+            .create_ledger_with_request(&handle, block.clone(), b"create")
+            .await
+            .unwrap();
+          ledger::Nonces::new()
+        } else {
+          for j in 0..num_nonces {
+            let mut nonce = [idx as u8; 16];
+            nonce[15] = nonce[15].wrapping_add(j as u8);
+            let nonce = Nonce::new(&nonce).unwrap();
+            store.attach_ledger_nonce(&handle, &nonce).await.unwrap();
+          }
+          let (_height, nonces) = store
+            .append_ledger_with_request(&handle, &block, idx, &[idx as u8])
+            .await
+            .unwrap();
+          nonces
+        };
+
+        let block_hash =
+          compute_aggregated_block_hash(&block.hash().to_bytes(), &nonces.hash().to_bytes());
+        let metablock = if idx == 0 {
+          MetaBlock::genesis(&block_hash)
+        } else {
+          MetaBlock::new(&prev.hash(), &block_hash, idx)
+        };
+        let message = group_identity
+          .digest_with(&ledger_view.digest_with(&handle.digest_with(&metablock.hash())));
+        let receipts = sign(ledger_sks, &ledger_view, &metablock, &message);
+        store
+          .attach_ledger_receipts(&handle, idx, &receipts)
+          .await
+          .unwrap();
+        prev = metablock;
+      }
+      // the first ledger was deleted
+      if i == 0 {
+        let idx = i as usize + 2;
+        store.append_tombstone(&handle, idx).await.unwrap();
+        let tombstone = MetaBlock::tombstone(&prev.hash(), idx);
+        let message = group_identity
+          .digest_with(&ledger_view.digest_with(&tombstone_hash(&handle, &tombstone)));
+        let receipts = sign(ledger_sks, &ledger_view, &tombstone, &message);
+        store
+          .attach_ledger_receipts(&handle, idx, &receipts)
+          .await
+          .unwrap();
+      }
+      handles.push(handle);
+    }
+    handles
+  }
+
+  async fn check_copied(source: &LedgerStoreRef, dest: &LedgerStoreRef, handles: &[Handle]) {
+    for handle in handles {
+      let height = source.ledger_height(handle).await.unwrap();
+      assert_eq!(dest.ledger_height(handle).await.unwrap(), height);
+      let checkpoint = source.ledger_checkpoint(handle).await.unwrap();
+      assert_eq!(dest.ledger_checkpoint(handle).await.unwrap(), checkpoint);
+      for idx in checkpoint..=height {
+        let src_entry = source.read_ledger_by_index(handle, idx).await.unwrap();
+        let dest_entry = dest.read_ledger_by_index(handle, idx).await.unwrap();
+        assert_eq!(
+          src_entry.get_block().to_bytes(),
+          dest_entry.get_block().to_bytes()
+        );
+        assert_eq!(
+          src_entry.get_nonces().to_bytes(),
+          dest_entry.get_nonces().to_bytes()
+        );
+        assert_eq!(
+          src_entry.get_receipts().to_bytes(),
+          dest_entry.get_receipts().to_bytes()
+        );
+        assert_eq!(src_entry.get_request_id(), dest_entry.get_request_id());
+        assert_eq!(src_entry.is_tombstone(), dest_entry.is_tombstone());
+      }
+    }
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_and_resume() {
+    let sk = PrivateKey::new();
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate(&source, &sk, &sk, 4).await;
+
+    // Copy part of the data as if an earlier migration was interrupted
+    let dest: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    assert_eq!(migration.copy_view_ledger().await.unwrap(), 1);
+    assert_eq!(migration.copy_ledger(&handles[1]).await.unwrap(), 2);
+    let genesis = source.read_ledger_by_index(&handles[3], 0).await.unwrap();
+    dest
+      .create_ledger_with_request(
+        &handles[3],
+        genesis.get_block().clone(),
+        genesis.get_request_id(),
+      )
+      .await
+      .unwrap();
+
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    assert_eq!(migration.copy_view_ledger().await.unwrap(), 1);
+    assert_eq!(migration.copy_ledgers(None, 3).await.unwrap(), 4);
+    check_copied(&source, &dest, &handles).await;
+
+    // The destination verifies on its own
+    assert!(Migration::new(dest.clone(), source.clone(), false)
+      .await
+      .is_ok());
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_pruned_ledgers() {
+    let sk = PrivateKey::new();
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate(&source, &sk, &sk, 3).await;
+
+    // A destination that stopped before the checkpoint cannot catch up
+    let stale: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let genesis = source.read_ledger_by_index(&handles[2], 0).await.unwrap();
+    stale
+      .create_ledger(&handles[2], genesis.get_block().clone())
+      .await
+      .unwrap();
+    assert_eq!(source.prune_ledger(&handles[2], 2).await.unwrap(), 2);
+    let migration = Migration::new(source.clone(), stale, false).await.unwrap();
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(
+      migration.copy_ledger(&handles[2]).await.unwrap_err(),
+      MigrationError::PrunedSource
+    );
+
+    // Pruned ledgers are verified and copied from their checkpoint
+    let dest: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    assert_eq!(migration.verify_ledgers(10).await.unwrap(), 3);
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(migration.copy_ledgers(None, 10).await.unwrap(), 3);
+    check_copied(&source, &dest, &handles).await;
+    assert_eq!(dest.ledger_checkpoint(&handles[2]).await.unwrap(), 2);
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_to_filestore() {
+    let sk = PrivateKey::new();
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate(&source, &sk, &sk, 3).await;
+    assert_eq!(source.prune_ledger(&handles[2], 2).await.unwrap(), 2);
+
+    // the ledgers carry nonces, which the destination keeps with their entries
+    let dir = std::env::temp_dir().join(format!("nimble_migrate_fstore_{}", std::process::id()));
+    let mut args = std::collections::HashMap::new();
+    args.insert(
+      "NIMBLE_FSTORE_DIR".to_string(),
+      dir.to_str().unwrap().to_string(),
+    );
+    let dest: LedgerStoreRef = Arc::new(Box::new(FileStore::new(&args).await.unwrap()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(migration.copy_ledgers(None, 10).await.unwrap(), 3);
+    check_copied(&source, &dest, &handles).await;
+    dest.reset_store().await.unwrap();
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_large_entries_to_filestore() {
+    // the entries carry more nonces and receipts than fit in a slot of the file store
+    let sks = (0..8).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate_with(&source, &sks, &sks, 3, 100).await;
+    let entry = source.read_ledger_by_index(&handles[1], 1).await.unwrap();
+    assert_eq!(entry.get_nonces().get().len(), 100);
+    assert_eq!(source.prune_ledger(&handles[2], 2).await.unwrap(), 2);
+
+    let dir = std::env::temp_dir().join(format!("nimble_migrate_large_{}", std::process::id()));
+    let mut args = std::collections::HashMap::new();
+    args.insert(
+      "NIMBLE_FSTORE_DIR".to_string(),
+      dir.to_str().unwrap().to_string(),
+    );
+    let dest: LedgerStoreRef = Arc::new(Box::new(FileStore::new(&args).await.unwrap()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(migration.copy_ledgers(None, 10).await.unwrap(), 3);
+    check_copied(&source, &dest, &handles).await;
+
+    // the entries are read back from their own files once the store is reopened, and are
+    // still verified
+    drop(migration);
+    drop(dest);
+    let dest: LedgerStoreRef = Arc::new(Box::new(FileStore::new(&args).await.unwrap()));
+    check_copied(&source, &dest, &handles).await;
+    let migration = Migration::new(dest.clone(), source.clone(), false)
+      .await
+      .unwrap();
+    assert_eq!(migration.verify_ledgers(10).await.unwrap(), 3);
+
+    // and pruning them removes their files
+    assert_eq!(dest.prune_ledger(&handles[1], 2).await.unwrap(), 2);
+    let name = handles[1]
+      .to_bytes()
+      .iter()
+      .map(|b| format!("{:02x}", b))
+      .collect::<String>();
+    let pruned = dir.join(format!("{}.1", name));
+    let kept = dir.join(format!("{}.2", name));
+    assert!(!pruned.exists() && kept.exists());
+    dest.reset_store().await.unwrap();
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_from_encrypted_store() {
+    let sk = PrivateKey::new();
+    let source: LedgerStoreRef = Arc::new(Box::new(
+      EncryptedLedgerStore::new(InMemoryLedgerStore::new(), &[3u8; 32]).unwrap(),
+    ));
+    let handles = populate(&source, &sk, &sk, 2).await;
+
+    let dest: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(migration.copy_ledgers(None, 10).await.unwrap(), 2);
+    check_copied(&source, &dest, &handles).await;
+  }
+
+  #[tokio::test]
+  pub async fn test_migrate_unverified_receipts() {
+    let sk = PrivateKey::new();
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    // the ledgers are signed by a key that is not in the view
+    let handles = populate(&source, &sk, &PrivateKey::new(), 2).await;
+
+    let dest: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let migration = Migration::new(source.clone(), dest.clone(), false)
+      .await
+      .unwrap();
+    migration.copy_view_ledger().await.unwrap();
+    assert_eq!(
+      migration.copy_ledgers(None, 10).await.unwrap_err(),
+      MigrationError::UnverifiedReceipts
+    );
+
+    let migration = Migration::new(source.clone(), dest.clone(), true)
+      .await
+      .unwrap();
+    assert_eq!(migration.copy_ledgers(None, 10).await.unwrap(), 2);
+    check_copied(&source, &dest, &handles).await;
+  }
+
+  async fn write_backup(source: &LedgerStoreRef, handles: &[Handle], path: &std::path::Path) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut writer = BackupWriter::new(file).unwrap();
+    let (_entry, view_height) = source.read_view_ledger_tail().await.unwrap();
+    for idx in 1..=view_height {
+      let entry = source.read_view_ledger_by_index(idx).await.unwrap();
+      writer.write_view_entry(idx, &entry).unwrap();
+    }
+    for handle in handles {
+      let height = source.ledger_height(handle).await.unwrap();
+      for idx in 0..=height {
+        let entry = source.read_ledger_by_index(handle, idx).await.unwrap();
+        writer.write_ledger_entry(handle, idx, &entry).unwrap();
+      }
+    }
+    writer.finish().unwrap();
+  }
+
+  #[tokio::test]
+  pub async fn test_restore_checks_before_writing() {
+    let dir = std::env::temp_dir().join(format!("nimble_restore_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let sk = PrivateKey::new();
+
+    // the endorsers of the backup cannot be reached to rule out a rollback
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate(&source, &sk, &sk, 2).await;
+    let path = dir.join("verified.backup");
+    write_backup(&source, &handles, &path).await;
+    let dest: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let res = restore::restore_backup(
+      path.to_str().unwrap(),
+      dest.clone(),
+      Some(vec!["http://127.0.0.1:1".to_string()]),
+      false,
+      10,
+    )
+    .await;
+    assert_eq!(res.unwrap_err(), MigrationError::FailedToReadEndorsers);
+
+    // receipts are verified before the endorsers are contacted
+    let source: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+    let handles = populate(&source, &sk, &PrivateKey::new(), 2).await;
+    let path = dir.join("unverified.backup");
+    write_backup(&source, &handles, &path).await;
+    let res = restore::restore_backup(path.to_str().unwrap(), dest.clone(), None, false, 10).await;
+    assert_eq!(res.unwrap_err(), MigrationError::UnverifiedReceipts);
+
+    // a corrupted backup is rejected
+    let mut bytes = std::fs::read(&path).unwrap();
+    let len = bytes.len();
+    bytes[len / 2] ^= 1;
+    std::fs::write(&path, &bytes).unwrap();
+    let res = restore::restore_backup(path.to_str().unwrap(), dest.clone(), None, false, 10).await;
+    assert_eq!(res.unwrap_err(), MigrationError::InvalidBackup);
+
+    // nothing was written to the destination
+    assert_eq!(dest.read_view_ledger_tail().await.unwrap().1, 0);
+    assert!(dest.list_ledgers(None, 10).await.unwrap().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,367 @@
+use crate::{errors::MigrationError, LedgerStoreRef, Migration};
+use ledger::{
+  endorser_proto::{self, LedgerTailMapEntry},
+  produce_hash_of_state,
+  signature::{PublicKey, PublicKeyTrait},
+  CustomSerde, EndorserHostnames, MetaBlock, NimbleDigest, NimbleHashTrait, Receipt,
+};
+use std::{collections::HashMap, fs::File, io::BufReader, sync::Arc};
+use store::{
+  backup::{load_backup, BackupManifest, BackupReader},
+  ledger::in_memory::InMemoryLedgerStore,
+};
+use tonic::transport::Endpoint;
+
+const ENDORSER_TIMEOUT: u64 = 10; // seconds: the connect and request timeout to endorsers
+
+/// The state an endorser reports: the tail of each ledger it holds, and its receipt for the
+/// tail of the view ledger, which also signs the hash of the ledger tails
+pub struct EndorserState {
+  pub receipt: Receipt,
+  pub ledger_tail_map: Vec<LedgerTailMapEntry>,
+}
+
+async fn read_endorser_state(uri: &str) -> Result<EndorserState, MigrationError> {
+  let endpoint = match Endpoint::from_shared(uri.to_string()) {
+    Ok(e) => e
+      .connect_timeout(std::time::Duration::from_secs(ENDORSER_TIMEOUT))
+      .timeout(std::time::Duration::from_secs(ENDORSER_TIMEOUT)),
+    Err(error) => {
+      eprintln!("Invalid endorser URI {} ({:?})", uri, error);
+      return Err(MigrationError::FailedToReadEndorsers);
+    },
+  };
+  let channel = match endpoint.connect().await {
+    Ok(c) => c,
+    Err(error) => {
+      eprintln!("Failed to connect to the endorser {} ({:?})", uri, error);
+      return Err(MigrationError::FailedToReadEndorsers);
+    },
+  };
+  let mut client = endorser_proto::endorser_call_client::EndorserCallClient::new(channel);
+
+  let resp = match client.read_state(endorser_proto::ReadStateReq {}).await {
+    Ok(r) => r.into_inner(),
+    Err(status) => {
+      eprintln!(
+        "Failed to read the state of the endorser {} ({:?})",
+        uri, status
+      );
+      return Err(MigrationError::FailedToReadEndorsers);
+    },
+  };
+  let receipt = match Receipt::from_bytes(&resp.receipt) {
+    Ok(r) => r,
+    Err(_) => {
+      eprintln!("The endorser {} returned an invalid receipt", uri);
+      return Err(MigrationError::FailedToReadEndorsers);
+    },
+  };
+
+  Ok(EndorserState {
+    receipt,
+    ledger_tail_map: resp.ledger_tail_map,
+  })
+}
+
+// returns the height of each ledger in `state` if the endorser with public key `pk` signed it
+// for the group `group_identity`. The signature covers the metablocks of the ledger tails but
+// not the heights listed next to them, so the heights are taken from the metablocks.
+fn verified_heights(
+  state: &EndorserState,
+  pk: &[u8],
+  group_identity: &NimbleDigest,
+) -> Option<Vec<(Vec<u8>, usize)>> {
+  let view = produce_hash_of_state(&state.ledger_tail_map);
+  if view != *state.receipt.get_view() {
+    return None;
+  }
+  let message = group_identity.digest_with(&view.digest_with(&state.receipt.get_metablock_hash()));
+  let pk = PublicKey::from_bytes(pk).ok()?;
+  state
+    .receipt
+    .get_id_sig()
+    .verify_with_id(&pk, &message.to_bytes())
+    .ok()?;
+
+  state
+    .ledger_tail_map
+    .iter()
+    .map(|entry| {
+      MetaBlock::from_bytes(&entry.metablock)
+        .ok()
+        .map(|m| (entry.handle.clone(), m.get_height()))
+    })
+    .collect()
+}
+
+/// Checks that restoring the backup would not roll back the view ledger or any ledger
+/// relative to the endorsers of its latest view.
+///
+/// Every append is endorsed by a majority of the endorsers, so the heights reported by any
+/// majority of them cover every append that completed. Endorsers whose key is not in the
+/// configuration (e.g., ones that were restarted from scratch) do not count, and neither do
+/// states that are not signed by the key they claim, since heights could otherwise be
+/// forged by anyone between the endorsers and this tool.
+pub fn check_rollback(
+  manifest: &BackupManifest,
+  config: &EndorserHostnames,
+  group_identity: &NimbleDigest,
+  states: &[EndorserState],
+) -> Result<(), MigrationError> {
+  let known = states
+    .iter()
+    .filter_map(|s| {
+      let id = s.receipt.get_id_sig().get_id();
+      if !config.iter().any(|(pk, _uri)| pk == id) {
+        return None;
+      }
+      let heights = verified_heights(s, id, group_identity);
+      if heights.is_none() {
+        eprintln!(
+          "The state of the endorser {} is not signed by its key",
+          base64_url::encode(id)
+        );
+      }
+      heights.map(|h| (s.receipt.get_height(), h))
+    })
+    .collect::<Vec<(usize, Vec<(Vec<u8>, usize)>)>>();
+  if known.len() <= config.len() / 2 {
+    eprintln!(
+      "Read the state of {} of the {} endorsers of the backup, which is not a majority",
+      known.len(),
+      config.len()
+    );
+    return Err(MigrationError::FailedToReadEndorsers);
+  }
+
+  let ledger_heights = manifest
+    .ledgers
+    .iter()
+    .map(|l| (l.handle.clone(), l.height as usize))
+    .collect::<HashMap<Vec<u8>, usize>>();
+
+  for (view_height, ledgers) in known {
+    if view_height > manifest.view_height as usize {
+      eprintln!(
+        "An endorser is at height {} of the view ledger, but the backup is at {}",
+        view_height, manifest.view_height
+      );
+      return Err(MigrationError::RollbackDetected);
+    }
+    for (handle, height) in &ledgers {
+      let backup_height = ledger_heights.get(handle).copied();
+      if backup_height.is_none() || backup_height < Some(*height) {
+        eprintln!(
+          "An endorser is at height {} of ledger {}, but the backup is at {:?}",
+          height,
+          base64_url::encode(handle),
+          backup_height
+        );
+        return Err(MigrationError::RollbackDetected);
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Restores a backup into `dest`.
+///
+/// The backup is loaded into memory and every receipt in it is verified before anything
+/// is written. The backup is then checked against the endorsers of its latest view, or
+/// against `endorsers` if given, so that a stale backup cannot roll back a ledger.
+pub async fn restore_backup(
+  path: &str,
+  dest: LedgerStoreRef,
+  endorsers: Option<Vec<String>>,
+  allow_unverified: bool,
+  page_size: usize,
+) -> Result<BackupManifest, MigrationError> {
+  let file = match File::open(path) {
+    Ok(f) => f,
+    Err(error) => {
+      eprintln!("Failed to open the backup {} ({:?})", path, error);
+      return Err(MigrationError::InvalidBackup);
+    },
+  };
+  let staging: LedgerStoreRef = Arc::new(Box::new(InMemoryLedgerStore::new()));
+  let manifest = {
+    let mut reader =
+      BackupReader::new(BufReader::new(file)).map_err(|_| MigrationError::InvalidBackup)?;
+    match load_backup(&mut reader, &**staging).await {
+      Ok(m) => m,
+      Err(error) => {
+        eprintln!("Failed to load the backup {} ({:?})", path, error);
+        return Err(MigrationError::InvalidBackup);
+      },
+    }
+  };
+  if manifest.view_height == 0 {
+    eprintln!("The backup has no view ledger");
+    return Err(MigrationError::InvalidViewLedger);
+  }
+
+  let migration = Migration::new(staging.clone(), dest, allow_unverified).await?;
+  let num_ledgers = migration.verify_ledgers(page_size).await?;
+  println!("Verified {} ledgers in the backup", num_ledgers);
+
+  // the group is identified by the hash of the first configuration in the view ledger
+  let group_identity = match staging.read_view_ledger_by_index(1).await {
+    Ok(entry) => entry.get_block().hash(),
+    Err(error) => {
+      eprintln!("Failed to read the view ledger of the backup {:?}", error);
+      return Err(MigrationError::FailedToReadSource);
+    },
+  };
+  let config: EndorserHostnames = match staging.read_view_ledger_tail().await {
+    Ok((entry, _height)) => match bincode::deserialize(&entry.get_block().to_bytes()) {
+      Ok(c) => c,
+      Err(error) => {
+        eprintln!("Failed to parse the endorsers of the backup {:?}", error);
+        return Err(MigrationError::InvalidViewLedger);
+      },
+    },
+    Err(error) => {
+      eprintln!("Failed to read the view ledger of the backup {:?}", error);
+      return Err(MigrationError::FailedToReadSource);
+    },
+  };
+  let uris = match endorsers {
+    Some(uris) => uris,
+    None => config.iter().map(|(_pk, uri)| uri.clone()).collect(),
+  };
+  let mut states = Vec::new();
+  for uri in &uris {
+    // unreachable endorsers are fine as long as a majority can be read
+    if let Ok(s) = read_endorser_state(uri).await {
+      states.push(s);
+    }
+  }
+  check_rollback(&manifest, &config, &group_identity, &states)?;
+
+  let view_height = migration.copy_view_ledger().await?;
+  println!("Restored the view ledger (height {})", view_height);
+  let num_ledgers = migration.copy_ledgers(None, page_size).await?;
+  println!("Restored {} ledgers", num_ledgers);
+
+  Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ledger::{
+    signature::{PrivateKey, PrivateKeyTrait},
+    IdSig,
+  };
+  use store::backup::LedgerManifest;
+
+  fn manifest(view_height: u64, ledgers: &[(u8, u64)]) -> BackupManifest {
+    BackupManifest {
+      created_at: String::new(),
+      view_height,
+      view_digest: Vec::new(),
+      ledgers: ledgers
+        .iter()
+        .map(|(h, height)| LedgerManifest {
+          handle: vec![*h; 32],
+          height: *height,
+          digest: Vec::new(),
+        })
+        .collect(),
+      digest: Vec::new(),
+    }
+  }
+
+  fn tail(height: usize) -> MetaBlock {
+    MetaBlock::new(&NimbleDigest::default(), &NimbleDigest::default(), height)
+  }
+
+  // signs the state the way endorsers do in `read_state`
+  fn endorser(
+    sk: &PrivateKey,
+    group_identity: &NimbleDigest,
+    view_height: usize,
+    ledgers: &[(u8, usize)],
+  ) -> EndorserState {
+    let ledger_tail_map = ledgers
+      .iter()
+      .map(|(h, height)| LedgerTailMapEntry {
+        handle: vec![*h; 32],
+        height: *height as u64,
+        metablock: tail(*height).to_bytes(),
+        block: Vec::new(),
+        nonces: Vec::new(),
+      })
+      .collect();
+    let view = produce_hash_of_state(&ledger_tail_map);
+    let metablock = tail(view_height);
+    let message = group_identity.digest_with(&view.digest_with(&metablock.hash()));
+    let id_sig = IdSig::new(
+      sk.get_public_key().unwrap(),
+      sk.sign(&message.to_bytes()).unwrap(),
+    );
+    EndorserState {
+      receipt: Receipt::new(view, metablock, id_sig),
+      ledger_tail_map,
+    }
+  }
+
+  #[test]
+  pub fn test_check_rollback() {
+    let keys = (0..4).map(|_| PrivateKey::new()).collect::<Vec<_>>();
+    let config: EndorserHostnames = keys[..3]
+      .iter()
+      .enumerate()
+      .map(|(i, sk)| (sk.get_public_key().unwrap().to_bytes(), format!("e{}", i)))
+      .collect();
+    let group = NimbleDigest::digest(b"the first configuration");
+    let backup = manifest(2, &[(1, 3), (2, 0)]);
+
+    // a majority at or behind the backup
+    let current = vec![
+      endorser(&keys[0], &group, 2, &[(1, 3), (2, 0)]),
+      endorser(&keys[1], &group, 2, &[(1, 2)]),
+    ];
+    assert!(check_rollback(&backup, &config, &group, &current).is_ok());
+
+    // one endorser is not a majority, and unknown endorsers do not count
+    assert_eq!(
+      check_rollback(&backup, &config, &group, &current[..1]).unwrap_err(),
+      MigrationError::FailedToReadEndorsers
+    );
+    let unknown = vec![
+      endorser(&keys[0], &group, 2, &[]),
+      endorser(&keys[3], &group, 2, &[]),
+    ];
+    assert_eq!(
+      check_rollback(&backup, &config, &group, &unknown).unwrap_err(),
+      MigrationError::FailedToReadEndorsers
+    );
+
+    // neither do states whose tails were changed after they were signed, or that were signed
+    // for another group
+    let mut forged = endorser(&keys[1], &group, 2, &[(1, 4)]);
+    forged.ledger_tail_map[0].metablock = tail(3).to_bytes();
+    let other_group = endorser(&keys[2], &NimbleDigest::digest(b"another group"), 2, &[]);
+    let states = vec![endorser(&keys[0], &group, 2, &[]), forged, other_group];
+    assert_eq!(
+      check_rollback(&backup, &config, &group, &states).unwrap_err(),
+      MigrationError::FailedToReadEndorsers
+    );
+
+    // the view ledger, a ledger, or a missing ledger is ahead of the backup
+    for ahead in [
+      endorser(&keys[2], &group, 3, &[]),
+      endorser(&keys[2], &group, 2, &[(1, 4)]),
+      endorser(&keys[2], &group, 2, &[(3, 0)]),
+    ] {
+      let states = vec![endorser(&keys[0], &group, 2, &[]), ahead];
+      assert_eq!(
+        check_rollback(&backup, &config, &group, &states).unwrap_err(),
+        MigrationError::RollbackDetected
+      );
+    }
+  }
+}
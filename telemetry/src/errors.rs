@@ -0,0 +1,7 @@
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TelemetryError {
+  /// returned if the exporter of spans to the OTLP collector cannot be set up
+  FailedToInstallExporter,
+  /// returned if a subscriber was already set for the process
+  FailedToSetSubscriber,
+}
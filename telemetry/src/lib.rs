@@ -0,0 +1,213 @@
+//! Tracing for Nimble's services: a subscriber that logs to stderr and, optionally, exports
+//! spans to an OTLP collector, and the request IDs and trace contexts that the services pass
+//! along with their gRPC calls and HTTP requests.
+
+pub mod errors;
+
+use crate::errors::TelemetryError;
+use opentelemetry::{
+  global,
+  propagation::{Extractor, Injector},
+  KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace as sdktrace, Resource};
+use std::{future::Future, io::IsTerminal};
+use tonic::metadata::{Ascii, MetadataMap, MetadataValue};
+use tracing::{Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// The gRPC metadata key and HTTP header that carry the ID of a request
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const DEFAULT_FILTER: &str = "info";
+const MAX_REQUEST_ID_LEN: usize = 128; // the longest request ID taken from a caller
+
+tokio::task_local! {
+  // the ID of the request that the task works on
+  static REQUEST_ID: String;
+}
+
+/// Sets up the subscriber of the process, which logs events to stderr at the level given in
+/// `RUST_LOG` (`info` by default), and exports spans to the OTLP collector at
+/// `otlp_endpoint` (e.g., `http://localhost:4317`) if one is given. Must be called from
+/// within a Tokio runtime.
+pub fn init(service_name: &str, otlp_endpoint: Option<&str>) -> Result<(), TelemetryError> {
+  global::set_text_map_propagator(TraceContextPropagator::new());
+
+  let filter = match EnvFilter::try_from_default_env() {
+    Ok(filter) => filter,
+    Err(_) => EnvFilter::new(DEFAULT_FILTER),
+  };
+  let fmt = tracing_subscriber::fmt::layer()
+    .with_writer(std::io::stderr)
+    .with_ansi(std::io::stderr().is_terminal())
+    .with_target(false);
+  let otlp = match otlp_endpoint {
+    Some(endpoint) => {
+      Some(tracing_opentelemetry::layer().with_tracer(otlp_tracer(service_name, endpoint)?))
+    },
+    None => None,
+  };
+
+  tracing_subscriber::registry()
+    .with(filter)
+    .with(fmt)
+    .with(otlp)
+    .try_init()
+    .map_err(|_| TelemetryError::FailedToSetSubscriber)
+}
+
+fn otlp_tracer(service_name: &str, endpoint: &str) -> Result<sdktrace::Tracer, TelemetryError> {
+  let exporter = opentelemetry_otlp::new_exporter()
+    .tonic()
+    .with_endpoint(endpoint);
+  let resource = Resource::new(vec![KeyValue::new(
+    "service.name",
+    service_name.to_string(),
+  )]);
+  opentelemetry_otlp::new_pipeline()
+    .tracing()
+    .with_exporter(exporter)
+    .with_trace_config(sdktrace::config().with_resource(resource))
+    .install_batch(opentelemetry_sdk::runtime::Tokio)
+    .map_err(|error| {
+      eprintln!("Failed to set up the OTLP exporter ({:?})", error);
+      TelemetryError::FailedToInstallExporter
+    })
+}
+
+/// Exports the spans that are still buffered
+pub fn shutdown() {
+  global::shutdown_tracer_provider();
+}
+
+pub fn new_request_id() -> String {
+  format!("{:016x}", rand::random::<u64>())
+}
+
+/// Returns the ID of the request the current task works on, if any
+pub fn current_request_id() -> Option<String> {
+  REQUEST_ID
+    .try_with(|id| id.clone())
+    .ok()
+    .filter(|id| !id.is_empty())
+}
+
+/// Runs `f` on behalf of the request with ID `request_id`
+pub async fn scope<F: Future>(request_id: String, f: F) -> F::Output {
+  REQUEST_ID.scope(request_id, f).await
+}
+
+/// Carries the request ID and the span of the current task over to `f`, so that a task
+/// spawned to run `f` works on behalf of the same request
+pub fn propagate<F: Future>(f: F) -> impl Future<Output = F::Output> {
+  let request_id = current_request_id().unwrap_or_default();
+  REQUEST_ID.scope(request_id, f.in_current_span())
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl<'a> Injector for MetadataInjector<'a> {
+  fn set(&mut self, key: &str, value: String) {
+    if let (Ok(key), Ok(value)) = (
+      tonic::metadata::MetadataKey::from_bytes(key.as_bytes()),
+      value.parse::<MetadataValue<Ascii>>(),
+    ) {
+      self.0.insert(key, value);
+    }
+  }
+}
+
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl<'a> Extractor for MetadataExtractor<'a> {
+  fn get(&self, key: &str) -> Option<&str> {
+    self.0.get(key).and_then(|v| v.to_str().ok())
+  }
+
+  fn keys(&self) -> Vec<&str> {
+    self
+      .0
+      .keys()
+      .filter_map(|k| match k {
+        tonic::metadata::KeyRef::Ascii(k) => Some(k.as_str()),
+        tonic::metadata::KeyRef::Binary(_) => None,
+      })
+      .collect()
+  }
+}
+
+/// Adds the ID of the current request and the context of the current span to the metadata
+/// of an outgoing call
+pub fn inject(metadata: &mut MetadataMap) {
+  if let Some(request_id) = current_request_id() {
+    if let Ok(value) = request_id.parse::<MetadataValue<Ascii>>() {
+      metadata.insert(REQUEST_ID_HEADER, value);
+    }
+  }
+  let context = Span::current().context();
+  global::get_text_map_propagator(|propagator| {
+    propagator.inject_context(&context, &mut MetadataInjector(metadata))
+  });
+}
+
+/// Builds an outgoing gRPC request that carries the ID of the current request and the
+/// context of the current span
+pub fn request<T>(message: T) -> tonic::Request<T> {
+  let mut request = tonic::Request::new(message);
+  inject(request.metadata_mut());
+  request
+}
+
+/// Returns the ID of an incoming request given by the caller, or a new one, and makes `span`
+/// a child of the caller's span if the caller is traced. The ID is recorded in the
+/// `request_id` field of `span`, if it has one.
+pub fn accept(metadata: &MetadataMap, span: &Span) -> String {
+  let parent =
+    global::get_text_map_propagator(|propagator| propagator.extract(&MetadataExtractor(metadata)));
+  span.set_parent(parent);
+
+  let request_id = metadata
+    .get(REQUEST_ID_HEADER)
+    .and_then(|v| v.to_str().ok())
+    .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+    .map(|id| id.to_string())
+    .unwrap_or_else(new_request_id);
+  span.record("request_id", request_id.as_str());
+  request_id
+}
+
+/// Returns the ID of an incoming HTTP request like `accept` does for gRPC requests
+pub fn accept_http(headers: &tonic::codegen::http::HeaderMap, span: &Span) -> String {
+  accept(&MetadataMap::from_headers(headers.clone()), span)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  pub async fn check_request_id_propagation() {
+    assert_eq!(current_request_id(), None);
+
+    // an outgoing request carries the ID of the request it is made on behalf of
+    let req = scope("abc".to_string(), async { request(()) }).await;
+    assert_eq!(req.metadata().get(REQUEST_ID_HEADER).unwrap(), "abc");
+    assert!(request(()).metadata().get(REQUEST_ID_HEADER).is_none());
+
+    // a spawned task keeps working on behalf of the same request
+    let id = scope("abc".to_string(), async {
+      tokio::spawn(propagate(async { current_request_id() }))
+        .await
+        .unwrap()
+    })
+    .await;
+    assert_eq!(id, Some("abc".to_string()));
+
+    // an incoming request keeps the caller's ID, or gets a new one
+    assert_eq!(accept(req.metadata(), &Span::none()), "abc");
+    assert_eq!(accept(&MetadataMap::new(), &Span::none()).len(), 16);
+  }
+}